@@ -0,0 +1,774 @@
+// src-tauri/src/agent.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::audit;
+use crate::automation::{self, AutomationBackend};
+use crate::context;
+use crate::error::{AppError, AppResult};
+use crate::llm::{self, ChatMessage, CompletionRequest, Feature, LlmProvider, Role, ToolCall, ToolSpec};
+use crate::permissions::{self, Capability};
+use crate::settings;
+
+/// No setting or argument can allow more tool calls than this.
+const MAX_STEPS_LIMIT: usize = 25;
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+const SYSTEM_PROMPT: &str = "You are Leo, an assistant that can operate the user's computer with the tools provided. \
+Only do what the request needs, one step at a time, and check each result before the next step. \
+When you are done, or cannot go on, answer in plain text without calling a tool.";
+
+/// How tool calls are carried out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Approval {
+    /// Run each call once the user approves it in the chat.
+    Confirm,
+    Auto,
+    /// Show each call without running it.
+    Preview,
+}
+
+impl Approval {
+    /// How much of a say the user has over each step: auto < confirm <
+    /// preview.
+    fn strictness(self) -> u8 {
+        match self {
+            Approval::Auto => 0,
+            Approval::Confirm => 1,
+            Approval::Preview => 2,
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "confirm" => Some(Approval::Confirm),
+            "auto" => Some(Approval::Auto),
+            "preview" => Some(Approval::Preview),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    AwaitingApproval,
+    Running,
+    Ok,
+    Failed,
+    Rejected,
+    Preview,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentStep {
+    pub run_id: String,
+    pub index: usize,
+    pub tool: String,
+    pub arguments: Value,
+    pub status: StepStatus,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    Answered,
+    StepLimit,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRun {
+    pub run_id: String,
+    pub approval: Approval,
+    pub stopped: StopReason,
+    /// The model's final answer.
+    pub answer: Option<String>,
+    pub error: Option<String>,
+    pub steps: Vec<AgentStep>,
+}
+
+/// A running agent, for `approve_agent_step` and `cancel_agent`.
+#[derive(Default)]
+struct RunControl {
+    cancelled: Arc<AtomicBool>,
+    /// The step waiting for approval and where the answer goes.
+    pending: Option<(usize, mpsc::Sender<bool>)>,
+}
+
+static RUNS: Lazy<Mutex<HashMap<String, RunControl>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_RUN: AtomicU64 = AtomicU64::new(1);
+
+/// The tools the model is offered.
+pub fn tools() -> Vec<ToolSpec> {
+    let tool = |name: &str, description: &str, parameters: Value| ToolSpec {
+        name: name.to_string(),
+        description: description.to_string(),
+        parameters,
+    };
+    let no_parameters = json!({ "type": "object", "properties": {} });
+    vec![
+        tool(
+            "open_application",
+            "Open an app, or bring it to the front if it is running.",
+            json!({
+                "type": "object",
+                "properties": { "app_name": { "type": "string", "description": "Name of the app, e.g. \"Safari\"" } },
+                "required": ["app_name"],
+            }),
+        ),
+        tool(
+            "type_text",
+            "Type text into the frontmost app, as if from the keyboard.",
+            json!({
+                "type": "object",
+                "properties": { "text": { "type": "string" } },
+                "required": ["text"],
+            }),
+        ),
+        tool(
+            "press_keys",
+            "Press a key or chord in the frontmost app, e.g. \"return\" or \"cmd+t\".",
+            json!({
+                "type": "object",
+                "properties": { "keys": { "type": "string" } },
+                "required": ["keys"],
+            }),
+        ),
+        tool(
+            "click_button",
+            "Click the button with this name in the frontmost window.",
+            json!({
+                "type": "object",
+                "properties": { "button_name": { "type": "string" } },
+                "required": ["button_name"],
+            }),
+        ),
+        tool(
+            "list_windows",
+            "List the frontmost app and the visible windows, front to back.",
+            no_parameters.clone(),
+        ),
+        tool(
+            "take_screenshot",
+            "Capture the frontmost window and look at it.",
+            no_parameters,
+        ),
+    ]
+}
+
+#[derive(Deserialize)]
+struct AppArgs {
+    app_name: String,
+}
+
+#[derive(Deserialize)]
+struct TextArgs {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct KeysArgs {
+    keys: String,
+}
+
+#[derive(Deserialize)]
+struct ButtonArgs {
+    button_name: String,
+}
+
+fn arguments<T: DeserializeOwned>(call: &ToolCall) -> AppResult<T> {
+    serde_json::from_value(call.arguments.clone())
+        .map_err(|e| AppError::InvalidInput(format!("Invalid arguments for {}: {}", call.name, e)))
+}
+
+/// What a tool call gives back to the model.
+struct ToolOutput {
+    text: String,
+    image: Option<String>,
+}
+
+impl ToolOutput {
+    fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            image: None,
+        }
+    }
+}
+
+/// An input automation on the focused app, checked and audited like the
+/// commands of the same name.
+fn act_on_focused_app<T>(
+    call: &ToolCall,
+    capability: Capability,
+    details: Value,
+    act: impl FnOnce() -> AppResult<T>,
+) -> AppResult<T> {
+    let target = permissions::focused_app();
+    let command = format!("agent:{}", call.name);
+    audit::track(&command, target.as_deref(), details, || {
+        permissions::require(capability, target.as_deref())?;
+        act()
+    })
+}
+
+/// Runs the tool-calling loop: ask the model, carry out the tools it calls,
+/// feed the results back, until it answers or runs out of steps.
+pub struct Agent {
+    provider: Box<dyn LlmProvider>,
+    backend: Arc<dyn AutomationBackend>,
+    approval: Approval,
+    max_steps: usize,
+    run_id: String,
+    cancelled: Arc<AtomicBool>,
+    steps: Vec<AgentStep>,
+}
+
+impl Agent {
+    pub fn new(
+        provider: Box<dyn LlmProvider>,
+        backend: Arc<dyn AutomationBackend>,
+        approval: Approval,
+        max_steps: usize,
+    ) -> Self {
+        let run_id = format!("agent-{}", NEXT_RUN.fetch_add(1, Ordering::Relaxed));
+        Self {
+            provider,
+            backend,
+            approval,
+            max_steps: max_steps.clamp(1, MAX_STEPS_LIMIT),
+            run_id,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn run(mut self, messages: Vec<ChatMessage>) -> AgentRun {
+        let control = RunControl {
+            cancelled: self.cancelled.clone(),
+            pending: None,
+        };
+        RUNS.lock().insert(self.run_id.clone(), control);
+        tracing::info!(run = %self.run_id, approval = ?self.approval, "Agent started");
+
+        let result = self.run_loop(messages);
+        RUNS.lock().remove(&self.run_id);
+
+        let (stopped, answer, error) = match result {
+            Ok(Some(answer)) => (StopReason::Answered, Some(answer), None),
+            Ok(None) => (
+                StopReason::StepLimit,
+                None,
+                Some(format!("Stopped after {} steps", self.max_steps)),
+            ),
+            Err(_) if self.cancelled.load(Ordering::SeqCst) => (StopReason::Cancelled, None, None),
+            Err(e) => (StopReason::Failed, None, Some(e.to_string())),
+        };
+        tracing::info!(run = %self.run_id, steps = self.steps.len(), ?stopped, "Agent finished");
+
+        AgentRun {
+            run_id: self.run_id,
+            approval: self.approval,
+            stopped,
+            answer,
+            error,
+            steps: self.steps,
+        }
+    }
+
+    /// The final answer, or `None` when the step limit was reached first.
+    fn run_loop(&mut self, mut messages: Vec<ChatMessage>) -> AppResult<Option<String>> {
+        if !messages.iter().any(|m| m.role == Role::System) {
+            messages.insert(0, ChatMessage::system(SYSTEM_PROMPT));
+        }
+        let tools = tools();
+
+        loop {
+            self.check_cancelled()?;
+            let request = CompletionRequest::new(messages.clone())
+                .with_tools(tools.clone())
+                .for_feature(Feature::Agent);
+            let completion = self.provider.complete(&request)?;
+            if completion.tool_calls.is_empty() {
+                return Ok(Some(completion.text.trim().to_string()));
+            }
+            messages.push(ChatMessage::assistant(&completion));
+
+            for call in &completion.tool_calls {
+                if self.steps.len() >= self.max_steps {
+                    return Ok(None);
+                }
+                self.check_cancelled()?;
+                let result = self.step(call)?;
+                messages.push(result);
+            }
+        }
+    }
+
+    fn check_cancelled(&self) -> AppResult<()> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            Err(AppError::Failed("Cancelled".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Carry out one call and return the message with its result. Failed
+    /// tools are reported to the model rather than ending the run.
+    fn step(&mut self, call: &ToolCall) -> AppResult<ChatMessage> {
+        let index = self.steps.len();
+        self.steps.push(AgentStep {
+            run_id: self.run_id.clone(),
+            index,
+            tool: call.name.clone(),
+            arguments: call.arguments.clone(),
+            status: StepStatus::Running,
+            output: None,
+            error: None,
+            duration_ms: 0,
+        });
+
+        if self.approval == Approval::Preview {
+            self.update(index, StepStatus::Preview, None, None);
+            return Ok(ChatMessage::tool_result(
+                call,
+                "Preview only: this step was not run. Carry on as if it had succeeded.",
+            ));
+        }
+        if self.approval == Approval::Confirm && !self.approved(index)? {
+            self.update(index, StepStatus::Rejected, None, None);
+            return Ok(ChatMessage::tool_result(call, "The user rejected this step."));
+        }
+
+        self.update(index, StepStatus::Running, None, None);
+        let started = Instant::now();
+        let result = self.execute(call);
+        self.steps[index].duration_ms = started.elapsed().as_millis() as u64;
+        match result {
+            Ok(output) => {
+                self.update(index, StepStatus::Ok, Some(output.text.clone()), None);
+                let message = ChatMessage::tool_result(call, output.text);
+                Ok(match output.image {
+                    Some(image) => message.with_image(image),
+                    None => message,
+                })
+            }
+            // The user's answers end the run; everything else the model may
+            // work around
+            Err(e @ (AppError::SafeMode | AppError::Locked)) => {
+                self.update(index, StepStatus::Failed, None, Some(e.to_string()));
+                Err(e)
+            }
+            Err(e) => {
+                self.update(index, StepStatus::Failed, None, Some(e.to_string()));
+                Ok(ChatMessage::tool_result(call, format!("Error: {}", e)))
+            }
+        }
+    }
+
+    fn update(&mut self, index: usize, status: StepStatus, output: Option<String>, error: Option<String>) {
+        let step = &mut self.steps[index];
+        step.status = status;
+        step.output = output;
+        step.error = error;
+        emit("agent_step", &*step);
+    }
+
+    /// Show the step as awaiting approval and wait for `approve_agent_step`.
+    fn approved(&mut self, index: usize) -> AppResult<bool> {
+        let (tx, rx) = mpsc::channel();
+        if let Some(control) = RUNS.lock().get_mut(&self.run_id) {
+            control.pending = Some((index, tx));
+        }
+        self.update(index, StepStatus::AwaitingApproval, None, None);
+
+        let answer = rx.recv_timeout(APPROVAL_TIMEOUT);
+        if let Some(control) = RUNS.lock().get_mut(&self.run_id) {
+            control.pending = None;
+        }
+        self.check_cancelled()?;
+        answer.map_err(|_| AppError::Failed("Timed out waiting for approval".to_string()))
+    }
+
+    fn execute(&self, call: &ToolCall) -> AppResult<ToolOutput> {
+        match call.name.as_str() {
+            "open_application" => {
+                let AppArgs { app_name } = arguments(call)?;
+                audit::track("agent:open_application", Some(&app_name), Value::Null, || {
+                    permissions::require(Capability::OpenApp, Some(&app_name))?;
+                    self.backend.open_application(&app_name)
+                })?;
+                Ok(ToolOutput::text(format!("Opened {}", app_name)))
+            }
+            "type_text" => {
+                let TextArgs { text } = arguments(call)?;
+                // Typed text may be a password, so only log its length
+                let details = json!({ "chars": text.chars().count() });
+                act_on_focused_app(call, Capability::TypeText, details, || self.backend.type_text(&text))?;
+                Ok(ToolOutput::text("Typed"))
+            }
+            "press_keys" => {
+                let KeysArgs { keys } = arguments(call)?;
+                let details = json!({ "keys": keys });
+                act_on_focused_app(call, Capability::PressKeys, details, || self.backend.press_keys(&keys))?;
+                Ok(ToolOutput::text(format!("Pressed {}", keys)))
+            }
+            "click_button" => {
+                let ButtonArgs { button_name } = arguments(call)?;
+                let details = json!({ "button": button_name });
+                act_on_focused_app(call, Capability::Click, details, || {
+                    self.backend.click_button(&button_name)
+                })?;
+                Ok(ToolOutput::text(format!("Clicked {}", button_name)))
+            }
+            "list_windows" => {
+                let windows = json!({
+                    "activeApp": context::current_active_app(),
+                    "windows": context::visible_windows(),
+                });
+                Ok(ToolOutput::text(windows.to_string()))
+            }
+            "take_screenshot" => {
+                // The model only asked for the window, so leave the selection alone
+                let (snapshot, screenshot) = act_on_focused_app(call, Capability::CaptureScreen, Value::Null, || {
+                    let mut snapshot = context::snapshot_with(context::SelectionGrab::Skip, true);
+                    let screenshot = snapshot.screenshot.take().ok_or_else(|| {
+                        if snapshot.withheld.iter().any(|field| field == "screenshot") {
+                            AppError::PermissionDenied("screenshots of this app are turned off".to_string())
+                        } else {
+                            AppError::BackendUnavailable("Could not capture the window".to_string())
+                        }
+                    })?;
+                    Ok((snapshot, screenshot))
+                })?;
+                let app = snapshot.active_app.map(|app| app.name).unwrap_or_default();
+                let title = snapshot.window_title.unwrap_or_default();
+                Ok(ToolOutput {
+                    text: format!("Screenshot of {} ({}) attached", app, title),
+                    image: Some(screenshot),
+                })
+            }
+            other => Err(AppError::NotFound(format!("Unknown tool: {}", other))),
+        }
+    }
+}
+
+fn emit<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Some(app_handle) = crate::APP_HANDLE.read().as_ref() {
+        if let Err(e) = app_handle.emit(event, payload) {
+            tracing::warn!("Failed to emit {}: {}", event, e);
+        }
+    }
+}
+
+/// Run the agent on a conversation with the configured provider and
+/// automation backend. `approval` and `max_steps` default to the settings;
+/// `approval` can only make the setting stricter.
+pub fn run(messages: Vec<ChatMessage>, approval: Option<Approval>, max_steps: Option<usize>) -> AppResult<AgentRun> {
+    crate::lock::ensure_unlocked()?;
+    if crate::crash::safe_mode() {
+        return Err(AppError::SafeMode);
+    }
+    if messages.is_empty() {
+        return Err(AppError::InvalidInput("No messages".to_string()));
+    }
+    let agent_settings = settings::current().agent;
+    let configured = Approval::parse(&agent_settings.approval)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown agent approval mode: {}", agent_settings.approval)))?;
+    let approval = match approval {
+        Some(requested) if requested.strictness() < configured.strictness() => {
+            return Err(AppError::PermissionDenied(format!(
+                "agent steps need {} approval in the settings",
+                agent_settings.approval
+            )));
+        }
+        Some(requested) => requested,
+        None => configured,
+    };
+    // Nobody could answer an approval request without the app's chat
+    if approval == Approval::Confirm && crate::APP_HANDLE.read().is_none() {
+        return Err(AppError::BackendUnavailable(
+            "Approving steps needs the Leo AI app; use preview".to_string(),
+        ));
+    }
+
+    let provider = llm::configured_provider()?;
+    let agent = Agent::new(
+        provider,
+        automation::backend(),
+        approval,
+        max_steps.unwrap_or(agent_settings.max_steps),
+    );
+    let run = agent.run(messages);
+    emit("agent_finished", &run);
+    Ok(run)
+}
+
+/// Chat with tools. Steps are emitted as `agent_step` events while the run
+/// goes on, and the whole run as `agent_finished` at the end.
+#[tauri::command]
+pub async fn run_agent(
+    messages: Vec<ChatMessage>,
+    approval: Option<Approval>,
+    max_steps: Option<usize>,
+) -> AppResult<AgentRun> {
+    tauri::async_runtime::spawn_blocking(move || run(messages, approval, max_steps)).await?
+}
+
+/// Answer the step `run_id` is waiting on.
+#[tauri::command]
+pub fn approve_agent_step(run_id: String, index: usize, approve: bool) -> AppResult<()> {
+    let mut runs = RUNS.lock();
+    let control = runs
+        .get_mut(&run_id)
+        .ok_or_else(|| AppError::NotFound(format!("No agent run {}", run_id)))?;
+    match control.pending.take() {
+        Some((pending, tx)) if pending == index => {
+            let _ = tx.send(approve);
+            Ok(())
+        }
+        other => {
+            control.pending = other;
+            Err(AppError::InvalidState(format!("Step {} is not awaiting approval", index)))
+        }
+    }
+}
+
+#[tauri::command]
+pub fn cancel_agent(run_id: String) -> AppResult<()> {
+    let mut runs = RUNS.lock();
+    let control = runs
+        .get_mut(&run_id)
+        .ok_or_else(|| AppError::NotFound(format!("No agent run {}", run_id)))?;
+    control.cancelled.store(true, Ordering::SeqCst);
+    // Wake a step waiting for approval
+    if let Some((_, tx)) = control.pending.take() {
+        let _ = tx.send(false);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automation::{AutomationCall, MockBackend};
+    use crate::llm::{MockProvider, MockTurn};
+    use crate::test_support;
+    use std::thread;
+
+    fn open(app_name: &str) -> MockTurn {
+        MockTurn {
+            tool_calls: vec![ToolCall {
+                id: String::new(),
+                name: "open_application".to_string(),
+                arguments: json!({ "app_name": app_name }),
+            }],
+            ..MockTurn::default()
+        }
+    }
+
+    fn answer(text: &str) -> MockTurn {
+        MockTurn {
+            text: text.to_string(),
+            ..MockTurn::default()
+        }
+    }
+
+    fn agent(turns: Vec<MockTurn>, approval: Approval, max_steps: usize) -> (Agent, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::default());
+        let agent = Agent::new(
+            Box::new(MockProvider::scripted(turns)),
+            backend.clone(),
+            approval,
+            max_steps,
+        );
+        (agent, backend)
+    }
+
+    fn statuses(run: &AgentRun) -> Vec<StepStatus> {
+        run.steps.iter().map(|step| step.status).collect()
+    }
+
+    fn agent_limit(max_steps: usize) -> usize {
+        agent(Vec::new(), Approval::Auto, max_steps).0.max_steps
+    }
+
+    /// The step `run_id` waits on, once it does.
+    fn pending_step(run_id: &str) -> usize {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some((index, _)) = RUNS.lock().get(run_id).and_then(|control| control.pending.as_ref()) {
+                return *index;
+            }
+            assert!(Instant::now() < deadline, "no step of {} awaits approval", run_id);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn tools_run_until_the_model_answers() {
+        let _isolated = test_support::isolated(|_| {});
+        let unknown = MockTurn {
+            tool_calls: vec![ToolCall {
+                id: String::new(),
+                name: "format_disk".to_string(),
+                arguments: json!({}),
+            }],
+            ..MockTurn::default()
+        };
+        let (agent, backend) = agent(
+            vec![open("Notes"), unknown, answer(" Notes is open. ")],
+            Approval::Auto,
+            10,
+        );
+        let run = agent.run(vec![ChatMessage::user("Open Notes")]);
+
+        assert_eq!(run.stopped, StopReason::Answered);
+        assert_eq!(run.answer.as_deref(), Some("Notes is open."));
+        // A failing tool goes back to the model instead of ending the run
+        assert_eq!(statuses(&run), [StepStatus::Ok, StepStatus::Failed]);
+        assert_eq!(run.steps[0].output.as_deref(), Some("Opened Notes"));
+        assert!(run.steps[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Unknown tool: format_disk"));
+        assert_eq!(
+            backend.calls(),
+            [AutomationCall::OpenApplication {
+                app_name: "Notes".to_string()
+            }]
+        );
+        assert!(RUNS.lock().get(&run.run_id).is_none());
+    }
+
+    #[test]
+    fn runs_stop_at_the_step_limit() {
+        let _isolated = test_support::isolated(|_| {});
+        let turns = vec![open("Notes"), open("Mail"), open("Safari"), answer("Done")];
+        let (agent, backend) = agent(turns, Approval::Auto, 2);
+        let run = agent.run(vec![ChatMessage::user("Open everything")]);
+
+        assert_eq!(run.stopped, StopReason::StepLimit);
+        assert_eq!(run.answer, None);
+        assert_eq!(run.error.as_deref(), Some("Stopped after 2 steps"));
+        assert_eq!(statuses(&run), [StepStatus::Ok, StepStatus::Ok]);
+        assert_eq!(backend.calls().len(), 2);
+
+        // No argument gets past the hard limit, or below one step
+        assert_eq!(agent_limit(1000), MAX_STEPS_LIMIT);
+        assert_eq!(agent_limit(0), 1);
+    }
+
+    #[test]
+    fn previews_show_steps_without_running_them() {
+        let _isolated = test_support::isolated(|_| {});
+        let (agent, backend) = agent(
+            vec![open("Notes"), open("Mail"), answer("Both open")],
+            Approval::Preview,
+            10,
+        );
+        let run = agent.run(vec![ChatMessage::user("Open Notes and Mail")]);
+
+        assert_eq!(run.stopped, StopReason::Answered);
+        assert_eq!(run.answer.as_deref(), Some("Both open"));
+        assert_eq!(statuses(&run), [StepStatus::Preview, StepStatus::Preview]);
+        assert_eq!(run.steps[1].arguments, json!({ "app_name": "Mail" }));
+        assert!(backend.calls().is_empty());
+    }
+
+    #[test]
+    fn rejected_steps_are_not_run() {
+        let _isolated = test_support::isolated(|_| {});
+        let (agent, backend) = agent(
+            vec![open("Terminal"), open("Notes"), answer("Opened Notes instead")],
+            Approval::Confirm,
+            10,
+        );
+        let run_id = agent.run_id.clone();
+        let running = thread::spawn(move || agent.run(vec![ChatMessage::user("Open a terminal")]));
+
+        assert_eq!(pending_step(&run_id), 0);
+        assert!(matches!(
+            approve_agent_step(run_id.clone(), 1, true),
+            Err(AppError::InvalidState(_))
+        ));
+        approve_agent_step(run_id.clone(), 0, false).unwrap();
+        assert_eq!(pending_step(&run_id), 1);
+        approve_agent_step(run_id.clone(), 1, true).unwrap();
+        let run = running.join().unwrap();
+
+        assert_eq!(run.stopped, StopReason::Answered);
+        assert_eq!(statuses(&run), [StepStatus::Rejected, StepStatus::Ok]);
+        assert_eq!(
+            backend.calls(),
+            [AutomationCall::OpenApplication {
+                app_name: "Notes".to_string()
+            }]
+        );
+        assert!(matches!(
+            approve_agent_step(run_id, 0, true),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn callers_can_only_make_approval_stricter() {
+        let _isolated = test_support::isolated(|settings| {
+            settings.ai.provider = "mock".to_string();
+            settings.agent.approval = "confirm".to_string();
+        });
+        let messages = || vec![ChatMessage::user("Open Notes")];
+
+        assert!(matches!(
+            run(messages(), Some(Approval::Auto), None),
+            Err(AppError::PermissionDenied(_))
+        ));
+        let previewed = run(messages(), Some(Approval::Preview), None).unwrap();
+        assert_eq!(previewed.approval, Approval::Preview);
+        assert_eq!(previewed.stopped, StopReason::Answered);
+
+        settings::update(|settings| settings.agent.approval = "auto".to_string()).unwrap();
+        assert_eq!(
+            run(messages(), Some(Approval::Auto), None).unwrap().approval,
+            Approval::Auto
+        );
+    }
+
+    #[test]
+    fn cancelling_ends_a_run_waiting_for_approval() {
+        let _isolated = test_support::isolated(|_| {});
+        let (agent, backend) = agent(vec![open("Notes"), answer("Done")], Approval::Confirm, 10);
+        let run_id = agent.run_id.clone();
+        let running = thread::spawn(move || agent.run(vec![ChatMessage::user("Open Notes")]));
+
+        pending_step(&run_id);
+        cancel_agent(run_id.clone()).unwrap();
+        let run = running.join().unwrap();
+
+        assert_eq!(run.stopped, StopReason::Cancelled);
+        assert_eq!(run.answer, None);
+        assert_eq!(run.error, None);
+        assert_eq!(statuses(&run), [StepStatus::AwaitingApproval]);
+        assert!(backend.calls().is_empty());
+        assert!(matches!(cancel_agent(run_id), Err(AppError::NotFound(_))));
+    }
+}
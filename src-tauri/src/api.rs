@@ -0,0 +1,335 @@
+// src-tauri/src/api.rs
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter};
+
+use crate::{agent, context, deep_link, ledger, local_llm, lock, prompts, resilience, vision};
+use crate::diagnostics::{self, DetectorState};
+use crate::error::{AppError, AppResult};
+use crate::llm::{self, ChatMessage};
+
+/// Methods `call` understands. Most share their name and arguments with the
+/// Tauri command of the same name.
+pub const METHODS: &[&str] = &[
+    "activate",
+    "ask",
+    "chat",
+    "start_chat",
+    "push_context",
+    "capture_context",
+    "move_window",
+    "set_window_size",
+    "set_window_visible",
+    "automate_mac",
+    "list_windows",
+    "clipboard_history",
+    "run_agent",
+    "vision_ask",
+    "list_local_models",
+    "get_llm_usage",
+    "provider_health",
+    "list_prompt_templates",
+    "validate_prompt_template",
+];
+
+/// Arguments of a second launch of the app.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ActivateParams {
+    args: Vec<String>,
+    cwd: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AskParams {
+    prompt: String,
+    /// Prompt template to use instead of the one for the active app.
+    #[serde(default)]
+    template: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatParams {
+    messages: Vec<ChatMessage>,
+    #[serde(default)]
+    template: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ValidatePromptParams {
+    source: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StartChatParams {
+    /// Sent as the first message of the new chat.
+    prompt: Option<String>,
+    thread: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PushContextParams {
+    source: String,
+    #[serde(default)]
+    title: Option<String>,
+    text: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct CaptureContextParams {
+    include_screenshot: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct MoveWindowParams {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Deserialize)]
+struct WindowSizeParams {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Deserialize)]
+struct WindowVisibleParams {
+    visible: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentParams {
+    /// Shorthand for a single user message.
+    #[serde(default)]
+    prompt: Option<String>,
+    #[serde(default)]
+    messages: Vec<ChatMessage>,
+    #[serde(default)]
+    approval: Option<agent::Approval>,
+    #[serde(default)]
+    max_steps: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VisionParams {
+    question: String,
+    #[serde(default)]
+    target: Option<vision::CaptureTarget>,
+    #[serde(default)]
+    capture_id: Option<String>,
+    #[serde(default)]
+    pin: bool,
+}
+
+#[derive(Deserialize)]
+struct LlmUsageParams {
+    #[serde(default = "default_usage_range")]
+    range: String,
+    #[serde(default)]
+    date: Option<String>,
+}
+
+impl Default for LlmUsageParams {
+    fn default() -> Self {
+        Self {
+            range: default_usage_range(),
+            date: None,
+        }
+    }
+}
+
+fn default_usage_range() -> String {
+    "day".to_string()
+}
+
+/// Same arguments as `automate_mac`.
+#[derive(Deserialize)]
+struct AutomateParams {
+    action: String,
+    #[serde(default)]
+    params: Option<String>,
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> AppResult<T> {
+    serde_json::from_value(params).map_err(|e| AppError::InvalidInput(format!("Invalid params: {}", e)))
+}
+
+/// Like `parse`, for methods whose params are all optional.
+fn parse_optional<T: DeserializeOwned + Default>(params: Value) -> AppResult<T> {
+    if params.is_null() {
+        return Ok(T::default());
+    }
+    parse(params)
+}
+
+/// Handle of the running app, for methods that drive its windows.
+fn app_handle() -> AppResult<AppHandle> {
+    crate::APP_HANDLE.read().clone().ok_or_else(|| {
+        AppError::BackendUnavailable("This method needs the Leo AI app to be running".to_string())
+    })
+}
+
+async fn complete(messages: Vec<ChatMessage>, template: Option<String>) -> AppResult<Value> {
+    Ok(json!(llm::chat(messages, template).await?))
+}
+
+async fn ask(prompt: String, template: Option<String>) -> AppResult<Value> {
+    if prompt.trim().is_empty() {
+        return Err(AppError::InvalidInput("Nothing to ask".to_string()));
+    }
+    complete(vec![ChatMessage::user(prompt)], template).await
+}
+
+/// Take over a second launch of the app: show and focus the bubble and pass
+/// on what it was launched with. Links it was opened with are carried out.
+fn activate(params: ActivateParams) -> AppResult<Value> {
+    let app_handle = app_handle()?;
+    tracing::info!(args = params.args.len(), cwd = ?params.cwd, "Activated by a second launch");
+    let window = crate::main_window(&app_handle)?;
+    window.show()?;
+    window.set_focus()?;
+    app_handle.emit("instance_activated", json!({ "args": params.args, "cwd": params.cwd }))?;
+
+    for link in params.args.into_iter().filter(|arg| deep_link::is_link(arg)) {
+        deep_link::open_in_background(link);
+    }
+    Ok(Value::Null)
+}
+
+/// Bring up the bubble with its chat open, as clicking it would.
+pub(crate) fn start_chat(prompt: Option<String>, thread: Option<String>) -> AppResult<Value> {
+    lock::ensure_unlocked()?;
+    let app_handle = app_handle()?;
+    let window = crate::main_window(&app_handle)?;
+    window.show()?;
+    window.set_focus()?;
+    app_handle.emit("chat_requested", json!({ "prompt": prompt, "thread": thread }))?;
+    Ok(Value::Null)
+}
+
+fn push_context(params: PushContextParams) -> AppResult<Value> {
+    if params.text.trim().is_empty() {
+        return Err(AppError::InvalidInput("No context text".to_string()));
+    }
+    let pushed = context::push(params.source, params.title, params.text);
+    // Without a running app the context is still used by this process
+    if let Ok(app_handle) = app_handle() {
+        app_handle.emit("context_pushed", &pushed)?;
+    }
+    Ok(json!(pushed))
+}
+
+fn list_windows() -> AppResult<Value> {
+    if diagnostics::detector_status().state != DetectorState::Ready {
+        return Err(AppError::BackendUnavailable(
+            "The window list comes from the window detector, which is not running".to_string(),
+        ));
+    }
+    Ok(json!({
+        "activeApp": context::current_active_app(),
+        "windows": context::visible_windows(),
+    }))
+}
+
+/// Run one of `METHODS`. The IPC server calls this for requests from the
+/// `leo` CLI and other local tools; the CLI calls it directly when no
+/// instance is running.
+pub async fn call(method: &str, params: Value) -> AppResult<Value> {
+    match method {
+        "activate" => activate(parse_optional(params)?),
+        "ask" => {
+            let AskParams { prompt, template } = parse(params)?;
+            ask(prompt, template).await
+        }
+        "chat" => {
+            let ChatParams { messages, template } = parse(params)?;
+            complete(messages, template).await
+        }
+        "start_chat" => {
+            let StartChatParams { prompt, thread } = parse_optional(params)?;
+            start_chat(prompt, thread)
+        }
+        "push_context" => push_context(parse(params)?),
+        "capture_context" => {
+            let CaptureContextParams { include_screenshot } = parse_optional(params)?;
+            Ok(json!(context::capture_context(include_screenshot).await?))
+        }
+        "move_window" => {
+            let MoveWindowParams { x, y } = parse(params)?;
+            crate::move_window(app_handle()?, x, y)?;
+            Ok(Value::Null)
+        }
+        "set_window_size" => {
+            let WindowSizeParams { width, height } = parse(params)?;
+            crate::set_window_size(app_handle()?, width, height)?;
+            Ok(Value::Null)
+        }
+        "set_window_visible" => {
+            let WindowVisibleParams { visible } = parse(params)?;
+            crate::set_window_visible(app_handle()?, visible)?;
+            Ok(Value::Null)
+        }
+        "automate_mac" => {
+            let AutomateParams { action, params } = parse(params)?;
+            let output = crate::automate_mac(&action, params).await?;
+            Ok(json!({ "output": output }))
+        }
+        "list_windows" => list_windows(),
+        "clipboard_history" => Ok(json!(context::clipboard_history()?)),
+        "run_agent" => {
+            let AgentParams {
+                prompt,
+                mut messages,
+                approval,
+                max_steps,
+            } = parse(params)?;
+            messages.extend(prompt.map(ChatMessage::user));
+            let run = tauri::async_runtime::spawn_blocking(move || agent::run(messages, approval, max_steps)).await??;
+            Ok(json!(run))
+        }
+        // Answered whole; the app's own chat gets the streamed text
+        "vision_ask" => {
+            let VisionParams {
+                question,
+                target,
+                capture_id,
+                pin,
+            } = parse(params)?;
+            let answer = tauri::async_runtime::spawn_blocking(move || {
+                vision::ask(
+                    vision::new_request_id(),
+                    &question,
+                    target.unwrap_or_default(),
+                    capture_id.as_deref(),
+                    pin,
+                    &mut |_| {},
+                )
+            })
+            .await??;
+            Ok(json!(answer))
+        }
+        "list_local_models" => Ok(json!(local_llm::list_local_models().await?)),
+        "get_llm_usage" => {
+            let LlmUsageParams { range, date } = parse_optional(params)?;
+            Ok(json!(ledger::summary(&range, date.as_deref())?))
+        }
+        "provider_health" => Ok(json!(resilience::health())),
+        "list_prompt_templates" => Ok(json!(prompts::list_prompt_templates()?)),
+        "validate_prompt_template" => {
+            let ValidatePromptParams { source } = parse(params)?;
+            Ok(json!(prompts::validate_prompt_template(source)))
+        }
+        other => Err(AppError::NotFound(format!("Unknown method: {}", other))),
+    }
+}
+
+/// `call` for threads outside the async runtime.
+pub fn call_blocking(method: &str, params: Value) -> AppResult<Value> {
+    tauri::async_runtime::block_on(call(method, params))
+}
@@ -0,0 +1,257 @@
+// src-tauri/src/audit.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::error::{AppError, AppResult};
+use crate::settings;
+
+const AUDIT_FILE: &str = "audit.jsonl";
+const DEFAULT_QUERY_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditKind {
+    Command,
+    Llm,
+    Permission,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok,
+    Error,
+    Denied,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub at_ms: i64,
+    pub kind: AuditKind,
+    /// Command name, LLM provider or permission capability.
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub details: Value,
+}
+
+impl AuditEntry {
+    pub fn new(kind: AuditKind, command: &str, outcome: Outcome) -> Self {
+        Self {
+            at_ms: chrono::Utc::now().timestamp_millis(),
+            kind,
+            command: command.to_string(),
+            target: None,
+            outcome,
+            error: None,
+            duration_ms: None,
+            details: Value::Null,
+        }
+    }
+}
+
+struct AuditLog {
+    dir: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+static LOG: Lazy<Mutex<Option<AuditLog>>> = Lazy::new(|| Mutex::new(None));
+
+/// Write the audit log into `log_dir`. Entries recorded before this are
+/// dropped.
+pub fn init(log_dir: &Path) {
+    if let Err(e) = fs::create_dir_all(log_dir) {
+        tracing::warn!("Failed to create {}: {}", log_dir.display(), e);
+    }
+    let size = fs::metadata(log_dir.join(AUDIT_FILE))
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    *LOG.lock() = Some(AuditLog {
+        dir: log_dir.to_path_buf(),
+        file: None,
+        size,
+    });
+}
+
+/// `audit.jsonl` is the current file; `audit.1.jsonl` is the newest rotated
+/// one.
+fn file_path(dir: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join(AUDIT_FILE)
+    } else {
+        dir.join(format!("audit.{}.jsonl", index))
+    }
+}
+
+fn rotate(log: &mut AuditLog, max_files: usize) -> Result<(), String> {
+    log.file = None;
+    let oldest = file_path(&log.dir, max_files);
+    if oldest.exists() {
+        fs::remove_file(&oldest).map_err(|e| e.to_string())?;
+    }
+    for index in (0..max_files).rev() {
+        let from = file_path(&log.dir, index);
+        if from.exists() {
+            fs::rename(&from, file_path(&log.dir, index + 1)).map_err(|e| e.to_string())?;
+        }
+    }
+    log.size = 0;
+    Ok(())
+}
+
+fn append(log: &mut AuditLog, line: &str) -> Result<(), String> {
+    let audit = settings::current().audit;
+    let line_len = line.len() as u64 + 1;
+    if log.size > 0 && log.size + line_len > audit.max_file_kb * 1024 {
+        rotate(log, audit.max_files.max(1))?;
+    }
+
+    if log.file.is_none() {
+        let path = file_path(&log.dir, 0);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        log.file = Some(file);
+    }
+    if let Some(file) = log.file.as_mut() {
+        writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+    }
+    log.size += line_len;
+    Ok(())
+}
+
+pub fn record(entry: AuditEntry) {
+    let mut log = LOG.lock();
+    let Some(log) = log.as_mut() else {
+        return;
+    };
+    let result = serde_json::to_string(&entry)
+        .map_err(|e| e.to_string())
+        .and_then(|line| append(log, &line));
+    if let Err(e) = result {
+        tracing::error!("Failed to write audit log: {}", e);
+    }
+}
+
+fn outcome_of<T>(result: &AppResult<T>) -> Outcome {
+    match result {
+        Ok(_) => Outcome::Ok,
+        Err(e) if e.is_denial() => Outcome::Denied,
+        Err(_) => Outcome::Error,
+    }
+}
+
+/// Record the outcome of a command that touches the OS.
+pub fn record_command<T>(
+    command: &str,
+    target: Option<&str>,
+    details: Value,
+    started: Instant,
+    result: &AppResult<T>,
+) {
+    let outcome = outcome_of(result);
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(_) => tracing::debug!(command, target, duration_ms, "Command finished"),
+        Err(e) => tracing::warn!(command, target, duration_ms, code = e.code(), "Command failed: {}", e),
+    }
+
+    let mut entry = AuditEntry::new(AuditKind::Command, command, outcome);
+    entry.target = target.map(|t| t.to_string());
+    entry.error = result.as_ref().err().map(AppError::to_string);
+    entry.duration_ms = Some(duration_ms);
+    entry.details = details;
+    record(entry);
+}
+
+/// Run a command that touches the OS and record its outcome.
+pub fn track<T>(
+    command: &str,
+    target: Option<&str>,
+    details: Value,
+    run: impl FnOnce() -> AppResult<T>,
+) -> AppResult<T> {
+    let _span = tracing::info_span!("command", name = command).entered();
+    let started = Instant::now();
+    let result = run();
+    record_command(command, target, details, started, &result);
+    result
+}
+
+/// Filters for `query_audit_log`; every field that is set must match.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AuditQuery {
+    pub since_ms: Option<i64>,
+    pub until_ms: Option<i64>,
+    pub kind: Option<AuditKind>,
+    /// Matches commands starting with this, e.g. "automate_mac".
+    pub command: Option<String>,
+    pub outcome: Option<Outcome>,
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.since_ms.is_none_or(|since| entry.at_ms >= since)
+            && self.until_ms.is_none_or(|until| entry.at_ms < until)
+            && self.kind.is_none_or(|kind| entry.kind == kind)
+            && self
+                .command
+                .as_deref()
+                .is_none_or(|command| entry.command.starts_with(command))
+            && self.outcome.is_none_or(|outcome| entry.outcome == outcome)
+    }
+}
+
+/// Matching entries, newest first.
+pub fn query(filter: &AuditQuery) -> AppResult<Vec<AuditEntry>> {
+    let dir = match LOG.lock().as_ref() {
+        Some(log) => log.dir.clone(),
+        None => return Err(AppError::BackendUnavailable("Audit log has not been initialized".to_string())),
+    };
+    let max_files = settings::current().audit.max_files.max(1);
+
+    let mut entries = Vec::new();
+    // Oldest rotated file first, so entries end up in time order
+    for index in (0..=max_files).rev() {
+        let Ok(file) = File::open(file_path(&dir, index)) else {
+            continue;
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            match serde_json::from_str::<AuditEntry>(&line) {
+                Ok(entry) if filter.matches(&entry) => entries.push(entry),
+                Ok(_) => {}
+                // A crash can leave a partial last line
+                Err(e) => tracing::warn!("Skipping unreadable audit entry: {}", e),
+            }
+        }
+    }
+
+    entries.reverse();
+    entries.truncate(filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT));
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn query_audit_log(filter: Option<AuditQuery>) -> AppResult<Vec<AuditEntry>> {
+    query(&filter.unwrap_or_default())
+}
@@ -0,0 +1,569 @@
+// src-tauri/src/automation.rs
+
+use once_cell::sync::Lazy;
+#[cfg(test)]
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+
+/// Screen rectangle of a window, in the same units as `click_at`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl WindowBounds {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
+/// Drives other apps on the user's machine. Every OS-level automation goes
+/// through one of these so it can be swapped for the mock backend.
+pub trait AutomationBackend: Send + Sync {
+    fn name(&self) -> &str;
+    fn open_application(&self, app_name: &str) -> AppResult<()>;
+    fn type_text(&self, text: &str) -> AppResult<()>;
+    /// Press a key chord such as "cmd+shift+4" or "return".
+    fn press_keys(&self, keys: &str) -> AppResult<()>;
+    fn click_button(&self, button_name: &str) -> AppResult<()>;
+    /// Click at screen coordinates in points.
+    fn click_at(&self, x: i32, y: i32) -> AppResult<()>;
+    /// Whether `app_name` has a window, optionally one whose title contains
+    /// `title`.
+    fn window_exists(&self, app_name: &str, title: Option<&str>) -> AppResult<bool>;
+    /// Bounds of the front window of `app_name`, if it has one.
+    fn window_bounds(&self, app_name: &str) -> AppResult<Option<WindowBounds>>;
+}
+
+/// Quote `text` as an AppleScript string literal.
+pub fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Split a chord like "cmd+shift+4" into its modifiers and final key.
+fn split_chord(keys: &str) -> AppResult<(Vec<String>, String)> {
+    let mut parts: Vec<String> = keys
+        .split('+')
+        .map(|part| part.trim().to_lowercase())
+        .filter(|part| !part.is_empty())
+        .collect();
+    let key = parts
+        .pop()
+        .ok_or_else(|| AppError::InvalidInput(format!("Invalid key chord: {}", keys)))?;
+    Ok((parts, key))
+}
+
+pub struct AppleScriptBackend;
+
+impl AppleScriptBackend {
+    fn run_output(&self, script: &str) -> AppResult<String> {
+        let output = Command::new("osascript")
+            .arg("-e")
+            .arg(script)
+            .output()
+            .map_err(|e| AppError::BackendUnavailable(format!("Failed to run osascript: {}", e)))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(AppError::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+        }
+    }
+
+    fn run(&self, script: &str) -> AppResult<()> {
+        self.run_output(script).map(|_| ())
+    }
+
+    fn key_code(key: &str) -> Option<u32> {
+        match key {
+            "return" | "enter" => Some(36),
+            "tab" => Some(48),
+            "space" => Some(49),
+            "delete" | "backspace" => Some(51),
+            "escape" | "esc" => Some(53),
+            "left" => Some(123),
+            "right" => Some(124),
+            "down" => Some(125),
+            "up" => Some(126),
+            _ => None,
+        }
+    }
+}
+
+impl AutomationBackend for AppleScriptBackend {
+    fn name(&self) -> &str {
+        "applescript"
+    }
+
+    fn open_application(&self, app_name: &str) -> AppResult<()> {
+        self.run(&format!(
+            "tell application {} to activate",
+            applescript_string(app_name)
+        ))
+    }
+
+    fn type_text(&self, text: &str) -> AppResult<()> {
+        self.run(&format!(
+            "tell application \"System Events\" to keystroke {}",
+            applescript_string(text)
+        ))
+    }
+
+    fn press_keys(&self, keys: &str) -> AppResult<()> {
+        let (modifiers, key) = split_chord(keys)?;
+        let modifiers = modifiers
+            .iter()
+            .map(|modifier| match modifier.as_str() {
+                "cmd" | "command" => Ok("command down"),
+                "shift" => Ok("shift down"),
+                "alt" | "option" => Ok("option down"),
+                "ctrl" | "control" => Ok("control down"),
+                other => Err(AppError::InvalidInput(format!("Unknown modifier: {}", other))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let press = match Self::key_code(&key) {
+            Some(code) => format!("key code {}", code),
+            None => format!("keystroke {}", applescript_string(&key)),
+        };
+        let using = if modifiers.is_empty() {
+            String::new()
+        } else {
+            format!(" using {{{}}}", modifiers.join(", "))
+        };
+        self.run(&format!(
+            "tell application \"System Events\" to {}{}",
+            press, using
+        ))
+    }
+
+    fn click_button(&self, button_name: &str) -> AppResult<()> {
+        self.run(&format!(
+            "tell application \"System Events\" to click button {} of front window of (first application process whose frontmost is true)",
+            applescript_string(button_name)
+        ))
+    }
+
+    fn click_at(&self, x: i32, y: i32) -> AppResult<()> {
+        self.run(&format!(
+            "tell application \"System Events\" to click at {{{}, {}}}",
+            x, y
+        ))
+    }
+
+    fn window_exists(&self, app_name: &str, title: Option<&str>) -> AppResult<bool> {
+        let process = format!("process {}", applescript_string(app_name));
+        let query = match title {
+            Some(title) => format!(
+                "exists (first window of {} whose name contains {})",
+                process,
+                applescript_string(title)
+            ),
+            None => format!("exists (first window of {})", process),
+        };
+        let script = format!(
+            "tell application \"System Events\"\nif not (exists {}) then return false\nreturn {}\nend tell",
+            process, query
+        );
+        Ok(self.run_output(&script)? == "true")
+    }
+
+    fn window_bounds(&self, app_name: &str) -> AppResult<Option<WindowBounds>> {
+        let process = format!("process {}", applescript_string(app_name));
+        let script = format!(
+            "tell application \"System Events\"\nif not (exists {0}) then return \"\"\ntell {0}\nif (count of windows) is 0 then return \"\"\nset {{x, y}} to position of front window\nset {{w, h}} to size of front window\nreturn (x as text) & \",\" & (y as text) & \",\" & (w as text) & \",\" & (h as text)\nend tell\nend tell",
+            process
+        );
+        let output = self.run_output(&script)?;
+        if output.is_empty() {
+            return Ok(None);
+        }
+        let values = output
+            .split(',')
+            .map(|value| value.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Failed(format!("Unexpected window bounds {}: {}", output, e)))?;
+        match values[..] {
+            [x, y, width, height] => Ok(Some(WindowBounds { x, y, width, height })),
+            _ => Err(AppError::Failed(format!("Unexpected window bounds: {}", output))),
+        }
+    }
+}
+
+/// Directories holding `.desktop` entries, most specific first.
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// The desktop entry whose file name or `Name=` is `app_name`, ignoring
+/// case. Entries that are hidden or not shown in menus do not count.
+fn find_desktop_entry(app_name: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+    let wanted = app_name.to_lowercase();
+    let mut by_name = None;
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let Ok(source) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let field = |key: &str| {
+                source
+                    .lines()
+                    .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            };
+            if field("NoDisplay") == Some("true") || field("Hidden") == Some("true") {
+                continue;
+            }
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            // "org.gnome.Nautilus" is found as "nautilus" too
+            let short = stem.rsplit('.').next().unwrap_or(stem);
+            if stem.to_lowercase() == wanted || short.to_lowercase() == wanted {
+                return Some(path);
+            }
+            if by_name.is_none() && field("Name").is_some_and(|name| name.to_lowercase() == wanted) {
+                by_name = Some(path);
+            }
+        }
+    }
+    by_name
+}
+
+/// Launch a desktop entry with `gtk-launch`, or `gio launch` where GTK's
+/// launcher is not installed.
+fn launch_desktop_entry(entry: &Path) -> AppResult<()> {
+    let id = entry.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let launched = match Command::new("gtk-launch").arg(id).status() {
+        Ok(status) => status.success(),
+        Err(_) => Command::new("gio")
+            .arg("launch")
+            .arg(entry)
+            .status()
+            .map_err(|e| AppError::BackendUnavailable(format!("Failed to launch {}: {}", id, e)))?
+            .success(),
+    };
+    if launched {
+        Ok(())
+    } else {
+        Err(AppError::Failed(format!("Failed to launch {}", id)))
+    }
+}
+
+/// X11 backend built on the `xdotool` CLI.
+pub struct XdotoolBackend;
+
+impl XdotoolBackend {
+    fn run_output(&self, args: &[&str]) -> AppResult<String> {
+        let output = Command::new("xdotool")
+            .args(args)
+            .output()
+            .map_err(|e| AppError::BackendUnavailable(format!("Failed to run xdotool: {}", e)))?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        } else {
+            Err(AppError::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+        }
+    }
+
+    fn run(&self, args: &[&str]) -> AppResult<()> {
+        self.run_output(args).map(|_| ())
+    }
+}
+
+impl AutomationBackend for XdotoolBackend {
+    fn name(&self) -> &str {
+        "xdotool"
+    }
+
+    fn open_application(&self, app_name: &str) -> AppResult<()> {
+        // App names come from the webview, the agent and IPC clients, so only
+        // installed desktop entries are launched, never a binary or path
+        if app_name.trim().is_empty() || app_name.contains('/') || app_name.starts_with('-') {
+            return Err(AppError::InvalidInput(format!("Invalid app name: {}", app_name)));
+        }
+
+        // Focus a running window first, otherwise launch its desktop entry
+        if self
+            .run(&["search", "--onlyvisible", "--class", app_name, "windowactivate"])
+            .is_ok()
+        {
+            return Ok(());
+        }
+        let entry = find_desktop_entry(app_name, &application_dirs())
+            .ok_or_else(|| AppError::NotFound(format!("No installed app named {}", app_name)))?;
+        launch_desktop_entry(&entry)
+    }
+
+    fn type_text(&self, text: &str) -> AppResult<()> {
+        self.run(&["type", "--clearmodifiers", "--", text])
+    }
+
+    fn press_keys(&self, keys: &str) -> AppResult<()> {
+        let (modifiers, key) = split_chord(keys)?;
+        let mut chord: Vec<&str> = modifiers
+            .iter()
+            .map(|modifier| match modifier.as_str() {
+                "cmd" | "command" | "super" => "super",
+                "alt" | "option" => "alt",
+                "ctrl" | "control" => "ctrl",
+                other => other,
+            })
+            .collect();
+        let key = match key.as_str() {
+            "enter" | "return" => "Return",
+            "tab" => "Tab",
+            "space" => "space",
+            "delete" | "backspace" => "BackSpace",
+            "escape" | "esc" => "Escape",
+            "left" => "Left",
+            "right" => "Right",
+            "up" => "Up",
+            "down" => "Down",
+            other => other,
+        };
+        chord.push(key);
+        self.run(&["key", "--clearmodifiers", &chord.join("+")])
+    }
+
+    fn click_button(&self, button_name: &str) -> AppResult<()> {
+        Err(AppError::BackendUnavailable(format!(
+            "Clicking buttons by name ({}) is not supported by xdotool",
+            button_name
+        )))
+    }
+
+    fn click_at(&self, x: i32, y: i32) -> AppResult<()> {
+        self.run(&["mousemove", &x.to_string(), &y.to_string(), "click", "1"])
+    }
+
+    fn window_exists(&self, app_name: &str, title: Option<&str>) -> AppResult<bool> {
+        // xdotool exits non-zero when the search finds nothing
+        let found = match title {
+            Some(title) => self.run(&["search", "--onlyvisible", "--name", title]),
+            None => self.run(&["search", "--onlyvisible", "--class", app_name]),
+        };
+        Ok(found.is_ok())
+    }
+
+    fn window_bounds(&self, app_name: &str) -> AppResult<Option<WindowBounds>> {
+        let Ok(output) = self.run_output(&[
+            "search",
+            "--onlyvisible",
+            "--limit",
+            "1",
+            "--class",
+            app_name,
+            "getwindowgeometry",
+            "--shell",
+        ]) else {
+            return Ok(None);
+        };
+
+        // Lines like "X=10", "Y=20", "WIDTH=800", "HEIGHT=600"
+        let field = |name: &str| {
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+                .and_then(|value| value.trim().parse::<i32>().ok())
+                .ok_or_else(|| AppError::Failed(format!("Missing {} in xdotool output", name)))
+        };
+        Ok(Some(WindowBounds {
+            x: field("X")?,
+            y: field("Y")?,
+            width: field("WIDTH")?,
+            height: field("HEIGHT")?,
+        }))
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "call", rename_all = "snake_case")]
+pub enum AutomationCall {
+    OpenApplication { app_name: String },
+    TypeText { text: String },
+    PressKeys { keys: String },
+    ClickButton { button_name: String },
+    ClickAt { x: i32, y: i32 },
+}
+
+/// Where the mock pretends every opened app has its window.
+#[cfg(test)]
+pub const MOCK_WINDOW_BOUNDS: WindowBounds = WindowBounds {
+    x: 100,
+    y: 80,
+    width: 1280,
+    height: 800,
+};
+
+/// Records calls instead of touching the OS; the backend of every test.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockBackend {
+    calls: Mutex<Vec<AutomationCall>>,
+    failures: Mutex<usize>,
+}
+
+#[cfg(test)]
+impl MockBackend {
+    pub fn calls(&self) -> Vec<AutomationCall> {
+        self.calls.lock().clone()
+    }
+
+    /// Fail the next `count` calls instead of recording them.
+    pub fn fail_next(&self, count: usize) {
+        *self.failures.lock() = count;
+    }
+
+    fn record(&self, call: AutomationCall) -> AppResult<()> {
+        let mut failures = self.failures.lock();
+        if *failures > 0 {
+            *failures -= 1;
+            return Err(AppError::Failed(format!("Mock automation failed: {:?}", call)));
+        }
+        tracing::debug!("Mock automation: {:?}", call);
+        self.calls.lock().push(call);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl AutomationBackend for MockBackend {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn open_application(&self, app_name: &str) -> AppResult<()> {
+        self.record(AutomationCall::OpenApplication {
+            app_name: app_name.to_string(),
+        })
+    }
+
+    fn type_text(&self, text: &str) -> AppResult<()> {
+        self.record(AutomationCall::TypeText {
+            text: text.to_string(),
+        })
+    }
+
+    fn press_keys(&self, keys: &str) -> AppResult<()> {
+        self.record(AutomationCall::PressKeys {
+            keys: keys.to_string(),
+        })
+    }
+
+    fn click_button(&self, button_name: &str) -> AppResult<()> {
+        self.record(AutomationCall::ClickButton {
+            button_name: button_name.to_string(),
+        })
+    }
+
+    fn click_at(&self, x: i32, y: i32) -> AppResult<()> {
+        self.record(AutomationCall::ClickAt { x, y })
+    }
+
+    /// Apps opened through the mock count as having a window.
+    fn window_exists(&self, app_name: &str, _title: Option<&str>) -> AppResult<bool> {
+        Ok(self.calls.lock().iter().any(|call| {
+            matches!(call, AutomationCall::OpenApplication { app_name: opened } if opened == app_name)
+        }))
+    }
+
+    fn window_bounds(&self, app_name: &str) -> AppResult<Option<WindowBounds>> {
+        Ok(self
+            .window_exists(app_name, None)?
+            .then_some(MOCK_WINDOW_BOUNDS))
+    }
+}
+
+#[cfg(test)]
+pub static MOCK_BACKEND: Lazy<Arc<MockBackend>> = Lazy::new(|| Arc::new(MockBackend::default()));
+
+#[cfg_attr(test, allow(dead_code))]
+fn platform_backend() -> Arc<dyn AutomationBackend> {
+    if cfg!(target_os = "macos") {
+        Arc::new(AppleScriptBackend)
+    } else {
+        Arc::new(XdotoolBackend)
+    }
+}
+
+#[cfg(not(test))]
+static BACKEND: Lazy<Arc<dyn AutomationBackend>> = Lazy::new(platform_backend);
+
+// Tests never drive the real desktop
+#[cfg(test)]
+static BACKEND: Lazy<Arc<dyn AutomationBackend>> = Lazy::new(|| MOCK_BACKEND.clone());
+
+/// The automation backend for this process.
+pub fn backend() -> Arc<dyn AutomationBackend> {
+    BACKEND.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_entry(dir: &Path, file: &str, contents: &str) {
+        fs::write(dir.join(file), format!("[Desktop Entry]\nType=Application\n{}", contents)).unwrap();
+    }
+
+    #[test]
+    fn finds_desktop_entries_by_file_name_or_name() {
+        let dir = tempfile::tempdir().unwrap();
+        write_entry(dir.path(), "firefox.desktop", "Name=Firefox\nExec=firefox %u\n");
+        write_entry(dir.path(), "org.gnome.Nautilus.desktop", "Name=Files\nExec=nautilus\n");
+        write_entry(dir.path(), "hidden.desktop", "Name=Hidden\nNoDisplay=true\n");
+        let dirs = [dir.path().to_path_buf()];
+
+        let found = |name: &str| {
+            find_desktop_entry(name, &dirs).map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        };
+        assert_eq!(found("Firefox").as_deref(), Some("firefox.desktop"));
+        assert_eq!(found("nautilus").as_deref(), Some("org.gnome.Nautilus.desktop"));
+        assert_eq!(found("files").as_deref(), Some("org.gnome.Nautilus.desktop"));
+        assert_eq!(found("hidden"), None);
+        assert_eq!(found("sh"), None);
+    }
+
+    #[test]
+    fn refuses_paths_and_options_as_app_names() {
+        for name in ["/bin/sh", "../../bin/sh", "--help", "  "] {
+            assert!(XdotoolBackend.open_application(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn tests_only_reach_the_mock_backend() {
+        backend().open_application("Safari").unwrap();
+        assert_eq!(backend().name(), "mock");
+        assert!(MOCK_BACKEND.calls().contains(&AutomationCall::OpenApplication {
+            app_name: "Safari".to_string()
+        }));
+        assert_eq!(backend().window_bounds("Safari").unwrap(), Some(MOCK_WINDOW_BOUNDS));
+    }
+}
@@ -0,0 +1,358 @@
+// src-tauri/src/bin/leo.rs
+
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+use std::process::ExitCode;
+
+use leo_ai_lib::error::{AppError, AppResult};
+use leo_ai_lib::{api, headless, ipc};
+
+/// Drive Leo AI from a terminal. Commands go to the running app when there
+/// is one and run in this process otherwise.
+#[derive(Parser)]
+#[command(name = "leo", version)]
+struct Cli {
+    /// Print results as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    /// Run in this process even when the app is running.
+    #[arg(long, global = true)]
+    local: bool,
+    /// Log to stderr.
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Ask the configured model.
+    Ask {
+        #[arg(required = true)]
+        prompt: Vec<String>,
+        /// Prompt template to use instead of the one for the active app.
+        #[arg(short, long)]
+        template: Option<String>,
+    },
+    /// Ask the vision model about the active window.
+    Vision {
+        #[arg(required = true)]
+        question: Vec<String>,
+        /// Drag out a part of the screen instead.
+        #[arg(long)]
+        region: bool,
+    },
+    /// Models the local model server offers.
+    Models,
+    /// Whether the running app is skipping LLM providers that kept failing.
+    Providers,
+    /// Prompt templates, the apps they are bound to and their problems.
+    Prompts,
+    /// LLM tokens and costs, with the budget left.
+    Usage {
+        #[arg(long, default_value = "day", value_parser = ["day", "week", "month"])]
+        range: String,
+        /// A day in the range, as YYYY-MM-DD. Defaults to today.
+        #[arg(long)]
+        date: Option<String>,
+    },
+    /// Let the model carry out a task with the automation tools.
+    Agent {
+        #[arg(required = true)]
+        prompt: Vec<String>,
+        /// "auto" runs each step, "preview" only shows them. Cannot be looser
+        /// than the app's setting.
+        #[arg(long, default_value = "preview", value_parser = ["auto", "preview", "confirm"])]
+        approval: String,
+        /// Stop after this many tool calls.
+        #[arg(long)]
+        max_steps: Option<usize>,
+    },
+    /// Run an action, as the bubble's buttons do.
+    #[command(subcommand)]
+    Automate(Automate),
+    /// Windows the running app knows about.
+    #[command(subcommand)]
+    Windows(Windows),
+    /// Clipboard history kept by the running app.
+    #[command(subcommand)]
+    Clipboard(Clipboard),
+}
+
+#[derive(Subcommand)]
+enum Automate {
+    /// Open or focus an app.
+    OpenApp { name: String },
+    /// Summarize the selection.
+    Summarize {
+        /// Paste the summary over the selection.
+        #[arg(long)]
+        paste: bool,
+    },
+    /// Translate the selection.
+    Translate {
+        /// Paste the translation over the selection.
+        #[arg(long)]
+        paste: bool,
+    },
+    /// Run a workflow by name.
+    Workflow { name: String },
+    /// Start, stop or toggle dictation.
+    Dictation {
+        #[arg(default_value = "toggle", value_parser = ["start", "stop", "toggle"])]
+        mode: String,
+    },
+    /// Start, stop or toggle macro recording.
+    Record {
+        #[arg(default_value = "toggle", value_parser = ["start", "stop", "toggle"])]
+        mode: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum Windows {
+    /// Windows on the current space, front to back.
+    List,
+}
+
+#[derive(Subcommand)]
+enum Clipboard {
+    /// Recently copied texts, newest first.
+    History {
+        /// Show at most this many entries.
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+}
+
+impl Command {
+    /// The `api` method and params this command maps to.
+    fn request(&self) -> (&'static str, Value) {
+        match self {
+            Command::Ask { prompt, template } => ("ask", json!({ "prompt": prompt.join(" "), "template": template })),
+            Command::Vision { question, region } => (
+                "vision_ask",
+                json!({
+                    "question": question.join(" "),
+                    "target": if *region { "region" } else { "active_window" },
+                }),
+            ),
+            Command::Agent {
+                prompt,
+                approval,
+                max_steps,
+            } => (
+                "run_agent",
+                json!({ "prompt": prompt.join(" "), "approval": approval, "maxSteps": max_steps }),
+            ),
+            Command::Automate(automate) => {
+                let (action, params) = automate.action();
+                ("automate_mac", json!({ "action": action, "params": params }))
+            }
+            Command::Models => ("list_local_models", Value::Null),
+            Command::Providers => ("provider_health", Value::Null),
+            Command::Prompts => ("list_prompt_templates", Value::Null),
+            Command::Usage { range, date } => ("get_llm_usage", json!({ "range": range, "date": date })),
+            Command::Windows(Windows::List) => ("list_windows", Value::Null),
+            Command::Clipboard(Clipboard::History { .. }) => ("clipboard_history", Value::Null),
+        }
+    }
+
+    /// Commands that only make sense inside the long-running app: its state
+    /// would be gone as soon as this process exits.
+    fn needs_app(&self) -> bool {
+        matches!(
+            self,
+            Command::Windows(_)
+                | Command::Clipboard(_)
+                | Command::Providers
+                | Command::Automate(Automate::Dictation { .. } | Automate::Record { .. })
+        )
+    }
+}
+
+impl Automate {
+    /// Action and params as `automate_mac` takes them.
+    fn action(&self) -> (&'static str, Option<String>) {
+        let selection = |paste: bool| Some(if paste { "selection:paste" } else { "selection" }.to_string());
+        match self {
+            Automate::OpenApp { name } => ("open_app", Some(name.clone())),
+            Automate::Summarize { paste } => ("summarize", selection(*paste)),
+            Automate::Translate { paste } => ("translate", selection(*paste)),
+            Automate::Workflow { name } => ("workflow", Some(name.clone())),
+            Automate::Dictation { mode } => ("dictation", Some(mode.clone())),
+            Automate::Record { mode } => ("record", Some(mode.clone())),
+        }
+    }
+}
+
+fn execute(cli: &Cli) -> AppResult<Value> {
+    let (method, params) = cli.command.request();
+    if !cli.local {
+        if let Some(result) = ipc::call(method, params.clone()) {
+            return result;
+        }
+    }
+    if cli.command.needs_app() {
+        return Err(AppError::BackendUnavailable(
+            "This command needs the Leo AI app to be running".to_string(),
+        ));
+    }
+
+    headless::init();
+    api::call_blocking(method, params)
+}
+
+fn text<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn print_human(command: &Command, value: &Value) {
+    match command {
+        Command::Ask { .. } | Command::Vision { .. } => println!("{}", text(value, "text").trim()),
+        Command::Models => {
+            for model in value.as_array().into_iter().flatten() {
+                match model.get("contextWindow").and_then(Value::as_u64) {
+                    Some(tokens) => println!("{}\t{} tokens", text(model, "id"), tokens),
+                    None => println!("{}", text(model, "id")),
+                }
+            }
+        }
+        Command::Providers => {
+            for provider in value.as_array().into_iter().flatten() {
+                let retry = match provider.get("retryInMs").and_then(Value::as_u64) {
+                    Some(ms) => format!("\tretrying in {}s", ms.div_ceil(1000)),
+                    None => String::new(),
+                };
+                println!(
+                    "{}\t{}\t{} failures{}",
+                    text(provider, "provider"),
+                    text(provider, "state"),
+                    provider["consecutiveFailures"],
+                    retry
+                );
+            }
+        }
+        Command::Prompts => {
+            for template in value["templates"].as_array().into_iter().flatten() {
+                let strings = |key: &str| -> Vec<&str> {
+                    let values = template[key].as_array().into_iter().flatten();
+                    values.filter_map(Value::as_str).collect()
+                };
+                let default = match template["isDefault"].as_bool() {
+                    Some(true) => " (default)",
+                    _ => "",
+                };
+                let source = template["file"].as_str().unwrap_or("built-in");
+                let apps = strings("apps").join(", ");
+                println!("{}{}\t{}\t{}", text(template, "name"), default, source, apps);
+                for problem in strings("problems") {
+                    println!("  {}", problem);
+                }
+            }
+            for problem in value["bindingProblems"].as_array().into_iter().flatten() {
+                println!("{}", problem.as_str().unwrap_or_default());
+            }
+        }
+        Command::Usage { .. } => {
+            let usage_line = |group: &Value| {
+                format!(
+                    "{}\t{} requests\t{} in / {} out\t${:.4}",
+                    text(group, "key"),
+                    group["requests"],
+                    group["promptTokens"],
+                    group["completionTokens"],
+                    group["costUsd"].as_f64().unwrap_or_default()
+                )
+            };
+            println!("{}", usage_line(&value["total"]));
+            for groups in ["byProvider", "byModel", "byFeature"] {
+                for group in value[groups].as_array().into_iter().flatten() {
+                    println!("  {}", usage_line(group));
+                }
+            }
+            if let Some(unpriced) = value["unpricedRequests"].as_u64().filter(|count| *count > 0) {
+                println!("{} requests to models without a price are not in the costs", unpriced);
+            }
+            let budget = &value["budget"];
+            for (name, spent, limit) in [
+                ("Today", "dailySpentUsd", "dailyLimitUsd"),
+                ("This month", "monthlySpentUsd", "monthlyLimitUsd"),
+            ] {
+                let spent = budget[spent].as_f64().unwrap_or_default();
+                match budget[limit].as_f64() {
+                    Some(limit) => println!("{}: ${:.2} of ${:.2}", name, spent, limit),
+                    None => println!("{}: ${:.2}, no limit", name, spent),
+                }
+            }
+        }
+        Command::Agent { .. } => {
+            for step in value.get("steps").and_then(Value::as_array).into_iter().flatten() {
+                let detail = step.get("error").and_then(Value::as_str).unwrap_or(text(step, "output"));
+                println!("{}\t{}\t{}\t{}", text(step, "status"), text(step, "tool"), step["arguments"], detail);
+            }
+            match value.get("answer").and_then(Value::as_str) {
+                Some(answer) => println!("{}", answer),
+                None => println!("Stopped ({}): {}", text(value, "stopped"), text(value, "error")),
+            }
+        }
+        Command::Automate(_) => {
+            let output = text(value, "output");
+            println!("{}", if output.is_empty() { "Done" } else { output });
+        }
+        Command::Windows(Windows::List) => {
+            let windows = value.get("windows").and_then(Value::as_array);
+            for window in windows.into_iter().flatten() {
+                println!("{}\t{}", text(window, "app"), text(window, "title"));
+            }
+        }
+        Command::Clipboard(Clipboard::History { .. }) => {
+            for entry in value.as_array().into_iter().flatten() {
+                let copied_at = entry
+                    .get("copiedAtMs")
+                    .and_then(Value::as_i64)
+                    .and_then(chrono::DateTime::from_timestamp_millis)
+                    .map(|at| at.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+                    .unwrap_or_default();
+                println!("{}  {}", copied_at, text(entry, "text").replace('\n', "⏎"));
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if cli.verbose {
+        headless::init_logging();
+    }
+
+    let result = execute(&cli).map(|mut value| {
+        if let Command::Clipboard(Clipboard::History { limit: Some(limit) }) = &cli.command {
+            if let Some(entries) = value.as_array_mut() {
+                entries.truncate(*limit);
+            }
+        }
+        value
+    });
+
+    match result {
+        Ok(value) if cli.json => {
+            println!("{}", serde_json::to_string_pretty(&value).unwrap_or_default());
+            ExitCode::SUCCESS
+        }
+        Ok(value) => {
+            print_human(&cli.command, &value);
+            ExitCode::SUCCESS
+        }
+        Err(e) if cli.json => {
+            let error = json!({ "error": e });
+            println!("{}", serde_json::to_string_pretty(&error).unwrap_or_default());
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("leo: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,669 @@
+// src-tauri/src/context.rs
+
+use base64::Engine;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::fs;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(target_os = "macos")]
+use std::thread;
+#[cfg(target_os = "macos")]
+use std::time::Duration;
+
+use crate::audit;
+use crate::error::{AppError, AppResult};
+use crate::permissions::{self, Capability};
+use crate::settings;
+
+#[cfg(target_os = "macos")]
+const CLIPBOARD_SETTLE_DELAY: Duration = Duration::from_millis(150);
+#[cfg(target_os = "macos")]
+const CLIPBOARD_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveApp {
+    pub name: String,
+    pub path: Option<String>,
+    pub pid: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub enum SelectionSource {
+    Accessibility,
+    Clipboard,
+    PrimarySelection,
+}
+
+/// Structured answer to "what is the user looking at". Fields blocked by the
+/// per-app rules are left empty and listed in `withheld`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextSnapshot {
+    pub captured_at_ms: i64,
+    pub active_app: Option<ActiveApp>,
+    pub window_title: Option<String>,
+    pub selected_text: Option<String>,
+    pub selection_source: Option<SelectionSource>,
+    /// PNG data URL of the active window.
+    pub screenshot: Option<String>,
+    pub withheld: Vec<String>,
+    /// Names of the redaction hooks that modified this snapshot.
+    pub redacted_by: Vec<String>,
+    /// Last context an editor plugin or script pushed over IPC.
+    pub pushed: Option<PushedContext>,
+}
+
+/// Last state reported by the window detector and the active-app observer.
+#[derive(Default)]
+struct WindowInventory {
+    active_app: Option<ActiveApp>,
+    windows: Vec<Value>,
+}
+
+static WINDOW_INVENTORY: Lazy<RwLock<WindowInventory>> =
+    Lazy::new(|| RwLock::new(WindowInventory::default()));
+
+/// A window on the current space, as listed by `visible_windows`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowSummary {
+    pub app: Option<String>,
+    /// Left out when the context rules withhold the app's window titles.
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardEntry {
+    pub copied_at_ms: i64,
+    pub text: String,
+    /// App that was active when the text was copied.
+    pub app: Option<String>,
+}
+
+/// Context from outside the app, e.g. the file open in an editor.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushedContext {
+    pub pushed_at_ms: i64,
+    /// Who pushed it, e.g. "vscode".
+    pub source: String,
+    pub title: Option<String>,
+    pub text: String,
+}
+
+static PUSHED_CONTEXT: Lazy<RwLock<Option<PushedContext>>> = Lazy::new(|| RwLock::new(None));
+
+static CLIPBOARD_HISTORY: Lazy<Mutex<VecDeque<ClipboardEntry>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+static CLIPBOARD_WATCHING: AtomicBool = AtomicBool::new(false);
+
+type RedactionHook = Box<dyn Fn(&mut ContextSnapshot) -> bool + Send + Sync>;
+
+static REDACTION_HOOKS: Lazy<RwLock<Vec<(&'static str, RedactionHook)>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Register a hook that runs on every snapshot before it is returned. The
+/// hook returns `true` when it changed something.
+pub fn register_redaction_hook<F>(name: &'static str, hook: F)
+where
+    F: Fn(&mut ContextSnapshot) -> bool + Send + Sync + 'static,
+{
+    REDACTION_HOOKS.write().push((name, Box::new(hook)));
+}
+
+/// Install the built-in redaction hooks.
+pub fn init() {
+    register_redaction_hook("selection_limit", |snapshot| {
+        let limit = settings::current().context.max_selection_chars;
+        match snapshot.selected_text.as_mut() {
+            Some(text) if text.chars().count() > limit => {
+                *text = text.chars().take(limit).collect();
+                true
+            }
+            _ => false,
+        }
+    });
+}
+
+/// Cache the window state payloads emitted by the Python detector.
+pub fn update_window_inventory(window_info: &Value) {
+    let mut inventory = WINDOW_INVENTORY.write();
+
+    if let Some(active_app) = window_info.get("active_app") {
+        if let Some(name) = active_app.get("name").and_then(|v| v.as_str()) {
+            inventory.active_app = Some(ActiveApp {
+                name: name.to_string(),
+                path: active_app
+                    .get("path")
+                    .and_then(|v| v.as_str())
+                    .filter(|p| !p.is_empty())
+                    .map(|p| p.to_string()),
+                pid: active_app.get("pid").and_then(|v| v.as_i64()).filter(|pid| *pid > 0),
+            });
+        }
+    }
+
+    if let Some(windows) = window_info.get("windows").and_then(|v| v.as_array()) {
+        inventory.windows = windows.clone();
+    }
+}
+
+fn string_field<'a>(window: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| window.get(*key).and_then(|v| v.as_str()))
+        .filter(|s| !s.is_empty())
+}
+
+/// Frontmost window belonging to `app_name`; the detector lists windows
+/// front to back. X11 window classes are often lowercase, so the case of
+/// the name does not matter.
+fn front_window_of(windows: &[Value], app_name: &str) -> Option<Value> {
+    windows
+        .iter()
+        .find(|window| {
+            string_field(window, &["owner", "kCGWindowOwnerName"])
+                .is_some_and(|owner| owner.eq_ignore_ascii_case(app_name))
+        })
+        .cloned()
+}
+
+pub fn current_active_app() -> Option<ActiveApp> {
+    let cached = WINDOW_INVENTORY.read().active_app.clone();
+
+    #[cfg(target_os = "macos")]
+    if let Some(name) = crate::get_active_app_from_menubar() {
+        return Some(match cached {
+            Some(app) if app.name == name => app,
+            _ => ActiveApp {
+                name,
+                ..ActiveApp::default()
+            },
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(app) = active_app_from_xdotool() {
+        return Some(app);
+    }
+
+    cached
+}
+
+/// The app of the focused X11 window, named by its window class.
+#[cfg(target_os = "linux")]
+fn active_app_from_xdotool() -> Option<ActiveApp> {
+    let output = Command::new("xdotool")
+        .args(["getactivewindow", "getwindowclassname", "getwindowpid"])
+        .output()
+        .ok()?;
+    // Windows without _NET_WM_PID still print their class before failing
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim);
+    let name = lines.next().filter(|name| !name.is_empty())?.to_string();
+    Some(ActiveApp {
+        name,
+        path: None,
+        pid: lines.next().and_then(|pid| pid.parse().ok()),
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn front_window_title_from_system() -> Option<String> {
+    let script = r#"
+        tell application "System Events"
+            get name of front window of (first application process whose frontmost is true)
+        end tell
+    "#;
+    crate::osascript(script)
+        .ok()
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn front_window_title_from_system() -> Option<String> {
+    None
+}
+
+fn active_window_title(front_window: Option<&Value>) -> Option<String> {
+    front_window
+        .and_then(|window| string_field(window, &["title", "name", "kCGWindowName"]))
+        .map(|title| title.to_string())
+        .or_else(front_window_title_from_system)
+}
+
+/// Read the system clipboard as text.
+#[cfg(target_os = "macos")]
+pub fn read_clipboard() -> Option<String> {
+    let output = Command::new("pbpaste").output().ok()?;
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Replace the system clipboard contents with `text`.
+#[cfg(target_os = "macos")]
+pub fn write_clipboard(text: &str) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new("pbcopy")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(stdin) = child.stdin.as_mut() {
+        stdin.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    child.wait().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn selection_from_accessibility() -> Option<String> {
+    let script = r#"
+        tell application "System Events"
+            set frontProc to first application process whose frontmost is true
+            get value of attribute "AXSelectedText" of (value of attribute "AXFocusedUIElement" of frontProc)
+        end tell
+    "#;
+    crate::osascript(script)
+        .ok()
+        .map(|text| text.trim_end_matches('\n').to_string())
+        .filter(|text| !text.is_empty() && text != "missing value")
+}
+
+/// Clipboard types `write_clipboard` puts back as they were, as AppleScript's
+/// `clipboard info` names them.
+#[cfg(any(target_os = "macos", test))]
+const PLAIN_TEXT_TYPES: &[&str] = &[
+    "«class utf8»",
+    "«class ut16»",
+    "«class utxt»",
+    "«class ustl»",
+    "string",
+    "Unicode text",
+    "international text",
+    "scrap styles",
+];
+
+/// Whether `clipboard info` lists nothing but plain text. An empty clipboard
+/// counts.
+#[cfg(any(target_os = "macos", test))]
+fn only_plain_text(clipboard_info: &str) -> bool {
+    clipboard_info
+        .split(", ")
+        .step_by(2)
+        .map(str::trim)
+        .filter(|kind| !kind.is_empty())
+        .all(|kind| PLAIN_TEXT_TYPES.contains(&kind))
+}
+
+/// Copy the selection with ⌘C and restore the previous clipboard afterwards.
+/// Only text can be restored, so with an image, files or rich text on the
+/// clipboard this is skipped rather than losing them.
+#[cfg(target_os = "macos")]
+fn selection_from_clipboard() -> Option<String> {
+    match crate::osascript("clipboard info") {
+        Ok(info) if only_plain_text(&info) => {}
+        Ok(_) => {
+            tracing::info!(
+                "Not copying the selection: the clipboard holds more than text, which could not be restored"
+            );
+            return None;
+        }
+        Err(e) => {
+            tracing::warn!("Not copying the selection: cannot tell what the clipboard holds: {}", e);
+            return None;
+        }
+    }
+    let saved = read_clipboard();
+    // Clear first so an empty selection is not mistaken for the old clipboard
+    write_clipboard("").ok()?;
+
+    let copied = crate::osascript(
+        r#"tell application "System Events" to keystroke "c" using command down"#,
+    )
+    .ok()
+    .and_then(|_| {
+        thread::sleep(CLIPBOARD_SETTLE_DELAY);
+        read_clipboard()
+    });
+
+    if let Some(saved) = saved {
+        if let Err(e) = write_clipboard(&saved) {
+            tracing::warn!("Failed to restore clipboard: {}", e);
+        }
+    }
+
+    copied.filter(|text| !text.is_empty())
+}
+
+/// Grab the current selection through accessibility, falling back to a
+/// clipboard round-trip with `SelectionGrab::Copy`.
+#[cfg(target_os = "macos")]
+pub fn capture_selection(grab: SelectionGrab) -> Option<(String, SelectionSource)> {
+    selection_from_accessibility()
+        .map(|text| (text, SelectionSource::Accessibility))
+        .or_else(|| {
+            (grab == SelectionGrab::Copy)
+                .then(selection_from_clipboard)
+                .flatten()
+                .map(|text| (text, SelectionSource::Clipboard))
+        })
+}
+
+/// Grab the current selection from the X11 primary selection, which needs
+/// no copying.
+#[cfg(not(target_os = "macos"))]
+pub fn capture_selection(_grab: SelectionGrab) -> Option<(String, SelectionSource)> {
+    let output = if which::which("xclip").is_ok() {
+        Command::new("xclip").args(["-o", "-selection", "primary"]).output()
+    } else if which::which("xsel").is_ok() {
+        Command::new("xsel").args(["-o", "-p"]).output()
+    } else {
+        return None;
+    };
+
+    output
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .filter(|text| !text.is_empty())
+        .map(|text| (text, SelectionSource::PrimarySelection))
+}
+
+pub(crate) fn screenshot_command(front_window: Option<&Value>, path: &std::path::Path) -> Option<Command> {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("screencapture");
+        command.args(["-x", "-o"]);
+        let window_id = front_window.and_then(|window| {
+            ["id", "kCGWindowNumber"]
+                .iter()
+                .find_map(|key| window.get(*key).and_then(|v| v.as_i64()))
+        });
+        match window_id {
+            Some(id) => command.arg(format!("-l{}", id)),
+            // Without a window id fall back to the main display
+            None => command.arg("-m"),
+        };
+        command.arg(path);
+        Some(command)
+    } else if which::which("import").is_ok() {
+        // Never the root window, which would show every other app as well
+        let window_id = focused_x11_window()?;
+        let mut command = Command::new("import");
+        command.args(["-window", &window_id]).arg(path);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+/// Id of the focused X11 window.
+fn focused_x11_window() -> Option<String> {
+    let output = Command::new("xdotool").arg("getactivewindow").output().ok()?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !id.is_empty()).then_some(id)
+}
+
+/// Capture the active window as a PNG data URL. The file goes into a
+/// private temp dir, removed with it, so other users cannot read it.
+fn capture_screenshot(front_window: Option<&Value>) -> Option<String> {
+    let dir = tempfile::Builder::new().prefix("leo-context-").tempdir().ok()?;
+    let path = dir.path().join("screenshot.png");
+    let mut command = screenshot_command(front_window, &path)?;
+
+    let status = command.status().ok()?;
+    let bytes = if status.success() { fs::read(&path).ok() } else { None };
+
+    bytes.map(|bytes| {
+        format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        )
+    })
+}
+
+fn apply_redaction_hooks(snapshot: &mut ContextSnapshot) {
+    let hooks = REDACTION_HOOKS.read();
+    for (name, hook) in hooks.iter() {
+        if hook(snapshot) {
+            snapshot.redacted_by.push(name.to_string());
+        }
+    }
+}
+
+/// Build a snapshot of the active app, honouring the privacy exclusion list
+/// and per-app context permissions before anything is captured.
+pub fn snapshot(include_screenshot: bool) -> ContextSnapshot {
+    snapshot_with(SelectionGrab::Copy, include_screenshot)
+}
+
+/// How far a snapshot goes to get the selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionGrab {
+    Skip,
+    /// Only what can be read without touching the app.
+    Read,
+    /// Copy it with a synthetic ⌘C when it cannot be read.
+    Copy,
+}
+
+/// Like `snapshot`, with a say in how the selection is grabbed: copying it
+/// means a round-trip through the clipboard.
+pub fn snapshot_with(selection: SelectionGrab, include_screenshot: bool) -> ContextSnapshot {
+    let app_settings = settings::current();
+    let mut snapshot = ContextSnapshot {
+        captured_at_ms: chrono::Local::now().timestamp_millis(),
+        active_app: current_active_app(),
+        ..ContextSnapshot::default()
+    };
+
+    let app_name = snapshot
+        .active_app
+        .as_ref()
+        .map(|app| app.name.clone())
+        .unwrap_or_default();
+    let mut permissions = app_settings.context.permissions_for(&app_name);
+    if app_settings.privacy.is_excluded(&app_name) {
+        permissions.window_title = false;
+        permissions.selection = false;
+        permissions.screenshot = false;
+    }
+
+    let front_window = front_window_of(&WINDOW_INVENTORY.read().windows, &app_name);
+
+    if permissions.window_title {
+        snapshot.window_title = active_window_title(front_window.as_ref());
+    } else {
+        snapshot.withheld.push("windowTitle".to_string());
+    }
+
+    if selection != SelectionGrab::Skip {
+        if permissions.selection {
+            if let Some((text, source)) = capture_selection(selection) {
+                snapshot.selected_text = Some(text);
+                snapshot.selection_source = Some(source);
+            }
+        } else {
+            snapshot.withheld.push("selectedText".to_string());
+        }
+    }
+
+    if include_screenshot {
+        if permissions.screenshot {
+            snapshot.screenshot = capture_screenshot(front_window.as_ref());
+        } else {
+            snapshot.withheld.push("screenshot".to_string());
+        }
+    }
+
+    snapshot.pushed = PUSHED_CONTEXT.read().clone();
+
+    apply_redaction_hooks(&mut snapshot);
+    snapshot
+}
+
+/// Keep context pushed from outside the app for the next snapshots,
+/// replacing what was pushed before.
+pub fn push(source: String, title: Option<String>, text: String) -> PushedContext {
+    let limit = settings::current().context.max_selection_chars;
+    let pushed = PushedContext {
+        pushed_at_ms: chrono::Local::now().timestamp_millis(),
+        source,
+        title,
+        text: text.chars().take(limit).collect(),
+    };
+    *PUSHED_CONTEXT.write() = Some(pushed.clone());
+    pushed
+}
+
+/// Snapshot for the webview and the local API. Reading the selection can
+/// go through the clipboard, so this is gated and logged like automation.
+#[tauri::command]
+pub async fn capture_context(include_screenshot: Option<bool>) -> AppResult<ContextSnapshot> {
+    let include_screenshot = include_screenshot.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::lock::ensure_unlocked()?;
+        let app = permissions::focused_app();
+        let details = json!({ "screenshot": include_screenshot });
+        audit::track("capture_context", app.as_deref(), details, || {
+            permissions::require(Capability::ReadSelection, app.as_deref())?;
+            if include_screenshot {
+                permissions::require(Capability::CaptureScreen, app.as_deref())?;
+            }
+            Ok(snapshot(include_screenshot))
+        })
+    })
+    .await?
+}
+
+/// Whether the privacy exclusion list and the per-app context rules allow
+/// screenshots of `app_name`.
+pub fn screenshot_allowed(app_name: &str) -> bool {
+    let app_settings = settings::current();
+    !app_settings.privacy.is_excluded(app_name) && app_settings.context.permissions_for(app_name).screenshot
+}
+
+/// Front window of `app_name` as last reported by the window detector.
+pub fn front_window(app_name: &str) -> Option<Value> {
+    front_window_of(&WINDOW_INVENTORY.read().windows, app_name)
+}
+
+/// Windows on the current space as last reported by the window detector,
+/// front to back.
+pub fn visible_windows() -> Vec<WindowSummary> {
+    let app_settings = settings::current();
+    WINDOW_INVENTORY
+        .read()
+        .windows
+        .iter()
+        .map(|window| {
+            let app = string_field(window, &["owner", "kCGWindowOwnerName"]).map(str::to_string);
+            let app_name = app.as_deref().unwrap_or_default();
+            let show_title = app_settings.context.permissions_for(app_name).window_title
+                && !app_settings.privacy.is_excluded(app_name);
+            let title = string_field(window, &["title", "name", "kCGWindowName"])
+                .filter(|_| show_title)
+                .map(str::to_string);
+            WindowSummary { app, title }
+        })
+        .collect()
+}
+
+/// Poll the clipboard and keep the texts copied while clipboard history is
+/// on. Copies made in excluded apps are skipped.
+#[cfg(target_os = "macos")]
+pub fn start_clipboard_watcher() {
+    if CLIPBOARD_WATCHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    thread::spawn(|| {
+        let mut last = read_clipboard();
+        loop {
+            thread::sleep(CLIPBOARD_POLL_INTERVAL);
+            let app_settings = settings::current();
+            if !app_settings.context.clipboard_history {
+                CLIPBOARD_HISTORY.lock().clear();
+                continue;
+            }
+
+            let Some(text) = read_clipboard().filter(|text| !text.trim().is_empty()) else {
+                continue;
+            };
+            if last.as_ref() == Some(&text) {
+                continue;
+            }
+            last = Some(text.clone());
+
+            let app = WINDOW_INVENTORY.read().active_app.as_ref().map(|app| app.name.clone());
+            if app.as_deref().is_some_and(|app| app_settings.privacy.is_excluded(app)) {
+                continue;
+            }
+            let limit = app_settings.context.max_selection_chars;
+            let entry = ClipboardEntry {
+                copied_at_ms: chrono::Utc::now().timestamp_millis(),
+                text: text.chars().take(limit).collect(),
+                app,
+            };
+
+            let mut history = CLIPBOARD_HISTORY.lock();
+            history.push_front(entry);
+            history.truncate(app_settings.context.clipboard_history_size);
+        }
+    });
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn start_clipboard_watcher() {}
+
+/// Texts copied while the app was running, newest first.
+pub fn clipboard_history() -> AppResult<Vec<ClipboardEntry>> {
+    crate::lock::ensure_unlocked()?;
+    if !settings::current().context.clipboard_history {
+        return Err(AppError::InvalidState(
+            "Clipboard history is off; turn it on in settings".to_string(),
+        ));
+    }
+    if !CLIPBOARD_WATCHING.load(Ordering::SeqCst) {
+        return Err(AppError::BackendUnavailable(
+            "Clipboard history is only kept by the running app".to_string(),
+        ));
+    }
+    Ok(CLIPBOARD_HISTORY.lock().iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_plain_text_clipboards_are_copied_through() {
+        assert!(only_plain_text(""));
+        assert!(only_plain_text(
+            "«class utf8», 5, «class ut16», 12, string, 5, Unicode text, 10\n"
+        ));
+        assert!(!only_plain_text("«class PNGf», 20480, «class 8BPS», 51234"));
+        assert!(!only_plain_text("«class RTF », 300, «class utf8», 5, string, 5"));
+        assert!(!only_plain_text("«class furl», 32, «class utf8», 12"));
+    }
+
+    #[test]
+    fn front_windows_are_found_whatever_the_case() {
+        let windows = [
+            json!({ "owner": "Slack", "title": "general" }),
+            json!({ "kCGWindowOwnerName": "Firefox", "kCGWindowName": "Docs" }),
+            json!({ "kCGWindowOwnerName": "Firefox", "kCGWindowName": "Mail" }),
+        ];
+        let window = front_window_of(&windows, "firefox").unwrap();
+        assert_eq!(active_window_title(Some(&window)).as_deref(), Some("Docs"));
+        assert!(front_window_of(&windows, "SLACK").is_some());
+        assert!(front_window_of(&windows, "Fire").is_none());
+    }
+}
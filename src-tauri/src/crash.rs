@@ -0,0 +1,281 @@
+// src-tauri/src/crash.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::diagnostics;
+use crate::error::AppResult;
+use crate::logging::{self, LogLine};
+use crate::{automation, dictation, recorder};
+
+/// Passed to the relaunched process after a crash.
+pub const SAFE_MODE_ARG: &str = "--safe-mode";
+/// Names the report the user has not been shown yet.
+const PENDING_FILE: &str = "pending";
+/// How long the report waits for state another thread has locked.
+const STATE_LOCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+static CRASH_DIR: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+static SAFE_MODE: AtomicBool = AtomicBool::new(false);
+static STARTED: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// What the app was doing when it crashed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSummary {
+    pub safe_mode: bool,
+    pub uptime_ms: u64,
+    pub automation_backend: String,
+    /// `None` when the session could not be checked in time.
+    pub recording: Option<bool>,
+    pub dictating: Option<bool>,
+    pub detector: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub at_ms: i64,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub thread: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub recent_logs: Vec<LogLine>,
+    pub state: StateSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashStatus {
+    pub safe_mode: bool,
+    /// Report from a previous crash that has not been dismissed.
+    pub pending: Option<CrashReport>,
+}
+
+/// Read the safe mode flag from the command line. Call early in `main`.
+pub fn init_from_args() {
+    Lazy::force(&STARTED);
+    let requested = std::env::args().any(|arg| arg == SAFE_MODE_ARG)
+        || std::env::var("LEO_SAFE_MODE").is_ok_and(|value| value == "1");
+    SAFE_MODE.store(requested, Ordering::SeqCst);
+}
+
+/// In safe mode the window detector, the app observer, hotkeys and every
+/// automation command are turned off.
+pub fn safe_mode() -> bool {
+    SAFE_MODE.load(Ordering::SeqCst)
+}
+
+/// Reports are written into `crash_dir` from now on.
+pub fn init(crash_dir: &Path) {
+    if let Err(e) = fs::create_dir_all(crash_dir) {
+        tracing::error!("Failed to create {}: {}", crash_dir.display(), e);
+    }
+    *CRASH_DIR.write() = Some(crash_dir.to_path_buf());
+}
+
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// The panicking thread may hold any of these locks, so none is waited on
+/// for long.
+fn state_summary() -> StateSummary {
+    StateSummary {
+        safe_mode: safe_mode(),
+        uptime_ms: STARTED.elapsed().as_millis() as u64,
+        automation_backend: automation::backend().name().to_string(),
+        recording: recorder::try_is_recording(STATE_LOCK_TIMEOUT),
+        dictating: dictation::try_is_running(STATE_LOCK_TIMEOUT),
+        detector: diagnostics::try_detector_status(STATE_LOCK_TIMEOUT)
+            .and_then(|detector| serde_json::to_value(detector).ok())
+            .unwrap_or_default(),
+    }
+}
+
+fn build_report(info: &PanicHookInfo<'_>) -> CrashReport {
+    CrashReport {
+        at_ms: chrono::Utc::now().timestamp_millis(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        thread: std::thread::current().name().unwrap_or("<unnamed>").to_string(),
+        message: panic_message(info),
+        location: info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line())),
+        backtrace: Backtrace::force_capture().to_string(),
+        recent_logs: logging::recent_lines(),
+        state: state_summary(),
+    }
+}
+
+/// Write a report for a panic and mark it as pending. Returns where it was
+/// written, or `None` before `init` has run.
+pub fn write_report(info: &PanicHookInfo<'_>) -> Option<PathBuf> {
+    let dir = CRASH_DIR.read().clone()?;
+    let report = build_report(info);
+    let name = format!("crash-{}.json", report.at_ms);
+    let path = dir.join(&name);
+
+    let result = serde_json::to_string_pretty(&report)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()))
+        .and_then(|_| fs::write(dir.join(PENDING_FILE), &name).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => Some(path),
+        Err(e) => {
+            tracing::error!("Failed to write crash report: {}", e);
+            None
+        }
+    }
+}
+
+/// Start a new instance in safe mode. Not done from safe mode itself, so a
+/// crash at startup cannot loop.
+pub fn relaunch_in_safe_mode() -> bool {
+    if safe_mode() {
+        return false;
+    }
+    // Free the socket first, or the new process would hand over to this one
+    crate::ipc::stop();
+    let spawned = std::env::current_exe()
+        .and_then(|exe| Command::new(exe).arg(SAFE_MODE_ARG).spawn());
+    match spawned {
+        Ok(_) => true,
+        Err(e) => {
+            tracing::error!("Failed to relaunch in safe mode: {}", e);
+            false
+        }
+    }
+}
+
+/// Write the report for a panic and log where it went.
+pub fn report_panic(info: &PanicHookInfo<'_>) {
+    let report = write_report(info);
+    tracing::error!(
+        report = ?report.as_deref().map(Path::display),
+        "Panic occurred: {}",
+        info
+    );
+}
+
+fn pending_report() -> Option<CrashReport> {
+    let dir = CRASH_DIR.read().clone()?;
+    let name = fs::read_to_string(dir.join(PENDING_FILE)).ok()?;
+    let contents = fs::read_to_string(dir.join(name.trim())).ok()?;
+    serde_json::from_str(&contents)
+        .map_err(|e| tracing::warn!("Failed to read crash report {}: {}", name.trim(), e))
+        .ok()
+}
+
+/// Whether we are in safe mode and the report the user has not seen yet.
+#[tauri::command]
+pub fn get_crash_status() -> CrashStatus {
+    CrashStatus {
+        safe_mode: safe_mode(),
+        pending: pending_report(),
+    }
+}
+
+/// Stop offering the pending report. The report itself stays on disk.
+#[tauri::command]
+pub fn dismiss_crash_report() -> AppResult<()> {
+    let Some(dir) = CRASH_DIR.read().clone() else {
+        return Ok(());
+    };
+    match fs::remove_file(dir.join(PENDING_FILE)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Leave safe mode by starting a normal instance and exiting this one.
+#[tauri::command]
+pub fn restart_normally(app_handle: tauri::AppHandle) -> AppResult<()> {
+    dismiss_crash_report()?;
+    let exe = std::env::current_exe()?;
+    crate::ipc::stop();
+    Command::new(exe).spawn()?;
+    app_handle.exit(0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tells the test binary, started again as a child, to crash into this dir.
+    const CHILD_CRASH_DIR: &str = "LEO_TEST_CRASH_DIR";
+
+    /// Only does something in the child started by `a_panic_leaves_a_report`.
+    #[test]
+    fn child_panics_on_request() {
+        let Some(dir) = std::env::var_os(CHILD_CRASH_DIR) else {
+            return;
+        };
+        logging::init();
+        init(Path::new(&dir));
+        std::panic::set_hook(Box::new(report_panic));
+        tracing::warn!("About to crash on purpose");
+
+        let worker = std::thread::Builder::new()
+            .name("crash-test-worker".to_string())
+            .spawn(|| panic!("deliberate crash"))
+            .unwrap();
+        assert!(worker.join().is_err());
+    }
+
+    #[test]
+    fn a_panic_leaves_a_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let child = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "crash::tests::child_panics_on_request", "--test-threads=1"])
+            .env(CHILD_CRASH_DIR, dir.path())
+            .output()
+            .unwrap();
+        assert!(child.status.success(), "{}", String::from_utf8_lossy(&child.stderr));
+
+        init(dir.path());
+        let report = get_crash_status().pending.expect("pending report");
+        assert_eq!(report.message, "deliberate crash");
+        assert_eq!(report.thread, "crash-test-worker");
+        assert!(report
+            .location
+            .as_deref()
+            .is_some_and(|location| location.contains("crash.rs")));
+        assert_eq!(report.app_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(report.os, std::env::consts::OS);
+        assert!(!report.backtrace.is_empty());
+        assert!(report
+            .recent_logs
+            .iter()
+            .any(|line| line.level == "WARN" && line.message == "About to crash on purpose"));
+        assert_eq!(report.state.automation_backend, "mock");
+        assert_eq!(report.state.recording, Some(false));
+        assert_eq!(report.state.dictating, Some(false));
+        assert!(!report.state.safe_mode);
+
+        dismiss_crash_report().unwrap();
+        assert!(get_crash_status().pending.is_none());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1, "the report itself stays");
+    }
+}
@@ -0,0 +1,193 @@
+// src-tauri/src/deep_link.rs
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tauri::AppHandle;
+use tauri_plugin_deep_link::DeepLinkExt;
+use url::Url;
+
+use crate::error::{AppError, AppResult};
+use crate::permissions;
+
+pub const SCHEME: &str = "leoai";
+
+/// Longest `q` an ask link may carry.
+const MAX_PROMPT_CHARS: usize = 4000;
+/// Longest app, workflow or thread name.
+const MAX_NAME_CHARS: usize = 200;
+
+/// What a `leoai://` link asks for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Intent {
+    /// `leoai://ask?q=...`: open the chat with `prompt` sent.
+    Ask { prompt: String },
+    /// `leoai://automate/<action>?...`: run an `automate_mac` action.
+    Automate {
+        action: String,
+        params: Option<String>,
+    },
+    /// `leoai://chat/<thread>`: open the chat on a thread.
+    Chat { thread: String },
+}
+
+/// What the confirmation dialog says an automation link wants to do.
+fn describe_automation(action: &str, params: Option<&str>) -> String {
+    match (action, params) {
+        ("open_app", Some(app)) => format!("open {}", app),
+        ("workflow", Some(name)) => format!("run the workflow \"{}\"", name),
+        ("summarize", _) => "summarize your selection".to_string(),
+        ("translate", _) => "translate your selection".to_string(),
+        ("dictation", mode) => format!("{} dictation", mode.unwrap_or("toggle")),
+        ("record", mode) => format!("{} macro recording", mode.unwrap_or("toggle")),
+        (action, _) => format!("run {}", action),
+    }
+}
+
+impl Intent {
+    fn kind(&self) -> &'static str {
+        match self {
+            Intent::Ask { .. } => "ask",
+            Intent::Automate { .. } => "automate",
+            Intent::Chat { .. } => "chat",
+        }
+    }
+}
+
+/// Errors name the link without its query, which may hold a prompt.
+fn invalid(url: &Url, reason: &str) -> AppError {
+    let host = url.host_str().unwrap_or_default();
+    AppError::InvalidInput(format!("Invalid link {}://{}{}: {}", SCHEME, host, url.path(), reason))
+}
+
+/// A query value that is present, non-empty, not too long and free of
+/// control characters.
+fn value(url: &Url, query: &HashMap<String, String>, key: &str, max_chars: usize) -> AppResult<String> {
+    let value = query
+        .get(key)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| invalid(url, &format!("{} is missing", key)))?;
+    if value.chars().count() > max_chars {
+        return Err(invalid(url, &format!("{} is too long", key)));
+    }
+    if key != "q" && value.chars().any(char::is_control) {
+        return Err(invalid(url, &format!("{} contains control characters", key)));
+    }
+    Ok(value.to_string())
+}
+
+fn mode(url: &Url, query: &HashMap<String, String>) -> AppResult<String> {
+    match query.get("mode").map(String::as_str) {
+        None => Ok("toggle".to_string()),
+        Some(mode @ ("start" | "stop" | "toggle")) => Ok(mode.to_string()),
+        Some(_) => Err(invalid(url, "mode must be start, stop or toggle")),
+    }
+}
+
+fn automate_intent(url: &Url, action: &str, query: &HashMap<String, String>) -> AppResult<Intent> {
+    let params = match action {
+        "open_app" => value(url, query, "name", MAX_NAME_CHARS)?,
+        "workflow" => value(url, query, "name", MAX_NAME_CHARS)?,
+        "summarize" | "translate" => {
+            let paste = query.get("paste").is_some_and(|paste| paste == "true" || paste == "1");
+            if paste { "selection:paste" } else { "selection" }.to_string()
+        }
+        "dictation" | "record" => mode(url, query)?,
+        _ => return Err(invalid(url, "unknown action")),
+    };
+    Ok(Intent::Automate {
+        action: action.to_string(),
+        params: Some(params),
+    })
+}
+
+/// Parse and validate a `leoai://` link.
+pub fn parse(link: &str) -> AppResult<Intent> {
+    let url = Url::parse(link).map_err(|e| AppError::InvalidInput(format!("Invalid link: {}", e)))?;
+    if url.scheme() != SCHEME {
+        return Err(AppError::InvalidInput(format!("Not a {}:// link", SCHEME)));
+    }
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+
+    match (url.host_str().unwrap_or_default(), segments.as_slice()) {
+        ("ask", []) => Ok(Intent::Ask {
+            prompt: value(&url, &query, "q", MAX_PROMPT_CHARS)?,
+        }),
+        ("automate", [action]) => automate_intent(&url, action, &query),
+        ("chat", [thread]) => {
+            let valid = thread.len() <= MAX_NAME_CHARS
+                && thread
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(invalid(&url, "thread ids are letters, digits, - and _"));
+            }
+            Ok(Intent::Chat {
+                thread: thread.to_string(),
+            })
+        }
+        _ => Err(invalid(&url, "unknown link")),
+    }
+}
+
+/// Whether a launch argument is a link for `open`.
+pub fn is_link(arg: &str) -> bool {
+    arg.strip_prefix(SCHEME).is_some_and(|rest| rest.starts_with("://"))
+}
+
+/// Carry out a link. Automation asks the user first: anyone can put a link
+/// on a page.
+pub async fn open(link: String) -> AppResult<Value> {
+    let intent = parse(&link)?;
+    tracing::info!("Opening {} link", intent.kind());
+
+    match intent {
+        Intent::Ask { prompt } => crate::api::start_chat(Some(prompt), None),
+        Intent::Chat { thread } => crate::api::start_chat(None, Some(thread)),
+        Intent::Automate { action, params } => {
+            let description = describe_automation(&action, params.as_deref());
+            let message = format!("A link asks Leo AI to {}.", description);
+            let source = format!("{}://automate/{}", SCHEME, action);
+            tauri::async_runtime::spawn_blocking(move || permissions::confirm(&source, &message)).await??;
+            let output = crate::automate_mac(&action, params).await?;
+            Ok(json!({ "output": output }))
+        }
+    }
+}
+
+/// `open` in the background, for links arriving outside of a request.
+pub fn open_in_background(link: String) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = open(link).await {
+            tracing::warn!("Failed to open link: {}", e);
+        }
+    });
+}
+
+/// Carry out links the app is opened with. macOS delivers them as events;
+/// elsewhere they are launch arguments, and a running instance gets later
+/// ones from the second launch through `activate`.
+pub fn register(app_handle: &AppHandle) {
+    app_handle.deep_link().on_open_url(|event| {
+        for url in event.urls() {
+            open_in_background(url.to_string());
+        }
+    });
+
+    #[cfg(any(windows, target_os = "linux"))]
+    {
+        // Bundles register the scheme when installed; this covers dev builds
+        if let Err(e) = app_handle.deep_link().register_all() {
+            tracing::warn!("Failed to register {}:// links: {}", SCHEME, e);
+        }
+        for link in std::env::args().skip(1).filter(|arg| is_link(arg)) {
+            open_in_background(link);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+// src-tauri/src/diagnostics.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{Manager, WebviewWindow};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::error::{AppError, AppResult};
+use crate::logging::{self, LogLine};
+use crate::{automation, settings};
+
+/// Log files added to an export bundle, newest first.
+const BUNDLED_LOG_FILES: usize = 2;
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectorState {
+    #[default]
+    NotStarted,
+    Starting,
+    Ready,
+    Stopped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectorStatus {
+    pub state: DetectorState,
+    pub error: Option<String>,
+    pub changed_at_ms: Option<i64>,
+    /// Window updates received from the detector since it started.
+    pub updates: u64,
+    pub last_update_ms: Option<i64>,
+}
+
+static DETECTOR: Lazy<RwLock<DetectorStatus>> = Lazy::new(|| RwLock::new(DetectorStatus::default()));
+
+/// Called by the window detector as it starts, connects and exits.
+pub fn set_detector_state(state: DetectorState, error: Option<String>) {
+    let mut detector = DETECTOR.write();
+    detector.state = state;
+    detector.error = error;
+    detector.changed_at_ms = Some(chrono::Utc::now().timestamp_millis());
+}
+
+pub fn detector_status() -> DetectorStatus {
+    DETECTOR.read().clone()
+}
+
+/// Like `detector_status`, or `None` when it stays locked for `timeout`.
+pub fn try_detector_status(timeout: Duration) -> Option<DetectorStatus> {
+    DETECTOR.try_read_for(timeout).map(|detector| detector.clone())
+}
+
+pub fn note_detector_update() {
+    let mut detector = DETECTOR.write();
+    detector.updates += 1;
+    detector.last_update_ms = Some(chrono::Utc::now().timestamp_millis());
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformInfo {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub family: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowInfo {
+    pub visible: bool,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    /// NSWindow level, macOS only.
+    pub level: Option<i64>,
+    /// NSWindowCollectionBehavior bits, macOS only.
+    pub collection_behavior: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostics {
+    pub generated_at_ms: i64,
+    pub app_version: String,
+    pub tauri_version: &'static str,
+    pub platform: PlatformInfo,
+    pub automation_backend: String,
+    pub monitors: Vec<MonitorInfo>,
+    pub window: Option<WindowInfo>,
+    pub detector: DetectorStatus,
+    /// Workspace notification observers registered by the app.
+    pub observers: i32,
+    pub recent_errors: Vec<LogLine>,
+    pub recent_logs: Vec<LogLine>,
+}
+
+#[cfg(target_os = "macos")]
+fn window_level(window: &WebviewWindow) -> (Option<i64>, Option<String>) {
+    use cocoa::base::id;
+    use cocoa::foundation::{NSInteger, NSUInteger};
+    use objc::{msg_send, sel, sel_impl};
+
+    let Ok(ns_window) = window.ns_window() else {
+        return (None, None);
+    };
+    unsafe {
+        let ns_window = ns_window as id;
+        let level: NSInteger = msg_send![ns_window, level];
+        let behavior: NSUInteger = msg_send![ns_window, collectionBehavior];
+        (Some(level as i64), Some(format!("{:#b}", behavior)))
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn window_level(_window: &WebviewWindow) -> (Option<i64>, Option<String>) {
+    (None, None)
+}
+
+fn monitors(window: &WebviewWindow) -> Vec<MonitorInfo> {
+    let current = window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .map(|monitor| *monitor.position());
+    window
+        .available_monitors()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|monitor| {
+            let position = *monitor.position();
+            let size = *monitor.size();
+            MonitorInfo {
+                name: monitor.name().cloned(),
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                scale_factor: monitor.scale_factor(),
+                current: current == Some(position),
+            }
+        })
+        .collect()
+}
+
+fn window_info(window: &WebviewWindow) -> Option<WindowInfo> {
+    let position = window.outer_position().ok()?;
+    let size = window.outer_size().ok()?;
+    let (level, collection_behavior) = window_level(window);
+    Some(WindowInfo {
+        visible: window.is_visible().unwrap_or(false),
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        scale_factor: window.scale_factor().unwrap_or(1.0),
+        level,
+        collection_behavior,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn observer_count() -> i32 {
+    *crate::OBSERVER_COUNT.lock()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn observer_count() -> i32 {
+    0
+}
+
+pub fn collect(app_handle: &tauri::AppHandle) -> Diagnostics {
+    let window = app_handle.get_webview_window("main");
+    Diagnostics {
+        generated_at_ms: chrono::Utc::now().timestamp_millis(),
+        app_version: app_handle.package_info().version.to_string(),
+        tauri_version: tauri::VERSION,
+        platform: PlatformInfo {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            family: std::env::consts::FAMILY,
+        },
+        automation_backend: automation::backend().name().to_string(),
+        monitors: window.as_ref().map(monitors).unwrap_or_default(),
+        window: window.as_ref().and_then(window_info),
+        detector: detector_status(),
+        observers: observer_count(),
+        recent_errors: logging::recent_warnings(),
+        recent_logs: logging::recent_lines(),
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    ["key", "token", "secret", "password"]
+        .iter()
+        .any(|secret| key.contains(secret))
+}
+
+/// Strip what could identify the user: secret-looking fields, URL query
+/// strings and the home directory in paths.
+fn redact(value: &mut Value, home: Option<&str>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_key(key) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value, home);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, home)),
+        Value::String(text) => {
+            if text.starts_with("http") {
+                if let Some(query) = text.find('?') {
+                    text.replace_range(query.., &format!("?{}", REDACTED));
+                }
+            }
+            if let Some(home) = home.filter(|home| !home.is_empty()) {
+                *text = text.replace(home, "~");
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn redacted_settings(home: Option<&Path>) -> AppResult<Value> {
+    let mut value = serde_json::to_value(settings::current()).map_err(|e| AppError::Failed(e.to_string()))?;
+    redact(&mut value, home.and_then(|home| home.to_str()));
+    Ok(value)
+}
+
+/// Write diagnostics, redacted settings and the latest log files to a zip.
+pub fn export_bundle(app_handle: &tauri::AppHandle, path: &Path) -> AppResult<()> {
+    let home = app_handle.path().home_dir().ok();
+    let diagnostics =
+        serde_json::to_vec_pretty(&collect(app_handle)).map_err(|e| AppError::Failed(e.to_string()))?;
+    let settings = serde_json::to_vec_pretty(&redacted_settings(home.as_deref())?)
+        .map_err(|e| AppError::Failed(e.to_string()))?;
+
+    // Never replace a file that is already there
+    let file = File::options()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| AppError::Io(format!("Failed to create {}: {}", path.display(), e)))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, contents: &[u8]| -> AppResult<()> {
+        zip.start_file(name, options).map_err(|e| AppError::Failed(e.to_string()))?;
+        Ok(zip.write_all(contents)?)
+    };
+
+    add("diagnostics.json", &diagnostics)?;
+    add("settings.json", &settings)?;
+    for log_file in logging::log_files().into_iter().take(BUNDLED_LOG_FILES) {
+        let Some(name) = log_file.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        match fs::read(&log_file) {
+            Ok(contents) => add(&format!("logs/{}", name), &contents)?,
+            Err(e) => tracing::warn!("Skipping {}: {}", log_file.display(), e),
+        }
+    }
+
+    zip.finish().map_err(|e| AppError::Failed(e.to_string()))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_diagnostics(app_handle: tauri::AppHandle) -> Diagnostics {
+    collect(&app_handle)
+}
+
+/// Where to write a bundle in `dir`: `file_name` with `.zip` added if it
+/// has no extension, or a timestamped name. Only a bare name that is not
+/// taken yet is accepted.
+fn bundle_path(dir: &Path, file_name: Option<&str>) -> AppResult<PathBuf> {
+    let file_name = match file_name.map(str::trim) {
+        Some(name) => {
+            let is_bare = !name.contains(['/', '\\']) && Path::new(name).file_name() == Some(name.as_ref());
+            if !is_bare || name.starts_with('.') {
+                return Err(AppError::InvalidInput(format!("Not a file name: {}", name)));
+            }
+            if Path::new(name).extension().is_some() {
+                name.to_string()
+            } else {
+                format!("{}.zip", name)
+            }
+        }
+        None => format!("leo-diagnostics-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S")),
+    };
+    let path = dir.join(file_name);
+    if path.exists() {
+        return Err(AppError::InvalidInput(format!("{} already exists", path.display())));
+    }
+    Ok(path)
+}
+
+/// Zip a diagnostics bundle for a bug report into the downloads folder and
+/// return where it was written.
+#[tauri::command]
+pub async fn export_diagnostics_bundle(app_handle: tauri::AppHandle, file_name: Option<String>) -> AppResult<String> {
+    let path = bundle_path(&app_handle.path().download_dir()?, file_name.as_deref())?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        export_bundle(&app_handle, &path)?;
+        tracing::info!("Exported diagnostics bundle to {}", path.display());
+        Ok(path.display().to_string())
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundles_only_go_to_new_files_in_the_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("taken.zip"), b"").unwrap();
+
+        assert_eq!(bundle_path(dir.path(), Some("report")).unwrap(), dir.path().join("report.zip"));
+        assert_eq!(bundle_path(dir.path(), Some("report.zip")).unwrap(), dir.path().join("report.zip"));
+        assert!(bundle_path(dir.path(), None).unwrap().starts_with(dir.path()));
+        for name in ["taken.zip", "taken", "../report.zip", "/tmp/report.zip", "a\\b.zip", "..", ".bashrc", ""] {
+            assert!(bundle_path(dir.path(), Some(name)).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+// src-tauri/src/dictation/audio.rs
+
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::error::{AppError, AppResult};
+
+/// Mono f32 audio in chunks. `Ok(None)` marks the end of the stream; live
+/// sources return empty chunks while waiting for input.
+pub trait AudioSource {
+    fn sample_rate(&self) -> u32;
+    fn read_chunk(&mut self) -> AppResult<Option<Vec<f32>>>;
+}
+
+fn downmix(interleaved: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return interleaved.to_vec();
+    }
+    interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// Reads a WAV file, so the pipeline can run without a microphone.
+pub struct WavSource {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    position: usize,
+    chunk_len: usize,
+}
+
+impl WavSource {
+    pub fn open(path: &Path) -> AppResult<Self> {
+        let mut reader = hound::WavReader::open(path)
+            .map_err(|e| AppError::InvalidInput(format!("Failed to open {}: {}", path.display(), e)))?;
+        let spec = reader.spec();
+
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .samples::<f32>()
+                .collect::<Result<_, _>>()
+                .map_err(|e| AppError::InvalidInput(format!("Failed to read {}: {}", path.display(), e)))?,
+            hound::SampleFormat::Int => {
+                let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|s| s as f32 / scale))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| AppError::InvalidInput(format!("Failed to read {}: {}", path.display(), e)))?
+            }
+        };
+
+        Ok(Self {
+            samples: downmix(&interleaved, spec.channels as usize),
+            sample_rate: spec.sample_rate,
+            position: 0,
+            // 100ms chunks, like the microphone callback roughly delivers
+            chunk_len: (spec.sample_rate / 10).max(1) as usize,
+        })
+    }
+}
+
+impl AudioSource for WavSource {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn read_chunk(&mut self) -> AppResult<Option<Vec<f32>>> {
+        if self.position >= self.samples.len() {
+            return Ok(None);
+        }
+        let end = (self.position + self.chunk_len).min(self.samples.len());
+        let chunk = self.samples[self.position..end].to_vec();
+        self.position = end;
+        Ok(Some(chunk))
+    }
+}
+
+/// Default input device captured through cpal. The stream is not `Send` on
+/// every platform, so create this on the thread that reads it.
+#[cfg(feature = "microphone")]
+pub struct MicrophoneSource {
+    _stream: cpal::Stream,
+    receiver: std::sync::mpsc::Receiver<Vec<f32>>,
+    sample_rate: u32,
+}
+
+#[cfg(feature = "microphone")]
+impl MicrophoneSource {
+    pub fn open_default() -> AppResult<Self> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let host = cpal::default_host();
+        let device = host
+            .default_input_device()
+            .ok_or_else(|| AppError::BackendUnavailable("No microphone available".to_string()))?;
+        let supported = device
+            .default_input_config()
+            .map_err(|e| AppError::BackendUnavailable(format!("Failed to query microphone: {}", e)))?;
+        let sample_rate = supported.sample_rate().0;
+        let channels = supported.channels() as usize;
+        let sample_format = supported.sample_format();
+        let config: cpal::StreamConfig = supported.into();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let on_error = |e: cpal::StreamError| tracing::warn!("Microphone stream error: {}", e);
+
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config,
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    let _ = sender.send(downmix(data, channels));
+                },
+                on_error,
+                None,
+            ),
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config,
+                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                    let floats: Vec<f32> = data.iter().map(|s| *s as f32 / 32768.0).collect();
+                    let _ = sender.send(downmix(&floats, channels));
+                },
+                on_error,
+                None,
+            ),
+            other => {
+                return Err(AppError::BackendUnavailable(format!(
+                    "Unsupported microphone sample format: {:?}",
+                    other
+                )))
+            }
+        }
+        .map_err(|e| AppError::BackendUnavailable(format!("Failed to open microphone: {}", e)))?;
+
+        stream
+            .play()
+            .map_err(|e| AppError::Failed(format!("Failed to start microphone: {}", e)))?;
+
+        Ok(Self {
+            _stream: stream,
+            receiver,
+            sample_rate,
+        })
+    }
+}
+
+#[cfg(feature = "microphone")]
+impl AudioSource for MicrophoneSource {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn read_chunk(&mut self) -> AppResult<Option<Vec<f32>>> {
+        use std::sync::mpsc::RecvTimeoutError;
+
+        match self.receiver.recv_timeout(std::time::Duration::from_millis(100)) {
+            Ok(chunk) => Ok(Some(chunk)),
+            Err(RecvTimeoutError::Timeout) => Ok(Some(Vec::new())),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+        }
+    }
+}
+
+/// Linear resampling; good enough for speech going into a recognizer.
+#[cfg_attr(not(feature = "whisper"), allow(dead_code))]
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio).floor() as usize;
+    (0..out_len)
+        .map(|i| {
+            let position = i as f64 * ratio;
+            let index = position.floor() as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index];
+            let next = samples.get(index + 1).copied().unwrap_or(current);
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+/// Encode mono samples as a 16-bit PCM WAV file in memory.
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> AppResult<Vec<u8>> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).map_err(|e| AppError::Failed(e.to_string()))?;
+        for sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            writer.write_sample(value).map_err(|e| AppError::Failed(e.to_string()))?;
+        }
+        writer.finalize().map_err(|e| AppError::Failed(e.to_string()))?;
+    }
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(path: &Path, spec: hound::WavSpec, samples: &[f32]) {
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for sample in samples {
+            match spec.sample_format {
+                hound::SampleFormat::Float => writer.write_sample(*sample).unwrap(),
+                hound::SampleFormat::Int => writer.write_sample((sample * i16::MAX as f32) as i16).unwrap(),
+            }
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn read_all(source: &mut WavSource) -> Vec<Vec<f32>> {
+        std::iter::from_fn(|| source.read_chunk().unwrap()).collect()
+    }
+
+    #[test]
+    fn stereo_wavs_are_read_as_mono_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stereo.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        // 250ms with the left channel at 0.5 and the right at -0.25
+        let frames: Vec<f32> = std::iter::repeat_n([0.5, -0.25], 2_000).flatten().collect();
+        write_wav(&path, spec, &frames);
+
+        let mut source = WavSource::open(&path).unwrap();
+        assert_eq!(source.sample_rate(), 8_000);
+        let chunks = read_all(&mut source);
+        assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), [800, 800, 400]);
+        assert!(chunks.iter().flatten().all(|s| (s - 0.125).abs() < 1e-3));
+        assert!(source.read_chunk().unwrap().is_none());
+    }
+
+    #[test]
+    fn float_wavs_keep_their_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("float.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        write_wav(&path, spec, &[0.0, 0.25, -0.75]);
+
+        let mut source = WavSource::open(&path).unwrap();
+        assert_eq!(read_all(&mut source), [vec![0.0, 0.25, -0.75]]);
+    }
+
+    #[test]
+    fn other_files_are_invalid_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.wav");
+        std::fs::write(&path, b"RIFF not really").unwrap();
+        assert!(matches!(WavSource::open(&path), Err(AppError::InvalidInput(_))));
+        assert!(matches!(
+            WavSource::open(&dir.path().join("missing.wav")),
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn encoded_wavs_read_back_clamped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("encoded.wav");
+        std::fs::write(&path, encode_wav(&[0.0, 0.5, -0.5, 2.0], 16_000).unwrap()).unwrap();
+
+        let mut source = WavSource::open(&path).unwrap();
+        let samples = read_all(&mut source).concat();
+        let expected = [0.0, 0.5, -0.5, 1.0];
+        assert_eq!(samples.len(), expected.len());
+        assert!(
+            samples.iter().zip(expected).all(|(s, e)| (s - e).abs() < 1e-3),
+            "{:?}",
+            samples
+        );
+    }
+
+    #[test]
+    fn resampling_scales_the_length() {
+        let samples: Vec<f32> = (0..480).map(|i| i as f32).collect();
+        assert_eq!(resample(&samples, 16_000, 16_000), samples);
+        let halved = resample(&samples, 48_000, 16_000);
+        assert_eq!(halved.len(), 160);
+        assert_eq!(&halved[..3], [0.0, 3.0, 6.0]);
+        assert_eq!(resample(&samples, 8_000, 16_000).len(), 960);
+    }
+}
@@ -0,0 +1,424 @@
+// src-tauri/src/dictation/mod.rs
+
+mod audio;
+mod transcriber;
+mod vad;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::audit;
+use crate::automation;
+use crate::error::{AppError, AppResult};
+use crate::permissions::{self, Capability};
+use crate::settings::{self, DictationSettings};
+use audio::AudioSource;
+use transcriber::Transcriber;
+use vad::{Vad, VadConfig};
+
+struct ActiveSession {
+    stop: Arc<AtomicBool>,
+    handle: thread::JoinHandle<()>,
+}
+
+static SESSION: Lazy<Mutex<Option<ActiveSession>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptEvent {
+    pub text: String,
+    pub typed: bool,
+}
+
+fn vad_config(settings: &DictationSettings) -> VadConfig {
+    VadConfig {
+        threshold: settings.vad_threshold,
+        silence_ms: settings.silence_ms,
+        ..VadConfig::default()
+    }
+}
+
+/// Run `source` through voice activity detection and transcribe each speech
+/// segment until the source ends or `stop` is set.
+fn run_pipeline(
+    source: &mut dyn AudioSource,
+    transcriber: &dyn Transcriber,
+    config: VadConfig,
+    stop: &AtomicBool,
+    mut on_segment: impl FnMut(AppResult<String>),
+) -> AppResult<()> {
+    let sample_rate = source.sample_rate();
+    let mut vad = Vad::new(config, sample_rate);
+    let mut transcribe = |segment: Vec<f32>| {
+        match transcriber.transcribe(&segment, sample_rate) {
+            Ok(text) if text.is_empty() => {}
+            result => on_segment(result),
+        }
+    };
+
+    while !stop.load(Ordering::SeqCst) {
+        match source.read_chunk()? {
+            Some(chunk) => vad.push(&chunk).into_iter().for_each(&mut transcribe),
+            None => break,
+        }
+    }
+    if let Some(segment) = vad.flush() {
+        transcribe(segment);
+    }
+    Ok(())
+}
+
+/// Type a transcript into the focused app and tell the frontend about it.
+fn deliver(text: &str, type_into_focused_app: bool) {
+    let typed = type_into_focused_app
+        && match automation::backend().type_text(&format!("{} ", text)) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to type dictated text: {}", e);
+                false
+            }
+        };
+
+    if let Some(app_handle) = crate::APP_HANDLE.read().as_ref() {
+        let event = TranscriptEvent {
+            text: text.to_string(),
+            typed,
+        };
+        if let Err(e) = app_handle.emit("dictation_transcript", event) {
+            tracing::warn!("Failed to emit dictation transcript: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "microphone")]
+fn open_microphone() -> AppResult<Box<dyn AudioSource>> {
+    Ok(Box::new(audio::MicrophoneSource::open_default()?))
+}
+
+#[cfg(not(feature = "microphone"))]
+fn open_microphone() -> AppResult<Box<dyn AudioSource>> {
+    Err(AppError::BackendUnavailable("Built without microphone support".to_string()))
+}
+
+/// Start live dictation from the default microphone.
+pub fn start() -> AppResult<()> {
+    let settings = settings::current().dictation;
+    let target = settings.type_into_focused_app.then(permissions::focused_app).flatten();
+    let details = serde_json::json!({
+        "transcriber": settings.transcriber,
+        "typeIntoFocusedApp": settings.type_into_focused_app,
+    });
+    audit::track("start_dictation", target.as_deref(), details, || {
+        start_session(settings, target.as_deref())
+    })
+}
+
+fn start_session(settings: DictationSettings, target: Option<&str>) -> AppResult<()> {
+    // Before taking the session lock, which the consent dialog would hold
+    // for as long as it is up
+    if settings.type_into_focused_app {
+        permissions::require(Capability::Dictation, target)?;
+    }
+
+    let mut session = SESSION.lock();
+    if session.as_ref().is_some_and(|s| !s.handle.is_finished()) {
+        return Err(AppError::InvalidState("Dictation is already running".to_string()));
+    }
+    let transcriber = transcriber::from_settings(&settings)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        // The cpal stream has to live on this thread
+        let mut source = match open_microphone() {
+            Ok(source) => {
+                let _ = ready_tx.send(Ok(()));
+                source
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+
+        tracing::info!(
+            "Dictation started with {} transcriber, typing via {}",
+            transcriber.name(),
+            automation::backend().name()
+        );
+        let result = run_pipeline(
+            source.as_mut(),
+            transcriber.as_ref(),
+            vad_config(&settings),
+            &thread_stop,
+            |segment| match segment {
+                Ok(text) => deliver(&text, settings.type_into_focused_app),
+                Err(e) => tracing::error!("Transcription failed: {}", e),
+            },
+        );
+        if let Err(e) = result {
+            tracing::error!("Dictation stopped with error: {}", e);
+        }
+        tracing::info!("Dictation stopped");
+    });
+
+    ready_rx
+        .recv()
+        .map_err(|_| AppError::Failed("Dictation thread exited unexpectedly".to_string()))??;
+    *session = Some(ActiveSession { stop, handle });
+    Ok(())
+}
+
+/// Stop live dictation, transcribing whatever speech is still buffered.
+pub fn stop() -> AppResult<()> {
+    let session = SESSION.lock().take();
+    match session {
+        Some(session) => {
+            session.stop.store(true, Ordering::SeqCst);
+            session
+                .handle
+                .join()
+                .map_err(|_| AppError::Failed("Dictation thread panicked".to_string()))
+        }
+        None => Err(AppError::InvalidState("Dictation is not running".to_string())),
+    }
+}
+
+pub fn is_running() -> bool {
+    SESSION
+        .lock()
+        .as_ref()
+        .is_some_and(|session| !session.handle.is_finished())
+}
+
+/// Like `is_running`, or `None` when the session stays locked for
+/// `timeout`.
+pub fn try_is_running(timeout: Duration) -> Option<bool> {
+    SESSION
+        .try_lock_for(timeout)
+        .map(|session| session.as_ref().is_some_and(|session| !session.handle.is_finished()))
+}
+
+/// Entry point for `automate_mac`: "start", "stop" or "toggle".
+pub fn handle_automation(params: Option<String>) -> AppResult<String> {
+    let command = params.unwrap_or_else(|| "toggle".to_string());
+    let start_requested = match command.as_str() {
+        "start" => true,
+        "stop" => false,
+        "toggle" => !is_running(),
+        other => {
+            return Err(AppError::InvalidInput(format!("Unsupported dictation params: {}", other)))
+        }
+    };
+
+    if start_requested {
+        start().map(|_| "started".to_string())
+    } else {
+        stop().map(|_| "stopped".to_string())
+    }
+}
+
+#[tauri::command]
+pub fn dictation_status() -> bool {
+    is_running()
+}
+
+/// The user's audio and downloads folders, which `transcribe_wav_file`
+/// reads from.
+fn wav_folders() -> Vec<PathBuf> {
+    [dirs::audio_dir(), dirs::download_dir()].into_iter().flatten().collect()
+}
+
+/// `path` with symlinks resolved, if it is a `.wav` file inside one of
+/// `folders`.
+fn allowed_wav(path: &Path, folders: &[PathBuf]) -> AppResult<PathBuf> {
+    let resolved = path
+        .canonicalize()
+        .map_err(|e| AppError::NotFound(format!("{}: {}", path.display(), e)))?;
+    let is_wav = resolved
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("wav"));
+    if !is_wav || !resolved.is_file() {
+        return Err(AppError::InvalidInput(format!("{} is not a .wav file", path.display())));
+    }
+    if !folders
+        .iter()
+        .filter_map(|folder| folder.canonicalize().ok())
+        .any(|folder| resolved.starts_with(folder))
+    {
+        return Err(AppError::PermissionDenied(
+            "only WAV files in the Music or Downloads folder can be transcribed".to_string(),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// Transcribe a WAV file from the audio or downloads folder through the
+/// same segmentation and transcriber as live dictation, optionally typing
+/// the result into the focused app.
+#[tauri::command]
+pub async fn transcribe_wav_file(path: String, type_result: Option<bool>) -> AppResult<String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = allowed_wav(Path::new(&path), &wav_folders())?;
+        let settings = settings::current().dictation;
+        let transcriber = transcriber::from_settings(&settings)?;
+        let mut source = audio::WavSource::open(&path)?;
+
+        let mut segments = Vec::new();
+        let mut failure = None;
+        run_pipeline(
+            &mut source,
+            transcriber.as_ref(),
+            vad_config(&settings),
+            &AtomicBool::new(false),
+            |segment| match segment {
+                Ok(text) => segments.push(text),
+                Err(e) => failure = Some(e),
+            },
+        )?;
+        if let Some(e) = failure {
+            return Err(e);
+        }
+
+        let transcript = segments.join(" ");
+        if type_result.unwrap_or(false) && !transcript.is_empty() {
+            let target = permissions::focused_app();
+            let details = serde_json::json!({ "chars": transcript.chars().count() });
+            audit::track("transcribe_wav_file", target.as_deref(), details, || {
+                permissions::require(Capability::TypeText, target.as_deref())?;
+                deliver(&transcript, true);
+                Ok(())
+            })?;
+        }
+        Ok(transcript)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const RATE: u32 = 16_000;
+
+    /// Answers each segment with the next scripted result and records how
+    /// long the segments were.
+    struct ScriptedTranscriber {
+        results: Mutex<Vec<AppResult<String>>>,
+        segments: Mutex<Vec<usize>>,
+    }
+
+    impl ScriptedTranscriber {
+        fn new(mut results: Vec<AppResult<String>>) -> Self {
+            results.reverse();
+            Self {
+                results: Mutex::new(results),
+                segments: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Transcriber for ScriptedTranscriber {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn transcribe(&self, samples: &[f32], sample_rate: u32) -> AppResult<String> {
+            assert_eq!(sample_rate, RATE);
+            self.segments.lock().push(samples.len());
+            self.results.lock().pop().unwrap_or_else(|| Ok("extra".to_string()))
+        }
+    }
+
+    /// Three 600ms utterances, each followed by a second of silence.
+    fn utterances_wav(dir: &Path) -> PathBuf {
+        let mut samples = vec![0.0; RATE as usize * 3 / 10];
+        for _ in 0..3 {
+            samples.extend(
+                (0..RATE as usize * 6 / 10)
+                    .map(|i| 0.3 * (i as f32 * 440.0 * std::f32::consts::TAU / RATE as f32).sin()),
+            );
+            samples.extend(vec![0.0; RATE as usize]);
+        }
+        let path = dir.join("utterances.wav");
+        fs::write(&path, audio::encode_wav(&samples, RATE).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn each_utterance_of_a_wav_file_is_transcribed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = audio::WavSource::open(&utterances_wav(dir.path())).unwrap();
+        let transcriber = ScriptedTranscriber::new(vec![
+            Ok("Hello".to_string()),
+            Ok(String::new()),
+            Err(AppError::Failed("model crashed".to_string())),
+        ]);
+
+        let mut results = Vec::new();
+        run_pipeline(
+            &mut source,
+            &transcriber,
+            VadConfig::default(),
+            &AtomicBool::new(false),
+            |result| results.push(result),
+        )
+        .unwrap();
+
+        assert_eq!(transcriber.segments.lock().len(), 3);
+        // Empty transcripts are left out
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_deref().unwrap(), "Hello");
+        assert!(matches!(results[1], Err(AppError::Failed(_))));
+    }
+
+    #[test]
+    fn a_stopped_pipeline_reads_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = audio::WavSource::open(&utterances_wav(dir.path())).unwrap();
+        let transcriber = ScriptedTranscriber::new(Vec::new());
+
+        run_pipeline(
+            &mut source,
+            &transcriber,
+            VadConfig::default(),
+            &AtomicBool::new(true),
+            |_| panic!("nothing to transcribe"),
+        )
+        .unwrap();
+        assert!(transcriber.segments.lock().is_empty());
+        assert_eq!(source.read_chunk().unwrap().map(|chunk| chunk.len()), Some(1_600));
+    }
+
+    #[test]
+    fn only_wav_files_in_the_given_folders_are_read() {
+        let allowed = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let folders = [allowed.path().to_path_buf()];
+        fs::write(allowed.path().join("memo.WAV"), b"RIFF").unwrap();
+        fs::write(allowed.path().join("notes.txt"), b"").unwrap();
+        fs::write(elsewhere.path().join("secret.wav"), b"RIFF").unwrap();
+
+        assert!(allowed_wav(&allowed.path().join("memo.WAV"), &folders).is_ok());
+        assert!(allowed_wav(&allowed.path().join("notes.txt"), &folders).is_err());
+        assert!(allowed_wav(&allowed.path().join("missing.wav"), &folders).is_err());
+        assert!(allowed_wav(&elsewhere.path().join("secret.wav"), &folders).is_err());
+        assert!(allowed_wav(&allowed.path().join("../secret.wav"), &folders).is_err());
+        #[cfg(unix)]
+        {
+            let link = allowed.path().join("link.wav");
+            std::os::unix::fs::symlink(elsewhere.path().join("secret.wav"), &link).unwrap();
+            assert!(allowed_wav(&link, &folders).is_err());
+        }
+    }
+}
@@ -0,0 +1,221 @@
+// src-tauri/src/dictation/transcriber.rs
+
+use std::time::Duration;
+
+use super::audio;
+use crate::error::{AppError, AppResult};
+use crate::llm::request_error;
+use crate::settings::DictationSettings;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Turns one speech segment into text.
+pub trait Transcriber: Send {
+    fn name(&self) -> &str;
+    fn transcribe(&self, samples: &[f32], sample_rate: u32) -> AppResult<String>;
+}
+
+/// Posts segments to an OpenAI-compatible `/audio/transcriptions` endpoint.
+pub struct RemoteTranscriber {
+    endpoint: String,
+    model: String,
+    language: Option<String>,
+    api_key: Option<String>,
+}
+
+impl RemoteTranscriber {
+    fn multipart_body(&self, wav: &[u8], boundary: &str) -> Vec<u8> {
+        let mut body = Vec::with_capacity(wav.len() + 512);
+        let mut field = |name: &str, value: &str| {
+            body.extend_from_slice(
+                format!(
+                    "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                    boundary, name, value
+                )
+                .as_bytes(),
+            );
+        };
+        field("model", &self.model);
+        field("response_format", "json");
+        if let Some(language) = self.language.as_deref() {
+            field("language", language);
+        }
+
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"segment.wav\"\r\nContent-Type: audio/wav\r\n\r\n",
+                boundary
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(wav);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        body
+    }
+}
+
+impl Transcriber for RemoteTranscriber {
+    fn name(&self) -> &str {
+        "remote"
+    }
+
+    fn transcribe(&self, samples: &[f32], sample_rate: u32) -> AppResult<String> {
+        let wav = audio::encode_wav(samples, sample_rate)?;
+        let boundary = format!("leo-{}", chrono::Local::now().timestamp_nanos_opt().unwrap_or_default());
+
+        let mut request = ureq::post(&self.endpoint)
+            .timeout(REQUEST_TIMEOUT)
+            .set(
+                "Content-Type",
+                &format!("multipart/form-data; boundary={}", boundary),
+            );
+        if let Some(api_key) = self.api_key.as_deref() {
+            request = request.set("Authorization", &format!("Bearer {}", api_key));
+        }
+
+        let response: serde_json::Value = request
+            .send_bytes(&self.multipart_body(&wav, &boundary))
+            .map_err(|e| request_error("Transcription", e))?
+            .into_json()
+            .map_err(|e| AppError::Failed(format!("Unreadable transcription response: {}", e)))?;
+
+        response["text"]
+            .as_str()
+            .map(|text| text.trim().to_string())
+            .ok_or_else(|| AppError::Failed(format!("Unexpected transcription response: {}", response)))
+    }
+}
+
+/// Local transcription with whisper.cpp. Needs the `whisper` feature and a
+/// GGML model file.
+#[cfg(feature = "whisper")]
+pub struct WhisperTranscriber {
+    context: whisper_rs::WhisperContext,
+    language: Option<String>,
+}
+
+#[cfg(feature = "whisper")]
+impl WhisperTranscriber {
+    const SAMPLE_RATE: u32 = 16_000;
+
+    pub fn load(model_path: &str, language: Option<String>) -> AppResult<Self> {
+        let context = whisper_rs::WhisperContext::new_with_params(
+            model_path,
+            whisper_rs::WhisperContextParameters::default(),
+        )
+        .map_err(|e| AppError::BackendUnavailable(format!("Failed to load whisper model {}: {}", model_path, e)))?;
+        Ok(Self { context, language })
+    }
+}
+
+#[cfg(feature = "whisper")]
+impl Transcriber for WhisperTranscriber {
+    fn name(&self) -> &str {
+        "whisper"
+    }
+
+    fn transcribe(&self, samples: &[f32], sample_rate: u32) -> AppResult<String> {
+        let samples = audio::resample(samples, sample_rate, Self::SAMPLE_RATE);
+        let mut state = self.context.create_state().map_err(|e| AppError::Failed(e.to_string()))?;
+
+        let mut params =
+            whisper_rs::FullParams::new(whisper_rs::SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some(self.language.as_deref().unwrap_or("auto")));
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+
+        state.full(params, &samples).map_err(|e| AppError::Failed(e.to_string()))?;
+        let segments = state.full_n_segments().map_err(|e| AppError::Failed(e.to_string()))?;
+        let mut text = String::new();
+        for index in 0..segments {
+            text.push_str(&state.full_get_segment_text(index).map_err(|e| AppError::Failed(e.to_string()))?);
+        }
+        Ok(text.trim().to_string())
+    }
+}
+
+/// Build the transcriber selected in the dictation settings. One that is
+/// unknown or not built in is unavailable rather than failed.
+pub fn from_settings(settings: &DictationSettings) -> AppResult<Box<dyn Transcriber>> {
+    let language = Some(settings.language.clone()).filter(|l| !l.is_empty());
+    match settings.transcriber.as_str() {
+        "remote" => Ok(Box::new(RemoteTranscriber {
+            endpoint: settings.endpoint.clone(),
+            model: settings.model.clone(),
+            language,
+            api_key: crate::secrets::get("openai"),
+        })),
+        #[cfg(feature = "whisper")]
+        "whisper" => Ok(Box::new(WhisperTranscriber::load(
+            &settings.whisper_model_path,
+            language,
+        )?)),
+        #[cfg(not(feature = "whisper"))]
+        "whisper" => Err(AppError::BackendUnavailable(
+            "Built without local whisper support".to_string(),
+        )),
+        other => Err(AppError::BackendUnavailable(format!("Unknown transcriber: {}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resilience::ErrorClass;
+    use crate::test_support::{Reply, StubServer};
+    use serde_json::json;
+
+    fn remote(endpoint: String) -> RemoteTranscriber {
+        RemoteTranscriber {
+            endpoint,
+            model: "whisper-1".to_string(),
+            language: Some("de".to_string()),
+            api_key: Some("sk-test".to_string()),
+        }
+    }
+
+    #[test]
+    fn segments_are_posted_as_wav_files() {
+        let server = StubServer::start(vec![Reply::json(200, json!({ "text": " Guten Morgen\n" }))]);
+        let transcriber = remote(format!("{}/v1/audio/transcriptions", server.url));
+
+        assert_eq!(transcriber.transcribe(&[0.1; 1_600], 16_000).unwrap(), "Guten Morgen");
+        let request = &server.requests()[0];
+        assert!(request.starts_with("POST /v1/audio/transcriptions "), "{}", request);
+        assert!(request.contains("Authorization: Bearer sk-test\r\n"));
+        assert!(request.contains("name=\"model\"\r\n\r\nwhisper-1\r\n"));
+        assert!(request.contains("name=\"language\"\r\n\r\nde\r\n"));
+        assert!(request.contains("filename=\"segment.wav\"\r\nContent-Type: audio/wav\r\n\r\nRIFF"));
+    }
+
+    #[test]
+    fn failed_requests_are_provider_errors() {
+        let server = StubServer::start(vec![
+            Reply::json(
+                401,
+                json!({ "error": { "message": "Incorrect API key", "code": "invalid_api_key" } }),
+            ),
+            Reply::json(200, json!({ "transcript": "elsewhere" })),
+        ]);
+        let transcriber = remote(server.url.clone());
+
+        match transcriber.transcribe(&[0.1; 160], 16_000) {
+            Err(AppError::Provider(error)) => assert_eq!(error.kind, ErrorClass::Auth),
+            other => panic!("expected a provider error, got {:?}", other),
+        }
+        assert!(matches!(
+            transcriber.transcribe(&[0.1; 160], 16_000),
+            Err(AppError::Failed(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_transcribers_are_unavailable() {
+        let settings = DictationSettings {
+            transcriber: "vosk".to_string(),
+            ..DictationSettings::default()
+        };
+        assert!(matches!(from_settings(&settings), Err(AppError::BackendUnavailable(_))));
+    }
+}
@@ -0,0 +1,224 @@
+// src-tauri/src/dictation/vad.rs
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone)]
+pub struct VadConfig {
+    pub frame_ms: u32,
+    /// Minimum RMS energy for a frame to count as speech.
+    pub threshold: f32,
+    /// Silence that ends a segment.
+    pub silence_ms: u32,
+    /// Segments shorter than this are dropped as clicks and breaths.
+    pub min_speech_ms: u32,
+    /// Long utterances are cut so transcription keeps up.
+    pub max_segment_ms: u32,
+    /// Audio kept from before speech starts so first syllables survive.
+    pub pre_roll_ms: u32,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            frame_ms: 30,
+            threshold: 0.01,
+            silence_ms: 700,
+            min_speech_ms: 250,
+            max_segment_ms: 15_000,
+            pre_roll_ms: 200,
+        }
+    }
+}
+
+fn rms(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+/// Energy-based voice activity detector that turns a sample stream into
+/// speech segments. The threshold adapts upwards to the background noise.
+pub struct Vad {
+    config: VadConfig,
+    frame_len: usize,
+    pending: Vec<f32>,
+    pre_roll: VecDeque<Vec<f32>>,
+    segment: Vec<f32>,
+    in_speech: bool,
+    speech_frames: u32,
+    silence_frames: u32,
+    noise_floor: f32,
+}
+
+impl Vad {
+    pub fn new(config: VadConfig, sample_rate: u32) -> Self {
+        let frame_len = (sample_rate as usize * config.frame_ms as usize / 1000).max(1);
+        Self {
+            config,
+            frame_len,
+            pending: Vec::new(),
+            pre_roll: VecDeque::new(),
+            segment: Vec::new(),
+            in_speech: false,
+            speech_frames: 0,
+            silence_frames: 0,
+            noise_floor: 0.0,
+        }
+    }
+
+    fn frames_for(&self, ms: u32) -> u32 {
+        (ms / self.config.frame_ms.max(1)).max(1)
+    }
+
+    /// Feed samples; returns any segments that ended inside them.
+    pub fn push(&mut self, samples: &[f32]) -> Vec<Vec<f32>> {
+        self.pending.extend_from_slice(samples);
+        let mut finished = Vec::new();
+
+        while self.pending.len() >= self.frame_len {
+            let frame: Vec<f32> = self.pending.drain(..self.frame_len).collect();
+            if let Some(segment) = self.process_frame(frame) {
+                finished.push(segment);
+            }
+        }
+        finished
+    }
+
+    fn process_frame(&mut self, frame: Vec<f32>) -> Option<Vec<f32>> {
+        let energy = rms(&frame);
+        let threshold = self.config.threshold.max(self.noise_floor * 3.0);
+        let is_speech = energy >= threshold;
+
+        if !self.in_speech {
+            if is_speech {
+                self.in_speech = true;
+                self.speech_frames = 1;
+                self.silence_frames = 0;
+                self.segment = self.pre_roll.drain(..).flatten().collect();
+                self.segment.extend_from_slice(&frame);
+            } else {
+                self.noise_floor = self.noise_floor * 0.95 + energy * 0.05;
+                self.pre_roll.push_back(frame);
+                while self.pre_roll.len() as u32 > self.frames_for(self.config.pre_roll_ms) {
+                    self.pre_roll.pop_front();
+                }
+            }
+            return None;
+        }
+
+        self.segment.extend_from_slice(&frame);
+        if is_speech {
+            self.speech_frames += 1;
+            self.silence_frames = 0;
+        } else {
+            self.silence_frames += 1;
+        }
+
+        let segment_frames = self.segment.len() as u32 / self.frame_len as u32;
+        if self.silence_frames >= self.frames_for(self.config.silence_ms)
+            || segment_frames >= self.frames_for(self.config.max_segment_ms)
+        {
+            return self.finish_segment();
+        }
+        None
+    }
+
+    fn finish_segment(&mut self) -> Option<Vec<f32>> {
+        self.in_speech = false;
+        self.silence_frames = 0;
+        let segment = std::mem::take(&mut self.segment);
+        let long_enough = self.speech_frames >= self.frames_for(self.config.min_speech_ms);
+        self.speech_frames = 0;
+        long_enough.then_some(segment)
+    }
+
+    /// End of stream: return the segment in progress, if any.
+    pub fn flush(&mut self) -> Option<Vec<f32>> {
+        if !self.pending.is_empty() {
+            let frame = std::mem::take(&mut self.pending);
+            if let Some(segment) = self.process_frame(frame) {
+                return Some(segment);
+            }
+        }
+        if self.in_speech {
+            self.finish_segment()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+    // 30ms frames at 16kHz
+    const FRAME: usize = 480;
+
+    fn tone(ms: usize) -> Vec<f32> {
+        (0..RATE as usize * ms / 1000)
+            .map(|i| 0.3 * (i as f32 * 440.0 * std::f32::consts::TAU / RATE as f32).sin())
+            .collect()
+    }
+
+    fn silence(ms: usize) -> Vec<f32> {
+        vec![0.0; RATE as usize * ms / 1000]
+    }
+
+    fn vad() -> Vad {
+        Vad::new(VadConfig::default(), RATE)
+    }
+
+    #[test]
+    fn silence_has_no_segments() {
+        let mut vad = vad();
+        assert!(vad.push(&silence(3000)).is_empty());
+        assert!(vad.flush().is_none());
+    }
+
+    #[test]
+    fn speech_ends_after_the_silence_and_keeps_its_pre_roll() {
+        let mut vad = vad();
+        assert!(vad.push(&silence(300)).is_empty());
+        assert!(vad.push(&tone(600)).is_empty());
+        let segments = vad.push(&silence(1000));
+
+        assert_eq!(segments.len(), 1);
+        // 200ms of pre-roll, the speech, then the 700ms of silence that ended it
+        let segment = &segments[0];
+        assert_eq!(segment.len(), (6 + 20 + 23) * FRAME);
+        assert!(segment[..6 * FRAME].iter().all(|s| *s == 0.0));
+        assert_eq!(&segment[6 * FRAME..26 * FRAME], &tone(600)[..]);
+        assert!(vad.flush().is_none());
+    }
+
+    #[test]
+    fn clicks_are_dropped() {
+        let mut vad = vad();
+        vad.push(&silence(300));
+        vad.push(&tone(60));
+        assert!(vad.push(&silence(1000)).is_empty());
+    }
+
+    #[test]
+    fn long_speech_is_cut_into_segments() {
+        let config = VadConfig {
+            max_segment_ms: 900,
+            ..VadConfig::default()
+        };
+        let mut vad = Vad::new(config, RATE);
+        let segments = vad.push(&tone(1800));
+        assert_eq!(segments.len(), 2);
+        assert!(segments.iter().all(|segment| segment.len() == 30 * FRAME));
+    }
+
+    #[test]
+    fn flush_returns_the_segment_in_progress() {
+        let mut vad = vad();
+        assert!(vad.push(&tone(600)).is_empty());
+        assert_eq!(vad.flush().map(|segment| segment.len()), Some(20 * FRAME));
+        assert!(vad.flush().is_none());
+    }
+}
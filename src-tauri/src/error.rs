@@ -0,0 +1,171 @@
+// src-tauri/src/error.rs
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Value};
+use std::fmt;
+
+use std::time::Duration;
+
+use crate::resilience::ProviderError;
+
+const PERMISSION_DENIED_PREFIX: &str = "Permission denied: ";
+const SAFE_MODE_MESSAGE: &str = "Automation is disabled in safe mode";
+const LOCKED_MESSAGE: &str = "Leo AI is locked";
+const BUDGET_EXCEEDED_PREFIX: &str = "Budget exceeded: ";
+
+/// Error returned by Tauri commands. Serializes as `{ code, message,
+/// details }` so the frontend can branch on `code` instead of parsing the
+/// message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// A consent or allowlist check failed, with the reason.
+    PermissionDenied(String),
+    /// Automation is off because the app runs in safe mode.
+    SafeMode,
+    /// The app lock is engaged; unlock before using this.
+    Locked,
+    /// An LLM spending limit is used up, with which one.
+    BudgetExceeded(String),
+    /// An LLM provider call failed, and how, after any retries.
+    Provider(ProviderError),
+    /// An automation backend, transcriber, LLM provider or other dependency
+    /// is missing, not built in or not configured.
+    BackendUnavailable(String),
+    /// The window with this label does not exist.
+    WindowNotFound(String),
+    /// A workflow, recording or other named item does not exist.
+    NotFound(String),
+    InvalidInput(String),
+    /// The request conflicts with what is running, e.g. starting a second
+    /// recording.
+    InvalidState(String),
+    Io(String),
+    /// Anything else that went wrong while carrying out the command.
+    Failed(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::PermissionDenied(_) => "PermissionDenied",
+            AppError::SafeMode => "SafeMode",
+            AppError::Locked => "Locked",
+            AppError::BudgetExceeded(_) => "BudgetExceeded",
+            AppError::Provider(..) => "Provider",
+            AppError::BackendUnavailable(_) => "BackendUnavailable",
+            AppError::WindowNotFound(_) => "WindowNotFound",
+            AppError::NotFound(_) => "NotFound",
+            AppError::InvalidInput(_) => "InvalidInput",
+            AppError::InvalidState(_) => "InvalidState",
+            AppError::Io(_) => "Io",
+            AppError::Failed(_) => "Failed",
+        }
+    }
+
+    /// Whether the user or a policy refused the action, as opposed to it
+    /// failing.
+    pub fn is_denial(&self) -> bool {
+        matches!(
+            self,
+            AppError::PermissionDenied(_) | AppError::SafeMode | AppError::Locked | AppError::BudgetExceeded(_)
+        )
+    }
+
+    pub fn details(&self) -> Value {
+        match self {
+            AppError::PermissionDenied(reason) => json!({ "reason": reason }),
+            AppError::BudgetExceeded(limit) => json!({ "limit": limit }),
+            AppError::Provider(error) => json!({
+                "class": error.kind,
+                "status": error.status,
+                "retryAfterMs": error.retry_after.map(|wait| wait.as_millis() as u64),
+            }),
+            AppError::WindowNotFound(label) => json!({ "window": label }),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::PermissionDenied(reason) => write!(f, "{}{}", PERMISSION_DENIED_PREFIX, reason),
+            AppError::SafeMode => write!(f, "{}", SAFE_MODE_MESSAGE),
+            AppError::Locked => write!(f, "{}", LOCKED_MESSAGE),
+            AppError::BudgetExceeded(limit) => write!(f, "{}{}", BUDGET_EXCEEDED_PREFIX, limit),
+            AppError::WindowNotFound(label) => write!(f, "Window not found: {}", label),
+            AppError::Provider(error) => write!(f, "{}", error),
+            AppError::BackendUnavailable(message)
+            | AppError::NotFound(message)
+            | AppError::InvalidInput(message)
+            | AppError::InvalidState(message)
+            | AppError::Io(message)
+            | AppError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct WireError {
+    code: String,
+    message: String,
+    #[serde(default)]
+    details: Value,
+}
+
+/// Rebuilds the error a running instance sent over the local socket.
+impl<'de> Deserialize<'de> for AppError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = WireError::deserialize(deserializer)?;
+        let detail = |key: &str| wire.details.get(key).and_then(Value::as_str).map(str::to_string);
+        Ok(match wire.code.as_str() {
+            "PermissionDenied" => AppError::PermissionDenied(detail("reason").unwrap_or(wire.message)),
+            "SafeMode" => AppError::SafeMode,
+            "Locked" => AppError::Locked,
+            "BudgetExceeded" => AppError::BudgetExceeded(detail("limit").unwrap_or(wire.message)),
+            "Provider" => match wire.details.get("class").cloned().map(serde_json::from_value) {
+                Some(Ok(kind)) => AppError::Provider(ProviderError {
+                    kind,
+                    status: wire.details["status"].as_u64().map(|status| status as u16),
+                    retry_after: wire.details["retryAfterMs"].as_u64().map(Duration::from_millis),
+                    message: wire.message,
+                }),
+                _ => AppError::Failed(wire.message),
+            },
+            "BackendUnavailable" => AppError::BackendUnavailable(wire.message),
+            "WindowNotFound" => AppError::WindowNotFound(detail("window").unwrap_or(wire.message)),
+            "NotFound" => AppError::NotFound(wire.message),
+            "InvalidInput" => AppError::InvalidInput(wire.message),
+            "InvalidState" => AppError::InvalidState(wire.message),
+            "Io" => AppError::Io(wire.message),
+            _ => AppError::Failed(wire.message),
+        })
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Io(error.to_string())
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(error: tauri::Error) -> Self {
+        AppError::Failed(error.to_string())
+    }
+}
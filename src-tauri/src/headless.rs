@@ -0,0 +1,74 @@
+// src-tauri/src/headless.rs
+
+use std::path::PathBuf;
+
+use crate::{audit, context, ledger, logging, prompts, secrets, settings, workflow};
+
+/// Must match `identifier` in tauri.conf.json, which names the app's
+/// directories.
+pub const APP_IDENTIFIER: &str = "com.leoai.app";
+
+/// Same as `app_config_dir` in Tauri's path resolver.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// Same as `app_data_dir` in Tauri's path resolver.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// Same as `app_log_dir` in Tauri's path resolver.
+#[cfg(target_os = "macos")]
+pub fn log_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|dir| dir.join("Library/Logs").join(APP_IDENTIFIER))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn log_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join(APP_IDENTIFIER).join("logs"))
+}
+
+/// Overrides `runtime_dir`.
+pub const RUNTIME_DIR_VAR: &str = "LEO_RUNTIME_DIR";
+
+/// Where the running instance puts its socket. `LEO_RUNTIME_DIR` overrides
+/// it, e.g. to keep test instances apart.
+pub fn runtime_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(RUNTIME_DIR_VAR) {
+        return Some(PathBuf::from(dir));
+    }
+    dirs::runtime_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// Log to stderr at the levels from settings, or from RUST_LOG.
+pub fn init_logging() {
+    logging::init();
+}
+
+/// Load settings, workflows and prompt templates and open the audit log and LLM ledger the
+/// way the app's setup does, so `api` calls can run in this process.
+pub fn init() {
+    match config_dir() {
+        Some(dir) => {
+            settings::init(&dir);
+            workflow::init(&dir);
+            prompts::init(&dir);
+            secrets::init(&dir);
+        }
+        None => tracing::warn!("Failed to resolve app config dir"),
+    }
+    logging::apply_settings(&settings::current().logging);
+
+    match log_dir() {
+        Some(dir) => audit::init(&dir),
+        None => tracing::warn!("Failed to resolve app log dir"),
+    }
+    match data_dir() {
+        Some(dir) => ledger::init(&dir),
+        None => tracing::warn!("Failed to resolve app data dir"),
+    }
+    context::init();
+}
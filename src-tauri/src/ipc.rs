@@ -0,0 +1,664 @@
+// src-tauri/src/ipc.rs
+
+use interprocess::local_socket::prelude::*;
+#[cfg(unix)]
+use interprocess::local_socket::GenericFilePath;
+#[cfg(not(unix))]
+use interprocess::local_socket::GenericNamespaced;
+use interprocess::local_socket::{Listener, ListenerOptions, Name, RecvHalf, SendHalf, Stream};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Instant;
+
+use crate::error::{AppError, AppResult};
+use crate::{api, audit, headless};
+
+const SOCKET_FILE: &str = "leo.sock";
+const CLIENTS_FILE: &str = "ipc-clients.json";
+/// Client the `leo` CLI and second launches of the app authenticate as;
+/// created with every method allowed.
+pub const CLI_CLIENT: &str = "leo";
+
+const JSONRPC_VERSION: &str = "2.0";
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Any other `AppError`; `data` holds it as `{ code, message, details }`.
+const APP_ERROR: i64 = -32000;
+const UNAUTHORIZED: i64 = -32001;
+
+static CLIENTS_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+/// Serializes changes to the clients file.
+static CLIENTS_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+/// Whether this process owns the socket.
+static LISTENING: AtomicBool = AtomicBool::new(false);
+/// Bound by `claim_instance`, served from once `start` runs.
+static LISTENER: Lazy<Mutex<Option<Listener>>> = Lazy::new(|| Mutex::new(None));
+
+/// A program allowed to use the socket, and what it may call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpcClient {
+    pub name: String,
+    pub token: String,
+    /// Method names, or prefixes ending in `*`; `"*"` allows every method.
+    pub methods: Vec<String>,
+    pub created_at_ms: i64,
+}
+
+impl IpcClient {
+    pub fn allows(&self, method: &str) -> bool {
+        self.methods.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => method.starts_with(prefix),
+            None => pattern == method,
+        })
+    }
+}
+
+/// An `IpcClient` without its token, for the settings UI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpcClientInfo {
+    pub name: String,
+    pub methods: Vec<String>,
+    pub created_at_ms: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ClientsFile {
+    clients: Vec<IpcClient>,
+}
+
+/// JSON-RPC 2.0, one object per line in each direction.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    /// Absent for notifications, which get no response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<AppError>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn unauthorized(error: AppError) -> Self {
+        RpcError {
+            code: UNAUTHORIZED,
+            ..RpcError::from(error)
+        }
+    }
+
+    fn into_app_error(self) -> AppError {
+        match self.data {
+            Some(error) => error,
+            None if self.code == UNAUTHORIZED => AppError::PermissionDenied(self.message),
+            None if self.code == METHOD_NOT_FOUND => AppError::NotFound(self.message),
+            None => AppError::InvalidInput(self.message),
+        }
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(error: AppError) -> Self {
+        let code = match error {
+            AppError::InvalidInput(_) => INVALID_PARAMS,
+            _ => APP_ERROR,
+        };
+        RpcError {
+            code,
+            message: error.to_string(),
+            data: Some(error),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(e) => (None, Some(e)),
+        };
+        Response {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+pub fn socket_path() -> Option<PathBuf> {
+    headless::runtime_dir().map(|dir| dir.join(SOCKET_FILE))
+}
+
+/// A socket file in the runtime dir on Unix, a per-user named pipe
+/// elsewhere.
+#[cfg(unix)]
+fn socket_name() -> AppResult<Name<'static>> {
+    let path = socket_path().ok_or_else(|| {
+        AppError::BackendUnavailable("No runtime dir for the local socket".to_string())
+    })?;
+    Ok(path.to_fs_name::<GenericFilePath>()?)
+}
+
+#[cfg(not(unix))]
+fn socket_name() -> AppResult<Name<'static>> {
+    // Pipes have no directory, so a `LEO_RUNTIME_DIR` goes into the name to
+    // keep test instances apart
+    let scope = match std::env::var(headless::RUNTIME_DIR_VAR) {
+        Ok(dir) => dir.replace(|c: char| !c.is_ascii_alphanumeric(), "-"),
+        Err(_) => std::env::var("USERNAME").unwrap_or_default(),
+    };
+    let name = format!("{}-{}", headless::APP_IDENTIFIER, scope);
+    Ok(name.to_ns_name::<GenericNamespaced>()?)
+}
+
+fn load_clients(path: &Path) -> AppResult<ClientsFile> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| AppError::Failed(format!("Failed to parse {}: {}", path.display(), e))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ClientsFile::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Tokens grant automation, so only the user may read the file.
+fn save_clients(path: &Path, clients: &ClientsFile) -> AppResult<()> {
+    let contents =
+        serde_json::to_string_pretty(clients).map_err(|e| AppError::Failed(e.to_string()))?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+fn new_token() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Compare without returning early, so response times do not tell how much
+/// of a token matched.
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn clients_path() -> AppResult<PathBuf> {
+    CLIENTS_PATH
+        .read()
+        .clone()
+        .ok_or_else(|| AppError::InvalidState("IPC clients have not been initialized".to_string()))
+}
+
+/// Keep client tokens in `<config_dir>/ipc-clients.json`, creating the
+/// CLI's client on first run.
+pub fn init(config_dir: &Path) {
+    let path = config_dir.join(CLIENTS_FILE);
+    *CLIENTS_PATH.write() = Some(path.clone());
+
+    let _guard = CLIENTS_LOCK.lock();
+    let result = load_clients(&path).and_then(|mut clients| {
+        if clients.clients.iter().any(|client| client.name == CLI_CLIENT) {
+            return Ok(());
+        }
+        clients.clients.push(IpcClient {
+            name: CLI_CLIENT.to_string(),
+            token: new_token(),
+            methods: vec!["*".to_string()],
+            created_at_ms: chrono::Local::now().timestamp_millis(),
+        });
+        fs::create_dir_all(config_dir)?;
+        save_clients(&path, &clients)
+    });
+    if let Err(e) = result {
+        tracing::error!("Failed to set up IPC clients: {}", e);
+    }
+}
+
+/// The file is read on every attempt so revoked tokens stop working
+/// without a restart.
+fn authenticate(token: &str) -> AppResult<IpcClient> {
+    load_clients(&clients_path()?)?
+        .clients
+        .into_iter()
+        .find(|client| tokens_match(&client.token, token))
+        .ok_or_else(|| AppError::PermissionDenied("Unknown IPC token".to_string()))
+}
+
+/// Whether this process got the socket in `claim_instance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instance {
+    /// This is the only instance; `start` serves on the socket.
+    Primary,
+    /// Another instance is listening; this one should hand over and exit.
+    Secondary,
+}
+
+fn bind(name: Name<'static>) -> std::io::Result<Listener> {
+    let options = ListenerOptions::new().name(name);
+    #[cfg(unix)]
+    let options = interprocess::os::unix::local_socket::ListenerOptionsExt::mode(options, 0o600);
+    options.create_sync()
+}
+
+/// Bind the socket before anything else starts, so that of two launches
+/// only one gets past this point. A launch that finds the socket taken by a
+/// live instance is `Secondary`.
+pub fn claim_instance() -> Instance {
+    #[cfg(unix)]
+    if let Some(dir) = socket_path().as_deref().and_then(Path::parent) {
+        use std::os::unix::fs::DirBuilderExt;
+        if let Err(e) = fs::DirBuilder::new().recursive(true).mode(0o700).create(dir) {
+            tracing::warn!("Failed to create {}: {}", dir.display(), e);
+            return Instance::Primary;
+        }
+    }
+    let name = match socket_name() {
+        Ok(name) => name,
+        Err(e) => {
+            tracing::warn!("Not listening for IPC: {}", e);
+            return Instance::Primary;
+        }
+    };
+
+    for attempt in 0..2 {
+        match bind(name.clone()) {
+            Ok(listener) => {
+                *LISTENER.lock() = Some(listener);
+                LISTENING.store(true, Ordering::SeqCst);
+                return Instance::Primary;
+            }
+            Err(_) if Stream::connect(name.clone()).is_ok() => return Instance::Secondary,
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && attempt == 0 => {
+                // Left behind by an instance that did not shut down cleanly
+                #[cfg(unix)]
+                if let Some(path) = socket_path() {
+                    let _ = fs::remove_file(path);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for IPC: {}", e);
+                break;
+            }
+        }
+    }
+    Instance::Primary
+}
+
+/// Hand this launch's arguments to the running instance, which focuses its
+/// window.
+pub fn forward_launch() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cwd = std::env::current_dir().ok();
+    match call("activate", json!({ "args": args, "cwd": cwd })) {
+        Some(Ok(_)) => tracing::info!("Handed launch over to the running instance"),
+        Some(Err(e)) => tracing::warn!("The running instance refused the launch: {}", e),
+        None => tracing::warn!("Could not reach the running instance"),
+    }
+}
+
+/// Serve on the socket bound by `claim_instance` so the `leo` CLI, editor
+/// plugins and scripts can reach this instance.
+pub fn start() {
+    let Some(listener) = LISTENER.lock().take() else {
+        tracing::debug!("No socket to serve on");
+        return;
+    };
+    tracing::info!("Listening for IPC");
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    thread::spawn(move || serve(stream));
+                }
+                Err(e) => tracing::warn!("Failed to accept connection: {}", e),
+            }
+        }
+    });
+}
+
+/// Remove the socket on shutdown.
+pub fn stop() {
+    if !LISTENING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    #[cfg(unix)]
+    if let Some(path) = socket_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[derive(Deserialize)]
+struct AuthParams {
+    token: String,
+}
+
+/// One connection; its first call must be `auth`.
+#[derive(Default)]
+struct Session {
+    client: Option<IpcClient>,
+}
+
+impl Session {
+    fn handle(&mut self, request: Request) -> Result<Value, RpcError> {
+        if request.jsonrpc != JSONRPC_VERSION {
+            return Err(RpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported"));
+        }
+        if request.method == "auth" {
+            return self.authenticate(request.params);
+        }
+
+        let Some(client) = self.client.as_ref() else {
+            return Err(RpcError::new(UNAUTHORIZED, "Call auth with a token first"));
+        };
+        if !api::METHODS.contains(&request.method.as_str()) {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method: {}", request.method),
+            ));
+        }
+        if !client.allows(&request.method) {
+            let reason = format!("{} may not call {}", client.name, request.method);
+            return Err(RpcError::unauthorized(AppError::PermissionDenied(reason)));
+        }
+
+        let started = Instant::now();
+        let result = api::call_blocking(&request.method, request.params);
+        let command = format!("ipc:{}", request.method);
+        audit::record_command(&command, Some(&client.name), Value::Null, started, &result);
+        result.map_err(RpcError::from)
+    }
+
+    fn authenticate(&mut self, params: Value) -> Result<Value, RpcError> {
+        let AuthParams { token } = serde_json::from_value(params)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params: {}", e)))?;
+        let client = authenticate(&token).map_err(RpcError::unauthorized)?;
+        tracing::info!("IPC client {} authenticated", client.name);
+
+        let methods: Vec<&str> = api::METHODS
+            .iter()
+            .copied()
+            .filter(|method| client.allows(method))
+            .collect();
+        let result = json!({ "client": client.name, "methods": methods });
+        self.client = Some(client);
+        Ok(result)
+    }
+}
+
+fn serve(stream: Stream) {
+    let _span = tracing::info_span!("ipc").entered();
+    let (reader, mut writer) = stream.split();
+    let mut session = Session::default();
+
+    for line in BufReader::new(reader).lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                tracing::debug!("Handling {}", request.method);
+                let id = request.id.clone();
+                let result = session.handle(request);
+                match id {
+                    Some(id) => Response::new(id, result),
+                    None => continue,
+                }
+            }
+            Err(e) => Response::new(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, format!("Invalid request: {}", e))),
+            ),
+        };
+        let sent = serde_json::to_string(&response)
+            .map_err(|e| std::io::Error::other(e.to_string()))
+            .and_then(|json| writeln!(writer, "{}", json));
+        if let Err(e) = sent {
+            tracing::warn!("Failed to send response: {}", e);
+            break;
+        }
+    }
+}
+
+/// Connection to a running instance, authenticated as one client.
+pub struct Client {
+    reader: BufReader<RecvHalf>,
+    writer: SendHalf,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connect and authenticate with `token`. `None` when no instance is
+    /// listening.
+    pub fn connect(token: &str) -> Option<AppResult<Client>> {
+        let stream = Stream::connect(socket_name().ok()?).ok()?;
+        let (reader, writer) = stream.split();
+        let mut client = Client {
+            reader: BufReader::new(reader),
+            writer,
+            next_id: 1,
+        };
+        Some(client.call("auth", json!({ "token": token })).map(|_| client))
+    }
+
+    /// Connect as the `leo` CLI with its token from the clients file.
+    pub fn connect_as_cli() -> Option<AppResult<Client>> {
+        let path = headless::config_dir()?.join(CLIENTS_FILE);
+        let token = load_clients(&path)
+            .ok()?
+            .clients
+            .into_iter()
+            .find(|client| client.name == CLI_CLIENT)?
+            .token;
+        Client::connect(&token)
+    }
+
+    pub fn call(&mut self, method: &str, params: Value) -> AppResult<Value> {
+        let request = Request {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id: Some(json!(self.next_id)),
+            method: method.to_string(),
+            params,
+        };
+        self.next_id += 1;
+        let json = serde_json::to_string(&request).map_err(|e| AppError::Failed(e.to_string()))?;
+        writeln!(self.writer, "{}", json)?;
+
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        if line.is_empty() {
+            return Err(AppError::Io(
+                "The running instance closed the connection".to_string(),
+            ));
+        }
+        let response: Response = serde_json::from_str(&line)
+            .map_err(|e| AppError::Failed(format!("Invalid response: {}", e)))?;
+        match response.error {
+            Some(e) => Err(e.into_app_error()),
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        }
+    }
+}
+
+/// Send one request to a running instance as the `leo` CLI. `None` when
+/// none is listening.
+pub fn call(method: &str, params: Value) -> Option<AppResult<Value>> {
+    Some(Client::connect_as_cli()?.and_then(|mut client| client.call(method, params)))
+}
+
+#[tauri::command]
+pub fn list_ipc_clients() -> AppResult<Vec<IpcClientInfo>> {
+    Ok(load_clients(&clients_path()?)?
+        .clients
+        .into_iter()
+        .map(|client| IpcClientInfo {
+            name: client.name,
+            methods: client.methods,
+            created_at_ms: client.created_at_ms,
+        })
+        .collect())
+}
+
+/// Add a client that may call `methods` and return its token, which is not
+/// shown again.
+#[tauri::command]
+pub fn create_ipc_client(name: String, methods: Vec<String>) -> AppResult<String> {
+    if name.trim().is_empty() {
+        return Err(AppError::InvalidInput("Client name is empty".to_string()));
+    }
+    let unknown = methods
+        .iter()
+        .find(|pattern| !pattern.ends_with('*') && !api::METHODS.contains(&pattern.as_str()));
+    if let Some(unknown) = unknown {
+        return Err(AppError::InvalidInput(format!("Unknown method: {}", unknown)));
+    }
+
+    let path = clients_path()?;
+    let _guard = CLIENTS_LOCK.lock();
+    let mut clients = load_clients(&path)?;
+    if clients.clients.iter().any(|client| client.name == name) {
+        return Err(AppError::InvalidInput(format!(
+            "A client named {} already exists",
+            name
+        )));
+    }
+    let token = new_token();
+    clients.clients.push(IpcClient {
+        name,
+        token: token.clone(),
+        methods,
+        created_at_ms: chrono::Local::now().timestamp_millis(),
+    });
+    save_clients(&path, &clients)?;
+    Ok(token)
+}
+
+/// Remove a client. Connections it already authenticated stay open until
+/// they close.
+#[tauri::command]
+pub fn revoke_ipc_client(name: String) -> AppResult<()> {
+    let path = clients_path()?;
+    let _guard = CLIENTS_LOCK.lock();
+    let mut clients = load_clients(&path)?;
+    let count = clients.clients.len();
+    clients.clients.retain(|client| client.name != name);
+    if clients.clients.len() == count {
+        return Err(AppError::NotFound(format!("No IPC client named {}", name)));
+    }
+    save_clients(&path, &clients)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{logging, test_support};
+    use std::process::Command;
+
+    /// Set in the child that plays the second launch.
+    const SECOND_LAUNCH: &str = "LEO_TEST_SECOND_LAUNCH";
+
+    /// Where `headless::config_dir` points under `home`.
+    fn config_dir_in(home: &Path) -> PathBuf {
+        let base = if cfg!(target_os = "macos") {
+            home.join("Library/Application Support")
+        } else {
+            home.join(".config")
+        };
+        base.join(headless::APP_IDENTIFIER)
+    }
+
+    /// Only does something in the child started by
+    /// `a_second_launch_hands_over_to_the_first`.
+    #[test]
+    fn second_launch() {
+        if std::env::var_os(SECOND_LAUNCH).is_none() {
+            return;
+        }
+        logging::init();
+        assert_eq!(claim_instance(), Instance::Secondary);
+        forward_launch();
+        // The test instance has no window to show, so it refuses after
+        // authenticating the launch and running `activate`
+        let lines = logging::recent_lines();
+        assert!(
+            lines.iter().any(
+                |line| line.message.starts_with("The running instance refused the launch")
+                    && line.message.contains("needs the Leo AI app to be running")
+            ),
+            "{:?}",
+            lines
+        );
+    }
+
+    #[test]
+    fn a_second_launch_hands_over_to_the_first() {
+        let _isolated = test_support::isolated(|_| {});
+        let runtime = tempfile::tempdir().unwrap();
+        let home = tempfile::tempdir().unwrap();
+        std::env::set_var(headless::RUNTIME_DIR_VAR, runtime.path());
+
+        init(&config_dir_in(home.path()));
+        assert_eq!(claim_instance(), Instance::Primary);
+        start();
+        let socket = runtime.path().join(SOCKET_FILE);
+        assert!(socket.exists());
+        assert!(matches!(
+            Client::connect("not a token"),
+            Some(Err(AppError::PermissionDenied(_)))
+        ));
+
+        let child = Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "ipc::tests::second_launch", "--test-threads=1"])
+            .env(SECOND_LAUNCH, "1")
+            .env(headless::RUNTIME_DIR_VAR, runtime.path())
+            .env("HOME", home.path())
+            .env("XDG_CONFIG_HOME", home.path().join(".config"))
+            .output()
+            .unwrap();
+
+        stop();
+        std::env::remove_var(headless::RUNTIME_DIR_VAR);
+        assert!(child.status.success(), "{}", String::from_utf8_lossy(&child.stdout));
+        assert!(!socket.exists());
+    }
+}
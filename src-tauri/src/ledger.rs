@@ -0,0 +1,504 @@
+// src-tauri/src/ledger.rs
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tauri::Emitter;
+
+use crate::error::{AppError, AppResult};
+use crate::llm::{Completion, CompletionRequest, Feature};
+use crate::settings::{self, BudgetSettings};
+use crate::usage;
+
+const LEDGER_FILE: &str = "llm_ledger.jsonl";
+/// Rough size of a token, for providers that do not report usage.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// US dollars per million input and output tokens, by model prefix. The
+/// longest matching prefix wins; `budget.prices` in the settings come first.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gemini-1.0-pro", 0.50, 1.50),
+    ("gemini-pro", 0.50, 1.50),
+    ("gemini-1.5-flash-8b", 0.0375, 0.15),
+    ("gemini-1.5-flash", 0.075, 0.30),
+    ("gemini-1.5-pro", 1.25, 5.00),
+    ("gemini-2.0-flash-lite", 0.075, 0.30),
+    ("gemini-2.0-flash", 0.10, 0.40),
+];
+
+/// Assumed for paid models without a price, on the high side, so that
+/// their calls still count toward the budget.
+const UNPRICED: (f64, f64) = (10.00, 30.00);
+
+/// Providers that cost nothing and are never held back by a budget.
+const FREE_PROVIDERS: &[&str] = &["local", "mock"];
+
+/// One LLM call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    pub at_ms: i64,
+    pub provider: String,
+    pub model: String,
+    pub feature: Feature,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// Counted from the text because the provider did not report usage.
+    #[serde(default)]
+    pub estimated: bool,
+    /// Unknown for models without a price.
+    pub cost_usd: Option<f64>,
+}
+
+impl LedgerEntry {
+    /// What the call counts for in the budget: its cost, or what it would
+    /// cost at the `UNPRICED` price.
+    fn budgeted_usd(&self) -> f64 {
+        self.cost_usd
+            .unwrap_or_else(|| cost_usd(self.prompt_tokens, self.completion_tokens, UNPRICED))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Day,
+    Month,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub daily_spent_usd: f64,
+    pub daily_limit_usd: Option<f64>,
+    pub monthly_spent_usd: f64,
+    pub monthly_limit_usd: Option<f64>,
+}
+
+/// Sent as `budget_warning` when spending passes the warning share of a
+/// limit, and again when it reaches the limit.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BudgetWarning {
+    period: Period,
+    spent_usd: f64,
+    limit_usd: f64,
+    exceeded: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageGroup {
+    pub key: String,
+    pub requests: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageGroup {
+    fn add(&mut self, entry: &LedgerEntry) {
+        self.requests += 1;
+        self.prompt_tokens += entry.prompt_tokens as u64;
+        self.completion_tokens += entry.completion_tokens as u64;
+        self.cost_usd += entry.cost_usd.unwrap_or(0.0);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmUsageSummary {
+    pub range_start_ms: i64,
+    pub range_end_ms: i64,
+    pub total: UsageGroup,
+    /// Calls to models without a price, left out of the costs. The budget
+    /// counts them at a high assumed price instead.
+    pub unpriced_requests: u32,
+    pub by_provider: Vec<UsageGroup>,
+    pub by_model: Vec<UsageGroup>,
+    pub by_feature: Vec<UsageGroup>,
+    pub budget: BudgetStatus,
+}
+
+#[derive(Default)]
+struct Ledger {
+    path: Option<PathBuf>,
+    /// Time and cost of this month's calls, for the budget checks.
+    month: Vec<(i64, f64)>,
+    /// Warnings already given, with the start of the period they were for.
+    warned: HashSet<(Period, bool, i64)>,
+}
+
+static LEDGER: Lazy<Mutex<Ledger>> = Lazy::new(|| Mutex::new(Ledger::default()));
+
+fn now_ms() -> i64 {
+    Local::now().timestamp_millis()
+}
+
+fn period_start_ms(period: Period) -> i64 {
+    let range = match period {
+        Period::Day => "day",
+        Period::Month => "month",
+    };
+    usage::resolve_range(range, None)
+        .map(|(start, _)| start)
+        .unwrap_or_default()
+}
+
+fn read_entries(path: &Path) -> Vec<LedgerEntry> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| match serde_json::from_str(&line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                tracing::warn!("Skipping unreadable ledger entry: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Keep the ledger in `data_dir` and load this month's spending.
+pub fn init(data_dir: &Path) {
+    if let Err(e) = fs::create_dir_all(data_dir) {
+        tracing::warn!("Failed to create {}: {}", data_dir.display(), e);
+    }
+    let path = data_dir.join(LEDGER_FILE);
+    let month_start = period_start_ms(Period::Month);
+    let month = read_entries(&path)
+        .into_iter()
+        .filter(|entry| entry.at_ms >= month_start)
+        .map(|entry| (entry.at_ms, entry.budgeted_usd()))
+        .collect();
+    let mut ledger = LEDGER.lock();
+    ledger.path = Some(path);
+    ledger.month = month;
+}
+
+/// Price per million input and output tokens of `model`.
+fn price_of(provider: &str, model: &str, budget: &BudgetSettings) -> Option<(f64, f64)> {
+    if FREE_PROVIDERS.contains(&provider) {
+        return Some((0.0, 0.0));
+    }
+    let configured = budget
+        .prices
+        .iter()
+        .filter(|price| model.starts_with(&price.model))
+        .max_by_key(|price| price.model.len())
+        .map(|price| (price.input_per_million, price.output_per_million));
+    configured.or_else(|| {
+        PRICES
+            .iter()
+            .filter(|(prefix, _, _)| model.starts_with(prefix))
+            .max_by_key(|(prefix, _, _)| prefix.len())
+            .map(|(_, input, output)| (*input, *output))
+    })
+}
+
+fn cost_usd(prompt_tokens: u32, completion_tokens: u32, (input, output): (f64, f64)) -> f64 {
+    (prompt_tokens as f64 * input + completion_tokens as f64 * output) / 1_000_000.0
+}
+
+fn spent_since(month: &[(i64, f64)], since_ms: i64) -> f64 {
+    month
+        .iter()
+        .filter(|(at_ms, _)| *at_ms >= since_ms)
+        .map(|(_, cost)| cost)
+        .sum()
+}
+
+fn limits(budget: &BudgetSettings) -> [(Period, Option<f64>); 2] {
+    [
+        (Period::Day, budget.daily_limit_usd),
+        (Period::Month, budget.monthly_limit_usd),
+    ]
+}
+
+pub fn status() -> BudgetStatus {
+    let budget = settings::current().budget;
+    let ledger = LEDGER.lock();
+    BudgetStatus {
+        daily_spent_usd: spent_since(&ledger.month, period_start_ms(Period::Day)),
+        daily_limit_usd: budget.daily_limit_usd,
+        monthly_spent_usd: spent_since(&ledger.month, period_start_ms(Period::Month)),
+        monthly_limit_usd: budget.monthly_limit_usd,
+    }
+}
+
+/// Refuse calls to a paid provider once a limit is used up.
+pub fn check(provider: &str) -> AppResult<()> {
+    if FREE_PROVIDERS.contains(&provider) {
+        return Ok(());
+    }
+    let budget = settings::current().budget;
+    let ledger = LEDGER.lock();
+    for (period, limit) in limits(&budget) {
+        let Some(limit) = limit else {
+            continue;
+        };
+        let spent = spent_since(&ledger.month, period_start_ms(period));
+        if spent >= limit {
+            let name = match period {
+                Period::Day => "daily",
+                Period::Month => "monthly",
+            };
+            return Err(AppError::BudgetExceeded(format!(
+                "the {} limit of ${:.2} is used up (${:.2} spent)",
+                name, limit, spent
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Warn once per period when spending passes the warning share of a limit,
+/// and once more when it reaches the limit.
+fn warn_if_needed(ledger: &mut Ledger, budget: &BudgetSettings) {
+    for (period, limit) in limits(budget) {
+        let Some(limit) = limit.filter(|limit| *limit > 0.0) else {
+            continue;
+        };
+        let start = period_start_ms(period);
+        let spent = spent_since(&ledger.month, start);
+        let exceeded = spent >= limit;
+        if !exceeded && spent < limit * budget.warn_at_percent.min(100) as f64 / 100.0 {
+            continue;
+        }
+        if !ledger.warned.insert((period, exceeded, start)) {
+            continue;
+        }
+        tracing::warn!(?period, spent, limit, "LLM spending is close to or over its limit");
+        if let Some(app_handle) = crate::APP_HANDLE.read().as_ref() {
+            let warning = BudgetWarning {
+                period,
+                spent_usd: spent,
+                limit_usd: limit,
+                exceeded,
+            };
+            if let Err(e) = app_handle.emit("budget_warning", warning) {
+                tracing::warn!("Failed to emit budget_warning: {}", e);
+            }
+        }
+    }
+}
+
+fn estimate_tokens(text: &str) -> u32 {
+    text.len().div_ceil(CHARS_PER_TOKEN) as u32
+}
+
+/// Add a finished call to the ledger.
+pub fn record(provider: &str, request: &CompletionRequest, completion: &Completion) {
+    let budget = settings::current().budget;
+    let (prompt_tokens, completion_tokens, estimated) = match completion.usage.as_ref() {
+        Some(usage) => (usage.prompt_tokens, usage.completion_tokens, false),
+        None => (
+            request
+                .messages
+                .iter()
+                .map(|message| estimate_tokens(&message.content))
+                .sum(),
+            estimate_tokens(&completion.text),
+            true,
+        ),
+    };
+    let price = price_of(provider, &completion.model, &budget);
+    if price.is_none() {
+        tracing::debug!(
+            "No price for {}; counting it at ${} and ${} per million tokens toward the budget",
+            completion.model,
+            UNPRICED.0,
+            UNPRICED.1
+        );
+    }
+    let entry = LedgerEntry {
+        at_ms: now_ms(),
+        provider: provider.to_string(),
+        model: completion.model.clone(),
+        feature: request.feature,
+        prompt_tokens,
+        completion_tokens,
+        estimated,
+        cost_usd: price.map(|price| cost_usd(prompt_tokens, completion_tokens, price)),
+    };
+
+    let mut ledger = LEDGER.lock();
+    // Drop last month's calls once a new one starts
+    let month_start = period_start_ms(Period::Month);
+    ledger.month.retain(|(at_ms, _)| *at_ms >= month_start);
+    ledger.month.push((entry.at_ms, entry.budgeted_usd()));
+    warn_if_needed(&mut ledger, &budget);
+
+    let Some(path) = ledger.path.as_ref() else {
+        return;
+    };
+    let result = serde_json::to_string(&entry)
+        .map_err(|e| e.to_string())
+        .and_then(|line| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        tracing::warn!("Failed to write the LLM ledger: {}", e);
+    }
+}
+
+fn grouped<'a>(entries: &'a [LedgerEntry], key: impl Fn(&'a LedgerEntry) -> String) -> Vec<UsageGroup> {
+    let mut groups: BTreeMap<String, UsageGroup> = BTreeMap::new();
+    for entry in entries {
+        let key = key(entry);
+        groups
+            .entry(key.clone())
+            .or_insert_with(|| UsageGroup {
+                key,
+                ..UsageGroup::default()
+            })
+            .add(entry);
+    }
+    let mut groups: Vec<UsageGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd).then_with(|| b.requests.cmp(&a.requests)));
+    groups
+}
+
+fn feature_name(feature: Feature) -> String {
+    serde_json::to_value(feature)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Tokens and costs for a "day", "week" or "month" containing `date`.
+pub fn summary(range: &str, date: Option<&str>) -> AppResult<LlmUsageSummary> {
+    let (from_ms, to_ms) = usage::resolve_range(range, date).map_err(AppError::InvalidInput)?;
+    let path = LEDGER.lock().path.clone();
+    let entries: Vec<LedgerEntry> = path
+        .map(|path| read_entries(&path))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.at_ms >= from_ms && entry.at_ms < to_ms)
+        .collect();
+
+    let mut total = UsageGroup {
+        key: "total".to_string(),
+        ..UsageGroup::default()
+    };
+    for entry in &entries {
+        total.add(entry);
+    }
+    Ok(LlmUsageSummary {
+        range_start_ms: from_ms,
+        range_end_ms: to_ms,
+        total,
+        unpriced_requests: entries.iter().filter(|entry| entry.cost_usd.is_none()).count() as u32,
+        by_provider: grouped(&entries, |entry| entry.provider.clone()),
+        by_model: grouped(&entries, |entry| entry.model.clone()),
+        by_feature: grouped(&entries, |entry| feature_name(entry.feature)),
+        budget: status(),
+    })
+}
+
+/// LLM tokens and estimated costs for a "day", "week" or "month" containing
+/// `date` (YYYY-MM-DD, default today), by provider, model and feature.
+#[tauri::command]
+pub fn get_llm_usage(range: String, date: Option<String>) -> AppResult<LlmUsageSummary> {
+    summary(&range, date.as_deref())
+}
+
+#[tauri::command]
+pub fn get_budget_status() -> BudgetStatus {
+    status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatMessage, TokenUsage};
+    use crate::settings::ModelPrice;
+    use crate::test_support;
+
+    fn call(provider: &str, model: &str, prompt_tokens: u32, completion_tokens: u32) {
+        let request = CompletionRequest::new(vec![ChatMessage::user("Hello")]);
+        let completion = Completion {
+            text: "Hi".to_string(),
+            model: model.to_string(),
+            usage: Some(TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+            }),
+            tool_calls: Vec::new(),
+        };
+        record(provider, &request, &completion);
+    }
+
+    #[test]
+    fn prices_come_from_the_longest_matching_prefix() {
+        let budget = BudgetSettings {
+            prices: vec![ModelPrice {
+                model: "gemini-1.5-pro-002".to_string(),
+                input_per_million: 2.0,
+                output_per_million: 6.0,
+            }],
+            ..BudgetSettings::default()
+        };
+        assert_eq!(
+            price_of("gemini", "gemini-1.5-flash-8b-001", &budget),
+            Some((0.0375, 0.15))
+        );
+        assert_eq!(price_of("gemini", "gemini-1.5-flash-002", &budget), Some((0.075, 0.30)));
+        assert_eq!(price_of("gemini", "gemini-1.5-pro-002", &budget), Some((2.0, 6.0)));
+        assert_eq!(price_of("local", "llama3", &budget), Some((0.0, 0.0)));
+        assert_eq!(price_of("gemini", "gemini-9-ultra", &budget), None);
+    }
+
+    #[test]
+    fn unpriced_models_count_toward_the_budget() {
+        let isolated = test_support::isolated(|settings| settings.budget.monthly_limit_usd = Some(0.05));
+
+        // 1000 tokens in and out come to $0.04 at the assumed price
+        call("gemini", "gemini-9-ultra", 1000, 1000);
+        check("gemini").unwrap();
+        call("gemini", "gemini-9-ultra", 1000, 1000);
+        assert!(matches!(check("gemini"), Err(AppError::BudgetExceeded(_))));
+        assert!((status().monthly_spent_usd - 0.08).abs() < 1e-9);
+        // Free providers are never held back
+        check("local").unwrap();
+
+        let usage = summary("month", None).unwrap();
+        assert_eq!(usage.unpriced_requests, 2);
+        assert_eq!(usage.total.requests, 2);
+        assert_eq!(usage.total.cost_usd, 0.0);
+
+        // The spending survives a restart
+        init(isolated.dir.path());
+        assert!(matches!(check("gemini"), Err(AppError::BudgetExceeded(_))));
+    }
+
+    #[test]
+    fn priced_calls_are_counted_at_their_price() {
+        let _isolated = test_support::isolated(|settings| settings.budget.daily_limit_usd = Some(1.0));
+
+        call("gemini", "gemini-1.5-pro-002", 100_000, 20_000);
+        call("mock", "mock", 1_000_000, 1_000_000);
+        let status = status();
+        assert!((status.daily_spent_usd - 0.225).abs() < 1e-9);
+        check("gemini").unwrap();
+
+        let usage = summary("day", None).unwrap();
+        assert_eq!(usage.unpriced_requests, 0);
+        assert_eq!(usage.by_provider[0].key, "gemini");
+        assert!((usage.by_provider[0].cost_usd - 0.225).abs() < 1e-9);
+        assert!(matches!(summary("year", None), Err(AppError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,1001 @@
+// src-tauri/src/lib.rs
+
+mod agent;
+pub mod api;
+mod audit;
+mod automation;
+mod context;
+mod crash;
+mod deep_link;
+mod diagnostics;
+mod dictation;
+mod ledger;
+pub mod error;
+pub mod headless;
+pub mod ipc;
+mod llm;
+mod local_llm;
+mod lock;
+mod logging;
+mod permissions;
+mod prompts;
+mod recorder;
+mod redaction;
+mod resilience;
+mod secrets;
+mod settings;
+#[cfg(test)]
+mod test_support;
+mod text_actions;
+mod usage;
+mod vision;
+mod workflow;
+
+use tauri::{Manager, PhysicalPosition, PhysicalSize, Position, Size, WebviewWindow};
+
+#[cfg(target_os = "macos")]
+use cocoa::base::{id, nil, NO, YES};
+#[cfg(target_os = "macos")]
+use cocoa::foundation::{NSUInteger, NSRect, NSString, NSInteger};
+#[cfg(target_os = "macos")]
+use objc::{class, msg_send, sel, sel_impl};
+#[cfg(target_os = "macos")]
+use objc::runtime::{Sel, Class, class_addMethod};
+
+use tauri::Emitter;
+use serde_json::json;
+
+use std::panic;
+use std::process::{Command, Stdio};
+use std::io::{BufRead, BufReader, Write};
+use std::thread;
+use std::time::Instant;
+use serde_json::Value;
+use std::sync::Arc;
+use tauri::AppHandle;
+use parking_lot::{Mutex, RwLock};
+use diagnostics::DetectorState;
+use error::{AppError, AppResult};
+use permissions::Capability;
+
+#[cfg(target_os = "macos")]
+#[derive(Debug, Clone, Copy)]
+struct NSSize {
+    width: f64,
+    height: f64,
+}
+
+// Use a safer approach for observer management
+#[cfg(target_os = "macos")]
+static OBSERVER_COUNT: once_cell::sync::Lazy<Arc<Mutex<i32>>> = 
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(0)));
+
+// Thread-safe storage for Python stdin
+static PYTHON_STDIN: once_cell::sync::Lazy<Arc<Mutex<Option<std::process::ChildStdin>>>> = 
+    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
+
+static APP_HANDLE: once_cell::sync::Lazy<Arc<RwLock<Option<AppHandle>>>> = 
+    once_cell::sync::Lazy::new(|| Arc::new(RwLock::new(None)));
+
+/// Get the scale factor for the monitor with bounds checking
+fn get_scale_factor(window: &WebviewWindow) -> f64 {
+    window
+        .current_monitor()
+        .unwrap_or(None)
+        .map(|m| m.scale_factor())
+        .unwrap_or(1.0)
+        .max(0.1) // Prevent division by zero or negative scales
+        .min(10.0) // Cap at reasonable maximum
+}
+
+/// The floating bubble window.
+fn main_window(app_handle: &tauri::AppHandle) -> AppResult<WebviewWindow> {
+    app_handle
+        .get_webview_window("main")
+        .ok_or_else(|| AppError::WindowNotFound("main".to_string()))
+}
+
+/// Tauri command that repositions the "main" window using physical coordinates,
+/// allowing cross-monitor movement with bounds checking.
+#[tauri::command]
+fn move_window(app_handle: tauri::AppHandle, x: i32, y: i32) -> AppResult<()> {
+    audit::track("move_window", None, json!({ "x": x, "y": y }), || {
+        let window = main_window(&app_handle)?;
+        // Bounds checking to prevent invalid positions
+        let clamped_x = x.clamp(-5000, 10000);
+        let clamped_y = y.clamp(-5000, 10000);
+
+        let physical_pos = PhysicalPosition::new(clamped_x, clamped_y);
+        window.set_position(Position::Physical(physical_pos))?;
+        Ok(())
+    })
+}
+
+/// Tauri command that resizes the "main" window with proper bounds checking.
+#[tauri::command]
+fn set_window_size(app_handle: tauri::AppHandle, width: u32, height: u32) -> AppResult<()> {
+    let details = json!({ "width": width, "height": height });
+    audit::track("set_window_size", None, details, || {
+        let window = main_window(&app_handle)?;
+        // Enforce minimum sizes to prevent crashes
+        let safe_width = width.max(100).min(3000);
+        let safe_height = height.max(100).min(3000);
+
+        let scale_factor = get_scale_factor(&window);
+        let physical_width = (safe_width as f64 * scale_factor).round() as u32;
+        let physical_height = (safe_height as f64 * scale_factor).round() as u32;
+
+        window.set_size(Size::Physical(PhysicalSize::new(
+            physical_width,
+            physical_height,
+        )))?;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+fn set_window_visible(app_handle: tauri::AppHandle, visible: bool) -> AppResult<()> {
+    audit::track("set_window_visible", None, json!({ "visible": visible }), || {
+        let window = main_window(&app_handle)?;
+        if visible {
+            window.show()?;
+        } else {
+            window.hide()?;
+        }
+        Ok(())
+    })
+}
+
+// Function to execute Apple Script
+fn osascript(script: &str) -> AppResult<String> {
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .map_err(|e| AppError::BackendUnavailable(format!("Failed to run osascript: {}", e)))?;
+
+    String::from_utf8(output.stdout).map_err(|e| AppError::Failed(e.to_string()))
+}
+
+/// Arbitrary scripts from the webview; off unless the developer setting
+/// allows them.
+#[tauri::command]
+async fn run_apple_script(script: String) -> AppResult<String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // Scripts can hold anything the page put in them, so only log the size
+        let details = json!({ "scriptChars": script.chars().count() });
+        audit::track("run_apple_script", None, details, || {
+            permissions::require(Capability::RunScript, None)?;
+            osascript(&script)
+        })
+    })
+    .await?
+}
+
+fn open_app(app_name: &str) -> AppResult<String> {
+    permissions::require(Capability::OpenApp, Some(app_name))?;
+    automation::backend().open_application(app_name)?;
+    Ok(String::new())
+}
+
+// Common automation commands; the permission check can wait on a consent
+// dialog, so not on the main thread
+#[tauri::command]
+async fn open_application(app_name: String) -> AppResult<String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        audit::track("open_application", Some(&app_name), Value::Null, || open_app(&app_name))
+    })
+    .await?
+}
+
+/// Run an input automation on a blocking thread once the focused app may
+/// receive it; the permission check can wait on a consent dialog.
+async fn act_on_focused_app(
+    command: &'static str,
+    capability: Capability,
+    details: Value,
+    act: impl FnOnce(&dyn automation::AutomationBackend) -> AppResult<()> + Send + 'static,
+) -> AppResult<String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let target = permissions::focused_app();
+        audit::track(command, target.as_deref(), details, || {
+            permissions::require(capability, target.as_deref())?;
+            act(automation::backend().as_ref())?;
+            Ok(String::new())
+        })
+    })
+    .await?
+}
+
+#[tauri::command]
+async fn type_text(text: String) -> AppResult<String> {
+    // Typed text may be a password, so only log its length
+    let details = json!({ "chars": text.chars().count() });
+    act_on_focused_app("type_text", Capability::TypeText, details, move |backend| {
+        backend.type_text(&text)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn click_button(button_name: String) -> AppResult<String> {
+    let details = json!({ "button": button_name });
+    act_on_focused_app("click_button", Capability::Click, details, move |backend| {
+        backend.click_button(&button_name)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn automate_mac(action: &str, params: Option<String>) -> AppResult<String> {
+    let started = Instant::now();
+    let target = params.clone();
+    let result = dispatch_automation(action, params).await;
+    let command = format!("automate_mac:{}", action);
+    audit::record_command(&command, target.as_deref(), Value::Null, started, &result);
+    result
+}
+
+async fn dispatch_automation(action: &str, params: Option<String>) -> AppResult<String> {
+    lock::ensure_unlocked()?;
+    match action {
+        "open_app" => tauri::async_runtime::spawn_blocking(move || open_app(&params.unwrap_or_default())).await?,
+        "summarize" => text_actions::handle_automation(text_actions::TextAction::Summarize, params).await,
+        "translate" => text_actions::handle_automation(text_actions::TextAction::Translate, params).await,
+        "dictation" => tauri::async_runtime::spawn_blocking(move || dictation::handle_automation(params)).await?,
+        "workflow" => workflow::handle_automation(params).await,
+        "record" => tauri::async_runtime::spawn_blocking(move || recorder::handle_automation(params)).await?,
+        other => Err(AppError::InvalidInput(format!("Unknown action: {}", other))),
+    }
+}
+
+#[tauri::command]
+fn start_drag(window: tauri::Window) -> AppResult<()> {
+    window.start_dragging()?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_webview_window_position(window: tauri::Window) -> AppResult<(f64, f64)> {
+    let position = window.outer_position()?;
+    Ok((position.x as f64, position.y as f64))
+}
+
+#[cfg(target_os = "macos")]
+fn is_screen_fullscreen(screen: id) -> bool {
+    unsafe {
+        let frame: NSRect = msg_send![screen, frame];
+        let visible_frame: NSRect = msg_send![screen, visibleFrame];
+        
+        // If the visible frame height is significantly different from the total frame height,
+        // we're likely in a full-screen space
+        tracing::debug!(
+            frame_height = frame.size.height,
+            visible_height = visible_frame.size.height,
+            "Screen check"
+        );
+
+        frame.size.height != visible_frame.size.height
+    }
+}
+
+
+#[cfg(target_os = "macos")]
+unsafe extern "C" fn space_change_callback(_this: id, _sel: Sel, _notification: id) {
+    let _span = tracing::info_span!("observer", event = "space_change").entered();
+    tracing::debug!("Space change detected - notifying Python detector");
+
+    unsafe {
+        // Get our app to maintain window level during transition
+        let app: id = msg_send![class!(NSApplication), sharedApplication];
+        let app_windows: id = msg_send![app, windows];
+        let app_count: NSUInteger = msg_send![app_windows, count];
+        
+        // First boost window level during transition to prevent flicker
+        for i in 0..app_count {
+            let window: id = msg_send![app_windows, objectAtIndex:i];
+            if window != nil {
+                let _: () = msg_send![window, setLevel: 2147483647];
+                let _: () = msg_send![window, orderFrontRegardless];
+            }
+        }
+        
+        // Check if we're entering a fullscreen space
+        let screen: id = msg_send![class!(NSScreen), mainScreen];
+        let is_fullscreen = is_screen_fullscreen(screen);
+        
+        // Adjust window properties for fullscreen spaces
+        for i in 0..app_count {
+            let window: id = msg_send![app_windows, objectAtIndex:i];
+            if window != nil {
+                if is_fullscreen {
+                    tracing::debug!("Entering fullscreen space - adjusting window properties");
+                    // In fullscreen spaces:
+                    // - Use NSWindowCollectionBehaviorTransient to avoid disrupting fullscreen
+                    // - Set higher level to stay above fullscreen window
+                    // - Remove shadow and make more subtle
+                    let behavior = 1 << 0 | 1 << 10; // CanJoinAllSpaces | Transient
+                    let _: () = msg_send![window, setCollectionBehavior:behavior];
+                    let _: () = msg_send![window, setLevel:25];
+                    let _: () = msg_send![window, setHasShadow:NO];
+                    let _: () = msg_send![window, setAlphaValue:0.95];
+                } else {
+                    tracing::debug!("Entering normal space - restoring window properties");
+                    // In normal spaces:
+                    // - Use standard behavior
+                    // - Set normal floating level
+                    // - Restore opacity
+                    let behavior = 1 << 0; // CanJoinAllSpaces only
+                    let _: () = msg_send![window, setCollectionBehavior:behavior];
+                    let _: () = msg_send![window, setLevel:5];
+                    let _: () = msg_send![window, setAlphaValue:1.0];
+                }
+            }
+        }
+    }
+    
+    // Notify Python detector through stdin
+    {
+        let mut stdin = PYTHON_STDIN.lock();
+        if let Some(ref mut stdin) = *stdin {
+            if let Err(e) = stdin.write_all(b"SPACE_CHANGED\n") {
+                tracing::warn!("Failed to notify Python: {}", e);
+            } else if let Err(e) = stdin.flush() {
+                tracing::warn!("Failed to flush Python stdin: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn apply_macos_window_customizations(window: &WebviewWindow) -> Result<(), String> {
+    tracing::debug!("Starting window customization");
+    
+    // Get window handle first
+    let ns_window = window.ns_window().map_err(|e| {
+        format!("Failed to get ns_window: {}", e)
+    })?;
+    let ns_window = ns_window as id;
+    
+    // Wrap all Objective-C calls in a single unsafe block with proper error handling
+    unsafe {
+        // Print current window properties
+        let current_level: NSInteger = msg_send![ns_window, level];
+        let current_collection_behavior: NSUInteger = msg_send![ns_window, collectionBehavior];
+        tracing::debug!(
+            level = current_level,
+            collection_behavior = format_args!("{:#b}", current_collection_behavior),
+            "Current window properties"
+        );
+        
+        // Set combined behaviors for maximum compatibility
+        let base_behavior: NSUInteger = 
+            1 << 0;  // NSWindowCollectionBehaviorCanJoinAllSpaces only for now
+        
+        // Set window properties with error checking - one at a time
+        let _: () = msg_send![ns_window, setCollectionBehavior: base_behavior];
+        let _: () = msg_send![ns_window, setLevel: 25];
+        
+        // Additional window properties like Highlight - simplified
+        let _: () = msg_send![ns_window, setOpaque: NO];
+        let _: () = msg_send![ns_window, setMovable: YES];
+        let _: () = msg_send![ns_window, setHasShadow: NO];
+        
+        // Print final window properties
+        let final_level: NSInteger = msg_send![ns_window, level];
+        let final_collection_behavior: NSUInteger = msg_send![ns_window, collectionBehavior];
+        tracing::debug!(
+            level = final_level,
+            collection_behavior = format_args!("{:#b}", final_collection_behavior),
+            "Final window properties"
+        );
+        
+        // Set up space change observer - simplified
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        if workspace == nil {
+            return Err("Failed to get workspace".into());
+        }
+        
+        let notification_center: id = msg_send![workspace, notificationCenter];
+        if notification_center == nil {
+            return Err("Failed to get notification center".into());
+        }
+        
+        // Create observer
+        let observer: id = msg_send![class!(NSObject), new];
+        if observer == nil {
+            return Err("Failed to create observer".into());
+        }
+        
+        // Store observer
+        {
+            let mut obs = OBSERVER_COUNT.lock();
+            *obs += 1;
+        }
+
+        // Basic selector setup
+        let selector = sel!(spaceDidChange:);
+        let types = std::ffi::CString::new("v@:@").map_err(|e| e.to_string())?;
+        
+        type Imp = unsafe extern "C" fn();
+        let imp: Imp = std::mem::transmute(space_change_callback as unsafe extern "C" fn(_, _, _));
+        
+        let observer_class: *mut Class = msg_send![observer, class];
+        class_addMethod(observer_class, selector, imp, types.as_ptr());
+        
+        // Set up notification
+        let notification_name = NSString::alloc(nil).init_str("NSWorkspaceActiveSpaceDidChangeNotification");
+        let _: () = msg_send![notification_center,
+            addObserver:observer
+            selector:selector
+            name:notification_name
+            object:nil];
+        
+        tracing::info!("Window customization complete");
+        Ok(())
+    }
+}
+
+// Command to re-invoke window settings
+#[cfg(target_os = "macos")]
+#[tauri::command]
+fn re_invoke_window_settings(window: WebviewWindow) -> AppResult<()> {
+    apply_macos_window_customizations(&window).map_err(AppError::Failed)
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tauri::command]
+fn re_invoke_window_settings(_window: WebviewWindow) -> AppResult<()> {
+    Err(AppError::BackendUnavailable("Not implemented on this platform.".into()))
+}
+
+fn setup_python_environment() -> Result<(), String> {
+    let python_path = "/Library/Frameworks/Python.framework/Versions/3.9/bin/python3.9";
+    tracing::info!("Setting up Python environment using {}", python_path);
+
+    // Check if Python 3.9 is available
+    let python_check = Command::new(python_path)
+        .arg("--version")
+        .output()
+        .map_err(|_| format!("Python 3.9 not found at {}", python_path))?;
+        
+    if !python_check.status.success() {
+        return Err("Python 3.9 check failed".to_string());
+    }
+
+    // Install required packages using pip
+    tracing::info!("Installing required packages");
+    let packages = [
+        "pyobjc-framework-Cocoa",
+        "pyobjc-framework-Quartz",
+        "pyobjc-core"
+    ];
+
+    for package in packages.iter() {
+        tracing::debug!("Installing {}", package);
+        let pip_result = Command::new(python_path)
+            .args(["-m", "pip", "install", "--user", package])
+            .output()
+            .map_err(|e| format!("Failed to install {}: {}", package, e))?;
+
+        if !pip_result.status.success() {
+            return Err(format!("Failed to install {}: {}", 
+                package, 
+                String::from_utf8_lossy(&pip_result.stderr)));
+        }
+    }
+
+    tracing::info!("Python environment setup complete");
+    Ok(())
+}
+
+/// Log why the detector could not run and show it in diagnostics.
+fn detector_failed(error: String) {
+    tracing::error!("Window detector failed: {}", error);
+    diagnostics::set_detector_state(DetectorState::Failed, Some(error));
+}
+
+fn start_window_detector(app_handle: tauri::AppHandle) {
+    diagnostics::set_detector_state(DetectorState::Starting, None);
+    let current_dir = match std::env::current_dir() {
+        Ok(dir) => dir,
+        Err(e) => return detector_failed(format!("Failed to get current directory: {}", e)),
+    };
+        
+    // Setup Python environment first
+    if let Err(e) = setup_python_environment() {
+        return detector_failed(format!("Failed to setup Python environment: {}", e));
+    }
+        
+    thread::spawn(move || {
+        let _span = tracing::info_span!("detector").entered();
+        let detector_script = current_dir.join("window_detector.py");
+        let python_path = "/Library/Frameworks/Python.framework/Versions/3.9/bin/python3.9";
+
+        tracing::info!(
+            python = python_path,
+            script = %detector_script.display(),
+            "Starting window detector"
+        );
+
+        let child = Command::new(python_path)
+            .arg(&detector_script)
+            .current_dir(&current_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => return detector_failed(format!("Failed to start window detector: {}", e)),
+        };
+
+        // Store stdin in thread-safe storage for sending notifications
+        *PYTHON_STDIN.lock() = child.stdin.take();
+
+        // Handle stderr in a separate thread
+        if let Some(stderr) = child.stderr.take() {
+            thread::spawn(move || {
+                let _span = tracing::info_span!("detector").entered();
+                let reader = BufReader::new(stderr);
+                for line in reader.lines().map_while(Result::ok) {
+                    logging::helper_line(&line);
+                }
+            });
+        }
+
+        // Handle stdout
+        let Some(stdout) = child.stdout.take() else {
+            return detector_failed("Window detector has no stdout".to_string());
+        };
+        let mut reader = BufReader::new(stdout);
+        let mut buffer = Vec::new();
+        
+        loop {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) => {
+                    tracing::info!("Python detector closed the pipe");
+                    diagnostics::set_detector_state(DetectorState::Stopped, None);
+                    break;
+                }
+                Ok(_) => {
+                    if let Ok(line) = String::from_utf8(buffer.clone()) {
+                        let line = line.trim();
+                        if line == "WINDOW_DETECTOR_READY" {
+                            tracing::info!("Window detector is ready");
+                            diagnostics::set_detector_state(DetectorState::Ready, None);
+                            continue;
+                        }
+                        
+                        // Handle window information from Python
+                        if let Ok(window_info) = serde_json::from_str::<Value>(line) {
+                            diagnostics::note_detector_update();
+                            context::update_window_inventory(&window_info);
+                            if let Some(active_app) = window_info.get("active_app") {
+                                if let Some(name) = active_app.get("name").and_then(|v| v.as_str()) {
+                                    let title = active_app
+                                        .get("title")
+                                        .and_then(|v| v.as_str())
+                                        .map(|s| s.to_string());
+                                    usage::record_focus(name, title);
+                                    recorder::note_app_switch(name);
+                                }
+                            }
+
+                            // Emit the window state update to the frontend
+                            if let Err(e) = app_handle.emit("window_state_update", window_info) {
+                                tracing::warn!("Failed to emit window state: {}", e);
+                            } else {
+                                tracing::trace!("Window state update emitted to frontend");
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Error reading from Python: {}", e);
+                    diagnostics::set_detector_state(DetectorState::Failed, Some(e.to_string()));
+                    break;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn get_active_app_from_menubar() -> Option<String> {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let active_app: id = msg_send![workspace, frontmostApplication];
+        if active_app != nil {
+            let app_name: id = msg_send![active_app, localizedName];
+            if app_name != nil {
+                // Convert NSString to Rust String using UTF8String
+                let utf8_str: *const i8 = msg_send![app_name, UTF8String];
+                if !utf8_str.is_null() {
+                    let c_str = std::ffi::CStr::from_ptr(utf8_str);
+                    return c_str.to_str().ok().map(|s| s.to_string());
+                }
+            }
+        }
+        None
+    }
+}
+
+fn handle_window_state_update(app_handle: &tauri::AppHandle, window_info: Value) {
+    // Log the active app from Python; the icon is only counted, it is a
+    // large base64 blob
+    if let Some(obj) = window_info.get("active_app").and_then(|v| v.as_object()) {
+        tracing::debug!(
+            name = ?obj.get("name").and_then(|v| v.as_str()),
+            path = ?obj.get("path").and_then(|v| v.as_str()),
+            pid = ?obj.get("pid"),
+            icon_len = obj.get("icon").and_then(|v| v.as_str()).map_or(0, |s| s.len()),
+            "Window state update"
+        );
+    }
+
+    // Emit the update to the frontend
+    if let Some(window) = app_handle.get_webview_window("main") {
+        if let Err(e) = window.emit("window_state_update", &window_info) {
+            tracing::warn!("Failed to emit window state: {}", e);
+        }
+    } else {
+        tracing::warn!("Main window not found");
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn get_app_icon(app_name: &str) -> Option<String> {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app_path = NSString::alloc(nil).init_str(app_name);
+        let full_path: id = msg_send![workspace, fullPathForApplication:app_path];
+        
+        if full_path != nil {
+            let icon: id = msg_send![workspace, iconForFile:full_path];
+            if icon != nil {
+                // Set icon size
+                let _: () = msg_send![icon, setSize:NSSize { width: 32.0, height: 32.0 }];
+                
+                // Get TIFF representation
+                let tiff_data: id = msg_send![icon, TIFFRepresentation];
+                if tiff_data != nil {
+                    // Create bitmap representation
+                    let bitmap: id = msg_send![class!(NSBitmapImageRep), alloc];
+                    let bitmap: id = msg_send![bitmap, initWithData:tiff_data];
+                    
+                    if bitmap != nil {
+                        // Convert to PNG
+                        let png_data: id = msg_send![bitmap, representationUsingType:4 properties:nil]; // 4 = PNG
+                        if png_data != nil {
+                            // Convert to base64
+                            let base64_str: id = msg_send![png_data, base64EncodedStringWithOptions:0];
+                            let base64_str = std::ffi::CStr::from_ptr(msg_send![base64_str, UTF8String])
+                                .to_string_lossy()
+                                .into_owned();
+                            
+                            return Some(format!("data:image/png;base64,{}", base64_str));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "macos")]
+unsafe extern "C" fn active_app_change_callback(_this: id, _sel: Sel, _notification: id) {
+    let _span = tracing::info_span!("observer", event = "active_app").entered();
+
+    // Get the active app info
+    if let Some(app_name) = get_active_app_from_menubar() {
+        tracing::debug!(app = %app_name, "Active app changed");
+        usage::record_focus(&app_name, None);
+        recorder::note_app_switch(&app_name);
+        
+        // Get app icon
+        let icon = get_app_icon(&app_name);
+        tracing::trace!(has_icon = icon.is_some(), "Fetched app icon");
+        
+        // Emit directly to frontend first
+        {
+            let app_handle = APP_HANDLE.read();
+            if let Some(app_handle) = app_handle.as_ref() {
+                let window_info = json!({
+                    "active_app": {
+                        "name": app_name,
+                        "path": "",
+                        "pid": 0,
+                        "icon": icon
+                    }
+                });
+                context::update_window_inventory(&window_info);
+
+                if let Some(window) = app_handle.get_webview_window("main") {
+                    if let Err(e) = window.emit("window_state_update", &window_info) {
+                        tracing::warn!("Failed to emit window state: {}", e);
+                    } else {
+                        tracing::trace!("Window state update emitted to frontend");
+                    }
+                }
+            }
+        }
+        
+        // Also notify Python for other functionality
+        {
+            let mut stdin = PYTHON_STDIN.lock();
+            if let Some(ref mut stdin) = *stdin {
+                let command = format!("APP_CHANGED:{}\n", app_name);
+                if let Err(e) = stdin.write_all(command.as_bytes()) {
+                    tracing::warn!("Failed to notify Python: {}", e);
+                } else if let Err(e) = stdin.flush() {
+                    tracing::warn!("Failed to flush Python stdin: {}", e);
+                } else {
+                    tracing::trace!("Notified Python about app change");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn setup_active_app_observer() -> Result<(), String> {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let notification_center: id = msg_send![workspace, notificationCenter];
+        
+        // Create observer
+        let observer: id = msg_send![class!(NSObject), new];
+        
+        // Store observer
+        {
+            let mut obs = OBSERVER_COUNT.lock();
+            *obs += 1;
+        }
+        
+        // Add method to observer
+        let selector = sel!(activeAppDidChange:);
+        let types = std::ffi::CString::new("v@:@").map_err(|e| e.to_string())?;
+        
+        type Imp = unsafe extern "C" fn();
+        let imp: Imp = std::mem::transmute(active_app_change_callback as unsafe extern "C" fn(_, _, _));
+        
+        let observer_class: *mut Class = msg_send![observer, class];
+        class_addMethod(observer_class, selector, imp, types.as_ptr());
+        
+        // Register for notifications
+        let notification_name = NSString::alloc(nil).init_str("NSWorkspaceDidActivateApplicationNotification");
+        let _: () = msg_send![notification_center,
+            addObserver:observer
+            selector:selector
+            name:notification_name
+            object:nil];
+            
+        tracing::info!("Active app observer setup complete");
+        Ok(())
+    }
+}
+
+// Cleanup function for observers to prevent memory leaks
+#[cfg(target_os = "macos")]
+fn cleanup_observers() {
+    unsafe {
+        let obs_count = OBSERVER_COUNT.lock();
+        for _ in 0..*obs_count {
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let notification_center: id = msg_send![workspace, notificationCenter];
+            let _: () = msg_send![notification_center, removeObserver: nil];
+        }
+    }
+}
+
+/// Run the Tauri app. The `leo` CLI uses the same modules through `api`.
+pub fn run() {
+    logging::init();
+
+    // A second launch hands its arguments to the running instance instead
+    // of starting another bubble, detector and set of observers
+    if ipc::claim_instance() == ipc::Instance::Secondary {
+        ipc::forward_launch();
+        return;
+    }
+    crash::init_from_args();
+
+    // Set custom panic hook with cleanup
+    panic::set_hook(Box::new(|panic_info| {
+        crash::report_panic(panic_info);
+
+        #[cfg(target_os = "macos")]
+        cleanup_observers();
+
+        // Other threads just end; a panic on the main thread takes the app
+        // down, so come back in safe mode
+        if thread::current().name() == Some("main") && crash::relaunch_in_safe_mode() {
+            std::process::exit(1);
+        }
+    }));
+
+    let result = tauri::Builder::default()
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_deep_link::init())
+        .invoke_handler(tauri::generate_handler![
+            move_window,
+            set_window_size,
+            set_window_visible,
+            run_apple_script,
+            open_application,
+            type_text,
+            click_button,
+            automate_mac,
+            start_drag,
+            get_webview_window_position,
+            re_invoke_window_settings,
+            settings::get_settings,
+            settings::update_settings,
+            usage::get_app_usage,
+            usage::export_usage_timeline,
+            usage::clear_usage_history,
+            context::capture_context,
+            text_actions::run_text_action,
+            dictation::dictation_status,
+            dictation::transcribe_wav_file,
+            workflow::list_workflows,
+            workflow::run_workflow,
+            workflow::validate_workflow,
+            workflow::reload_workflows,
+            permissions::get_permission_state,
+            permissions::get_permission_decisions,
+            permissions::revoke_permissions,
+            permissions::set_allowed_apps,
+            audit::query_audit_log,
+            crash::get_crash_status,
+            crash::dismiss_crash_report,
+            crash::restart_normally,
+            diagnostics::get_diagnostics,
+            diagnostics::export_diagnostics_bundle,
+            ipc::list_ipc_clients,
+            ipc::create_ipc_client,
+            ipc::revoke_ipc_client,
+            secrets::set_secret,
+            secrets::has_secret,
+            secrets::delete_secret,
+            lock::lock_status,
+            lock::lock_app,
+            lock::unlock_app,
+            lock::set_lock_password,
+            lock::clear_lock_password,
+            lock::update_security_settings,
+            redaction::preview_redaction,
+            agent::run_agent,
+            agent::approve_agent_step,
+            agent::cancel_agent,
+            vision::vision_ask,
+            vision::list_pinned_captures,
+            vision::unpin_capture,
+            local_llm::list_local_models,
+            llm::chat,
+            llm::set_ai_provider,
+            prompts::list_prompt_templates,
+            prompts::validate_prompt_template,
+            ledger::get_llm_usage,
+            ledger::get_budget_status,
+            resilience::get_provider_health,
+            recorder::start_recording,
+            recorder::stop_recording,
+            recorder::recording_status,
+            recorder::trim_recording,
+            recorder::save_recording
+        ])
+        .setup(|app| {
+            let _span = tracing::info_span!("setup").entered();
+            tracing::info!("Starting setup");
+
+            let app_handle = app.handle();
+            
+            // Store app handle safely
+            {
+                let mut handle = APP_HANDLE.write();
+                *handle = Some(app_handle.clone());
+            }
+
+            match app.path().app_config_dir() {
+                Ok(dir) => {
+                    settings::init(&dir);
+                    workflow::init(&dir);
+                    prompts::init(&dir);
+                    ipc::init(&dir);
+                    secrets::init(&dir);
+                }
+                Err(e) => tracing::error!("Failed to resolve app config dir: {}", e),
+            }
+
+            let logging_settings = settings::current().logging;
+            logging::apply_settings(&logging_settings);
+
+            // Settings come first; the log files and the audit log read
+            // their rotation limits
+            match app.path().app_log_dir() {
+                Ok(dir) => {
+                    logging::attach_log_dir(&dir, &logging_settings);
+                    audit::init(&dir);
+                    crash::init(&dir.join("crashes"));
+                }
+                Err(e) => tracing::error!("Failed to resolve app log dir: {}", e),
+            }
+
+            // Focus history and the LLM ledger live in the app data dir
+            match app.path().app_data_dir() {
+                Ok(dir) => {
+                    usage::init(&dir);
+                    ledger::init(&dir);
+                    secrets::migrate_plaintext(&dir);
+                }
+                Err(e) => tracing::error!("Failed to resolve app data dir: {}", e),
+            }
+            usage::start_idle_monitor();
+            // Needs settings and secrets
+            lock::init();
+            lock::start_auto_lock();
+            context::init();
+            context::start_clipboard_watcher();
+            ipc::start();
+
+            workflow::register_hotkeys(app_handle);
+            deep_link::register(app_handle);
+
+            if crash::safe_mode() {
+                tracing::warn!("Started in safe mode; automation and the window detector are off");
+            } else {
+                // Setup active app observer
+                #[cfg(target_os = "macos")]
+                if let Err(e) = setup_active_app_observer() {
+                    tracing::error!("Failed to setup active app observer: {}", e);
+                }
+
+                // Start the window detector with app handle
+                start_window_detector(app_handle.clone());
+            }
+            
+            let window = app.get_webview_window("main").ok_or_else(|| {
+                tracing::error!("Failed to get main window");
+                "Failed to get main window"
+            })?;
+
+            tracing::debug!("Setting window properties");
+            window.set_always_on_top(true)?;
+
+            #[cfg(target_os = "macos")]
+            {
+                tracing::debug!("Configuring macOS specific settings");
+                apply_macos_window_customizations(&window)?;
+            }
+            tracing::info!("Setup completed successfully");
+            Ok(())
+        })
+        .on_window_event(|_window, event| match event {
+            tauri::WindowEvent::CloseRequested { api, .. } => {
+                #[cfg(target_os = "macos")]
+                cleanup_observers();
+                api.prevent_close();
+                if let Err(e) = _window.hide() {
+                    tracing::warn!("Failed to hide window on close: {}", e);
+                }
+            }
+            _ => {}
+        })
+        .run(tauri::generate_context!());
+
+    ipc::stop();
+    if let Err(e) = result {
+        tracing::error!("Error running application: {}", e);
+        #[cfg(target_os = "macos")]
+        cleanup_observers();
+        std::process::exit(1);
+    }
+    
+    // Final cleanup
+    #[cfg(target_os = "macos")]
+    cleanup_observers();
+}
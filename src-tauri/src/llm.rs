@@ -0,0 +1,881 @@
+// src-tauri/src/llm.rs
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::time::{Duration, Instant};
+
+use crate::audit::{self, AuditEntry, AuditKind, Outcome};
+use crate::error::{AppError, AppResult};
+use crate::ledger;
+use crate::local_llm::{self, LocalProvider};
+use crate::redaction::{self, Redactor, Vault};
+use crate::resilience::{self, ErrorClass, ProviderError, ResilientProvider, CONTENT_FILTERED};
+use crate::secrets;
+use crate::settings::{self, AiSettings};
+
+const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// Longest placeholder the redaction vault hands out, e.g. `[CREDIT_CARD_12]`.
+const MAX_PLACEHOLDER_LEN: usize = 32;
+/// Finish reasons of answers Gemini's safety settings held back.
+const GEMINI_BLOCK_REASONS: &[&str] = &["SAFETY", "RECITATION", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII", "IMAGE_SAFETY"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// Result of a tool call, answering `tool_call_id`.
+    Tool,
+}
+
+/// A tool the model may call, with a JSON schema for its arguments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Filled in by the provider when the API has no ids of its own.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMessage {
+    pub role: Role,
+    #[serde(default)]
+    pub content: String,
+    /// Images sent along, as data URLs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    /// Tools an assistant message asked to call.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    fn new(role: Role, content: String) -> Self {
+        Self {
+            role,
+            content,
+            images: Vec::new(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content.into())
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content.into())
+    }
+
+    /// The model's turn, as returned in `completion`.
+    pub fn assistant(completion: &Completion) -> Self {
+        Self {
+            tool_calls: completion.tool_calls.clone(),
+            ..Self::new(Role::Assistant, completion.text.clone())
+        }
+    }
+
+    pub fn tool_result(call: &ToolCall, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: Some(call.id.clone()),
+            ..Self::new(Role::Tool, content.into())
+        }
+    }
+
+    pub fn with_image(mut self, data_url: String) -> Self {
+        self.images.push(data_url);
+        self
+    }
+}
+
+/// The error for a failed HTTP request to `provider`: a classified
+/// `ProviderError` for error responses and unreachable servers.
+pub(crate) fn request_error(provider: &str, e: ureq::Error) -> AppError {
+    match e {
+        ureq::Error::Status(code, response) => {
+            let retry_after = resilience::retry_after_header(&response);
+            let body = response.into_string().unwrap_or_default();
+            AppError::Provider(ProviderError::from_response(provider, code, retry_after, &body))
+        }
+        ureq::Error::Transport(transport) => {
+            // Without the URL, which carries Gemini's API key
+            let detail = match transport.message() {
+                Some(message) => format!("{}: {}", transport.kind(), message),
+                None => transport.kind().to_string(),
+            };
+            match transport.kind() {
+                ureq::ErrorKind::Dns | ureq::ErrorKind::ConnectionFailed | ureq::ErrorKind::Io => AppError::Provider(
+                    ProviderError::new(ErrorClass::Network, format!("Cannot reach {}: {}", provider, detail)),
+                ),
+                _ => AppError::Failed(format!("{} request failed: {}", provider, detail)),
+            }
+        }
+    }
+}
+
+/// A stream that broke off midway, which another try may get through.
+pub(crate) fn stream_error(provider: &str, e: std::io::Error) -> AppError {
+    AppError::Provider(ProviderError::new(
+        ErrorClass::Network,
+        format!("The {} stream broke off: {}", provider, e),
+    ))
+}
+
+/// Media type and base64 payload of a `data:` URL.
+fn split_data_url(url: &str) -> Option<(&str, &str)> {
+    url.strip_prefix("data:")?.split_once(";base64,")
+}
+
+/// What a request is for, so spending can be broken down by feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    #[default]
+    Chat,
+    Summarize,
+    Translate,
+    Vision,
+    Agent,
+    Workflow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionRequest {
+    pub messages: Vec<ChatMessage>,
+    pub temperature: f32,
+    pub max_output_tokens: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSpec>,
+    #[serde(default)]
+    pub feature: Feature,
+}
+
+impl CompletionRequest {
+    pub fn new(messages: Vec<ChatMessage>) -> Self {
+        // Defaults for requests that no prompt template sets up
+        Self {
+            messages,
+            temperature: 0.7,
+            max_output_tokens: 1000,
+            tools: Vec::new(),
+            feature: Feature::Chat,
+        }
+    }
+
+    pub fn with_tools(mut self, tools: Vec<ToolSpec>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn for_feature(mut self, feature: Feature) -> Self {
+        self.feature = feature;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    pub text: String,
+    pub model: String,
+    pub usage: Option<TokenUsage>,
+    /// Tools the model wants called before it answers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// A chat completion backend. Calls are blocking; async commands should run
+/// them through `tauri::async_runtime::spawn_blocking`.
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion>;
+
+    /// Like `complete`, passing text on to `on_text` as it arrives. Providers
+    /// that cannot stream hand over the whole answer at once.
+    fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> AppResult<Completion> {
+        let completion = self.complete(request)?;
+        on_text(&completion.text);
+        Ok(completion)
+    }
+}
+
+pub struct GeminiProvider {
+    api_key: String,
+    model: String,
+    base_url: String,
+}
+
+impl GeminiProvider {
+    pub fn new(api_key: String, model: String) -> Self {
+        Self {
+            api_key,
+            model,
+            base_url: GEMINI_BASE_URL.to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    fn request_body(request: &CompletionRequest) -> Value {
+        let system: Vec<&str> = request
+            .messages
+            .iter()
+            .filter(|m| m.role == Role::System)
+            .map(|m| m.content.as_str())
+            .collect();
+
+        let contents: Vec<Value> = request
+            .messages
+            .iter()
+            .filter(|m| m.role != Role::System)
+            .map(|m| {
+                let mut parts = Vec::new();
+                if m.role == Role::Tool {
+                    // Gemini matches results to calls by function name
+                    let name = request
+                        .messages
+                        .iter()
+                        .flat_map(|m| &m.tool_calls)
+                        .find(|call| Some(&call.id) == m.tool_call_id.as_ref())
+                        .map(|call| call.name.as_str())
+                        .unwrap_or_default();
+                    parts.push(json!({
+                        "functionResponse": { "name": name, "response": { "content": m.content } }
+                    }));
+                } else if !m.content.is_empty() || (m.images.is_empty() && m.tool_calls.is_empty()) {
+                    parts.push(json!({ "text": m.content }));
+                }
+                for call in &m.tool_calls {
+                    parts.push(json!({ "functionCall": { "name": call.name, "args": call.arguments } }));
+                }
+                for (mime_type, data) in m.images.iter().filter_map(|image| split_data_url(image)) {
+                    parts.push(json!({ "inlineData": { "mimeType": mime_type, "data": data } }));
+                }
+                let role = if m.role == Role::Assistant { "model" } else { "user" };
+                json!({ "role": role, "parts": parts })
+            })
+            .collect();
+
+        let mut body = json!({
+            "contents": contents,
+            "generationConfig": {
+                "maxOutputTokens": request.max_output_tokens,
+                "temperature": request.temperature,
+                "topP": 0.8,
+                "topK": 40,
+            }
+        });
+        if !system.is_empty() {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system.join("\n\n") }] });
+        }
+        if !request.tools.is_empty() {
+            let declarations: Vec<Value> = request
+                .tools
+                .iter()
+                .map(|tool| json!({ "name": tool.name, "description": tool.description, "parameters": tool.parameters }))
+                .collect();
+            body["tools"] = json!([{ "functionDeclarations": declarations }]);
+        }
+        body
+    }
+
+    /// POST to `method`, e.g. "generateContent", of the configured model.
+    fn post(&self, method: &str, request: &CompletionRequest) -> AppResult<ureq::Response> {
+        let url = format!("{}/models/{}:{}", self.base_url, self.model, method);
+        ureq::post(&url)
+            .timeout(REQUEST_TIMEOUT)
+            .query("key", &self.api_key)
+            .send_json(Self::request_body(request))
+            .map_err(|e| request_error("Gemini", e))
+    }
+
+    /// Add a response, or one chunk of a streamed one, to `completion`.
+    fn add_chunk(completion: &mut Completion, chunk: &Value, on_text: &mut dyn FnMut(&str)) {
+        let parts = chunk["candidates"][0]["content"]["parts"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        for part in &parts {
+            if let Some(text) = part["text"].as_str() {
+                on_text(text);
+                completion.text.push_str(text);
+            }
+            if let Some(call) = part.get("functionCall") {
+                completion.tool_calls.push(ToolCall {
+                    id: format!("call_{}", completion.tool_calls.len() + 1),
+                    name: call["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: call.get("args").cloned().unwrap_or_else(|| json!({})),
+                });
+            }
+        }
+        if let Some(meta) = chunk.get("usageMetadata") {
+            completion.usage = Some(TokenUsage {
+                prompt_tokens: meta["promptTokenCount"].as_u64().unwrap_or(0) as u32,
+                completion_tokens: meta["candidatesTokenCount"].as_u64().unwrap_or(0) as u32,
+            });
+        }
+        if let Some(model) = chunk["modelVersion"].as_str() {
+            completion.model = model.to_string();
+        }
+    }
+
+    /// Why Gemini held back the answer, if it did.
+    fn blocked_reason(response: &Value) -> Option<&str> {
+        response["promptFeedback"]["blockReason"].as_str().or_else(|| {
+            response["candidates"][0]["finishReason"]
+                .as_str()
+                .filter(|reason| GEMINI_BLOCK_REASONS.contains(reason))
+        })
+    }
+
+    /// Error for a response without text or tool calls.
+    fn no_text_error(response: &Value) -> AppError {
+        match Self::blocked_reason(response) {
+            Some(reason) => AppError::Provider(ProviderError::new(
+                ErrorClass::ContentFilter,
+                format!("{} of Gemini ({})", CONTENT_FILTERED, reason),
+            )),
+            None => AppError::Failed(format!("Gemini returned no text: {}", response)),
+        }
+    }
+
+    fn empty_completion(&self) -> Completion {
+        Completion {
+            text: String::new(),
+            model: self.model.clone(),
+            usage: None,
+            tool_calls: Vec::new(),
+        }
+    }
+}
+
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &str {
+        "gemini"
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion> {
+        let response: Value = self
+            .post("generateContent", request)?
+            .into_json()
+            .map_err(|e| AppError::Failed(format!("Gemini sent an unreadable answer: {}", e)))?;
+
+        let mut completion = self.empty_completion();
+        Self::add_chunk(&mut completion, &response, &mut |_| {});
+        if completion.text.is_empty() && completion.tool_calls.is_empty() {
+            return Err(Self::no_text_error(&response));
+        }
+        Ok(completion)
+    }
+
+    fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> AppResult<Completion> {
+        let response = self.post("streamGenerateContent?alt=sse", request)?;
+
+        let mut completion = self.empty_completion();
+        let mut last_chunk = Value::Null;
+        // Server-sent events, one JSON response per `data:` line
+        for line in BufReader::new(response.into_reader()).lines() {
+            let line = line.map_err(|e| stream_error("Gemini", e))?;
+            let Some(data) = line.strip_prefix("data:") else {
+                continue;
+            };
+            last_chunk = serde_json::from_str(data.trim())
+                .map_err(|e| AppError::Failed(format!("Gemini sent an unreadable answer: {}", e)))?;
+            Self::add_chunk(&mut completion, &last_chunk, on_text);
+        }
+        if completion.text.is_empty() && completion.tool_calls.is_empty() {
+            return Err(Self::no_text_error(&last_chunk));
+        }
+        Ok(completion)
+    }
+}
+
+/// One scripted answer of the mock provider.
+#[derive(Debug, Clone, Default)]
+pub struct MockTurn {
+    pub text: String,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Offline provider that answers with canned text. Selected with
+/// `ai.provider = "mock"`; handy for exercising actions without an API key.
+pub struct MockProvider {
+    turns: parking_lot::Mutex<VecDeque<MockTurn>>,
+}
+
+impl MockProvider {
+    pub fn new(responses: Vec<String>) -> Self {
+        Self::scripted(
+            responses
+                .into_iter()
+                .map(|text| MockTurn {
+                    text,
+                    ..MockTurn::default()
+                })
+                .collect(),
+        )
+    }
+
+    pub fn scripted(turns: Vec<MockTurn>) -> Self {
+        Self {
+            turns: parking_lot::Mutex::new(turns.into()),
+        }
+    }
+}
+
+impl LlmProvider for MockProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion> {
+        // Scripted turns first, then echo the last user message
+        let turn = self.turns.lock().pop_front().unwrap_or_else(|| MockTurn {
+            text: request
+                .messages
+                .iter()
+                .rev()
+                .find(|m| m.role == Role::User)
+                .map(|m| m.content.clone())
+                .unwrap_or_default(),
+            ..MockTurn::default()
+        });
+        let tool_calls = turn
+            .tool_calls
+            .into_iter()
+            .enumerate()
+            .map(|(i, call)| ToolCall {
+                id: if call.id.is_empty() { format!("call_{}", i + 1) } else { call.id },
+                ..call
+            })
+            .collect();
+
+        let prompt_chars: usize = request.messages.iter().map(|m| m.content.len()).sum();
+        Ok(Completion {
+            usage: Some(TokenUsage {
+                prompt_tokens: (prompt_chars / 4) as u32,
+                completion_tokens: (turn.text.len() / 4) as u32,
+            }),
+            text: turn.text,
+            model: "mock".to_string(),
+            tool_calls,
+        })
+    }
+}
+
+/// Records every call in the audit log. Prompts and responses are only
+/// included when the audit settings ask for them.
+pub struct AuditedProvider {
+    inner: Box<dyn LlmProvider>,
+    model: String,
+}
+
+impl AuditedProvider {
+    pub fn new(inner: Box<dyn LlmProvider>, model: String) -> Self {
+        Self { inner, model }
+    }
+
+    fn record(&self, request: &CompletionRequest, started: Instant, result: &AppResult<Completion>) {
+        let mut entry = AuditEntry::new(
+            AuditKind::Llm,
+            self.inner.name(),
+            if result.is_ok() { Outcome::Ok } else { Outcome::Error },
+        );
+        entry.duration_ms = Some(started.elapsed().as_millis() as u64);
+        let mut details = json!({
+            "model": self.model,
+            "messages": request.messages.len(),
+        });
+        match result {
+            Ok(completion) => {
+                details["model"] = json!(completion.model);
+                if let Some(usage) = completion.usage.as_ref() {
+                    details["promptTokens"] = json!(usage.prompt_tokens);
+                    details["completionTokens"] = json!(usage.completion_tokens);
+                }
+            }
+            Err(e) => entry.error = Some(e.to_string()),
+        }
+        if settings::current().audit.include_llm_content {
+            // Images would swamp the log; only their size is kept
+            let messages: Vec<ChatMessage> = request
+                .messages
+                .iter()
+                .cloned()
+                .map(|mut message| {
+                    for image in message.images.iter_mut() {
+                        *image = format!("[image, {} bytes]", image.len());
+                    }
+                    message
+                })
+                .collect();
+            details["messages"] = json!(messages);
+            if let Ok(completion) = result {
+                details["response"] = json!(completion.text);
+            }
+        }
+        entry.details = details;
+        audit::record(entry);
+    }
+}
+
+impl LlmProvider for AuditedProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion> {
+        let started = Instant::now();
+        let result = self.inner.complete(request);
+        self.record(request, started, &result);
+        result
+    }
+
+    fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> AppResult<Completion> {
+        let started = Instant::now();
+        let result = self.inner.stream(request, on_text);
+        self.record(request, started, &result);
+        result
+    }
+}
+
+/// Masks personal data and secrets before a request leaves the machine, and
+/// puts the originals back into the response. Wraps `AuditedProvider`, so the
+/// audit log only ever sees masked prompts.
+pub struct RedactingProvider {
+    inner: Box<dyn LlmProvider>,
+}
+
+impl RedactingProvider {
+    pub fn new(inner: Box<dyn LlmProvider>) -> Self {
+        Self { inner }
+    }
+
+    fn mask(&self, redactor: &Redactor, request: &CompletionRequest) -> (CompletionRequest, Vault) {
+        let mut vault = Vault::default();
+        let mut masked = request.clone();
+        for message in masked.messages.iter_mut() {
+            message.content = redactor.redact(&message.content, &mut vault);
+            for call in message.tool_calls.iter_mut() {
+                redactor.redact_value(&mut call.arguments, &mut vault);
+            }
+        }
+        if !vault.is_empty() {
+            tracing::info!("Masked before sending to {}: {:?}", self.inner.name(), vault.counts());
+        }
+        (masked, vault)
+    }
+
+    fn unmask(vault: &Vault, mut completion: Completion) -> Completion {
+        completion.text = vault.restore(&completion.text);
+        for call in completion.tool_calls.iter_mut() {
+            vault.restore_value(&mut call.arguments);
+        }
+        completion
+    }
+}
+
+impl LlmProvider for RedactingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion> {
+        let Some(redactor) = redaction::current_redactor() else {
+            return self.inner.complete(request);
+        };
+        let (masked, vault) = self.mask(&redactor, request);
+        let completion = self.inner.complete(&masked)?;
+        Ok(Self::unmask(&vault, completion))
+    }
+
+    fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> AppResult<Completion> {
+        let Some(redactor) = redaction::current_redactor() else {
+            return self.inner.stream(request, on_text);
+        };
+        let (masked, vault) = self.mask(&redactor, request);
+
+        // A placeholder can be split across chunks, so text from an open
+        // `[` on is held back until it is closed
+        let mut pending = String::new();
+        let result = self.inner.stream(&masked, &mut |text| {
+            pending.push_str(text);
+            let ready = match pending.rfind('[') {
+                Some(open) if !pending[open..].contains(']') && pending.len() - open < MAX_PLACEHOLDER_LEN => open,
+                _ => pending.len(),
+            };
+            if ready > 0 {
+                on_text(&vault.restore(&pending[..ready]));
+                pending.drain(..ready);
+            }
+        });
+        let completion = result?;
+        if !pending.is_empty() {
+            on_text(&vault.restore(&pending));
+        }
+        Ok(Self::unmask(&vault, completion))
+    }
+}
+
+/// Answers with a local model when the cloud provider cannot be reached,
+/// e.g. while offline, is overloaded or rate limited, or is being skipped
+/// after failing. Other errors are passed on, and so is a stream that broke
+/// off after some of its text was shown.
+pub struct FallbackProvider {
+    primary: Box<dyn LlmProvider>,
+    fallback: Box<dyn LlmProvider>,
+}
+
+impl FallbackProvider {
+    pub fn new(primary: Box<dyn LlmProvider>, fallback: Box<dyn LlmProvider>) -> Self {
+        Self { primary, fallback }
+    }
+
+    fn fall_back(
+        &self,
+        error: AppError,
+        run: impl FnOnce(&dyn LlmProvider) -> AppResult<Completion>,
+    ) -> AppResult<Completion> {
+        tracing::warn!("{}; answering with {}", error, self.fallback.name());
+        run(self.fallback.as_ref()).map_err(|e| {
+            let message = format!("{} (the {} fallback failed too: {})", error, self.fallback.name(), e);
+            match error {
+                AppError::Provider(error) => AppError::Provider(ProviderError { message, ..error }),
+                _ => AppError::Failed(message),
+            }
+        })
+    }
+}
+
+impl LlmProvider for FallbackProvider {
+    fn name(&self) -> &str {
+        self.primary.name()
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion> {
+        match self.primary.complete(request) {
+            Err(e) if resilience::class_of(&e).is_outage() => self.fall_back(e, |provider| provider.complete(request)),
+            result => result,
+        }
+    }
+
+    fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> AppResult<Completion> {
+        let mut passed_on = false;
+        let result = self.primary.stream(request, &mut |text| {
+            passed_on = true;
+            on_text(text);
+        });
+        match result {
+            Err(e) if resilience::class_of(&e).is_outage() && !passed_on => {
+                self.fall_back(e, |provider| provider.stream(request, on_text))
+            }
+            result => result,
+        }
+    }
+}
+
+/// Holds calls back once a spending limit is used up and books every answer
+/// in the ledger. Wraps each concrete provider, so a fallback answer is
+/// booked under the provider that gave it.
+pub struct MeteredProvider {
+    inner: Box<dyn LlmProvider>,
+}
+
+impl MeteredProvider {
+    pub fn new(inner: Box<dyn LlmProvider>) -> Self {
+        Self { inner }
+    }
+}
+
+impl LlmProvider for MeteredProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion> {
+        ledger::check(self.inner.name())?;
+        let completion = self.inner.complete(request)?;
+        ledger::record(self.inner.name(), request, &completion);
+        Ok(completion)
+    }
+
+    fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> AppResult<Completion> {
+        ledger::check(self.inner.name())?;
+        let completion = self.inner.stream(request, on_text)?;
+        ledger::record(self.inner.name(), request, &completion);
+        Ok(completion)
+    }
+}
+
+#[cfg(not(test))]
+fn mock_provider() -> MockProvider {
+    MockProvider::new(Vec::new())
+}
+
+/// Answers the `mock` provider gives next, before it goes back to echoing.
+/// Lets tests script what `configured_provider` answers.
+#[cfg(test)]
+pub static MOCK_SCRIPT: once_cell::sync::Lazy<parking_lot::Mutex<Vec<MockTurn>>> =
+    once_cell::sync::Lazy::new(Default::default);
+
+#[cfg(test)]
+fn mock_provider() -> MockProvider {
+    MockProvider::scripted(std::mem::take(&mut *MOCK_SCRIPT.lock()))
+}
+
+fn cloud_provider(ai: &AiSettings) -> AppResult<Box<dyn LlmProvider>> {
+    let provider: Box<dyn LlmProvider> = match ai.provider.as_str() {
+        "gemini" => {
+            let api_key = secrets::get("gemini").ok_or_else(|| {
+                AppError::BackendUnavailable("No Gemini API key configured".to_string())
+            })?;
+            Box::new(GeminiProvider::new(api_key, ai.model.clone()))
+        }
+        "mock" => Box::new(mock_provider()),
+        other => {
+            return Err(AppError::BackendUnavailable(format!("Unknown LLM provider: {}", other)))
+        }
+    };
+    let provider = Box::new(MeteredProvider::new(Box::new(ResilientProvider::new(provider))));
+    Ok(Box::new(AuditedProvider::new(provider, ai.model.clone())))
+}
+
+fn local_provider(ai: &AiSettings) -> Box<dyn LlmProvider> {
+    let provider = LocalProvider::new(&ai.local);
+    let model = provider.model().to_string();
+    let provider = Box::new(MeteredProvider::new(Box::new(ResilientProvider::new(Box::new(provider)))));
+    Box::new(AuditedProvider::new(provider, model))
+}
+
+/// Build the provider selected in the AI settings. Requests to a model
+/// server on this machine never leave it and are not masked.
+pub fn provider_from_settings(ai: &AiSettings) -> AppResult<Box<dyn LlmProvider>> {
+    if ai.provider == "local" {
+        let provider = local_provider(ai);
+        if local_llm::is_loopback(&ai.local.endpoint) {
+            return Ok(provider);
+        }
+        return Ok(Box::new(RedactingProvider::new(provider)));
+    }
+    let mut provider = cloud_provider(ai)?;
+    if ai.local.offline_fallback && ai.provider != "mock" {
+        provider = Box::new(FallbackProvider::new(provider, local_provider(ai)));
+    }
+    Ok(Box::new(RedactingProvider::new(provider)))
+}
+
+pub fn configured_provider() -> AppResult<Box<dyn LlmProvider>> {
+    provider_from_settings(&settings::current().ai)
+}
+
+/// Providers the settings can switch to; others are set in settings.json.
+const SELECTABLE_PROVIDERS: &[&str] = &["gemini", "local"];
+
+/// Switch the provider and the local model server, which `update_settings`
+/// leaves alone. Sending chats off this machine needs the user's consent.
+#[tauri::command]
+pub async fn set_ai_provider(provider: String, endpoint: Option<String>) -> AppResult<()> {
+    if !SELECTABLE_PROVIDERS.contains(&provider.as_str()) {
+        return Err(AppError::InvalidInput(format!("Unknown LLM provider: {}", provider)));
+    }
+    let current = settings::current().ai;
+    let endpoint = match endpoint {
+        Some(endpoint) => endpoint.trim().trim_end_matches('/').to_string(),
+        None => current.local.endpoint.clone(),
+    };
+    url::Url::parse(&endpoint).map_err(|e| AppError::InvalidInput(format!("Invalid endpoint {}: {}", endpoint, e)))?;
+
+    let message = if provider != current.provider && provider != "local" {
+        Some(format!("Leo AI wants to send your chats to {}.", provider))
+    } else if endpoint != current.local.endpoint && !local_llm::is_loopback(&endpoint) {
+        Some(format!(
+            "Leo AI wants to send your chats to the model server at {}, which is not on this computer.",
+            endpoint
+        ))
+    } else {
+        None
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Some(message) = message {
+            crate::permissions::confirm("set_ai_provider", &message)?;
+        }
+        settings::update(|settings| {
+            settings.ai.provider = provider;
+            settings.ai.local.endpoint = endpoint;
+        })?;
+        Ok(())
+    })
+    .await?
+}
+
+/// Answer a chat with the configured provider. The bubble's chat goes
+/// through here, so its calls are audited, masked and metered like the rest.
+/// `template` names the prompt template to use instead of the one bound to
+/// the active app.
+#[tauri::command]
+pub async fn chat(messages: Vec<ChatMessage>, template: Option<String>) -> AppResult<Completion> {
+    crate::lock::ensure_unlocked()?;
+    if messages.is_empty() {
+        return Err(AppError::InvalidInput("No messages".to_string()));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let (request, ai) = crate::prompts::chat_request(messages, template.as_deref())?;
+        let provider = provider_from_settings(&ai)?;
+        provider.complete(&request.for_feature(Feature::Chat))
+    })
+    .await?
+}
@@ -0,0 +1,731 @@
+// src-tauri/src/local_llm.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::llm::{
+    request_error, stream_error, ChatMessage, Completion, CompletionRequest, LlmProvider, Role, TokenUsage, ToolCall,
+};
+use crate::resilience::{ErrorClass, ProviderError, CONTENT_FILTERED};
+use crate::secrets;
+use crate::settings::{self, LocalModelSettings};
+
+/// Local models can take a while on slow machines.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Assumed when neither the settings nor the server give a context window.
+const DEFAULT_CONTEXT_WINDOW: u32 = 4096;
+/// Rough size of a token, for fitting prompts into the context window.
+const CHARS_PER_TOKEN: usize = 4;
+/// What an image is counted as, since its real cost depends on the model.
+const IMAGE_TOKENS: usize = 768;
+/// Per-message overhead of the chat template.
+const MESSAGE_TOKENS: usize = 4;
+
+/// A model the local server offers.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalModel {
+    pub id: String,
+    /// Tokens the model takes in, when the server reports it.
+    pub context_window: Option<u32>,
+}
+
+/// Models found so far, by endpoint and configured model. Asking the server
+/// is slow enough that it should not happen on every request.
+static MODELS: Lazy<Mutex<HashMap<(String, String), LocalModel>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Whether `endpoint` is on this machine. Requests to a server anywhere
+/// else are masked like cloud requests.
+pub fn is_loopback(endpoint: &str) -> bool {
+    match url::Url::parse(endpoint).ok().as_ref().and_then(url::Url::host) {
+        Some(url::Host::Domain(host)) => host.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// A model behind an OpenAI-compatible chat completions API on this
+/// machine, such as Ollama or llama.cpp's `llama-server`. Selected with
+/// `ai.provider = "local"`, and used while offline otherwise.
+pub struct LocalProvider {
+    endpoint: String,
+    /// Servers that check keys get the `local` secret as a bearer token.
+    api_key: Option<String>,
+    model: String,
+    context_window: Option<u32>,
+}
+
+impl LocalProvider {
+    pub fn new(settings: &LocalModelSettings) -> Self {
+        Self {
+            endpoint: settings.endpoint.trim_end_matches('/').to_string(),
+            api_key: secrets::get("local"),
+            model: settings.model.clone(),
+            context_window: settings.context_window,
+        }
+    }
+
+    /// The configured model; empty when the server's first model is used.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn authorized(&self, request: ureq::Request) -> ureq::Request {
+        match self.api_key.as_deref() {
+            Some(key) => request.set("Authorization", &format!("Bearer {}", key)),
+            None => request,
+        }
+    }
+
+    /// Models the server lists under `/models`.
+    pub fn list_models(&self) -> AppResult<Vec<LocalModel>> {
+        let url = format!("{}/models", self.endpoint);
+        let response: Value = self
+            .authorized(ureq::get(&url).timeout(DISCOVERY_TIMEOUT))
+            .call()
+            .map_err(|e| request_error("the local model server", e))?
+            .into_json()
+            .map_err(|e| AppError::Failed(format!("The local model server sent an unreadable answer: {}", e)))?;
+
+        Ok(response["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                Some(LocalModel {
+                    id: entry["id"].as_str()?.to_string(),
+                    context_window: listed_context_window(entry),
+                })
+            })
+            .collect())
+    }
+
+    /// Ollama leaves the context window out of `/v1/models` but has it in
+    /// `/api/show`.
+    fn ollama_context_window(&self, model: &str) -> Option<u32> {
+        let root = self.endpoint.trim_end_matches("/v1");
+        let response: Value = self
+            .authorized(ureq::post(&format!("{}/api/show", root)).timeout(DISCOVERY_TIMEOUT))
+            .send_json(json!({ "model": model }))
+            .ok()?
+            .into_json()
+            .ok()?;
+        response["model_info"]
+            .as_object()?
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|tokens| tokens as u32)
+    }
+
+    /// Find the model to ask and its context window on the server.
+    fn discover(&self) -> AppResult<LocalModel> {
+        let models = self.list_models()?;
+        let mut model = if self.model.is_empty() {
+            models
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    AppError::BackendUnavailable(format!("The local model server at {} has no models", self.endpoint))
+                })?
+        } else {
+            models
+                .into_iter()
+                .find(|model| model.id == self.model)
+                .unwrap_or_else(|| LocalModel {
+                    id: self.model.clone(),
+                    context_window: None,
+                })
+        };
+        if model.context_window.is_none() {
+            model.context_window = self.ollama_context_window(&model.id);
+        }
+        Ok(model)
+    }
+
+    /// The model to ask and its context window.
+    fn resolve(&self) -> AppResult<(String, u32)> {
+        let key = (self.endpoint.clone(), self.model.clone());
+        let cached = MODELS.lock().get(&key).cloned();
+        let model = match cached {
+            Some(model) => model,
+            None => {
+                let model = self.discover()?;
+                if model.context_window.is_none() && self.context_window.is_none() {
+                    tracing::info!(
+                        "No context window reported for {}; assuming {} tokens",
+                        model.id,
+                        DEFAULT_CONTEXT_WINDOW
+                    );
+                }
+                MODELS.lock().insert(key, model.clone());
+                model
+            }
+        };
+        let context_window = self
+            .context_window
+            .or(model.context_window)
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW);
+        Ok((model.id, context_window))
+    }
+
+    fn post(&self, body: &Value) -> AppResult<ureq::Response> {
+        let url = format!("{}/chat/completions", self.endpoint);
+        self.authorized(ureq::post(&url).timeout(REQUEST_TIMEOUT))
+            .send_json(body)
+            .map_err(|e| request_error("the local model server", e))
+    }
+
+    fn request_body(&self, request: &CompletionRequest, stream: bool) -> AppResult<(Value, String)> {
+        let (model, context_window) = self.resolve()?;
+        let (messages, max_tokens) = fit(&request.messages, request.max_output_tokens, context_window, &model)?;
+
+        let mut body = json!({
+            "model": model,
+            "messages": messages.iter().map(message_json).collect::<Vec<_>>(),
+            "temperature": request.temperature,
+            "max_tokens": max_tokens,
+            "stream": stream,
+        });
+        if stream {
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        if !request.tools.is_empty() {
+            body["tools"] = request
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": { "name": tool.name, "description": tool.description, "parameters": tool.parameters },
+                    })
+                })
+                .collect();
+        }
+        Ok((body, model))
+    }
+}
+
+/// Context window in a `/models` entry. llama.cpp reports the size the model
+/// was trained with, vLLM and others their own field names.
+fn listed_context_window(entry: &Value) -> Option<u32> {
+    [
+        &entry["context_length"],
+        &entry["context_window"],
+        &entry["max_model_len"],
+        &entry["meta"]["n_ctx"],
+        &entry["meta"]["n_ctx_train"],
+    ]
+    .into_iter()
+    .find_map(Value::as_u64)
+    .map(|tokens| tokens as u32)
+}
+
+fn estimate_tokens(message: &ChatMessage) -> usize {
+    let arguments: usize = message
+        .tool_calls
+        .iter()
+        .map(|call| call.name.len() + call.arguments.to_string().len())
+        .sum();
+    (message.content.len() + arguments) / CHARS_PER_TOKEN + message.images.len() * IMAGE_TOKENS + MESSAGE_TOKENS
+}
+
+/// Drop the oldest messages until the prompt and the answer fit into the
+/// context window. System messages and the last message are always kept,
+/// and tool calls go together with their results.
+fn fit(
+    messages: &[ChatMessage],
+    max_output_tokens: u32,
+    context_window: u32,
+    model: &str,
+) -> AppResult<(Vec<ChatMessage>, u32)> {
+    // Leave at least half of the window for the prompt
+    let max_tokens = max_output_tokens.min(context_window / 2);
+    let budget = (context_window - max_tokens) as usize;
+
+    let too_long = || {
+        AppError::InvalidInput(format!(
+            "The prompt is too long for the {}-token context window of {}",
+            context_window, model
+        ))
+    };
+
+    let mut kept: Vec<ChatMessage> = messages.to_vec();
+    let mut dropped = 0;
+    while kept.iter().map(estimate_tokens).sum::<usize>() > budget {
+        let oldest = kept[..kept.len().saturating_sub(1)]
+            .iter()
+            .position(|message| message.role != Role::System)
+            .ok_or_else(too_long)?;
+        // Tool results cannot outlive the call they answer
+        let calls: Vec<&str> = kept[oldest].tool_calls.iter().map(|call| call.id.as_str()).collect();
+        let drop: Vec<usize> = (oldest..kept.len())
+            .filter(|&i| {
+                i == oldest
+                    || kept[i].role == Role::Tool
+                        && kept[i].tool_call_id.as_deref().is_some_and(|id| calls.contains(&id))
+            })
+            .collect();
+        // The last message answers this call, so neither can go
+        if drop.contains(&(kept.len() - 1)) {
+            return Err(too_long());
+        }
+        for &i in drop.iter().rev() {
+            kept.remove(i);
+        }
+        dropped += drop.len();
+    }
+    if dropped > 0 {
+        tracing::info!(
+            "Dropped {} old messages to fit the {}-token context window of {}",
+            dropped,
+            context_window,
+            model
+        );
+    }
+    Ok((kept, max_tokens))
+}
+
+fn message_json(message: &ChatMessage) -> Value {
+    let role = match message.role {
+        Role::System => "system",
+        Role::User => "user",
+        Role::Assistant => "assistant",
+        Role::Tool => "tool",
+    };
+    let content = if message.images.is_empty() {
+        json!(message.content)
+    } else {
+        let mut parts = vec![json!({ "type": "text", "text": message.content })];
+        parts.extend(
+            message
+                .images
+                .iter()
+                .map(|image| json!({ "type": "image_url", "image_url": { "url": image } })),
+        );
+        Value::Array(parts)
+    };
+
+    let mut value = json!({ "role": role, "content": content });
+    if !message.tool_calls.is_empty() {
+        value["tool_calls"] = message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": { "name": call.name, "arguments": call.arguments.to_string() },
+                })
+            })
+            .collect();
+    }
+    if let Some(id) = message.tool_call_id.as_ref() {
+        value["tool_call_id"] = json!(id);
+    }
+    value
+}
+
+/// Arguments come as a JSON string; servers that do not escape them
+/// properly get theirs passed on as text.
+fn parse_arguments(arguments: &str) -> Value {
+    if arguments.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(arguments).unwrap_or_else(|_| json!(arguments))
+}
+
+fn parse_usage(usage: &Value) -> Option<TokenUsage> {
+    usage.as_object().map(|_| TokenUsage {
+        prompt_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+        completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as u32,
+    })
+}
+
+impl LlmProvider for LocalProvider {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion> {
+        let (body, model) = self.request_body(request, false)?;
+        let response: Value = self
+            .post(&body)?
+            .into_json()
+            .map_err(|e| AppError::Failed(format!("The local model server sent an unreadable answer: {}", e)))?;
+
+        let message = &response["choices"][0]["message"];
+        let text = message["content"].as_str().unwrap_or_default().to_string();
+        let tool_calls: Vec<ToolCall> = message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, call)| ToolCall {
+                id: call["id"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("call_{}", i + 1)),
+                name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                arguments: parse_arguments(call["function"]["arguments"].as_str().unwrap_or_default()),
+            })
+            .collect();
+        if text.is_empty() && tool_calls.is_empty() {
+            if response["choices"][0]["finish_reason"] == "content_filter" {
+                return Err(AppError::Provider(ProviderError::new(
+                    ErrorClass::ContentFilter,
+                    format!("{} of the local model server", CONTENT_FILTERED),
+                )));
+            }
+            return Err(AppError::Failed(format!("The local model returned no text: {}", response)));
+        }
+
+        Ok(Completion {
+            text,
+            model: response["model"].as_str().unwrap_or(&model).to_string(),
+            usage: parse_usage(&response["usage"]),
+            tool_calls,
+        })
+    }
+
+    fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> AppResult<Completion> {
+        let (body, model) = self.request_body(request, true)?;
+        let response = self.post(&body)?;
+
+        let mut completion = Completion {
+            text: String::new(),
+            model,
+            usage: None,
+            tool_calls: Vec::new(),
+        };
+        // Tool calls arrive in pieces: id, name and argument text by index
+        let mut calls: Vec<(String, String, String)> = Vec::new();
+        let mut filtered = false;
+        for line in BufReader::new(response.into_reader()).lines() {
+            let line = line.map_err(|e| stream_error("local model", e))?;
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                continue;
+            };
+            if data == "[DONE]" {
+                break;
+            }
+            let chunk: Value = serde_json::from_str(data)
+                .map_err(|e| AppError::Failed(format!("The local model server sent an unreadable answer: {}", e)))?;
+            filtered |= chunk["choices"][0]["finish_reason"] == "content_filter";
+            let delta = &chunk["choices"][0]["delta"];
+            if let Some(text) = delta["content"].as_str().filter(|text| !text.is_empty()) {
+                on_text(text);
+                completion.text.push_str(text);
+            }
+            for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                let index = call["index"].as_u64().unwrap_or(calls.len() as u64) as usize;
+                if calls.len() <= index {
+                    calls.resize(index + 1, Default::default());
+                }
+                let (id, name, arguments) = &mut calls[index];
+                id.push_str(call["id"].as_str().unwrap_or_default());
+                name.push_str(call["function"]["name"].as_str().unwrap_or_default());
+                arguments.push_str(call["function"]["arguments"].as_str().unwrap_or_default());
+            }
+            if let Some(usage) = parse_usage(&chunk["usage"]) {
+                completion.usage = Some(usage);
+            }
+            if let Some(model) = chunk["model"].as_str() {
+                completion.model = model.to_string();
+            }
+        }
+
+        completion.tool_calls = calls
+            .into_iter()
+            .enumerate()
+            .map(|(i, (id, name, arguments))| ToolCall {
+                id: if id.is_empty() { format!("call_{}", i + 1) } else { id },
+                name,
+                arguments: parse_arguments(&arguments),
+            })
+            .collect();
+        if completion.text.is_empty() && completion.tool_calls.is_empty() {
+            if filtered {
+                return Err(AppError::Provider(ProviderError::new(
+                    ErrorClass::ContentFilter,
+                    format!("{} of the local model server", CONTENT_FILTERED),
+                )));
+            }
+            return Err(AppError::Failed("The local model returned no text".to_string()));
+        }
+        Ok(completion)
+    }
+}
+
+/// Models the configured local server offers.
+#[tauri::command]
+pub async fn list_local_models() -> AppResult<Vec<LocalModel>> {
+    tauri::async_runtime::spawn_blocking(|| {
+        LocalProvider::new(&settings::current().ai.local).list_models()
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{Reply, StubServer};
+
+    fn provider(endpoint: &str, model: &str) -> LocalProvider {
+        LocalProvider {
+            endpoint: endpoint.to_string(),
+            api_key: Some("local-key".to_string()),
+            model: model.to_string(),
+            context_window: None,
+        }
+    }
+
+    fn body(request: &str) -> Value {
+        let (_, body) = request.split_once("\r\n\r\n").expect("request has a body");
+        serde_json::from_str(body).expect("JSON body")
+    }
+
+    fn call(id: &str) -> ChatMessage {
+        ChatMessage::assistant(&Completion {
+            text: String::new(),
+            model: "llama3".to_string(),
+            usage: None,
+            tool_calls: vec![ToolCall {
+                id: id.to_string(),
+                name: "open_application".to_string(),
+                arguments: json!({ "app_name": "Notes" }),
+            }],
+        })
+    }
+
+    fn result(id: &str, content: &str) -> ChatMessage {
+        ChatMessage::tool_result(
+            &ToolCall {
+                id: id.to_string(),
+                name: "open_application".to_string(),
+                arguments: Value::Null,
+            },
+            content,
+        )
+    }
+
+    #[test]
+    fn the_listed_model_and_its_context_window_are_used() {
+        let server = StubServer::start(vec![
+            Reply::json(
+                200,
+                json!({ "data": [{ "id": "llama3", "meta": { "n_ctx_train": 1000 } }, { "id": "phi3" }] }),
+            ),
+            Reply::json(
+                200,
+                json!({
+                    "model": "llama3",
+                    "choices": [{ "message": { "content": "", "tool_calls": [
+                        { "function": { "name": "open_application", "arguments": "{\"app_name\":\"Notes\"}" } }
+                    ] } }],
+                    "usage": { "prompt_tokens": 12, "completion_tokens": 5 },
+                }),
+            ),
+        ]);
+        let provider = provider(&format!("{}/v1", server.url), "");
+        let completion = provider
+            .complete(&CompletionRequest::new(vec![ChatMessage::user("Open Notes")]))
+            .unwrap();
+
+        assert_eq!(completion.model, "llama3");
+        assert_eq!(completion.tool_calls[0].id, "call_1");
+        assert_eq!(completion.tool_calls[0].arguments, json!({ "app_name": "Notes" }));
+        assert_eq!(completion.usage.unwrap().completion_tokens, 5);
+        let requests = server.requests();
+        assert!(requests[0].starts_with("GET /v1/models "), "{}", requests[0]);
+        assert!(requests[1].starts_with("POST /v1/chat/completions "), "{}", requests[1]);
+        assert!(requests
+            .iter()
+            .all(|request| request.contains("Authorization: Bearer local-key\r\n")));
+        let sent = body(&requests[1]);
+        assert_eq!(sent["model"], "llama3");
+        // Half of the window is left for the prompt
+        assert_eq!(sent["max_tokens"], 500);
+        assert_eq!(sent["messages"][0], json!({ "role": "user", "content": "Open Notes" }));
+    }
+
+    #[test]
+    fn ollama_is_asked_for_the_context_window() {
+        let server = StubServer::start(vec![
+            Reply::json(200, json!({ "data": [{ "id": "qwen2" }] })),
+            Reply::json(200, json!({ "model_info": { "qwen2.context_length": 600 } })),
+            Reply::json(200, json!({ "choices": [{ "message": { "content": "Hi" } }] })),
+        ]);
+        let provider = provider(&format!("{}/v1", server.url), "qwen2");
+        let completion = provider
+            .complete(&CompletionRequest::new(vec![ChatMessage::user("Hello")]))
+            .unwrap();
+
+        assert_eq!(completion.text, "Hi");
+        assert_eq!(completion.model, "qwen2");
+        let requests = server.requests();
+        assert!(requests[1].starts_with("POST /api/show "), "{}", requests[1]);
+        assert!(
+            requests[1].contains("Authorization: Bearer local-key\r\n"),
+            "{}",
+            requests[1]
+        );
+        assert_eq!(body(&requests[1]), json!({ "model": "qwen2" }));
+        assert_eq!(body(&requests[2])["max_tokens"], 300);
+    }
+
+    #[test]
+    fn streamed_text_and_tool_calls_are_put_together() {
+        let events = [
+            json!({ "model": "llama3", "choices": [{ "delta": { "content": "Open" } }] }),
+            json!({ "choices": [{ "delta": { "content": "ing" } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [
+                { "index": 0, "id": "c1", "function": { "name": "open_", "arguments": "{\"app_" } }
+            ] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [
+                { "index": 0, "function": { "name": "application", "arguments": "name\":\"Notes\"}" } }
+            ] } }] }),
+            json!({ "choices": [], "usage": { "prompt_tokens": 7, "completion_tokens": 3 } }),
+        ];
+        let mut stream: String = events.iter().map(|event| format!("data: {}\n\n", event)).collect();
+        stream.push_str("data: [DONE]\n\n");
+        let server = StubServer::start(vec![
+            Reply::json(200, json!({ "data": [{ "id": "llama3", "context_length": 8192 }] })),
+            Reply::Respond {
+                status: 200,
+                headers: vec![("Content-Type".to_string(), "text/event-stream".to_string())],
+                body: stream,
+            },
+        ]);
+        let provider = provider(&server.url, "llama3");
+        let mut streamed = Vec::new();
+        let completion = provider
+            .stream(
+                &CompletionRequest::new(vec![ChatMessage::user("Open Notes")]),
+                &mut |text| streamed.push(text.to_string()),
+            )
+            .unwrap();
+
+        assert_eq!(streamed, ["Open", "ing"]);
+        assert_eq!(completion.text, "Opening");
+        assert_eq!(completion.tool_calls[0].id, "c1");
+        assert_eq!(completion.tool_calls[0].name, "open_application");
+        assert_eq!(completion.tool_calls[0].arguments, json!({ "app_name": "Notes" }));
+        assert_eq!(completion.usage.unwrap().prompt_tokens, 7);
+        let sent = body(&server.requests()[1]);
+        assert_eq!(sent["stream"], true);
+        assert_eq!(sent["stream_options"], json!({ "include_usage": true }));
+    }
+
+    #[test]
+    fn empty_and_filtered_answers_are_errors() {
+        let server = StubServer::start(vec![
+            Reply::json(200, json!({ "data": [{ "id": "llama3", "context_length": 8192 }] })),
+            Reply::json(
+                200,
+                json!({ "choices": [{ "message": { "content": "" }, "finish_reason": "content_filter" }] }),
+            ),
+            Reply::json(
+                200,
+                json!({ "choices": [{ "message": { "content": "" }, "finish_reason": "stop" }] }),
+            ),
+        ]);
+        let provider = provider(&server.url, "llama3");
+        let request = CompletionRequest::new(vec![ChatMessage::user("Hello")]);
+
+        match provider.complete(&request) {
+            Err(AppError::Provider(error)) => assert_eq!(error.kind, ErrorClass::ContentFilter),
+            other => panic!("expected a provider error, got {:?}", other),
+        }
+        assert!(matches!(provider.complete(&request), Err(AppError::Failed(_))));
+    }
+
+    #[test]
+    fn an_empty_server_has_no_model_to_use() {
+        let server = StubServer::start(vec![Reply::json(200, json!({ "data": [] }))]);
+        let provider = provider(&server.url, "");
+        assert!(matches!(
+            provider.complete(&CompletionRequest::new(vec![ChatMessage::user("Hello")])),
+            Err(AppError::BackendUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn old_tool_calls_are_dropped_with_their_results() {
+        let messages = vec![
+            ChatMessage::system("Be brief"),
+            call("call_1"),
+            result("call_1", &"r".repeat(400)),
+            call("call_2"),
+            result("call_2", "done"),
+            ChatMessage::user("And now?"),
+        ];
+        let (kept, max_tokens) = fit(&messages, 1000, 200, "llama3").unwrap();
+
+        assert_eq!(max_tokens, 100);
+        let kept: Vec<(Role, Option<&str>)> = kept
+            .iter()
+            .map(|message| (message.role, message.tool_call_id.as_deref()))
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                (Role::System, None),
+                (Role::Assistant, None),
+                (Role::Tool, Some("call_2")),
+                (Role::User, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_result_is_never_sent_without_its_call() {
+        let messages = vec![
+            ChatMessage::system("Be brief"),
+            ChatMessage::user("u".repeat(400)),
+            call("call_1"),
+            result("call_1", &"r".repeat(400)),
+        ];
+        assert!(matches!(
+            fit(&messages, 1000, 200, "llama3"),
+            Err(AppError::InvalidInput(_))
+        ));
+
+        let (kept, _) = fit(&messages, 1000, 8192, "llama3").unwrap();
+        assert_eq!(kept.len(), 4);
+    }
+
+    #[test]
+    fn only_this_machine_counts_as_loopback() {
+        for endpoint in [
+            "http://localhost:11434/v1",
+            "http://127.0.0.1:8080/v1",
+            "http://[::1]:8080/v1",
+            "http://LOCALHOST/v1",
+        ] {
+            assert!(is_loopback(endpoint), "{}", endpoint);
+        }
+        for endpoint in [
+            "http://192.168.1.5:11434/v1",
+            "https://localhost.example.com/v1",
+            "http://127.0.0.1.nip.io/v1",
+            "localhost:11434",
+            "",
+        ] {
+            assert!(!is_loopback(endpoint), "{}", endpoint);
+        }
+    }
+}
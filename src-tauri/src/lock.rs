@@ -0,0 +1,658 @@
+// src-tauri/src/lock.rs
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(test)]
+use std::sync::atomic::AtomicUsize;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+use crate::audit::{self, AuditEntry, AuditKind, Outcome};
+use crate::error::{AppError, AppResult};
+use crate::settings::{self, SecuritySettings};
+use crate::{secrets, usage};
+
+/// Secret holding the Argon2 hash of the app password. The `.` keeps it out
+/// of reach of the secret commands.
+const PASSWORD_SECRET: &str = "lock.password";
+const MIN_PASSWORD_CHARS: usize = 6;
+const AUTH_METHODS: [&str; 4] = ["none", "password", "system", "biometric"];
+/// Failed unlocks allowed before `LOCKOUT` applies.
+const MAX_FAILURES: u32 = 5;
+const LOCKOUT: Duration = Duration::from_secs(30);
+const MONITOR_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a system authentication dialog may stay up.
+#[cfg(target_os = "macos")]
+const AUTH_TIMEOUT: Duration = Duration::from_secs(120);
+
+static LOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Failed unlocks in a row, and when the last one happened.
+static FAILURES: Lazy<Mutex<(u32, Option<Instant>)>> = Lazy::new(|| Mutex::new((0, None)));
+
+/// Verifies the user through the OS: their account password, or biometrics
+/// where the platform has them.
+pub trait Authenticator: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// `Ok(false)` when the user failed or cancelled.
+    fn authenticate(&self, reason: &str, biometric: bool) -> AppResult<bool>;
+}
+
+/// LocalAuthentication: Touch ID, or the account password.
+#[cfg(target_os = "macos")]
+#[cfg_attr(test, allow(dead_code))]
+pub struct LocalAuthenticator;
+
+#[cfg(target_os = "macos")]
+impl Authenticator for LocalAuthenticator {
+    fn name(&self) -> &'static str {
+        "local_authentication"
+    }
+
+    fn authenticate(&self, reason: &str, biometric: bool) -> AppResult<bool> {
+        use block::ConcreteBlock;
+        use cocoa::base::{id, nil, BOOL, YES};
+        use cocoa::foundation::NSString;
+        use objc::{class, msg_send, sel, sel_impl};
+        use std::sync::mpsc;
+
+        #[link(name = "LocalAuthentication", kind = "framework")]
+        extern "C" {}
+
+        // LAPolicyDeviceOwnerAuthenticationWithBiometrics, LAPolicyDeviceOwnerAuthentication
+        let policy: i64 = if biometric { 1 } else { 2 };
+        let (tx, rx) = mpsc::channel();
+        unsafe {
+            let context: id = msg_send![class!(LAContext), new];
+            let reason = NSString::alloc(nil).init_str(reason);
+            let reply = ConcreteBlock::new(move |success: BOOL, _error: id| {
+                let _ = tx.send(success == YES);
+            })
+            .copy();
+            let _: () = msg_send![context, evaluatePolicy: policy localizedReason: reason reply: &*reply];
+            let result = rx
+                .recv_timeout(AUTH_TIMEOUT)
+                .map_err(|_| AppError::Failed("Timed out waiting for authentication".to_string()));
+            let _: () = msg_send![reason, release];
+            let _: () = msg_send![context, release];
+            result
+        }
+    }
+}
+
+/// polkit, which asks for the account password through the desktop's agent.
+#[cfg(target_os = "linux")]
+#[cfg_attr(test, allow(dead_code))]
+pub struct PolkitAuthenticator;
+
+#[cfg(target_os = "linux")]
+impl Authenticator for PolkitAuthenticator {
+    fn name(&self) -> &'static str {
+        "polkit"
+    }
+
+    fn authenticate(&self, _reason: &str, biometric: bool) -> AppResult<bool> {
+        if biometric {
+            return Err(AppError::BackendUnavailable(
+                "Biometric unlock is not available on Linux".to_string(),
+            ));
+        }
+        let status = std::process::Command::new("pkcheck")
+            .args(["--action-id", "org.freedesktop.policykit.exec", "--process"])
+            .arg(std::process::id().to_string())
+            .arg("--allow-user-interaction")
+            .status()
+            .map_err(|e| AppError::BackendUnavailable(format!("Failed to run pkcheck: {}", e)))?;
+        // 1 means pkcheck itself failed; 2 and 3 are a refusal or a dismissed dialog
+        match status.code() {
+            Some(0) => Ok(true),
+            Some(2 | 3) => Ok(false),
+            _ => Err(AppError::Failed(format!("pkcheck failed: {}", status))),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+#[cfg_attr(test, allow(dead_code))]
+pub struct UnsupportedAuthenticator;
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+impl Authenticator for UnsupportedAuthenticator {
+    fn name(&self) -> &'static str {
+        "unsupported"
+    }
+
+    fn authenticate(&self, _reason: &str, _biometric: bool) -> AppResult<bool> {
+        Err(AppError::BackendUnavailable(
+            "System authentication is not available on this platform".to_string(),
+        ))
+    }
+}
+
+#[cfg(all(not(test), target_os = "macos"))]
+static AUTHENTICATOR: LocalAuthenticator = LocalAuthenticator;
+#[cfg(all(not(test), target_os = "linux"))]
+static AUTHENTICATOR: PolkitAuthenticator = PolkitAuthenticator;
+#[cfg(all(not(test), not(any(target_os = "macos", target_os = "linux"))))]
+static AUTHENTICATOR: UnsupportedAuthenticator = UnsupportedAuthenticator;
+
+/// Answers without asking, and counts how often it was asked. Allows until
+/// a test says otherwise.
+#[cfg(test)]
+pub struct MockAuthenticator {
+    pub allow: AtomicBool,
+    pub calls: AtomicUsize,
+}
+
+#[cfg(test)]
+impl Authenticator for MockAuthenticator {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn authenticate(&self, _reason: &str, _biometric: bool) -> AppResult<bool> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(self.allow.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(test)]
+pub static AUTHENTICATOR: Lazy<MockAuthenticator> = Lazy::new(|| MockAuthenticator {
+    allow: AtomicBool::new(true),
+    calls: AtomicUsize::new(0),
+});
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    pub locked: bool,
+    /// Whether the settings ask for a lock that can actually be unlocked.
+    pub enabled: bool,
+    pub auth_method: String,
+    pub password_set: bool,
+    pub authenticator: &'static str,
+    /// False when this system cannot tell how long it has been idle, so
+    /// `auto_lock_minutes` has no effect.
+    pub idle_lock_available: bool,
+}
+
+fn password_hash() -> AppResult<Option<String>> {
+    secrets::get_internal(PASSWORD_SECRET)
+}
+
+/// A password lock without a password could never be unlocked, so it does
+/// not count. One whose password cannot be read still does.
+fn enabled() -> bool {
+    match settings::current().security.auth_method.as_str() {
+        "none" => false,
+        "password" => !matches!(password_hash(), Ok(None)),
+        _ => true,
+    }
+}
+
+pub fn is_locked() -> bool {
+    LOCKED.load(Ordering::SeqCst)
+}
+
+/// Fail with `AppError::Locked` while the app is locked. Called by anything
+/// that shows history or acts for the user.
+pub fn ensure_unlocked() -> AppResult<()> {
+    if is_locked() {
+        Err(AppError::Locked)
+    } else {
+        Ok(())
+    }
+}
+
+fn set_locked(locked: bool, reason: &str) {
+    if LOCKED.swap(locked, Ordering::SeqCst) == locked {
+        return;
+    }
+    tracing::info!("App {}: {}", if locked { "locked" } else { "unlocked" }, reason);
+
+    let mut entry = AuditEntry::new(AuditKind::Permission, if locked { "lock" } else { "unlock" }, Outcome::Ok);
+    entry.details = serde_json::json!({ "reason": reason });
+    audit::record(entry);
+
+    if let Some(app_handle) = crate::APP_HANDLE.read().as_ref() {
+        if let Err(e) = app_handle.emit("lock_changed", serde_json::json!({ "locked": locked })) {
+            tracing::warn!("Failed to emit lock_changed: {}", e);
+        }
+    }
+}
+
+fn hash_password(password: &str) -> AppResult<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| AppError::Failed(format!("Failed to hash password: {}", e)))?;
+    Ok(hash.to_string())
+}
+
+fn verify_password(password: &str, hash: &str) -> AppResult<bool> {
+    let hash = PasswordHash::new(hash).map_err(|e| AppError::Failed(format!("Stored password hash is invalid: {}", e)))?;
+    Ok(Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+/// Refuse while locked out after too many failures.
+fn check_lockout() -> AppResult<()> {
+    let (failures, last) = *FAILURES.lock();
+    if failures < MAX_FAILURES {
+        return Ok(());
+    }
+    let remaining = last.map_or(Duration::ZERO, |last| LOCKOUT.saturating_sub(last.elapsed()));
+    if remaining.is_zero() {
+        return Ok(());
+    }
+    Err(AppError::PermissionDenied(format!(
+        "too many failed attempts; try again in {} s",
+        remaining.as_secs().max(1)
+    )))
+}
+
+fn note_attempt(success: bool) {
+    let mut failures = FAILURES.lock();
+    if success {
+        *failures = (0, None);
+        return;
+    }
+    // A failure after the lockout ran out starts a new round
+    if failures.0 >= MAX_FAILURES {
+        failures.0 = 0;
+    }
+    *failures = (failures.0 + 1, Some(Instant::now()));
+}
+
+/// Check the user is who the lock expects: the app password, or the OS.
+fn authenticate(password: Option<&str>, reason: &str) -> AppResult<()> {
+    check_lockout()?;
+    let method = settings::current().security.auth_method;
+    let verified = match method.as_str() {
+        "password" => {
+            let hash = password_hash()?.ok_or_else(|| AppError::InvalidState("No app password is set".to_string()))?;
+            let password = password.ok_or_else(|| AppError::InvalidInput("Enter the app password".to_string()))?;
+            verify_password(password, &hash)?
+        }
+        "system" | "biometric" => AUTHENTICATOR.authenticate(reason, method == "biometric")?,
+        _ => true,
+    };
+    note_attempt(verified);
+    if verified {
+        Ok(())
+    } else {
+        Err(AppError::PermissionDenied("authentication failed".to_string()))
+    }
+}
+
+/// Lock on start when a lock is configured, or when the app password
+/// cannot be read to tell.
+pub fn init() {
+    if settings::current().security.auth_method != "password" {
+        if enabled() {
+            set_locked(true, "app started");
+        }
+        return;
+    }
+    match password_hash() {
+        Ok(Some(_)) => set_locked(true, "app started"),
+        Ok(None) => tracing::warn!("The app lock is set to password but no password is set; not locking"),
+        Err(e) => {
+            tracing::error!("Failed to read the app password, starting locked: {}", e);
+            set_locked(true, "app password unreadable");
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn screen_locked() -> Option<bool> {
+    use core_foundation::base::{CFType, TCFType};
+    use core_foundation::boolean::CFBoolean;
+    use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+    use core_foundation::string::CFString;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGSessionCopyCurrentDictionary() -> CFDictionaryRef;
+    }
+    let dict = unsafe { CGSessionCopyCurrentDictionary() };
+    if dict.is_null() {
+        return None;
+    }
+    let dict: CFDictionary<CFString, CFType> = unsafe { CFDictionary::wrap_under_create_rule(dict) };
+    // The key is only there while the screen is locked
+    let key = CFString::from_static_string("CGSSessionScreenIsLocked");
+    Some(
+        dict.find(&key)
+            .and_then(|value| value.downcast::<CFBoolean>())
+            .is_some_and(bool::from),
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn screen_locked() -> Option<bool> {
+    match usage::session_properties(&["LockedHint"])?.get("LockedHint")?.as_str() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn screen_locked() -> Option<bool> {
+    None
+}
+
+/// Lock after `auto_lock_minutes` without input, and when the screen locks.
+pub fn start_auto_lock() {
+    if usage::system_idle_seconds().is_none() {
+        tracing::warn!("Idle time is not available on this system; the app will not lock when idle");
+    }
+    thread::spawn(|| loop {
+        thread::sleep(MONITOR_INTERVAL);
+        if is_locked() || !enabled() {
+            continue;
+        }
+        let security = settings::current().security;
+
+        if security.auto_lock_minutes > 0 {
+            let idle = usage::system_idle_seconds();
+            if idle.is_some_and(|secs| secs >= (security.auto_lock_minutes * 60) as f64) {
+                set_locked(true, "idle");
+                continue;
+            }
+        }
+        if security.lock_on_screen_lock && screen_locked() == Some(true) {
+            set_locked(true, "screen locked");
+        }
+    });
+}
+
+/// Unlocked, without failed attempts, and with system authentication
+/// answering yes.
+#[cfg(test)]
+pub fn reset() {
+    LOCKED.store(false, Ordering::SeqCst);
+    *FAILURES.lock() = (0, None);
+    AUTHENTICATOR.allow.store(true, Ordering::SeqCst);
+}
+
+#[tauri::command]
+pub fn lock_status() -> LockStatus {
+    let security = settings::current().security;
+    LockStatus {
+        locked: is_locked(),
+        enabled: enabled(),
+        auth_method: security.auth_method,
+        password_set: matches!(password_hash(), Ok(Some(_))),
+        authenticator: AUTHENTICATOR.name(),
+        idle_lock_available: usage::system_idle_seconds().is_some(),
+    }
+}
+
+#[tauri::command]
+pub fn lock_app() -> AppResult<()> {
+    if !enabled() {
+        return Err(AppError::InvalidState(
+            "Choose how to unlock Leo AI in the security settings first".to_string(),
+        ));
+    }
+    set_locked(true, "locked by user");
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unlock_app(password: Option<String>) -> AppResult<()> {
+    if !is_locked() {
+        return Ok(());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = authenticate(password.as_deref(), "unlock Leo AI");
+        if let Err(e) = &result {
+            let mut entry = AuditEntry::new(AuditKind::Permission, "unlock", Outcome::Denied);
+            entry.details = serde_json::json!({ "reason": e.to_string() });
+            audit::record(entry);
+        }
+        result?;
+        set_locked(false, "authenticated");
+        Ok(())
+    })
+    .await?
+}
+
+/// Set or change the app password. Changing it needs the current one.
+#[tauri::command]
+pub async fn set_lock_password(current: Option<String>, password: String) -> AppResult<()> {
+    if password.chars().count() < MIN_PASSWORD_CHARS {
+        return Err(AppError::InvalidInput(format!(
+            "The password needs at least {} characters",
+            MIN_PASSWORD_CHARS
+        )));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        ensure_unlocked()?;
+        if let Some(hash) = password_hash()? {
+            check_lockout()?;
+            let current = current.ok_or_else(|| AppError::InvalidInput("Enter the current password".to_string()))?;
+            let verified = verify_password(&current, &hash)?;
+            note_attempt(verified);
+            if !verified {
+                return Err(AppError::PermissionDenied("the current password is wrong".to_string()));
+            }
+        }
+        secrets::set_internal(PASSWORD_SECRET, &hash_password(&password)?)?;
+        tracing::info!("App password set");
+        Ok(())
+    })
+    .await?
+}
+
+/// Remove the app password. A password lock is off until a new one is set.
+#[tauri::command]
+pub async fn clear_lock_password(current: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        ensure_unlocked()?;
+        let Some(hash) = password_hash()? else {
+            return Ok(());
+        };
+        check_lockout()?;
+        let verified = verify_password(&current, &hash)?;
+        note_attempt(verified);
+        if !verified {
+            return Err(AppError::PermissionDenied("the current password is wrong".to_string()));
+        }
+        secrets::delete_internal(PASSWORD_SECRET)?;
+        tracing::info!("App password removed");
+        Ok(())
+    })
+    .await?
+}
+
+/// Change how the app locks. While a lock is on, this needs the current
+/// app password or the OS to say yes first.
+#[tauri::command]
+pub async fn update_security_settings(current: Option<String>, security: SecuritySettings) -> AppResult<()> {
+    if !AUTH_METHODS.contains(&security.auth_method.as_str()) {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown authentication method: {}",
+            security.auth_method
+        )));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        ensure_unlocked()?;
+        if enabled() {
+            authenticate(current.as_deref(), "change the Leo AI lock")?;
+        }
+        settings::update(|settings| settings.security = security)?;
+        tracing::info!("Security settings changed");
+        Ok(())
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn an_unreadable_password_starts_locked() {
+        let isolated = test_support::isolated(|settings| settings.security.auth_method = "password".to_string());
+        std::fs::write(isolated.dir.path().join("secrets.enc"), b"truncated").unwrap();
+
+        init();
+        assert!(is_locked());
+        assert!(lock_status().enabled);
+        assert!(!lock_status().password_set);
+    }
+
+    #[test]
+    fn a_password_lock_without_a_password_stays_open() {
+        let _isolated = test_support::isolated(|settings| settings.security.auth_method = "password".to_string());
+
+        init();
+        assert!(!is_locked());
+        assert!(!lock_status().enabled);
+    }
+
+    fn isolated(auth_method: &str) -> test_support::Isolated {
+        let auth_method = auth_method.to_string();
+        test_support::isolated(|settings| settings.security.auth_method = auth_method)
+    }
+
+    fn unlock(password: Option<&str>) -> AppResult<()> {
+        tauri::async_runtime::block_on(unlock_app(password.map(str::to_string)))
+    }
+
+    fn set_password(current: Option<&str>, password: &str) -> AppResult<()> {
+        tauri::async_runtime::block_on(set_lock_password(current.map(str::to_string), password.to_string()))
+    }
+
+    fn asked() -> usize {
+        AUTHENTICATOR.calls.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn system_authentication_unlocks() {
+        let _isolated = isolated("biometric");
+        init();
+        assert!(is_locked());
+        assert!(matches!(ensure_unlocked(), Err(AppError::Locked)));
+        assert_eq!(lock_status().authenticator, "mock");
+
+        let before = asked();
+        unlock(None).unwrap();
+        assert!(!is_locked());
+        // Nothing to ask for while unlocked
+        unlock(None).unwrap();
+        assert_eq!(asked(), before + 1);
+    }
+
+    #[test]
+    fn repeated_failures_lock_out_for_a_while() {
+        let _isolated = isolated("system");
+        AUTHENTICATOR.allow.store(false, Ordering::SeqCst);
+        lock_app().unwrap();
+
+        let before = asked();
+        for _ in 0..MAX_FAILURES {
+            let error = unlock(None).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                AppError::PermissionDenied("authentication failed".to_string()).to_string()
+            );
+        }
+        AUTHENTICATOR.allow.store(true, Ordering::SeqCst);
+        let error = unlock(None).unwrap_err();
+        assert!(error.to_string().contains("too many failed attempts"), "{}", error);
+        assert_eq!(asked(), before + MAX_FAILURES as usize, "not asked while locked out");
+        assert!(is_locked());
+
+        // Once the lockout is over the next attempt goes through
+        FAILURES.lock().1 = Instant::now().checked_sub(LOCKOUT);
+        unlock(None).unwrap();
+        assert!(!is_locked());
+        assert_eq!(*FAILURES.lock(), (0, None));
+    }
+
+    #[test]
+    fn the_password_can_only_be_changed_with_the_current_one() {
+        let _isolated = isolated("password");
+        assert!(matches!(set_password(None, "short"), Err(AppError::InvalidInput(_))));
+        set_password(None, "first secret").unwrap();
+        assert!(lock_status().password_set);
+
+        assert!(matches!(
+            set_password(None, "second secret"),
+            Err(AppError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            set_password(Some("wrong"), "second secret"),
+            Err(AppError::PermissionDenied(_))
+        ));
+        set_password(Some("first secret"), "second secret").unwrap();
+
+        init();
+        assert!(is_locked());
+        assert!(matches!(
+            set_password(Some("second secret"), "third secret"),
+            Err(AppError::Locked)
+        ));
+        assert!(matches!(unlock(None), Err(AppError::InvalidInput(_))));
+        assert!(matches!(
+            unlock(Some("first secret")),
+            Err(AppError::PermissionDenied(_))
+        ));
+        unlock(Some("second secret")).unwrap();
+        assert!(!is_locked());
+
+        let clear = |current: &str| tauri::async_runtime::block_on(clear_lock_password(current.to_string()));
+        assert!(matches!(clear("first secret"), Err(AppError::PermissionDenied(_))));
+        clear("second secret").unwrap();
+        assert!(!lock_status().enabled);
+        assert!(matches!(lock_app(), Err(AppError::InvalidState(_))));
+    }
+
+    #[test]
+    fn changing_the_lock_needs_authentication() {
+        let _isolated = isolated("system");
+        let change = |current: Option<&str>, auth_method: &str| {
+            let security = SecuritySettings {
+                auth_method: auth_method.to_string(),
+                ..settings::current().security
+            };
+            tauri::async_runtime::block_on(update_security_settings(current.map(str::to_string), security))
+        };
+        assert!(matches!(change(None, "fingerprint"), Err(AppError::InvalidInput(_))));
+
+        AUTHENTICATOR.allow.store(false, Ordering::SeqCst);
+        assert!(matches!(change(None, "none"), Err(AppError::PermissionDenied(_))));
+        assert_eq!(settings::current().security.auth_method, "system");
+
+        AUTHENTICATOR.allow.store(true, Ordering::SeqCst);
+        let before = asked();
+        change(None, "password").unwrap();
+        assert_eq!(asked(), before + 1);
+        // Without a password set the lock is off, so nothing to ask for
+        change(None, "system").unwrap();
+        assert_eq!(asked(), before + 1);
+
+        lock_app().unwrap();
+        assert!(matches!(change(None, "none"), Err(AppError::Locked)));
+        unlock(None).unwrap();
+
+        change(None, "password").unwrap();
+        set_password(None, "first secret").unwrap();
+        assert!(matches!(
+            change(Some("wrong"), "none"),
+            Err(AppError::PermissionDenied(_))
+        ));
+        change(Some("first secret"), "none").unwrap();
+        assert!(!lock_status().enabled);
+    }
+}
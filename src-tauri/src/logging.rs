@@ -0,0 +1,249 @@
+// src-tauri/src/logging.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::writer::OptionalWriter;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+use crate::settings::LoggingSettings;
+
+/// Target of events re-emitted from the window detector's stderr.
+pub const HELPER_TARGET: &str = "helper";
+const LOG_FILE_PREFIX: &str = "leo";
+/// Lines kept in memory for the diagnostics panel.
+const RECENT_LINES: usize = 300;
+const RECENT_WARNING_LINES: usize = 100;
+
+static FILTER: Lazy<Mutex<Option<reload::Handle<EnvFilter, Registry>>>> =
+    Lazy::new(|| Mutex::new(None));
+static LOG_FILE: Lazy<RwLock<Option<NonBlocking>>> = Lazy::new(|| RwLock::new(None));
+/// Flushes buffered lines when dropped, so it lives as long as the process.
+static LOG_FILE_GUARD: Lazy<Mutex<Option<WorkerGuard>>> = Lazy::new(|| Mutex::new(None));
+static LOG_DIR: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+static RECENT: Lazy<Mutex<VecDeque<LogLine>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+/// Warnings and errors, kept longer than other lines.
+static RECENT_WARNINGS: Lazy<Mutex<VecDeque<LogLine>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+    pub at_ms: i64,
+    pub level: String,
+    pub target: String,
+    /// The message followed by the event's fields.
+    pub message: String,
+}
+
+#[derive(Default)]
+struct LineVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
+
+fn push_bounded(lines: &Mutex<VecDeque<LogLine>>, line: LogLine, capacity: usize) {
+    let mut lines = lines.lock();
+    if lines.len() == capacity {
+        lines.pop_front();
+    }
+    lines.push_back(line);
+}
+
+/// Keeps the last lines that passed the filter in memory.
+struct RecentLayer;
+
+impl<S: Subscriber> Layer<S> for RecentLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = LineVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        let line = LogLine {
+            at_ms: chrono::Utc::now().timestamp_millis(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.message + &visitor.fields,
+        };
+
+        if *metadata.level() <= Level::WARN {
+            push_bounded(&RECENT_WARNINGS, line.clone(), RECENT_WARNING_LINES);
+        }
+        push_bounded(&RECENT, line, RECENT_LINES);
+    }
+}
+
+/// The panic hook reads these too; waiting for a lock the panicking thread
+/// holds would hang it, so give up after a moment.
+fn snapshot(lines: &Mutex<VecDeque<LogLine>>) -> Vec<LogLine> {
+    lines
+        .try_lock_for(Duration::from_millis(100))
+        .map(|lines| lines.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Most recent log lines, oldest first.
+pub fn recent_lines() -> Vec<LogLine> {
+    snapshot(&RECENT)
+}
+
+/// Most recent warnings and errors, oldest first.
+pub fn recent_warnings() -> Vec<LogLine> {
+    snapshot(&RECENT_WARNINGS)
+}
+
+/// Log files in the app log dir, newest first.
+pub fn log_files() -> Vec<PathBuf> {
+    let Some(dir) = LOG_DIR.read().clone() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX) && name.ends_with(".log"))
+        })
+        .collect();
+    // Daily files are named leo.YYYY-MM-DD.log, so names sort by date
+    files.sort();
+    files.reverse();
+    files
+}
+
+/// Turn the logging settings into filter directives. Bare module names are
+/// resolved against this crate, e.g. "workflow" becomes
+/// "leo_ai_lib::workflow".
+fn directives(settings: &LoggingSettings) -> String {
+    let mut directives = vec![settings.level.clone()];
+    for (target, level) in &settings.modules {
+        if target == HELPER_TARGET || target.contains("::") {
+            directives.push(format!("{}={}", target, level));
+        } else {
+            directives.push(format!("{}::{}={}", env!("CARGO_CRATE_NAME"), target, level));
+        }
+    }
+    directives.join(",")
+}
+
+/// RUST_LOG, when set, takes precedence over the settings. Only the
+/// default settings are used before the subscriber is installed, so bad
+/// levels can be reported through it.
+fn build_filter(settings: &LoggingSettings) -> EnvFilter {
+    if let Ok(filter) = EnvFilter::try_from_default_env() {
+        return filter;
+    }
+    EnvFilter::try_new(directives(settings)).unwrap_or_else(|e| {
+        tracing::warn!("Invalid log levels in settings: {}", e);
+        EnvFilter::new(LoggingSettings::default().level)
+    })
+}
+
+fn file_writer() -> OptionalWriter<NonBlocking> {
+    match LOG_FILE.read().as_ref() {
+        Some(writer) => OptionalWriter::some(writer.clone()),
+        None => OptionalWriter::none(),
+    }
+}
+
+/// Install the global subscriber. Events go to stderr right away and also
+/// to the log file once `attach_log_dir` has run.
+pub fn init() {
+    let (filter, handle) = reload::Layer::new(build_filter(&LoggingSettings::default()));
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(fmt::layer().with_ansi(false).with_writer(file_writer))
+        .with(RecentLayer)
+        .try_init();
+
+    match result {
+        Ok(()) => *FILTER.lock() = Some(handle),
+        Err(e) => eprintln!("Failed to install log subscriber: {}", e),
+    }
+}
+
+/// Start writing daily rotated log files into `log_dir`.
+pub fn attach_log_dir(log_dir: &Path, settings: &LoggingSettings) {
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(settings.max_files.max(1))
+        .build(log_dir);
+
+    match appender {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            *LOG_FILE.write() = Some(writer);
+            *LOG_FILE_GUARD.lock() = Some(guard);
+            *LOG_DIR.write() = Some(log_dir.to_path_buf());
+            tracing::info!("Writing logs to {}", log_dir.display());
+        }
+        Err(e) => tracing::error!("Failed to open log file in {}: {}", log_dir.display(), e),
+    }
+}
+
+/// Apply changed log levels without a restart.
+pub fn apply_settings(settings: &LoggingSettings) {
+    let Some(handle) = FILTER.lock().clone() else {
+        return;
+    };
+    if let Err(e) = handle.reload(build_filter(settings)) {
+        tracing::error!("Failed to update log levels: {}", e);
+    }
+}
+
+/// Re-emit a line the window detector wrote to stderr. Lines from its
+/// logger start with the level name; anything else is ad-hoc output.
+pub fn helper_line(line: &str) {
+    let line = line.trim_end();
+    if line.trim().is_empty() {
+        return;
+    }
+    let (level, message) = match line.split_once(' ') {
+        Some(("ERROR" | "CRITICAL", rest)) => (Level::ERROR, rest),
+        Some(("WARNING", rest)) => (Level::WARN, rest),
+        Some(("INFO", rest)) => (Level::INFO, rest),
+        Some(("DEBUG", rest)) => (Level::DEBUG, rest),
+        _ if line.starts_with('❌') || line.starts_with("Traceback") => (Level::WARN, line),
+        _ => (Level::DEBUG, line),
+    };
+
+    match level {
+        Level::ERROR => tracing::error!(target: HELPER_TARGET, "{}", message),
+        Level::WARN => tracing::warn!(target: HELPER_TARGET, "{}", message),
+        Level::INFO => tracing::info!(target: HELPER_TARGET, "{}", message),
+        _ => tracing::debug!(target: HELPER_TARGET, "{}", message),
+    }
+}
@@ -0,0 +1,547 @@
+// src-tauri/src/permissions.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::process::Command;
+
+use crate::audit::{self, AuditEntry, AuditKind, Outcome};
+use crate::error::{AppError, AppResult};
+use crate::settings::{self, RememberedDecision};
+
+/// Decisions kept in memory for `get_permission_decisions`.
+const DECISION_HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Risk {
+    /// Allowed without asking.
+    Low,
+    /// Acts on another app; asks once per app.
+    Medium,
+    /// Can do anything the user can; asks once per target.
+    High,
+}
+
+/// What an automation command is about to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    OpenApp,
+    ReadSelection,
+    CaptureScreen,
+    TypeText,
+    PressKeys,
+    Click,
+    Dictation,
+    RecordInput,
+    RunWorkflow,
+    RunScript,
+}
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::OpenApp => "open_app",
+            Capability::ReadSelection => "read_selection",
+            Capability::CaptureScreen => "capture_screen",
+            Capability::TypeText => "type_text",
+            Capability::PressKeys => "press_keys",
+            Capability::Click => "click",
+            Capability::Dictation => "dictation",
+            Capability::RecordInput => "record_input",
+            Capability::RunWorkflow => "run_workflow",
+            Capability::RunScript => "run_script",
+        }
+    }
+
+    pub fn risk(self) -> Risk {
+        match self {
+            Capability::OpenApp | Capability::ReadSelection | Capability::CaptureScreen => Risk::Low,
+            Capability::TypeText
+            | Capability::PressKeys
+            | Capability::Click
+            | Capability::Dictation
+            | Capability::RecordInput => Risk::Medium,
+            // Workflows may run shell commands
+            Capability::RunWorkflow | Capability::RunScript => Risk::High,
+        }
+    }
+
+    /// Whether the target is an app, and so subject to the allowlist.
+    /// Recording covers every app, so it has none.
+    fn targets_app(self) -> bool {
+        !matches!(
+            self,
+            Capability::RecordInput | Capability::RunWorkflow | Capability::RunScript
+        )
+    }
+
+    /// Whether "Always Allow" may cover later calls. Every script does
+    /// something else, so one answer says nothing about the next.
+    fn rememberable(self) -> bool {
+        self != Capability::RunScript
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Capability::OpenApp => "open an app",
+            Capability::ReadSelection => "read your selection",
+            Capability::CaptureScreen => "capture your screen",
+            Capability::TypeText => "type text",
+            Capability::PressKeys => "press keys",
+            Capability::Click => "click",
+            Capability::Dictation => "type dictated text",
+            Capability::RecordInput => "record your keystrokes and clicks",
+            Capability::RunWorkflow => "run the workflow",
+            Capability::RunScript => "run an AppleScript",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionDecision {
+    pub at_ms: i64,
+    pub capability: Capability,
+    pub target: Option<String>,
+    pub risk: Risk,
+    pub allowed: bool,
+    pub reason: String,
+}
+
+static DECISIONS: Lazy<Mutex<VecDeque<PermissionDecision>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consent {
+    Deny,
+    AllowOnce,
+    AllowAlways,
+}
+
+/// Asks the user outside the webview, so page scripts cannot answer for
+/// them.
+pub trait ConsentPrompter: Send + Sync {
+    fn ask(&self, message: &str) -> AppResult<Consent>;
+}
+
+const DENY: &str = "Deny";
+const ALLOW_ONCE: &str = "Allow Once";
+const ALLOW_ALWAYS: &str = "Always Allow";
+
+#[cfg_attr(test, allow(dead_code))]
+pub struct NativePrompter;
+
+#[cfg_attr(test, allow(dead_code))]
+impl NativePrompter {
+    #[cfg(target_os = "macos")]
+    fn show(&self, message: &str) -> AppResult<String> {
+        use crate::automation::applescript_string;
+
+        let script = format!(
+            "display dialog {} with title \"Leo AI\" buttons {{\"{}\", \"{}\", \"{}\"}} default button \"{}\" with icon caution giving up after 60",
+            applescript_string(message),
+            DENY,
+            ALLOW_ONCE,
+            ALLOW_ALWAYS,
+            DENY
+        );
+        let output = Command::new("osascript")
+            .arg("-e")
+            .arg(&script)
+            .output()
+            .map_err(|e| AppError::BackendUnavailable(format!("Failed to run osascript: {}", e)))?;
+        // Cancelling exits non-zero; treat it like Deny
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .split(',')
+            .find_map(|part| part.trim().strip_prefix("button returned:"))
+            .unwrap_or(DENY)
+            .to_string())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn show(&self, message: &str) -> AppResult<String> {
+        let output = Command::new("zenity")
+            .args([
+                "--question",
+                "--title=Leo AI",
+                &format!("--text={}", message),
+                &format!("--ok-label={}", ALLOW_ONCE),
+                &format!("--cancel-label={}", DENY),
+                &format!("--extra-button={}", ALLOW_ALWAYS),
+                "--timeout=60",
+            ])
+            .output()
+            .map_err(|e| AppError::BackendUnavailable(format!("Failed to run zenity: {}", e)))?;
+        if output.status.success() {
+            return Ok(ALLOW_ONCE.to_string());
+        }
+        // The extra button exits like Cancel but prints its label
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+impl ConsentPrompter for NativePrompter {
+    fn ask(&self, message: &str) -> AppResult<Consent> {
+        Ok(match self.show(message)?.as_str() {
+            ALLOW_ONCE => Consent::AllowOnce,
+            ALLOW_ALWAYS => Consent::AllowAlways,
+            _ => Consent::Deny,
+        })
+    }
+}
+
+#[cfg(not(test))]
+static PROMPTER: NativePrompter = NativePrompter;
+
+/// Answers every prompt with `answer` instead of showing a dialog, and
+/// counts how often it was asked. Denies until a test says otherwise.
+#[cfg(test)]
+pub struct FixedPrompter {
+    pub answer: Mutex<Consent>,
+    pub asked: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(test)]
+impl ConsentPrompter for FixedPrompter {
+    fn ask(&self, _message: &str) -> AppResult<Consent> {
+        self.asked.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(*self.answer.lock())
+    }
+}
+
+#[cfg(test)]
+pub static PROMPTER: Lazy<FixedPrompter> = Lazy::new(|| FixedPrompter {
+    answer: Mutex::new(Consent::Deny),
+    asked: Default::default(),
+});
+
+/// Only one consent dialog at a time.
+static PROMPT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn record(capability: Capability, target: Option<&str>, allowed: bool, reason: &str) {
+    let decision = PermissionDecision {
+        at_ms: chrono::Utc::now().timestamp_millis(),
+        capability,
+        target: target.map(|t| t.to_string()),
+        risk: capability.risk(),
+        allowed,
+        reason: reason.to_string(),
+    };
+    tracing::info!(
+        "Permission {} for {} on {}: {}",
+        if allowed { "granted" } else { "denied" },
+        capability.name(),
+        target.unwrap_or("-"),
+        reason
+    );
+
+    let mut entry = AuditEntry::new(
+        AuditKind::Permission,
+        capability.name(),
+        if allowed { Outcome::Ok } else { Outcome::Denied },
+    );
+    entry.target = decision.target.clone();
+    entry.details = serde_json::json!({ "risk": decision.risk, "reason": reason });
+    audit::record(entry);
+
+    let mut decisions = DECISIONS.lock();
+    if decisions.len() == DECISION_HISTORY {
+        decisions.pop_front();
+    }
+    decisions.push_back(decision);
+}
+
+fn remembered(capability: Capability, target: Option<&str>) -> Option<bool> {
+    if !capability.rememberable() {
+        return None;
+    }
+    settings::current()
+        .permissions
+        .remembered
+        .iter()
+        .rev()
+        .find(|decision| {
+            decision.capability == capability.name()
+                && match (&decision.target, target) {
+                    // A decision without an app only ever covers capabilities without one
+                    (None, _) => !capability.targets_app(),
+                    (Some(remembered), Some(target)) => remembered.eq_ignore_ascii_case(target),
+                    (Some(_), None) => false,
+                }
+        })
+        .map(|decision| decision.allow)
+}
+
+fn remember(capability: Capability, target: Option<&str>) {
+    if capability.targets_app() && target.is_none() {
+        return;
+    }
+    let decision = RememberedDecision {
+        capability: capability.name().to_string(),
+        target: target.map(|t| t.to_string()),
+        allow: true,
+        decided_at_ms: chrono::Utc::now().timestamp_millis(),
+    };
+    if let Err(e) = settings::update(|settings| settings.permissions.remembered.push(decision)) {
+        tracing::warn!("Failed to remember permission: {}", e);
+    }
+}
+
+fn evaluate(capability: Capability, target: Option<&str>) -> (bool, String) {
+    let settings = settings::current();
+
+    if capability == Capability::RunScript && !settings.developer.allow_raw_scripts {
+        return (false, "raw script execution is disabled".to_string());
+    }
+    if capability.targets_app() {
+        // Without the app there is nothing to check the allowlist or past
+        // decisions against
+        let Some(app) = target else {
+            return (false, "the app it would act on is not known".to_string());
+        };
+        if !settings.permissions.app_allowed(app) {
+            return (false, format!("{} is not in the allowed apps", app));
+        }
+    }
+    if capability.risk() == Risk::Low {
+        return (true, "low risk".to_string());
+    }
+    if let Some(allow) = remembered(capability, target) {
+        return (allow, "remembered".to_string());
+    }
+
+    let _prompt = PROMPT_LOCK.lock();
+    let message = match target {
+        Some(target) => format!("Leo AI wants to {} in {}.", capability.describe(), target),
+        None => format!("Leo AI wants to {}.", capability.describe()),
+    };
+    match PROMPTER.ask(&message) {
+        Ok(Consent::AllowAlways) if capability.rememberable() => {
+            remember(capability, target);
+            (true, "approved and remembered".to_string())
+        }
+        Ok(Consent::AllowOnce | Consent::AllowAlways) => (true, "approved once".to_string()),
+        Ok(Consent::Deny) => (false, "denied by user".to_string()),
+        Err(e) => (false, format!("could not ask for consent: {}", e)),
+    }
+}
+
+/// Check that `capability` may act on `target`, asking the user when
+/// needed. Every decision is logged.
+pub fn require(capability: Capability, target: Option<&str>) -> AppResult<()> {
+    if crate::crash::safe_mode() {
+        record(capability, target, false, "safe mode");
+        return Err(AppError::SafeMode);
+    }
+    if crate::lock::is_locked() {
+        record(capability, target, false, "app locked");
+        return Err(AppError::Locked);
+    }
+    let (allowed, reason) = evaluate(capability, target);
+    record(capability, target, allowed, &reason);
+    if allowed {
+        Ok(())
+    } else {
+        Err(AppError::PermissionDenied(reason))
+    }
+}
+
+/// Ask before running something the user did not start from the app, such
+/// as an automation from a link. Never remembered, so every link asks again;
+/// the capability checks of the automation itself still apply afterwards.
+pub fn confirm(source: &str, message: &str) -> AppResult<()> {
+    if crate::crash::safe_mode() {
+        return Err(AppError::SafeMode);
+    }
+    crate::lock::ensure_unlocked()?;
+    let consent = {
+        let _prompt = PROMPT_LOCK.lock();
+        PROMPTER.ask(message)
+    };
+    let (allowed, reason) = match consent {
+        Ok(Consent::AllowOnce | Consent::AllowAlways) => (true, "approved once".to_string()),
+        Ok(Consent::Deny) => (false, "denied by user".to_string()),
+        Err(e) => (false, format!("could not ask for confirmation: {}", e)),
+    };
+    tracing::info!("Confirmation {} for {}: {}", if allowed { "given" } else { "refused" }, source, reason);
+
+    let mut entry = AuditEntry::new(
+        AuditKind::Permission,
+        "confirm",
+        if allowed { Outcome::Ok } else { Outcome::Denied },
+    );
+    entry.target = Some(source.to_string());
+    entry.details = serde_json::json!({ "reason": reason });
+    audit::record(entry);
+
+    if allowed {
+        Ok(())
+    } else {
+        Err(AppError::PermissionDenied(reason))
+    }
+}
+
+/// The frontmost app, which keystrokes and clicks land in.
+pub fn focused_app() -> Option<String> {
+    crate::context::current_active_app().map(|app| app.name)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionState {
+    pub allowed_apps: Vec<String>,
+    pub remembered: Vec<RememberedDecision>,
+    pub allow_raw_scripts: bool,
+}
+
+#[tauri::command]
+pub fn get_permission_state() -> PermissionState {
+    let settings = settings::current();
+    PermissionState {
+        allowed_apps: settings.permissions.allowed_apps,
+        remembered: settings.permissions.remembered,
+        allow_raw_scripts: settings.developer.allow_raw_scripts,
+    }
+}
+
+/// Most recent decisions, oldest first.
+#[tauri::command]
+pub fn get_permission_decisions() -> Vec<PermissionDecision> {
+    DECISIONS.lock().iter().cloned().collect()
+}
+
+/// Forget remembered approvals; `capability` and `target` narrow what is
+/// forgotten.
+#[tauri::command]
+pub fn revoke_permissions(capability: Option<String>, target: Option<String>) -> AppResult<()> {
+    settings::update(|settings| {
+        settings.permissions.remembered.retain(|decision| {
+            let capability_matches = capability.as_ref().is_none_or(|c| *c == decision.capability);
+            let target_matches = target.is_none() || decision.target == target;
+            !(capability_matches && target_matches)
+        })
+    })?;
+    Ok(())
+}
+
+/// Replace the app allowlist. Loosening it needs the user's consent.
+#[tauri::command]
+pub async fn set_allowed_apps(apps: Vec<String>) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let current = settings::current().permissions.allowed_apps;
+        // An empty list allows every app, so only a non-empty one can loosen
+        let loosens = !current.is_empty()
+            && (apps.is_empty()
+                || apps
+                    .iter()
+                    .any(|app| !current.iter().any(|c| c.eq_ignore_ascii_case(app))));
+        if loosens {
+            let message = if apps.is_empty() {
+                "Leo AI wants to allow automation of any app.".to_string()
+            } else {
+                format!("Leo AI wants to allow automation of: {}.", apps.join(", "))
+            };
+            let _prompt = PROMPT_LOCK.lock();
+            if !matches!(PROMPTER.ask(&message)?, Consent::AllowOnce | Consent::AllowAlways) {
+                return Err(AppError::PermissionDenied("denied by user".to_string()));
+            }
+        }
+        settings::update(|settings| settings.permissions.allowed_apps = apps)?;
+        Ok(())
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use std::sync::atomic::Ordering;
+
+    fn answer(consent: Consent) {
+        *PROMPTER.answer.lock() = consent;
+    }
+
+    fn asked() -> usize {
+        PROMPTER.asked.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn acting_on_an_unknown_app_is_denied_without_asking() {
+        let _isolated = test_support::isolated(|_| {});
+        answer(Consent::AllowAlways);
+        let before = asked();
+
+        for capability in [Capability::ReadSelection, Capability::TypeText, Capability::Dictation] {
+            assert!(matches!(require(capability, None), Err(AppError::PermissionDenied(_))));
+        }
+        assert_eq!(asked(), before);
+        assert!(settings::current().permissions.remembered.is_empty());
+    }
+
+    #[test]
+    fn decisions_without_an_app_do_not_cover_apps() {
+        let _isolated = test_support::isolated(|settings| {
+            settings.permissions.remembered.push(RememberedDecision {
+                capability: "type_text".to_string(),
+                target: None,
+                allow: true,
+                decided_at_ms: 0,
+            })
+        });
+
+        assert!(require(Capability::TypeText, Some("Terminal")).is_err());
+    }
+
+    #[test]
+    fn always_allow_is_remembered_per_app() {
+        let _isolated = test_support::isolated(|_| {});
+        answer(Consent::AllowAlways);
+        require(Capability::TypeText, Some("Notes")).unwrap();
+
+        answer(Consent::Deny);
+        let before = asked();
+        require(Capability::TypeText, Some("notes")).unwrap();
+        assert_eq!(asked(), before);
+        assert!(require(Capability::TypeText, Some("Mail")).is_err());
+    }
+
+    #[test]
+    fn recording_is_not_tied_to_an_app() {
+        let _isolated = test_support::isolated(|settings| {
+            settings.permissions.allowed_apps = vec!["Notes".to_string()];
+        });
+        answer(Consent::AllowAlways);
+        require(Capability::RecordInput, None).unwrap();
+
+        answer(Consent::Deny);
+        require(Capability::RecordInput, None).unwrap();
+        assert!(require(Capability::TypeText, Some("Mail")).is_err());
+    }
+
+    #[test]
+    fn scripts_are_asked_about_every_time() {
+        let _isolated = test_support::isolated(|settings| {
+            settings.developer.allow_raw_scripts = true;
+            settings.permissions.remembered.push(RememberedDecision {
+                capability: "run_script".to_string(),
+                target: None,
+                allow: true,
+                decided_at_ms: 0,
+            })
+        });
+        answer(Consent::AllowAlways);
+        let before = asked();
+        require(Capability::RunScript, None).unwrap();
+        assert_eq!(asked(), before + 1);
+        assert_eq!(settings::current().permissions.remembered.len(), 1);
+
+        answer(Consent::Deny);
+        assert!(require(Capability::RunScript, None).is_err());
+        assert_eq!(asked(), before + 2);
+    }
+}
@@ -0,0 +1,498 @@
+// src-tauri/src/prompts.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::context::{self, SelectionGrab};
+use crate::error::{AppError, AppResult};
+use crate::llm::{ChatMessage, CompletionRequest, Role};
+use crate::permissions::{self, Capability};
+use crate::settings::{self, AiSettings};
+use crate::text_actions::render_template;
+
+/// Opens and closes the TOML front-matter of a template file.
+const FRONT_MATTER_FENCE: &str = "+++";
+/// Variables a template can use, filled in from the active app.
+pub const VARIABLES: &[&str] = &["selection", "active_app", "window_title"];
+const MAX_TEMPERATURE: f32 = 2.0;
+
+/// Templates that exist without a file. A file with the same name replaces
+/// one.
+const BUILT_IN: &[(&str, &str)] = &[
+    (
+        "assistant",
+        r#"+++
+description = "General help in the bubble's chat"
+temperature = 0.7
+max_tokens = 1000
++++
+You are Leo AI, an assistant on the user's desktop. Answer concisely and ask
+when a request is ambiguous."#,
+    ),
+    (
+        "coding",
+        r#"+++
+description = "Programming help for editors and IDEs"
+temperature = 0.2
+max_tokens = 2000
++++
+You are Leo AI, helping a programmer who is working in {{active_app}} on
+{{window_title}}. Answer with working code in fenced blocks and keep
+explanations short. When the question is about selected code, this is it:
+
+{{selection}}"#,
+    ),
+    (
+        "email",
+        r#"+++
+description = "Reading and writing mail"
+temperature = 0.6
+max_tokens = 1000
++++
+You are Leo AI, helping the user with email in {{active_app}}. Match the tone
+of the conversation, keep replies brief and never invent facts, dates or
+commitments. When the question is about selected text, this is it:
+
+{{selection}}"#,
+    ),
+];
+
+static PROMPTS_DIR: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+static VARIABLE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{([^{}]*)\}\}").expect("valid regex"));
+
+/// Front-matter of a template file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FrontMatter {
+    name: Option<String>,
+    description: String,
+    /// Model of the cloud provider; the local provider keeps its own.
+    model: Option<String>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+}
+
+/// A system prompt and the generation settings to send it with.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    pub name: String,
+    pub description: String,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub body: String,
+}
+
+impl PromptTemplate {
+    /// Variables the body uses, in order of first use.
+    pub fn variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for capture in VARIABLE.captures_iter(&self.body) {
+            let name = capture[1].to_string();
+            if !variables.contains(&name) {
+                variables.push(name);
+            }
+        }
+        variables
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplateSummary {
+    /// None for built-in templates.
+    pub file: Option<String>,
+    pub name: String,
+    pub description: String,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub variables: Vec<String>,
+    /// Apps the settings bind this template to.
+    pub apps: Vec<String>,
+    pub is_default: bool,
+    /// Validation or parse errors; templates with problems are not used.
+    pub problems: Vec<String>,
+}
+
+/// Use `<config_dir>/prompts` for template files.
+pub fn init(config_dir: &Path) {
+    let dir = config_dir.join("prompts");
+    if let Err(e) = fs::create_dir_all(&dir) {
+        tracing::warn!("Failed to create {}: {}", dir.display(), e);
+    }
+    *PROMPTS_DIR.write() = Some(dir);
+}
+
+fn is_template_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|ext| ext.to_str()), Some("md" | "txt"))
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Parse a template: optional TOML front-matter between `+++` lines, then
+/// the prompt. `fallback_name` names templates whose front-matter does not.
+pub fn parse(source: &str, fallback_name: &str) -> Result<PromptTemplate, String> {
+    let source = source.trim_start_matches('\u{feff}');
+    let (front_matter, body) = match source.strip_prefix(FRONT_MATTER_FENCE) {
+        Some(rest) => {
+            let rest = rest.trim_start_matches([' ', '\t']);
+            let rest = rest
+                .strip_prefix("\r\n")
+                .or_else(|| rest.strip_prefix('\n'))
+                .ok_or_else(|| format!("Nothing may follow the opening {} on its line", FRONT_MATTER_FENCE))?;
+            // Lines keep their ends so the offsets stay right for CRLF files
+            let end = rest
+                .split_inclusive('\n')
+                .scan(0, |offset, line| {
+                    let start = *offset;
+                    *offset += line.len();
+                    Some((start, line))
+                })
+                .find(|(_, line)| line.trim_end() == FRONT_MATTER_FENCE)
+                .map(|(start, _)| start)
+                .ok_or_else(|| format!("The front-matter is not closed with {}", FRONT_MATTER_FENCE))?;
+            let front_matter: FrontMatter =
+                toml::from_str(&rest[..end]).map_err(|e| format!("Invalid front-matter: {}", e.message()))?;
+            let body = rest[end..].split_once('\n').map_or("", |(_, body)| body);
+            (front_matter, body)
+        }
+        None => (FrontMatter::default(), source),
+    };
+
+    Ok(PromptTemplate {
+        name: front_matter.name.unwrap_or_else(|| fallback_name.to_string()),
+        description: front_matter.description,
+        model: front_matter.model.filter(|model| !model.trim().is_empty()),
+        temperature: front_matter.temperature,
+        max_tokens: front_matter.max_tokens,
+        body: body.trim().to_string(),
+    })
+}
+
+/// Problems that keep a template from being used.
+pub fn validate(template: &PromptTemplate) -> Vec<String> {
+    let mut problems = Vec::new();
+    if template.name.trim().is_empty() {
+        problems.push("The template has no name".to_string());
+    }
+    if template.body.is_empty() {
+        problems.push("The prompt is empty".to_string());
+    }
+    if let Some(temperature) = template.temperature {
+        if !(0.0..=MAX_TEMPERATURE).contains(&temperature) {
+            problems.push(format!("temperature must be between 0 and {}", MAX_TEMPERATURE));
+        }
+    }
+    if template.max_tokens == Some(0) {
+        problems.push("max_tokens must be more than 0".to_string());
+    }
+    for variable in template.variables() {
+        if !VARIABLES.contains(&variable.as_str()) {
+            problems.push(format!(
+                "Unknown variable {{{{{}}}}}; use one of {}",
+                variable,
+                VARIABLES.join(", ")
+            ));
+        }
+    }
+    problems
+}
+
+pub fn prompts_dir() -> AppResult<PathBuf> {
+    PROMPTS_DIR
+        .read()
+        .clone()
+        .ok_or_else(|| AppError::InvalidState("Prompt templates have not been initialized".to_string()))
+}
+
+/// A template file and its parse result.
+type LoadedTemplate = (PathBuf, Result<PromptTemplate, String>);
+
+/// Every template file, sorted by file name.
+fn load_files() -> AppResult<Vec<LoadedTemplate>> {
+    let dir = prompts_dir()?;
+    let mut entries: Vec<PathBuf> = fs::read_dir(&dir)
+        .map_err(|e| AppError::Io(format!("Failed to read {}: {}", dir.display(), e)))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_template_file(path))
+        .collect();
+    entries.sort();
+
+    Ok(entries
+        .into_iter()
+        .map(|path| {
+            let template = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| parse(&source, &file_stem(&path)));
+            (path, template)
+        })
+        .collect())
+}
+
+fn built_in() -> Vec<PromptTemplate> {
+    BUILT_IN
+        .iter()
+        .filter_map(|(name, source)| parse(source, name).ok())
+        .collect()
+}
+
+/// Find a usable template by its name or file stem. Files come before the
+/// built-in templates.
+pub fn find(name: &str) -> AppResult<PromptTemplate> {
+    let file = load_files()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|(path, template)| file_stem(path) == name || template.as_ref().is_ok_and(|t| t.name == name));
+    let template = match file {
+        Some((_, template)) => template.map_err(AppError::InvalidInput)?,
+        None => built_in()
+            .into_iter()
+            .find(|template| template.name == name)
+            .ok_or_else(|| AppError::NotFound(format!("No prompt template named {}", name)))?,
+    };
+    let problems = validate(&template);
+    if !problems.is_empty() {
+        return Err(AppError::InvalidInput(format!(
+            "Prompt template {} cannot be used: {}",
+            template.name,
+            problems.join("; ")
+        )));
+    }
+    Ok(template)
+}
+
+/// The template to use in `app_name`: the one bound to the app, or the
+/// default. A binding to a missing or broken template falls back to the
+/// default, and that to the built-in assistant.
+fn template_for(app_name: Option<&str>) -> PromptTemplate {
+    let prompts = settings::current().prompts;
+    let candidates = [
+        prompts.template_for(app_name),
+        prompts.default_template.as_str(),
+        BUILT_IN[0].0,
+    ];
+    for name in candidates {
+        match find(name) {
+            Ok(template) => return template,
+            Err(e) => tracing::warn!("Skipping prompt template {}: {}", name, e),
+        }
+    }
+    built_in().remove(0)
+}
+
+/// Fill in the template's variables from the active app. The selection is
+/// only read when the template uses it and the app may be read from, and
+/// never copied: that would press ⌘C in the user's app on every message.
+fn render(template: &PromptTemplate) -> String {
+    let variables = template.variables();
+    if variables.is_empty() {
+        return template.body.clone();
+    }
+    let include_selection = variables.iter().any(|name| name == "selection")
+        && match permissions::require(Capability::ReadSelection, permissions::focused_app().as_deref()) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::info!("Leaving the selection out of the prompt: {}", e);
+                false
+            }
+        };
+    let selection = if include_selection {
+        SelectionGrab::Read
+    } else {
+        SelectionGrab::Skip
+    };
+    let snapshot = context::snapshot_with(selection, false);
+    let active_app = snapshot.active_app.map(|app| app.name).unwrap_or_default();
+    let window_title = snapshot.window_title.unwrap_or_default();
+    let selection = snapshot.selected_text.unwrap_or_default();
+    render_template(
+        &template.body,
+        &[
+            ("selection", &selection),
+            ("active_app", &active_app),
+            ("window_title", &window_title),
+        ],
+    )
+    .trim()
+    .to_string()
+}
+
+/// Chat request for `messages` with the template `name`, or the one for the
+/// active app, and the AI settings to send it with. Messages that bring
+/// their own system prompt keep it; the generation settings still apply.
+pub fn chat_request(messages: Vec<ChatMessage>, name: Option<&str>) -> AppResult<(CompletionRequest, AiSettings)> {
+    let template = match name {
+        Some(name) => find(name)?,
+        None => template_for(context::current_active_app().map(|app| app.name).as_deref()),
+    };
+    tracing::debug!("Using prompt template {}", template.name);
+
+    let mut messages = messages;
+    if !messages.iter().any(|message| message.role == Role::System) {
+        messages.insert(0, ChatMessage::system(render(&template)));
+    }
+    let mut request = CompletionRequest::new(messages);
+    if let Some(temperature) = template.temperature {
+        request.temperature = temperature;
+    }
+    if let Some(max_tokens) = template.max_tokens {
+        request.max_output_tokens = max_tokens;
+    }
+    let mut ai = settings::current().ai;
+    if let Some(model) = template.model {
+        ai.model = model;
+    }
+    Ok((request, ai))
+}
+
+fn summary(
+    file: Option<String>,
+    template: Result<PromptTemplate, String>,
+    fallback_name: String,
+) -> PromptTemplateSummary {
+    let prompts = settings::current().prompts;
+    let bound_apps = |name: &str| {
+        prompts
+            .app_templates
+            .iter()
+            .filter(|binding| binding.template == name)
+            .map(|binding| binding.app.clone())
+            .collect()
+    };
+    match template {
+        Ok(template) => PromptTemplateSummary {
+            file,
+            apps: bound_apps(&template.name),
+            is_default: template.name == prompts.default_template,
+            problems: validate(&template),
+            variables: template.variables(),
+            name: template.name,
+            description: template.description,
+            model: template.model,
+            temperature: template.temperature,
+            max_tokens: template.max_tokens,
+        },
+        Err(e) => PromptTemplateSummary {
+            file,
+            apps: bound_apps(&fallback_name),
+            is_default: fallback_name == prompts.default_template,
+            name: fallback_name,
+            description: String::new(),
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            variables: Vec::new(),
+            problems: vec![e],
+        },
+    }
+}
+
+/// Template files, then the built-in templates no file replaces.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplates {
+    pub templates: Vec<PromptTemplateSummary>,
+    /// Settings that name templates which do not exist.
+    pub binding_problems: Vec<String>,
+}
+
+#[tauri::command]
+pub fn list_prompt_templates() -> AppResult<PromptTemplates> {
+    let mut templates: Vec<PromptTemplateSummary> = load_files()?
+        .into_iter()
+        .map(|(path, template)| {
+            let file = path.file_name().and_then(|name| name.to_str()).map(str::to_string);
+            summary(file, template, file_stem(&path))
+        })
+        .collect();
+    for template in built_in() {
+        if !templates.iter().any(|summary| summary.name == template.name) {
+            let name = template.name.clone();
+            templates.push(summary(None, Ok(template), name));
+        }
+    }
+
+    let prompts = settings::current().prompts;
+    let exists = |name: &str| templates.iter().any(|summary| summary.name == name);
+    let mut binding_problems = Vec::new();
+    if !exists(&prompts.default_template) {
+        binding_problems.push(format!(
+            "The default template {} does not exist",
+            prompts.default_template
+        ));
+    }
+    for binding in prompts
+        .app_templates
+        .iter()
+        .filter(|binding| !exists(&binding.template))
+    {
+        binding_problems.push(format!(
+            "{} is bound to {}, which does not exist",
+            binding.app, binding.template
+        ));
+    }
+    Ok(PromptTemplates {
+        templates,
+        binding_problems,
+    })
+}
+
+/// Check template source without saving it.
+#[tauri::command]
+pub fn validate_prompt_template(source: String) -> Vec<String> {
+    match parse(&source, "untitled") {
+        Ok(template) => validate(&template),
+        Err(e) => vec![e],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn the_selection_needs_read_selection() {
+        let _isolated = test_support::isolated(|settings| {
+            settings.permissions.allowed_apps = vec!["No Such App".to_string()];
+        });
+        let template = parse("Selected: {{selection}}", "test").unwrap();
+
+        assert_eq!(render(&template), "Selected:");
+        let decision = permissions::get_permission_decisions().pop().unwrap();
+        assert_eq!(decision.capability, Capability::ReadSelection);
+        assert!(!decision.allowed);
+    }
+
+    #[test]
+    fn parses_front_matter_with_any_line_ends() {
+        for source in [
+            "+++\nname = \"review\"\ntemperature = 0.2\n+++\nRéviser {{window_title}}.\n",
+            "+++\r\nname = \"review\"\r\ntemperature = 0.2\r\n+++\r\nRéviser {{window_title}}.\r\n",
+            "\u{feff}+++\nname = \"review\" # ünïcödé\ntemperature = 0.2\n+++ \nRéviser {{window_title}}.",
+        ] {
+            let template = parse(source, "fallback").unwrap();
+            assert_eq!(template.name, "review");
+            assert_eq!(template.temperature, Some(0.2));
+            assert_eq!(template.body, "Réviser {{window_title}}.");
+        }
+    }
+
+    #[test]
+    fn rejects_unclosed_front_matter() {
+        assert!(parse("+++\nname = \"é\"\n", "fallback").is_err());
+        assert!(parse("+++ name = \"x\"\n+++\nBody", "fallback").is_err());
+        assert_eq!(parse("No front-matter", "fallback").unwrap().name, "fallback");
+    }
+}
@@ -0,0 +1,120 @@
+// src-tauri/src/recorder/capture.rs
+
+/// Global keyboard and mouse listener. rdev uses an event tap on macOS and
+/// XRecord on Linux, so recording on Linux needs an X11 session.
+#[cfg(feature = "recorder")]
+mod listener {
+    use once_cell::sync::Lazy;
+    use parking_lot::Mutex;
+    use rdev::{Button, Event, EventType, Key};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::super::recording::{Input, InputKey, Modifier};
+    use crate::error::{AppError, AppResult};
+
+    static RUNNING: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+    fn input_key(key: Key) -> Option<InputKey> {
+        let key = match key {
+            Key::MetaLeft | Key::MetaRight => InputKey::Modifier(Modifier::Cmd),
+            Key::ControlLeft | Key::ControlRight => InputKey::Modifier(Modifier::Ctrl),
+            Key::Alt | Key::AltGr => InputKey::Modifier(Modifier::Alt),
+            Key::ShiftLeft | Key::ShiftRight => InputKey::Modifier(Modifier::Shift),
+            Key::Return | Key::KpReturn => InputKey::Named("return"),
+            Key::Tab => InputKey::Named("tab"),
+            Key::Backspace => InputKey::Named("backspace"),
+            Key::Escape => InputKey::Named("escape"),
+            Key::LeftArrow => InputKey::Named("left"),
+            Key::RightArrow => InputKey::Named("right"),
+            Key::UpArrow => InputKey::Named("up"),
+            Key::DownArrow => InputKey::Named("down"),
+            Key::Space => InputKey::Char("space".to_string()),
+            Key::Minus => InputKey::Char("-".to_string()),
+            Key::Equal => InputKey::Char("=".to_string()),
+            Key::LeftBracket => InputKey::Char("[".to_string()),
+            Key::RightBracket => InputKey::Char("]".to_string()),
+            Key::SemiColon => InputKey::Char(";".to_string()),
+            Key::Quote => InputKey::Char("'".to_string()),
+            Key::BackSlash => InputKey::Char("\\".to_string()),
+            Key::BackQuote => InputKey::Char("`".to_string()),
+            Key::Comma => InputKey::Char(",".to_string()),
+            Key::Dot => InputKey::Char(".".to_string()),
+            Key::Slash => InputKey::Char("/".to_string()),
+            other => {
+                // Letters and digits are named like "KeyA" and "Num1"
+                let name = format!("{:?}", other);
+                let base = name
+                    .strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Num"))
+                    .filter(|base| base.len() == 1)?;
+                InputKey::Char(base.to_lowercase())
+            }
+        };
+        Some(key)
+    }
+
+    fn translate(event: Event, position: &mut (f64, f64)) -> Option<Input> {
+        match event.event_type {
+            EventType::MouseMove { x, y } => {
+                *position = (x, y);
+                None
+            }
+            // The backends only replay left clicks
+            EventType::ButtonPress(Button::Left) => Some(Input::Click {
+                x: position.0.round() as i32,
+                y: position.1.round() as i32,
+            }),
+            EventType::KeyPress(key) => input_key(key).map(|key| Input::KeyDown {
+                key,
+                text: event.name,
+            }),
+            EventType::KeyRelease(key) => input_key(key).map(|key| Input::KeyUp { key }),
+            _ => None,
+        }
+    }
+
+    /// Start the listener thread once. rdev cannot stop listening, so input
+    /// is forwarded only while a recording is running.
+    pub fn ensure_running() -> AppResult<()> {
+        let mut running = RUNNING.lock();
+        if *running {
+            return Ok(());
+        }
+
+        let (error_tx, error_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut position = (0.0, 0.0);
+            let result = rdev::listen(move |event| {
+                if let Some(input) = translate(event, &mut position) {
+                    super::super::send(input);
+                }
+            });
+            if let Err(e) = result {
+                let _ = error_tx.send(format!("{:?}", e));
+                *RUNNING.lock() = false;
+            }
+        });
+
+        // listen() only returns when it fails, which happens right away
+        match error_rx.recv_timeout(Duration::from_millis(300)) {
+            Ok(e) => Err(AppError::BackendUnavailable(format!("Failed to listen for input: {}", e))),
+            Err(_) => {
+                *running = true;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "recorder")]
+pub use listener::ensure_running;
+
+#[cfg(not(feature = "recorder"))]
+use crate::error::{AppError, AppResult};
+
+#[cfg(not(feature = "recorder"))]
+pub fn ensure_running() -> AppResult<()> {
+    Err(AppError::BackendUnavailable("Built without input recording support".to_string()))
+}
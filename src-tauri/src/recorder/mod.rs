@@ -0,0 +1,219 @@
+// src-tauri/src/recorder/mod.rs
+
+mod capture;
+mod recording;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+use tauri::Manager;
+
+use crate::audit;
+use crate::automation::{self, WindowBounds};
+use crate::error::{AppError, AppResult};
+use crate::permissions::{self, Capability};
+use crate::{context, workflow};
+pub use recording::Recording;
+use recording::{Input, RecordingBuilder};
+
+/// Names our own app shows up as in the active-app observer: the product
+/// name from tauri.conf.json and the binary name in dev builds.
+const OWN_APP_NAMES: &[&str] = &["Leo AI", "Leo_AI"];
+
+static SINK: Lazy<Mutex<Option<Sender<Input>>>> = Lazy::new(|| Mutex::new(None));
+static SESSION: Lazy<Mutex<Option<thread::JoinHandle<Recording>>>> = Lazy::new(|| Mutex::new(None));
+static LAST_RECORDING: Lazy<Mutex<Option<Recording>>> = Lazy::new(|| Mutex::new(None));
+
+/// Forward input to the running recording, if any.
+fn send(input: Input) {
+    if let Some(sender) = SINK.lock().as_ref() {
+        let _ = sender.send(input);
+    }
+}
+
+/// Called by the active-app observer and the window detector.
+pub fn note_app_switch(app_name: &str) {
+    send(Input::AppSwitch {
+        app: app_name.to_string(),
+    });
+}
+
+fn is_own_app(app_name: &str) -> bool {
+    OWN_APP_NAMES.contains(&app_name)
+}
+
+/// Screen bounds of our floating window, so clicks on it (like the stop
+/// button) stay out of recordings.
+fn own_window_bounds() -> Option<WindowBounds> {
+    let handle = crate::APP_HANDLE.read();
+    let window = handle.as_ref()?.get_webview_window("main")?;
+    if !window.is_visible().unwrap_or(false) {
+        return None;
+    }
+    let position = window.outer_position().ok()?;
+    let size = window.outer_size().ok()?;
+    // Tauri reports physical pixels; on macOS input arrives in points
+    let scale = if cfg!(target_os = "macos") {
+        window.scale_factor().ok()?
+    } else {
+        1.0
+    };
+    Some(WindowBounds {
+        x: (position.x as f64 / scale) as i32,
+        y: (position.y as f64 / scale) as i32,
+        width: (size.width as f64 / scale) as i32,
+        height: (size.height as f64 / scale) as i32,
+    })
+}
+
+pub fn start() -> AppResult<()> {
+    let mut session = SESSION.lock();
+    if session.is_some() {
+        return Err(AppError::InvalidState("Already recording".to_string()));
+    }
+    permissions::require(Capability::RecordInput, None)?;
+    capture::ensure_running()?;
+
+    let front_app = context::current_active_app().map(|app| app.name);
+    let mut own_app_focused = front_app.as_deref().is_some_and(is_own_app);
+    let initial_app = front_app.filter(|app| !is_own_app(app));
+    let backend = automation::backend();
+    let (sender, receiver) = mpsc::channel();
+
+    let handle = thread::spawn(move || {
+        let mut builder = RecordingBuilder::new(initial_app, backend);
+        // Ends once stop() drops the sender
+        for input in receiver {
+            match &input {
+                Input::AppSwitch { app } if is_own_app(app) => {
+                    own_app_focused = true;
+                    continue;
+                }
+                Input::AppSwitch { .. } => own_app_focused = false,
+                Input::KeyDown { .. } | Input::Click { .. } if own_app_focused => continue,
+                Input::Click { x, y } if own_window_bounds().is_some_and(|b| b.contains(*x, *y)) => {
+                    continue
+                }
+                _ => {}
+            }
+            builder.push(input);
+        }
+        builder.finish()
+    });
+
+    *SINK.lock() = Some(sender);
+    *session = Some(handle);
+    tracing::info!("Macro recording started");
+    Ok(())
+}
+
+pub fn stop() -> AppResult<Recording> {
+    let handle = SESSION
+        .lock()
+        .take()
+        .ok_or_else(|| AppError::InvalidState("Not recording".to_string()))?;
+    SINK.lock().take();
+    let recording = handle
+        .join()
+        .map_err(|_| AppError::Failed("Recorder thread panicked".to_string()))?;
+    tracing::info!("Macro recording stopped with {} events", recording.events.len());
+    *LAST_RECORDING.lock() = Some(recording.clone());
+    Ok(recording)
+}
+
+pub fn is_recording() -> bool {
+    SESSION.lock().is_some()
+}
+
+/// Like `is_recording`, or `None` when the session stays locked for
+/// `timeout`, e.g. by a thread that panicked while holding it.
+pub fn try_is_recording(timeout: Duration) -> Option<bool> {
+    SESSION.try_lock_for(timeout).map(|session| session.is_some())
+}
+
+/// Entry point for `automate_mac`: "start", "stop" or "toggle".
+pub fn handle_automation(params: Option<String>) -> AppResult<String> {
+    let command = params.unwrap_or_else(|| "toggle".to_string());
+    let start_requested = match command.as_str() {
+        "start" => true,
+        "stop" => false,
+        "toggle" => !is_recording(),
+        other => {
+            return Err(AppError::InvalidInput(format!("Unsupported record params: {}", other)))
+        }
+    };
+
+    if start_requested {
+        start().map(|_| "started".to_string())
+    } else {
+        stop().map(|_| "stopped".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn start_recording() -> AppResult<()> {
+    // May wait on a consent dialog
+    tauri::async_runtime::spawn_blocking(|| audit::track("start_recording", None, Value::Null, start))
+        .await?
+}
+
+#[tauri::command]
+pub fn stop_recording() -> AppResult<Recording> {
+    audit::track("stop_recording", None, Value::Null, stop)
+}
+
+#[tauri::command]
+pub fn recording_status() -> bool {
+    is_recording()
+}
+
+fn nothing_recorded() -> AppError {
+    AppError::NotFound("Nothing has been recorded".to_string())
+}
+
+/// Keep only events `from..to` of the last recording.
+#[tauri::command]
+pub fn trim_recording(from: usize, to: usize) -> AppResult<Recording> {
+    let mut last = LAST_RECORDING.lock();
+    let trimmed = last
+        .as_ref()
+        .ok_or_else(nothing_recorded)?
+        .trim(from, to)
+        .map_err(AppError::InvalidInput)?;
+    *last = Some(trimmed.clone());
+    Ok(trimmed)
+}
+
+/// Save the last recording as a workflow and return its file name. `speed`
+/// scales the recorded pauses, e.g. 2.0 replays twice as fast.
+#[tauri::command]
+pub fn save_recording(
+    app_handle: tauri::AppHandle,
+    name: String,
+    hotkey: Option<String>,
+    speed: Option<f64>,
+) -> AppResult<String> {
+    let speed = speed.unwrap_or(1.0);
+    if !(speed > 0.0 && speed.is_finite()) {
+        return Err(AppError::InvalidInput(format!("Invalid replay speed: {}", speed)));
+    }
+
+    let workflow = LAST_RECORDING
+        .lock()
+        .as_ref()
+        .ok_or_else(nothing_recorded)?
+        .to_workflow(&name, hotkey, speed);
+    let path = workflow::save(&workflow)?;
+    if workflow.hotkey.is_some() {
+        workflow::register_hotkeys(&app_handle);
+    }
+
+    Ok(path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_string())
+}
@@ -0,0 +1,606 @@
+// src-tauri/src/recorder/recording.rs
+
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::automation::AutomationBackend;
+use crate::workflow::{Action, Step, Workflow};
+
+/// Pauses shorter than this are not replayed.
+const MIN_PAUSE_MS: u64 = 100;
+/// Idle stretches in a recording replay as at most this long.
+const MAX_PAUSE_MS: u64 = 5_000;
+const APP_SWITCH_TIMEOUT_MS: u64 = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(not(feature = "recorder"), allow(dead_code))]
+pub enum Modifier {
+    Cmd,
+    Ctrl,
+    Alt,
+    Shift,
+}
+
+impl Modifier {
+    fn chord_name(self) -> &'static str {
+        match self {
+            Modifier::Cmd => "cmd",
+            Modifier::Ctrl => "ctrl",
+            Modifier::Alt => "alt",
+            Modifier::Shift => "shift",
+        }
+    }
+}
+
+/// A key as the automation backends name it in chords.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "recorder"), allow(dead_code))]
+pub enum InputKey {
+    Modifier(Modifier),
+    /// Keys that never produce text, e.g. "return" or "left".
+    Named(&'static str),
+    /// Keys that usually type something; the name is the unshifted key.
+    Char(String),
+}
+
+/// Raw input as delivered by the platform listener and the active-app
+/// observer.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "recorder"), allow(dead_code))]
+pub enum Input {
+    KeyDown { key: InputKey, text: Option<String> },
+    KeyUp { key: InputKey },
+    Click { x: i32, y: i32 },
+    AppSwitch { app: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    AppSwitch {
+        app: String,
+    },
+    Text {
+        text: String,
+    },
+    Keys {
+        keys: String,
+    },
+    /// With `window` set, x and y are offsets from that app's front window.
+    Click {
+        x: i32,
+        y: i32,
+        window: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimedEvent {
+    /// Time since the start of the recording.
+    pub offset_ms: u64,
+    #[serde(flatten)]
+    pub event: RecordedEvent,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    pub started_at_ms: i64,
+    pub duration_ms: u64,
+    /// App that was in front when the recording starts.
+    pub initial_app: Option<String>,
+    pub events: Vec<TimedEvent>,
+}
+
+/// Bring `app` to the front and wait until it shows a window.
+fn switch_to(steps: &mut Vec<Step>, app: &str) {
+    steps.push(Step::new(Action::OpenApp {
+        app: app.to_string(),
+    }));
+    let mut wait = Step::new(Action::WaitForWindow {
+        app: app.to_string(),
+        title: None,
+    });
+    wait.timeout_ms = Some(APP_SWITCH_TIMEOUT_MS);
+    steps.push(wait);
+}
+
+impl Recording {
+    /// Keep events `from..to`, shifting them so the first kept event is at
+    /// offset zero.
+    pub fn trim(&self, from: usize, to: usize) -> Result<Recording, String> {
+        if from >= to || to > self.events.len() {
+            return Err(format!(
+                "Invalid trim range {}..{} for {} events",
+                from,
+                to,
+                self.events.len()
+            ));
+        }
+
+        let base = self.events[from].offset_ms;
+        // Replays of the trimmed part should still start in the right app
+        let initial_app = self.events[..from]
+            .iter()
+            .rev()
+            .find_map(|timed| match &timed.event {
+                RecordedEvent::AppSwitch { app } => Some(app.clone()),
+                _ => None,
+            })
+            .or_else(|| self.initial_app.clone());
+        let events: Vec<TimedEvent> = self.events[from..to]
+            .iter()
+            .map(|timed| TimedEvent {
+                offset_ms: timed.offset_ms - base,
+                event: timed.event.clone(),
+            })
+            .collect();
+
+        Ok(Recording {
+            started_at_ms: self.started_at_ms + base as i64,
+            duration_ms: events.last().map(|timed| timed.offset_ms).unwrap_or(0),
+            initial_app,
+            events,
+        })
+    }
+
+    /// Convert to workflow steps, replaying pauses at `speed` times the
+    /// recorded pace.
+    pub fn to_workflow(&self, name: &str, hotkey: Option<String>, speed: f64) -> Workflow {
+        let mut steps = Vec::new();
+        let mut variables = HashMap::new();
+        if let Some(app) = self.initial_app.as_deref() {
+            switch_to(&mut steps, app);
+        }
+
+        let mut previous_ms = 0;
+        for timed in &self.events {
+            let pause = (timed.offset_ms.saturating_sub(previous_ms) as f64 / speed) as u64;
+            previous_ms = timed.offset_ms;
+            if pause >= MIN_PAUSE_MS {
+                steps.push(Step::new(Action::Sleep {
+                    ms: pause.min(MAX_PAUSE_MS),
+                }));
+            }
+
+            match &timed.event {
+                RecordedEvent::AppSwitch { app } => switch_to(&mut steps, app),
+                RecordedEvent::Text { text } => {
+                    // Typed text may contain "{{", so pass it through a
+                    // variable rather than as a template
+                    let text = if text.contains("{{") {
+                        let variable = format!("typed_{}", variables.len() + 1);
+                        variables.insert(variable.clone(), text.clone());
+                        format!("{{{{{}}}}}", variable)
+                    } else {
+                        text.clone()
+                    };
+                    steps.push(Step::new(Action::TypeText { text }));
+                }
+                RecordedEvent::Keys { keys } => {
+                    steps.push(Step::new(Action::PressKeys { keys: keys.clone() }));
+                }
+                RecordedEvent::Click { x, y, window } => {
+                    steps.push(Step::new(Action::Click {
+                        button: None,
+                        x: Some(*x),
+                        y: Some(*y),
+                        window: window.clone(),
+                    }));
+                }
+            }
+        }
+
+        Workflow {
+            name: name.to_string(),
+            description: format!("Recorded {} events", self.events.len()),
+            hotkey,
+            variables,
+            timeout_ms: None,
+            steps,
+        }
+    }
+}
+
+/// Turns raw input into recorded events: typed characters are merged into
+/// text, modifier combinations become chords and clicks are made relative
+/// to the window they land in.
+pub struct RecordingBuilder {
+    started: Instant,
+    recording: Recording,
+    backend: Arc<dyn AutomationBackend>,
+    modifiers: BTreeSet<Modifier>,
+    current_app: Option<String>,
+}
+
+impl RecordingBuilder {
+    pub fn new(initial_app: Option<String>, backend: Arc<dyn AutomationBackend>) -> Self {
+        Self {
+            started: Instant::now(),
+            recording: Recording {
+                started_at_ms: chrono::Utc::now().timestamp_millis(),
+                initial_app: initial_app.clone(),
+                ..Recording::default()
+            },
+            backend,
+            modifiers: BTreeSet::new(),
+            current_app: initial_app,
+        }
+    }
+
+    fn push_event(&mut self, event: RecordedEvent) {
+        self.recording.events.push(TimedEvent {
+            offset_ms: self.started.elapsed().as_millis() as u64,
+            event,
+        });
+    }
+
+    fn chord(&self, key: &str) -> String {
+        self.modifiers
+            .iter()
+            .map(|modifier| modifier.chord_name())
+            .chain(std::iter::once(key))
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+
+    /// Whether a modifier other than shift is held, so keys form shortcuts
+    /// rather than text.
+    fn shortcut_held(&self) -> bool {
+        self.modifiers.iter().any(|modifier| *modifier != Modifier::Shift)
+    }
+
+    fn push_key(&mut self, key: InputKey, text: Option<String>) {
+        match key {
+            InputKey::Modifier(modifier) => {
+                self.modifiers.insert(modifier);
+            }
+            InputKey::Named("backspace") if !self.shortcut_held() => {
+                // Undo the last typed character instead of replaying it
+                if let Some(TimedEvent {
+                    event: RecordedEvent::Text { text },
+                    ..
+                }) = self.recording.events.last_mut()
+                {
+                    text.pop();
+                    if text.is_empty() {
+                        self.recording.events.pop();
+                    }
+                    return;
+                }
+                let keys = self.chord("backspace");
+                self.push_event(RecordedEvent::Keys { keys });
+            }
+            InputKey::Named(name) => {
+                let keys = self.chord(name);
+                self.push_event(RecordedEvent::Keys { keys });
+            }
+            InputKey::Char(name) if self.shortcut_held() => {
+                let keys = self.chord(&name);
+                self.push_event(RecordedEvent::Keys { keys });
+            }
+            InputKey::Char(_) => {
+                let Some(typed) = text.filter(|t| !t.is_empty() && !t.chars().any(char::is_control))
+                else {
+                    return;
+                };
+                if let Some(TimedEvent {
+                    event: RecordedEvent::Text { text },
+                    ..
+                }) = self.recording.events.last_mut()
+                {
+                    text.push_str(&typed);
+                } else {
+                    self.push_event(RecordedEvent::Text { text: typed });
+                }
+            }
+        }
+    }
+
+    fn push_click(&mut self, x: i32, y: i32) {
+        let bounds = self.current_app.as_deref().and_then(|app| {
+            match self.backend.window_bounds(app) {
+                Ok(bounds) => bounds.map(|bounds| (app.to_string(), bounds)),
+                Err(e) => {
+                    tracing::warn!("Failed to locate window of {}: {}", app, e);
+                    None
+                }
+            }
+        });
+
+        let event = match bounds {
+            Some((app, bounds)) if bounds.contains(x, y) => RecordedEvent::Click {
+                x: x - bounds.x,
+                y: y - bounds.y,
+                window: Some(app),
+            },
+            // Outside the app's window (menu bar, dock, ...): keep screen
+            // coordinates
+            _ => RecordedEvent::Click { x, y, window: None },
+        };
+        self.push_event(event);
+    }
+
+    pub fn push(&mut self, input: Input) {
+        match input {
+            Input::KeyDown { key, text } => self.push_key(key, text),
+            Input::KeyUp {
+                key: InputKey::Modifier(modifier),
+            } => {
+                self.modifiers.remove(&modifier);
+            }
+            Input::KeyUp { .. } => {}
+            Input::Click { x, y } => self.push_click(x, y),
+            Input::AppSwitch { app } => {
+                if self.current_app.as_deref() != Some(app.as_str()) {
+                    self.current_app = Some(app.clone());
+                    self.push_event(RecordedEvent::AppSwitch { app });
+                }
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Recording {
+        self.recording.duration_ms = self.started.elapsed().as_millis() as u64;
+        self.recording
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automation::{AutomationCall, MockBackend, MOCK_WINDOW_BOUNDS};
+    use crate::workflow::Engine;
+
+    fn builder(app: &str) -> (RecordingBuilder, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::default());
+        // The mock only knows the windows of apps opened through it
+        backend.open_application(app).unwrap();
+        (RecordingBuilder::new(Some(app.to_string()), backend.clone()), backend)
+    }
+
+    fn type_char(builder: &mut RecordingBuilder, c: &str) {
+        builder.push(Input::KeyDown {
+            key: InputKey::Char(c.to_lowercase()),
+            text: Some(c.to_string()),
+        });
+        builder.push(Input::KeyUp {
+            key: InputKey::Char(c.to_lowercase()),
+        });
+    }
+
+    fn press(builder: &mut RecordingBuilder, modifiers: &[Modifier], key: InputKey) {
+        for modifier in modifiers {
+            builder.push(Input::KeyDown {
+                key: InputKey::Modifier(*modifier),
+                text: None,
+            });
+        }
+        builder.push(Input::KeyDown { key, text: None });
+        for modifier in modifiers {
+            builder.push(Input::KeyUp {
+                key: InputKey::Modifier(*modifier),
+            });
+        }
+    }
+
+    fn events(recording: &Recording) -> Vec<RecordedEvent> {
+        recording.events.iter().map(|timed| timed.event.clone()).collect()
+    }
+
+    fn text(text: &str) -> RecordedEvent {
+        RecordedEvent::Text { text: text.to_string() }
+    }
+
+    fn keys(keys: &str) -> RecordedEvent {
+        RecordedEvent::Keys { keys: keys.to_string() }
+    }
+
+    fn at(offset_ms: u64, event: RecordedEvent) -> TimedEvent {
+        TimedEvent { offset_ms, event }
+    }
+
+    #[test]
+    fn typed_characters_merge_and_backspace_undoes_them() {
+        let (mut builder, _) = builder("Notes");
+        for c in ["H", "i", "!"] {
+            type_char(&mut builder, c);
+        }
+        press(&mut builder, &[], InputKey::Named("backspace"));
+        builder.push(Input::KeyDown {
+            key: InputKey::Char("tab".to_string()),
+            text: Some("\t".to_string()),
+        });
+        press(&mut builder, &[], InputKey::Named("return"));
+        type_char(&mut builder, "x");
+        press(&mut builder, &[], InputKey::Named("backspace"));
+        press(&mut builder, &[], InputKey::Named("backspace"));
+
+        assert_eq!(
+            events(&builder.finish()),
+            [text("Hi"), keys("return"), keys("backspace")]
+        );
+    }
+
+    #[test]
+    fn held_modifiers_make_chords() {
+        let (mut builder, _) = builder("Notes");
+        press(&mut builder, &[Modifier::Cmd], InputKey::Char("s".to_string()));
+        builder.push(Input::KeyDown {
+            key: InputKey::Modifier(Modifier::Shift),
+            text: None,
+        });
+        type_char(&mut builder, "A");
+        builder.push(Input::KeyUp {
+            key: InputKey::Modifier(Modifier::Shift),
+        });
+        press(&mut builder, &[Modifier::Shift, Modifier::Ctrl], InputKey::Named("tab"));
+        press(&mut builder, &[Modifier::Alt], InputKey::Named("backspace"));
+        type_char(&mut builder, "b");
+
+        assert_eq!(
+            events(&builder.finish()),
+            [
+                keys("cmd+s"),
+                text("A"),
+                keys("ctrl+shift+tab"),
+                keys("alt+backspace"),
+                text("b")
+            ]
+        );
+    }
+
+    #[test]
+    fn clicks_in_the_front_window_are_relative_to_it() {
+        let (mut builder, _) = builder("Notes");
+        builder.push(Input::Click {
+            x: MOCK_WINDOW_BOUNDS.x + 15,
+            y: MOCK_WINDOW_BOUNDS.y + 25,
+        });
+        // The menu bar is outside the window
+        builder.push(Input::Click { x: 5, y: 5 });
+        builder.push(Input::AppSwitch {
+            app: "Notes".to_string(),
+        });
+        builder.push(Input::AppSwitch {
+            app: "Finder".to_string(),
+        });
+        builder.push(Input::Click {
+            x: MOCK_WINDOW_BOUNDS.x + 15,
+            y: MOCK_WINDOW_BOUNDS.y + 25,
+        });
+
+        assert_eq!(
+            events(&builder.finish()),
+            [
+                RecordedEvent::Click {
+                    x: 15,
+                    y: 25,
+                    window: Some("Notes".to_string())
+                },
+                RecordedEvent::Click {
+                    x: 5,
+                    y: 5,
+                    window: None
+                },
+                RecordedEvent::AppSwitch {
+                    app: "Finder".to_string()
+                },
+                RecordedEvent::Click {
+                    x: MOCK_WINDOW_BOUNDS.x + 15,
+                    y: MOCK_WINDOW_BOUNDS.y + 25,
+                    window: None
+                },
+            ]
+        );
+    }
+
+    fn sample() -> Recording {
+        Recording {
+            started_at_ms: 1_000,
+            duration_ms: 13_000,
+            initial_app: Some("Notes".to_string()),
+            events: vec![
+                at(0, text("Dear {{name}}")),
+                at(50, keys("return")),
+                at(
+                    450,
+                    RecordedEvent::AppSwitch {
+                        app: "Mail".to_string(),
+                    },
+                ),
+                at(
+                    12_450,
+                    RecordedEvent::Click {
+                        x: 15,
+                        y: 25,
+                        window: Some("Mail".to_string()),
+                    },
+                ),
+                at(12_500, keys("cmd+enter")),
+            ],
+        }
+    }
+
+    #[test]
+    fn trimming_keeps_the_app_in_front() {
+        let trimmed = sample().trim(3, 5).unwrap();
+        assert_eq!(trimmed.initial_app.as_deref(), Some("Mail"));
+        assert_eq!(trimmed.started_at_ms, 13_450);
+        assert_eq!(trimmed.duration_ms, 50);
+        let offsets: Vec<_> = trimmed.events.iter().map(|timed| timed.offset_ms).collect();
+        assert_eq!(offsets, [0, 50]);
+
+        assert_eq!(sample().trim(0, 2).unwrap().initial_app.as_deref(), Some("Notes"));
+        assert!(sample().trim(2, 2).is_err());
+        assert!(sample().trim(1, 6).is_err());
+    }
+
+    #[test]
+    fn recordings_become_workflows_with_scaled_pauses() {
+        let workflow = sample().to_workflow("Reply", Some("CmdOrCtrl+Shift+R".to_string()), 2.0);
+        let kinds: Vec<_> = workflow.steps.iter().map(|step| step.action.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                "open_app",
+                "wait_for_window",
+                "type_text",
+                "press_keys",
+                "sleep",
+                "open_app",
+                "wait_for_window",
+                "sleep",
+                "click",
+                "press_keys"
+            ]
+        );
+        // 400ms at double speed, and 12s capped
+        assert!(matches!(workflow.steps[4].action, Action::Sleep { ms: 200 }));
+        assert!(matches!(workflow.steps[7].action, Action::Sleep { ms: MAX_PAUSE_MS }));
+        assert!(matches!(&workflow.steps[2].action, Action::TypeText { text } if text == "{{typed_1}}"));
+        assert_eq!(workflow.variables["typed_1"], "Dear {{name}}");
+        assert_eq!(workflow.description, "Recorded 5 events");
+        let source = serde_json::to_string(&workflow).unwrap();
+        assert!(crate::workflow::validate_workflow(source, "json".to_string()).is_empty());
+    }
+
+    #[test]
+    fn recordings_play_back_on_the_backend() {
+        let (mut builder, _) = builder("Notes");
+        for c in ["{", "{", "x", "}", "}"] {
+            type_char(&mut builder, c);
+        }
+        builder.push(Input::Click {
+            x: MOCK_WINDOW_BOUNDS.x + 40,
+            y: MOCK_WINDOW_BOUNDS.y + 60,
+        });
+        press(&mut builder, &[Modifier::Cmd], InputKey::Char("s".to_string()));
+        let workflow = builder.finish().to_workflow("Replay", None, 100.0);
+
+        let backend = Arc::new(MockBackend::default());
+        let report = Engine::new(backend.clone(), false).run(&workflow, HashMap::new());
+        assert!(report.success, "{:?}", report.error);
+        assert_eq!(
+            backend.calls(),
+            [
+                AutomationCall::OpenApplication {
+                    app_name: "Notes".to_string()
+                },
+                AutomationCall::TypeText {
+                    text: "{{x}}".to_string()
+                },
+                AutomationCall::ClickAt {
+                    x: MOCK_WINDOW_BOUNDS.x + 40,
+                    y: MOCK_WINDOW_BOUNDS.y + 60
+                },
+                AutomationCall::PressKeys {
+                    keys: "cmd+s".to_string()
+                },
+            ]
+        );
+    }
+}
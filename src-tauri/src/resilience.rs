@@ -0,0 +1,684 @@
+// src-tauri/src/resilience.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::error::{AppError, AppResult};
+use crate::llm::{Completion, CompletionRequest, LlmProvider};
+use crate::settings::{self, ResilienceSettings};
+
+/// Starts errors for answers a provider's content filter held back.
+pub(crate) const CONTENT_FILTERED: &str = "Blocked by the content filter";
+
+/// What went wrong with an LLM call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// 429, or the client-side rate limit.
+    RateLimited,
+    /// Missing, wrong or unauthorized API key.
+    Auth,
+    /// The provider answered with a server error, e.g. 503 while overloaded.
+    Overloaded,
+    /// The provider could not be reached or did not answer in time.
+    Network,
+    /// The provider refused to answer because of the content.
+    ContentFilter,
+    /// The circuit breaker is skipping the provider.
+    CircuitOpen,
+    /// The provider rejected the request itself.
+    Invalid,
+    Other,
+}
+
+impl ErrorClass {
+    /// Worth trying again after a pause.
+    pub fn is_transient(self) -> bool {
+        matches!(self, ErrorClass::RateLimited | ErrorClass::Overloaded | ErrorClass::Network)
+    }
+
+    /// Another provider may well answer instead.
+    pub fn is_outage(self) -> bool {
+        self.is_transient() || self == ErrorClass::CircuitOpen
+    }
+}
+
+/// A failed LLM call, classified where it failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderError {
+    pub kind: ErrorClass,
+    /// Status of the error response, when the provider sent one.
+    pub status: Option<u16>,
+    /// The wait the provider asked for before the next call.
+    pub retry_after: Option<Duration>,
+    pub message: String,
+}
+
+impl ProviderError {
+    pub fn new(kind: ErrorClass, message: String) -> Self {
+        Self {
+            kind,
+            status: None,
+            retry_after: None,
+            message,
+        }
+    }
+
+    /// Classify an error response of `provider` by its status and the
+    /// fields of its JSON body. `retry_after` is the `Retry-After` header;
+    /// without one, the wait Gemini puts in the body is used.
+    pub fn from_response(provider: &str, status: u16, retry_after: Option<Duration>, body: &str) -> Self {
+        let json: Value = serde_json::from_str(body).unwrap_or_default();
+        let error = &json["error"];
+        Self {
+            kind: classify(status, error),
+            status: Some(status),
+            retry_after: retry_after.or_else(|| retry_delay(error)),
+            message: format!("{} request failed ({}): {}", provider, status, body.trim()),
+        }
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// `error.details` entries of Google APIs with this `@type` suffix.
+fn google_details<'a>(error: &'a Value, type_suffix: &'a str) -> impl Iterator<Item = &'a Value> {
+    error["details"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(move |detail| detail["@type"].as_str().is_some_and(|t| t.ends_with(type_suffix)))
+}
+
+/// Classify an error response from its status and the `error` object of
+/// its body: Gemini's `status` and `ErrorInfo.reason`, or the `code` and
+/// `type` of OpenAI-compatible servers.
+fn classify(status: u16, error: &Value) -> ErrorClass {
+    let reasons: Vec<&str> = google_details(error, "ErrorInfo")
+        .filter_map(|detail| detail["reason"].as_str())
+        .chain(["code", "type"].iter().filter_map(|field| error[field].as_str()))
+        .collect();
+    let google_status = error["status"].as_str().unwrap_or_default();
+
+    if reasons.contains(&"content_filter") {
+        return ErrorClass::ContentFilter;
+    }
+    // Gemini answers a bad key with 400
+    if reasons.contains(&"API_KEY_INVALID") || reasons.contains(&"invalid_api_key") {
+        return ErrorClass::Auth;
+    }
+    match (status, google_status) {
+        (429, _) | (_, "RESOURCE_EXHAUSTED") => ErrorClass::RateLimited,
+        (401 | 403, _) | (_, "UNAUTHENTICATED" | "PERMISSION_DENIED") => ErrorClass::Auth,
+        (408, _) | (_, "DEADLINE_EXCEEDED") => ErrorClass::Network,
+        (400..=499, _) => ErrorClass::Invalid,
+        (500..=599, _) => ErrorClass::Overloaded,
+        _ => ErrorClass::Other,
+    }
+}
+
+/// Gemini's `RetryInfo`, e.g. `"retryDelay": "38s"`.
+fn retry_delay(error: &Value) -> Option<Duration> {
+    google_details(error, "RetryInfo")
+        .filter_map(|detail| detail["retryDelay"].as_str())
+        .find_map(|delay| delay.trim_end_matches('s').parse::<f64>().ok())
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// How a provider call failed. Errors that are not the provider's, such as
+/// a used-up budget, count as `Other`.
+pub fn class_of(error: &AppError) -> ErrorClass {
+    match error {
+        AppError::Provider(error) => error.kind,
+        _ => ErrorClass::Other,
+    }
+}
+
+/// `Retry-After` of a response, in seconds or as an HTTP date.
+pub(crate) fn retry_after_header(response: &ureq::Response) -> Option<Duration> {
+    let value = response.header("retry-after")?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.timestamp_millis() - chrono::Utc::now().timestamp_millis();
+    Some(Duration::from_millis(wait.max(0) as u64))
+}
+
+/// Token bucket of one provider's rate limit.
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+static BUCKETS: Lazy<Mutex<HashMap<String, Bucket>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Take a request from `provider`'s rate limit, waiting for one when the
+/// bucket is empty. Fails when the wait would be longer than `max_delay_ms`.
+fn acquire(provider: &str, settings: &ResilienceSettings) -> AppResult<()> {
+    let Some(limit) = settings
+        .rate_limits
+        .iter()
+        .find(|limit| limit.provider == provider && limit.requests_per_minute > 0)
+    else {
+        return Ok(());
+    };
+    let per_second = limit.requests_per_minute as f64 / 60.0;
+    let burst = limit.burst.max(1) as f64;
+
+    let wait = {
+        let mut buckets = BUCKETS.lock();
+        let now = Instant::now();
+        let bucket = buckets.entry(provider.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * per_second;
+        bucket.tokens = (bucket.tokens + refill).min(burst);
+        bucket.updated = now;
+        // Reserve the request now and wait for it below, so that callers
+        // waiting at the same time go out in turn
+        bucket.tokens -= 1.0;
+        let wait = Duration::from_secs_f64((-bucket.tokens).max(0.0) / per_second);
+        if wait > Duration::from_millis(settings.max_delay_ms) {
+            bucket.tokens += 1.0;
+            return Err(AppError::Provider(ProviderError {
+                retry_after: Some(wait),
+                ..ProviderError::new(
+                    ErrorClass::RateLimited,
+                    format!(
+                        "Rate limit reached for {} ({} requests a minute); try again in {}s",
+                        provider,
+                        limit.requests_per_minute,
+                        wait.as_secs().max(1)
+                    ),
+                )
+            }));
+        }
+        wait
+    };
+    if !wait.is_zero() {
+        tracing::debug!(?wait, "Waiting for the {} rate limit", provider);
+        std::thread::sleep(wait);
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Breaker {
+    /// Transient failures in a row.
+    failures: u32,
+    /// Set while the provider is skipped.
+    open_until: Option<Instant>,
+    /// A call is trying the provider again after the cooldown.
+    probing: bool,
+}
+
+static BREAKERS: Lazy<Mutex<HashMap<String, Breaker>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    /// The cooldown is over and the next call tries the provider again.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    /// Until the provider is tried again, while the circuit is open.
+    pub retry_in_ms: Option<u64>,
+}
+
+/// Let a call through unless `provider` is being skipped. After the
+/// cooldown one call at a time tries it again; for that call this returns
+/// true.
+fn admit(provider: &str) -> AppResult<bool> {
+    let mut breakers = BREAKERS.lock();
+    let breaker = breakers.entry(provider.to_string()).or_default();
+    let Some(open_until) = breaker.open_until else {
+        return Ok(false);
+    };
+    let now = Instant::now();
+    if now < open_until || breaker.probing {
+        let wait = open_until.saturating_duration_since(now);
+        return Err(AppError::Provider(ProviderError {
+            retry_after: Some(wait),
+            ..ProviderError::new(
+                ErrorClass::CircuitOpen,
+                format!(
+                    "Skipping {} after {} failed calls in a row; trying it again in {}s",
+                    provider,
+                    breaker.failures,
+                    wait.as_secs().max(1)
+                ),
+            )
+        }));
+    }
+    breaker.probing = true;
+    Ok(true)
+}
+
+/// Give up the trial call `admit` allowed without making it.
+fn withdraw(provider: &str) {
+    if let Some(breaker) = BREAKERS.lock().get_mut(provider) {
+        breaker.probing = false;
+    }
+}
+
+/// Count the outcome of a call. Only transient errors count as failures:
+/// a provider that rejects a request is still up.
+fn settle(provider: &str, class: Option<ErrorClass>, settings: &ResilienceSettings) {
+    let mut breakers = BREAKERS.lock();
+    let breaker = breakers.entry(provider.to_string()).or_default();
+    let probing = std::mem::take(&mut breaker.probing);
+    if !class.is_some_and(ErrorClass::is_transient) {
+        if breaker.open_until.take().is_some() {
+            tracing::info!("{} is answering again", provider);
+        }
+        breaker.failures = 0;
+        return;
+    }
+    breaker.failures += 1;
+    if probing || breaker.failures >= settings.failure_threshold.max(1) {
+        tracing::warn!(
+            failures = breaker.failures,
+            "Skipping {} for {}s",
+            provider,
+            settings.cooldown_secs
+        );
+        breaker.open_until = Some(Instant::now() + Duration::from_secs(settings.cooldown_secs));
+    }
+}
+
+/// Circuit state of every provider called so far.
+pub fn health() -> Vec<ProviderHealth> {
+    let now = Instant::now();
+    let mut health: Vec<ProviderHealth> = BREAKERS
+        .lock()
+        .iter()
+        .map(|(provider, breaker)| {
+            let state = match breaker.open_until {
+                Some(until) if until > now => CircuitState::Open,
+                Some(_) => CircuitState::HalfOpen,
+                None => CircuitState::Closed,
+            };
+            ProviderHealth {
+                provider: provider.clone(),
+                state,
+                consecutive_failures: breaker.failures,
+                retry_in_ms: breaker
+                    .open_until
+                    .filter(|_| state == CircuitState::Open)
+                    .map(|until| until.saturating_duration_since(now).as_millis() as u64),
+            }
+        })
+        .collect();
+    health.sort_by(|a, b| a.provider.cmp(&b.provider));
+    health
+}
+
+/// Full rate limit buckets and no failures on record.
+#[cfg(test)]
+pub fn reset() {
+    BUCKETS.lock().clear();
+    BREAKERS.lock().clear();
+}
+
+/// Pause before retry number `retry` (from 0): what the provider asked for,
+/// or exponential backoff with full jitter. `None` when that is longer than
+/// the settings allow.
+fn backoff(retry_after: Option<Duration>, retry: u32, settings: &ResilienceSettings) -> Option<Duration> {
+    let max_delay = Duration::from_millis(settings.max_delay_ms);
+    if let Some(wait) = retry_after {
+        return (wait <= max_delay).then_some(wait);
+    }
+    let ceiling = settings
+        .base_delay_ms
+        .saturating_mul(1 << retry.min(16))
+        .min(settings.max_delay_ms);
+    Some(Duration::from_millis(rand::random::<u64>() % (ceiling + 1)))
+}
+
+/// Rate limits, retries and circuit breaking for one concrete provider.
+/// Streams are only retried until the first text has been passed on.
+pub struct ResilientProvider {
+    inner: Box<dyn LlmProvider>,
+}
+
+impl ResilientProvider {
+    pub fn new(inner: Box<dyn LlmProvider>) -> Self {
+        Self { inner }
+    }
+
+    fn call(
+        &self,
+        run: &mut dyn FnMut() -> AppResult<Completion>,
+        passed_on: &dyn Fn() -> bool,
+    ) -> AppResult<Completion> {
+        let settings = settings::current().resilience;
+        let provider = self.inner.name();
+        let probing = admit(provider)?;
+        // Held back by the rate limit, the provider never saw the call
+        if let Err(e) = acquire(provider, &settings) {
+            if probing {
+                withdraw(provider);
+            }
+            return Err(e);
+        }
+
+        let mut retry = 0;
+        loop {
+            let error = match run() {
+                Ok(completion) => {
+                    settle(provider, None, &settings);
+                    return Ok(completion);
+                }
+                Err(error) => error,
+            };
+            let (class, retry_after) = match &error {
+                AppError::Provider(error) => (error.kind, error.retry_after),
+                _ => (ErrorClass::Other, None),
+            };
+            // A trial call after the cooldown gets one attempt
+            let pause = (class.is_transient() && !probing && retry < settings.max_retries && !passed_on())
+                .then(|| backoff(retry_after, retry, &settings))
+                .flatten();
+            let Some(pause) = pause else {
+                settle(provider, Some(class), &settings);
+                return Err(error);
+            };
+            retry += 1;
+            tracing::warn!(?class, ?pause, retry, "{}; retrying", error);
+            std::thread::sleep(pause);
+            if acquire(provider, &settings).is_err() {
+                settle(provider, Some(class), &settings);
+                return Err(error);
+            }
+        }
+    }
+}
+
+impl LlmProvider for ResilientProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn complete(&self, request: &CompletionRequest) -> AppResult<Completion> {
+        self.call(&mut || self.inner.complete(request), &|| false)
+    }
+
+    fn stream(
+        &self,
+        request: &CompletionRequest,
+        on_text: &mut dyn FnMut(&str),
+    ) -> AppResult<Completion> {
+        let passed_on = Cell::new(false);
+        self.call(
+            &mut || {
+                self.inner.stream(request, &mut |text| {
+                    passed_on.set(true);
+                    on_text(text);
+                })
+            },
+            &|| passed_on.get(),
+        )
+    }
+}
+
+/// Circuit state of each LLM provider the app has called.
+#[tauri::command]
+pub fn get_provider_health() -> Vec<ProviderHealth> {
+    health()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{ChatMessage, FallbackProvider, GeminiProvider, MockProvider};
+    use crate::test_support::{self, Reply, StubServer};
+    use serde_json::json;
+
+    fn answer(text: &str) -> Reply {
+        Reply::json(
+            200,
+            json!({ "candidates": [{ "content": { "parts": [{ "text": text }] } }] }),
+        )
+    }
+
+    fn gemini_error(status: u16, google_status: &str, details: Value) -> Reply {
+        Reply::json(
+            status,
+            json!({ "error": { "code": status, "message": "failed", "status": google_status, "details": details } }),
+        )
+    }
+
+    fn gemini(url: &str) -> Box<dyn LlmProvider> {
+        Box::new(GeminiProvider::new("key".to_string(), "gemini-test".to_string()).with_base_url(url.to_string()))
+    }
+
+    fn ask(provider: &dyn LlmProvider) -> AppResult<Completion> {
+        provider.complete(&CompletionRequest::new(vec![ChatMessage::user("hi")]))
+    }
+
+    fn provider_error(result: AppResult<Completion>) -> ProviderError {
+        match result {
+            Err(AppError::Provider(error)) => error,
+            other => panic!("expected a provider error, got {:?}", other),
+        }
+    }
+
+    fn quick(settings: &mut settings::AppSettings) {
+        settings.resilience.base_delay_ms = 1;
+        settings.resilience.rate_limits.clear();
+    }
+
+    #[test]
+    fn classifies_error_responses_by_status_and_body() {
+        let key_invalid = json!({ "error": { "code": 400, "status": "INVALID_ARGUMENT", "details": [
+            { "@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "API_KEY_INVALID" }
+        ] } });
+        let quota = json!({ "error": { "code": 429, "status": "RESOURCE_EXHAUSTED", "details": [
+            { "@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "38s" }
+        ] } });
+        let filtered =
+            json!({ "error": { "message": "filtered", "type": "invalid_request_error", "code": "content_filter" } });
+        let cases = [
+            (400, key_invalid.to_string(), ErrorClass::Auth),
+            (400, filtered.to_string(), ErrorClass::ContentFilter),
+            (
+                400,
+                json!({ "error": { "status": "RESOURCE_EXHAUSTED" } }).to_string(),
+                ErrorClass::RateLimited,
+            ),
+            (400, "bad request".to_string(), ErrorClass::Invalid),
+            (401, String::new(), ErrorClass::Auth),
+            (408, String::new(), ErrorClass::Network),
+            (429, quota.to_string(), ErrorClass::RateLimited),
+            (503, "overloaded".to_string(), ErrorClass::Overloaded),
+        ];
+        for (status, body, kind) in cases {
+            let error = ProviderError::from_response("Gemini", status, None, &body);
+            assert_eq!(error.kind, kind, "{} {}", status, body);
+            assert_eq!(error.status, Some(status));
+        }
+
+        let error = ProviderError::from_response("Gemini", 429, None, &quota.to_string());
+        assert_eq!(error.retry_after, Some(Duration::from_secs(38)));
+        // The header wins over the body
+        let error = ProviderError::from_response("Gemini", 429, Some(Duration::from_secs(2)), &quota.to_string());
+        assert_eq!(error.retry_after, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn retries_overloaded_and_rate_limited_calls() {
+        let _isolated = test_support::isolated(quick);
+        let server = StubServer::start(vec![
+            gemini_error(503, "UNAVAILABLE", json!([])),
+            gemini_error(429, "RESOURCE_EXHAUSTED", json!([])).header("Retry-After", "0"),
+            answer("hello"),
+        ]);
+
+        let completion = ask(&ResilientProvider::new(gemini(&server.url))).unwrap();
+        assert_eq!(completion.text, "hello");
+        assert_eq!(server.requests().len(), 3);
+        assert_eq!(health()[0].consecutive_failures, 0);
+    }
+
+    #[test]
+    fn fails_fast_on_bad_keys_and_long_waits() {
+        let _isolated = test_support::isolated(quick);
+        let server = StubServer::start(vec![
+            gemini_error(
+                400,
+                "INVALID_ARGUMENT",
+                json!([{ "@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "API_KEY_INVALID" }]),
+            ),
+            gemini_error(429, "RESOURCE_EXHAUSTED", json!([])).header("Retry-After", "120"),
+        ]);
+        let provider = ResilientProvider::new(gemini(&server.url));
+
+        assert_eq!(provider_error(ask(&provider)).kind, ErrorClass::Auth);
+        assert_eq!(server.requests().len(), 1);
+
+        let error = provider_error(ask(&provider));
+        assert_eq!(error.kind, ErrorClass::RateLimited);
+        assert_eq!(error.retry_after, Some(Duration::from_secs(120)));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn unreachable_providers_are_network_errors() {
+        let _isolated = test_support::isolated(|settings| {
+            quick(settings);
+            settings.resilience.max_retries = 0;
+        });
+        let server = StubServer::start(vec![Reply::Disconnect]);
+
+        assert_eq!(
+            provider_error(ask(&ResilientProvider::new(gemini(&server.url)))).kind,
+            ErrorClass::Network
+        );
+        let closed = test_support::closed_url();
+        assert_eq!(
+            provider_error(ask(&ResilientProvider::new(gemini(&closed)))).kind,
+            ErrorClass::Network
+        );
+    }
+
+    #[test]
+    fn skips_a_failing_provider_and_falls_back() {
+        let _isolated = test_support::isolated(|settings| {
+            quick(settings);
+            settings.resilience.max_retries = 0;
+            settings.resilience.failure_threshold = 2;
+        });
+        let server = StubServer::start(vec![
+            gemini_error(503, "UNAVAILABLE", json!([])),
+            gemini_error(503, "UNAVAILABLE", json!([])),
+            answer("too early"),
+        ]);
+        let provider = FallbackProvider::new(
+            Box::new(ResilientProvider::new(gemini(&server.url))),
+            Box::new(MockProvider::new(vec![
+                "local 1".to_string(),
+                "local 2".to_string(),
+                "local 3".to_string(),
+            ])),
+        );
+
+        for expected in ["local 1", "local 2", "local 3"] {
+            assert_eq!(ask(&provider).unwrap().text, expected);
+        }
+        // The third call never reached the server
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(health()[0].state, CircuitState::Open);
+        assert_eq!(
+            provider_error(ask(&ResilientProvider::new(gemini(&server.url)))).kind,
+            ErrorClass::CircuitOpen
+        );
+    }
+
+    #[test]
+    fn a_stream_that_broke_off_midway_is_not_answered_again() {
+        let _isolated = test_support::isolated(|settings| {
+            quick(settings);
+            settings.resilience.max_retries = 1;
+        });
+        let chunk = |text: &str| {
+            let chunk = json!({ "candidates": [{ "content": { "parts": [{ "text": text }] } }] });
+            format!("data: {}\n\n", chunk)
+        };
+        let server = StubServer::start(vec![Reply::CutOff {
+            body: chunk("The answer is"),
+        }]);
+        let provider = FallbackProvider::new(
+            Box::new(ResilientProvider::new(gemini(&server.url))),
+            Box::new(MockProvider::new(vec!["local answer".to_string()])),
+        );
+        let request = CompletionRequest::new(vec![ChatMessage::user("hi")]);
+
+        let mut shown = String::new();
+        let error = provider_error(provider.stream(&request, &mut |text| shown.push_str(text)));
+        assert_eq!(error.kind, ErrorClass::Network);
+        assert_eq!(shown, "The answer is", "neither retried nor answered by the fallback");
+        assert_eq!(server.requests().len(), 1);
+
+        // Broken off before any text, the fallback answers
+        let mut shown = String::new();
+        let server = StubServer::start(vec![
+            Reply::CutOff { body: String::new() },
+            Reply::CutOff { body: String::new() },
+        ]);
+        let provider = FallbackProvider::new(
+            Box::new(ResilientProvider::new(gemini(&server.url))),
+            Box::new(MockProvider::new(vec!["local answer".to_string()])),
+        );
+        assert_eq!(
+            provider
+                .stream(&request, &mut |text| shown.push_str(text))
+                .unwrap()
+                .text,
+            "local answer"
+        );
+        assert_eq!(shown, "local answer");
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn the_client_side_rate_limit_holds_calls_back() {
+        let _isolated = test_support::isolated(|settings| {
+            quick(settings);
+            settings.resilience.max_delay_ms = 10;
+            settings.resilience.rate_limits = vec![settings::RateLimit {
+                provider: "gemini".to_string(),
+                requests_per_minute: 1,
+                burst: 1,
+            }];
+        });
+        let server = StubServer::start(vec![answer("first"), answer("second")]);
+        let provider = ResilientProvider::new(gemini(&server.url));
+
+        assert_eq!(ask(&provider).unwrap().text, "first");
+        let error = provider_error(ask(&provider));
+        assert_eq!(error.kind, ErrorClass::RateLimited);
+        assert!(error.status.is_none());
+        assert_eq!(server.requests().len(), 1);
+    }
+}
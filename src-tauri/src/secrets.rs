@@ -0,0 +1,418 @@
+// src-tauri/src/secrets.rs
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::error::{AppError, AppResult};
+use crate::headless;
+
+const SECRETS_FILE: &str = "secrets.enc";
+const KEY_FILE: &str = "secrets.key";
+/// Which store was picked, so a keyring that is down for a moment does not
+/// send the app to an empty file.
+const STORE_CHOICE_FILE: &str = "secrets.store";
+const NONCE_LEN: usize = 12;
+/// Where the webview's settings store kept the API key in plaintext.
+const LEGACY_STORE_FILE: &str = ".settings.dat";
+
+/// Environment variables each secret used to come from.
+const ENV_VARS: &[(&str, &[&str])] = &[
+    ("gemini", &["GOOGLE_API_KEY", "VITE_GOOGLE_API_KEY"]),
+    ("openai", &["OPENAI_API_KEY"]),
+];
+
+/// Where provider keys are kept. Secrets go in, and only Rust gets them
+/// back out.
+pub trait SecretStore: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn get(&self, name: &str) -> AppResult<Option<String>>;
+    fn set(&self, name: &str, value: &str) -> AppResult<()>;
+    /// Returns whether there was something to delete.
+    fn delete(&self, name: &str) -> AppResult<bool>;
+}
+
+/// Keychain on macOS, Secret Service on Linux, Credential Manager on
+/// Windows.
+pub struct KeyringStore;
+
+impl KeyringStore {
+    fn entry(name: &str) -> AppResult<keyring::Entry> {
+        keyring::Entry::new(headless::APP_IDENTIFIER, name).map_err(Self::error)
+    }
+
+    fn error(error: keyring::Error) -> AppError {
+        AppError::Failed(format!("OS keyring: {}", error))
+    }
+
+    /// Whether the platform keyring answers at all; on Linux there may be no
+    /// Secret Service running.
+    fn available() -> bool {
+        // A missing entry still means the keyring works
+        let probe = Self::entry("probe").and_then(|entry| match entry.get_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(Self::error(e)),
+        });
+        if let Err(e) = &probe {
+            tracing::warn!("OS keyring unavailable: {}", e);
+        }
+        probe.is_ok()
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn get(&self, name: &str) -> AppResult<Option<String>> {
+        match Self::entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(Self::error(e)),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> AppResult<()> {
+        Self::entry(name)?.set_password(value).map_err(Self::error)
+    }
+
+    fn delete(&self, name: &str) -> AppResult<bool> {
+        match Self::entry(name)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(Self::error(e)),
+        }
+    }
+}
+
+/// Fallback when there is no keyring: secrets encrypted with
+/// ChaCha20-Poly1305 under a random key in a separate file. Both files are
+/// readable by the user only, so this keeps keys out of backups and
+/// settings exports rather than away from other programs the user runs.
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    key_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl EncryptedFileStore {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(SECRETS_FILE),
+            key_path: dir.join(KEY_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn cipher(&self) -> AppResult<ChaCha20Poly1305> {
+        let key = match fs::read(&self.key_path) {
+            Ok(key) => key,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = rand::random::<[u8; 32]>().to_vec();
+                write_private(&self.key_path, &key)?;
+                key
+            }
+            Err(e) => return Err(AppError::Io(format!("Failed to read {}: {}", self.key_path.display(), e))),
+        };
+        if key.len() != 32 {
+            return Err(AppError::Failed(format!("{} is not a valid key", self.key_path.display())));
+        }
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+
+    fn load(&self) -> AppResult<BTreeMap<String, String>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(AppError::Io(format!("Failed to read {}: {}", self.path.display(), e))),
+        };
+        if data.len() < NONCE_LEN {
+            return Err(AppError::Failed(format!("{} is truncated", self.path.display())));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::Failed(format!("Failed to decrypt {}", self.path.display())))?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| AppError::Failed(format!("Failed to parse {}: {}", self.path.display(), e)))
+    }
+
+    fn save(&self, secrets: &BTreeMap<String, String>) -> AppResult<()> {
+        let plaintext = serde_json::to_vec(secrets).map_err(|e| AppError::Failed(e.to_string()))?;
+        let nonce = rand::random::<[u8; NONCE_LEN]>();
+        let ciphertext = self
+            .cipher()?
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .map_err(|e| AppError::Failed(format!("Failed to encrypt secrets: {}", e)))?;
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&ciphertext);
+        write_private(&self.path, &data)
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn name(&self) -> &'static str {
+        "encrypted_file"
+    }
+
+    fn get(&self, name: &str) -> AppResult<Option<String>> {
+        let _guard = self.lock.lock();
+        Ok(self.load()?.remove(name))
+    }
+
+    fn set(&self, name: &str, value: &str) -> AppResult<()> {
+        let _guard = self.lock.lock();
+        let mut secrets = self.load()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.save(&secrets)
+    }
+
+    fn delete(&self, name: &str) -> AppResult<bool> {
+        let _guard = self.lock.lock();
+        let mut secrets = self.load()?;
+        let existed = secrets.remove(name).is_some();
+        if existed {
+            self.save(&secrets)?;
+        }
+        Ok(existed)
+    }
+}
+
+fn write_private(path: &Path, contents: &[u8]) -> AppResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| AppError::Io(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+static STORE: Lazy<RwLock<Option<Arc<dyn SecretStore>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Pick the keyring, or the encrypted file in `config_dir` when there is
+/// none, and stick with it on later starts. `LEO_SECRET_STORE=keyring|file`
+/// forces one.
+pub fn init(config_dir: &Path) {
+    let choice_path = config_dir.join(STORE_CHOICE_FILE);
+    let saved = fs::read_to_string(&choice_path).ok();
+    let forced = std::env::var("LEO_SECRET_STORE").ok();
+    let choice = match forced.as_deref().or(saved.as_deref().map(str::trim)) {
+        Some("file") => "file",
+        Some("keyring") => "keyring",
+        // Installs from before the choice was saved may already use the file
+        _ if config_dir.join(SECRETS_FILE).exists() => "file",
+        _ if KeyringStore::available() => "keyring",
+        _ => "file",
+    };
+    if saved.as_deref().map(str::trim) != Some(choice) {
+        if let Err(e) = fs::create_dir_all(config_dir).and_then(|()| fs::write(&choice_path, choice)) {
+            tracing::warn!("Failed to save the secret store choice: {}", e);
+        }
+    }
+
+    let store: Arc<dyn SecretStore> = match choice {
+        "keyring" => Arc::new(KeyringStore),
+        _ => Arc::new(EncryptedFileStore::new(config_dir)),
+    };
+    tracing::info!("Keeping secrets in {}", store.name());
+    *STORE.write() = Some(store);
+}
+
+fn store() -> AppResult<Arc<dyn SecretStore>> {
+    STORE
+        .read()
+        .clone()
+        .ok_or_else(|| AppError::InvalidState("Secrets have not been initialized".to_string()))
+}
+
+fn validate_name(name: &str) -> AppResult<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::InvalidInput(format!("Invalid secret name: {}", name)))
+    }
+}
+
+/// The secret stored under `name`, falling back to the environment
+/// variables it used to come from.
+pub fn get(name: &str) -> Option<String> {
+    let stored = store().and_then(|store| store.get(name));
+    match stored {
+        Ok(Some(value)) => return Some(value),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to read secret {}: {}", name, e),
+    }
+    env_value(name)
+}
+
+fn env_value(name: &str) -> Option<String> {
+    ENV_VARS
+        .iter()
+        .find(|(secret, _)| *secret == name)
+        .and_then(|(_, vars)| vars.iter().find_map(|var| std::env::var(var).ok()))
+        .filter(|value| !value.is_empty())
+}
+
+/// Provider the webview's "API Key" field belonged to, by its model select.
+fn legacy_provider(model: &str) -> &'static str {
+    match model {
+        "gemini" => "gemini",
+        "claude" => "anthropic",
+        _ => "openai",
+    }
+}
+
+/// Move plaintext keys into the store: the API key the settings panel kept
+/// in `.settings.dat` in `data_dir`, which is then blanked, and keys from
+/// environment variables that have no stored counterpart yet. Runs on every
+/// start and only does something the first time.
+pub fn migrate_plaintext(data_dir: &Path) {
+    let store = match store() {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::warn!("Not migrating secrets: {}", e);
+            return;
+        }
+    };
+
+    let legacy_path = data_dir.join(LEGACY_STORE_FILE);
+    if let Err(e) = migrate_legacy_store(store.as_ref(), &legacy_path) {
+        tracing::warn!("Failed to migrate the key in {}: {}", legacy_path.display(), e);
+    }
+
+    for (name, _) in ENV_VARS {
+        let Some(value) = env_value(name) else {
+            continue;
+        };
+        match store.get(name) {
+            Ok(None) => match store.set(name, &value) {
+                Ok(()) => tracing::info!("Moved the {} key from the environment to {}", name, store.name()),
+                Err(e) => tracing::warn!("Failed to store the {} key: {}", name, e),
+            },
+            Ok(Some(_)) => {}
+            Err(e) => tracing::warn!("Failed to read secret {}: {}", name, e),
+        }
+    }
+}
+
+fn migrate_legacy_store(store: &dyn SecretStore, path: &Path) -> AppResult<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut data: Value = serde_json::from_str(&contents).map_err(|e| AppError::Failed(e.to_string()))?;
+    let Some(ai) = data.pointer_mut("/settings/ai") else {
+        return Ok(());
+    };
+    let key = ai.get("apiKey").and_then(Value::as_str).unwrap_or_default().trim().to_string();
+    if key.is_empty() {
+        return Ok(());
+    }
+    let provider = legacy_provider(ai.get("model").and_then(Value::as_str).unwrap_or_default());
+
+    store.set(provider, &key)?;
+    ai["apiKey"] = Value::String(String::new());
+    let contents = serde_json::to_string(&data).map_err(|e| AppError::Failed(e.to_string()))?;
+    fs::write(path, contents)?;
+    tracing::info!("Moved the {} key from {} to {}", provider, LEGACY_STORE_FILE, store.name());
+    Ok(())
+}
+
+/// Values only Rust manages, like the app lock's password hash. Their names
+/// contain a `.`, which the secret commands reject, so the webview cannot
+/// read, replace or delete them.
+pub fn get_internal(name: &str) -> AppResult<Option<String>> {
+    store()?.get(name)
+}
+
+pub fn set_internal(name: &str, value: &str) -> AppResult<()> {
+    store()?.set(name, value)
+}
+
+pub fn delete_internal(name: &str) -> AppResult<bool> {
+    store()?.delete(name)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretStatus {
+    pub name: String,
+    pub stored: bool,
+    /// Only available from an environment variable.
+    pub from_env: bool,
+    pub backend: String,
+}
+
+#[tauri::command]
+pub fn set_secret(name: String, value: String) -> AppResult<()> {
+    validate_name(&name)?;
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(AppError::InvalidInput("The secret is empty".to_string()));
+    }
+    store()?.set(&name, value)?;
+    tracing::info!("Stored secret {}", name);
+    Ok(())
+}
+
+/// Whether a secret is set, without its value.
+#[tauri::command]
+pub fn has_secret(name: String) -> AppResult<SecretStatus> {
+    validate_name(&name)?;
+    let store = store()?;
+    let stored = store.get(&name)?.is_some();
+    Ok(SecretStatus {
+        from_env: !stored && env_value(&name).is_some(),
+        name,
+        stored,
+        backend: store.name().to_string(),
+    })
+}
+
+#[tauri::command]
+pub fn delete_secret(name: String) -> AppResult<()> {
+    validate_name(&name)?;
+    if !store()?.delete(&name)? {
+        return Err(AppError::NotFound(format!("No secret named {}", name)));
+    }
+    tracing::info!("Deleted secret {}", name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn the_store_choice_is_kept() {
+        let _isolated = test_support::isolated(|_| {});
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(SECRETS_FILE), b"").unwrap();
+
+        init(dir.path());
+        assert_eq!(store().unwrap().name(), "encrypted_file");
+        assert_eq!(fs::read_to_string(dir.path().join(STORE_CHOICE_FILE)).unwrap(), "file");
+    }
+}
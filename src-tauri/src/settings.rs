@@ -0,0 +1,708 @@
+// src-tauri/src/settings.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, AppResult};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Settings owned by the Rust side. Field names follow the camelCase used by
+/// `Settings.tsx` so the frontend can round-trip the whole object.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AppSettings {
+    pub privacy: PrivacySettings,
+    pub context: ContextSettings,
+    pub ai: AiSettings,
+    pub dictation: DictationSettings,
+    pub permissions: PermissionSettings,
+    pub developer: DeveloperSettings,
+    pub audit: AuditSettings,
+    pub logging: LoggingSettings,
+    pub security: SecuritySettings,
+    pub redaction: RedactionSettings,
+    pub agent: AgentSettings,
+    pub vision: VisionSettings,
+    pub budget: BudgetSettings,
+    pub resilience: ResilienceSettings,
+    pub prompts: PromptSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AiSettings {
+    /// Provider used by Rust-side LLM calls: "gemini", "local" or "mock".
+    /// Changed with `set_ai_provider`, like `local.endpoint`.
+    pub provider: String,
+    pub model: String,
+    /// Language selections are translated into.
+    pub target_language: String,
+    /// Used instead when the selection is already in the target language.
+    pub fallback_language: String,
+    pub local: LocalModelSettings,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        Self {
+            provider: "gemini".to_string(),
+            model: "gemini-pro".to_string(),
+            target_language: "English".to_string(),
+            fallback_language: "Spanish".to_string(),
+            local: LocalModelSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LocalModelSettings {
+    /// Base URL of an OpenAI-compatible server such as Ollama or llama.cpp's
+    /// `llama-server`.
+    pub endpoint: String,
+    /// Empty picks the first model the server lists.
+    pub model: String,
+    /// Tokens the model takes in, for servers that do not report it.
+    pub context_window: Option<u32>,
+    /// Answer with the local model when the cloud provider cannot be reached.
+    pub offline_fallback: bool,
+}
+
+impl Default for LocalModelSettings {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost:11434/v1".to_string(),
+            model: String::new(),
+            context_window: None,
+            offline_fallback: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PrivacySettings {
+    /// Record focus sessions into the local usage timeline.
+    pub usage_tracking: bool,
+    /// Apps (by name, case-insensitive) that are never recorded.
+    pub excluded_apps: Vec<String>,
+    /// Seconds without input before a focus session turns into an idle gap.
+    pub idle_threshold_secs: u64,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            usage_tracking: true,
+            excluded_apps: Vec::new(),
+            idle_threshold_secs: 120,
+        }
+    }
+}
+
+impl PrivacySettings {
+    pub fn is_excluded(&self, app_name: &str) -> bool {
+        self.excluded_apps
+            .iter()
+            .any(|excluded| excluded.eq_ignore_ascii_case(app_name))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AgentSettings {
+    /// Tool calls a chat may make before it is stopped.
+    pub max_steps: usize,
+    /// "confirm" (each step waits for the user), "auto" or "preview" (steps
+    /// are shown but not run). Only changed by editing settings.json.
+    pub approval: String,
+}
+
+impl Default for AgentSettings {
+    fn default() -> Self {
+        Self {
+            max_steps: 8,
+            approval: "confirm".to_string(),
+        }
+    }
+}
+
+/// Spending limits for LLM calls, in US dollars. Costs are estimates from
+/// the token counts and the price list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BudgetSettings {
+    /// Per local calendar day; unset means no limit.
+    pub daily_limit_usd: Option<f64>,
+    /// Per calendar month.
+    pub monthly_limit_usd: Option<f64>,
+    /// Share of a limit, in percent, at which the user is warned.
+    pub warn_at_percent: u8,
+    /// Prices that replace or add to the built-in ones.
+    pub prices: Vec<ModelPrice>,
+}
+
+impl Default for BudgetSettings {
+    fn default() -> Self {
+        Self {
+            daily_limit_usd: None,
+            monthly_limit_usd: None,
+            warn_at_percent: 80,
+            prices: Vec::new(),
+        }
+    }
+}
+
+/// How LLM calls cope with failing or busy providers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ResilienceSettings {
+    /// Retries after a rate limit, overload or network error.
+    pub max_retries: u32,
+    /// First backoff; it doubles with each retry and is jittered.
+    pub base_delay_ms: u64,
+    /// Longest wait before a retry or for the rate limit. A provider that
+    /// asks for a longer `Retry-After` is not retried.
+    pub max_delay_ms: u64,
+    /// Failed calls in a row after which a provider is skipped.
+    pub failure_threshold: u32,
+    /// How long a provider is skipped before it is tried again.
+    pub cooldown_secs: u64,
+    pub rate_limits: Vec<RateLimit>,
+}
+
+impl Default for ResilienceSettings {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            base_delay_ms: 500,
+            max_delay_ms: 20_000,
+            failure_threshold: 5,
+            cooldown_secs: 30,
+            rate_limits: vec![RateLimit {
+                provider: "gemini".to_string(),
+                requests_per_minute: 60,
+                burst: 10,
+            }],
+        }
+    }
+}
+
+/// Requests a provider gets from this app, enforced before they are sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimit {
+    /// "gemini", "local" or "mock".
+    pub provider: String,
+    pub requests_per_minute: u32,
+    /// Requests that may go out at once after a quiet spell.
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    /// Model name or prefix, e.g. "gemini-1.5-flash".
+    pub model: String,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+/// Which prompt template the chat uses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PromptSettings {
+    /// Used in apps without a template of their own.
+    pub default_template: String,
+    pub app_templates: Vec<AppTemplate>,
+}
+
+impl Default for PromptSettings {
+    fn default() -> Self {
+        Self {
+            default_template: "assistant".to_string(),
+            app_templates: [
+                ("Code", "coding"),
+                ("Cursor", "coding"),
+                ("Xcode", "coding"),
+                ("Terminal", "coding"),
+                ("Mail", "email"),
+                ("Microsoft Outlook", "email"),
+            ]
+            .into_iter()
+            .map(|(app, template)| AppTemplate {
+                app: app.to_string(),
+                template: template.to_string(),
+            })
+            .collect(),
+        }
+    }
+}
+
+impl PromptSettings {
+    pub fn template_for(&self, app_name: Option<&str>) -> &str {
+        app_name
+            .and_then(|app_name| {
+                self.app_templates
+                    .iter()
+                    .find(|binding| binding.app.eq_ignore_ascii_case(app_name))
+            })
+            .map_or(self.default_template.as_str(), |binding| binding.template.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AppTemplate {
+    pub app: String,
+    /// Template name, e.g. "coding".
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct VisionSettings {
+    /// Model for requests with a screenshot; the chat model may not take
+    /// images.
+    pub model: String,
+    /// Longest side, in pixels, captures are scaled down to.
+    pub max_dimension: u32,
+    /// JPEG quality (1-100) of the image that is sent.
+    pub jpeg_quality: u8,
+}
+
+impl Default for VisionSettings {
+    fn default() -> Self {
+        Self {
+            model: "gemini-1.5-flash".to_string(),
+            max_dimension: 1568,
+            jpeg_quality: 70,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DictationSettings {
+    /// "remote" (OpenAI-compatible API) or "whisper" (local whisper.cpp).
+    /// Only changed by editing settings.json, like `endpoint`.
+    pub transcriber: String,
+    /// Receives the OpenAI API key with each recording.
+    pub endpoint: String,
+    pub model: String,
+    pub whisper_model_path: String,
+    /// ISO language code; empty lets the transcriber detect it.
+    pub language: String,
+    pub vad_threshold: f32,
+    pub silence_ms: u32,
+    pub type_into_focused_app: bool,
+}
+
+impl Default for DictationSettings {
+    fn default() -> Self {
+        Self {
+            transcriber: "remote".to_string(),
+            endpoint: "https://api.openai.com/v1/audio/transcriptions".to_string(),
+            model: "whisper-1".to_string(),
+            whisper_model_path: String::new(),
+            language: String::new(),
+            vad_threshold: 0.01,
+            silence_ms: 700,
+            type_into_focused_app: true,
+        }
+    }
+}
+
+/// Consent state for automation commands. Only changed from Rust, after the
+/// user confirms in a native dialog; `update_settings` keeps it as is.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PermissionSettings {
+    /// Apps automation may target. Empty allows any app.
+    pub allowed_apps: Vec<String>,
+    pub remembered: Vec<RememberedDecision>,
+}
+
+impl PermissionSettings {
+    pub fn app_allowed(&self, app_name: &str) -> bool {
+        self.allowed_apps.is_empty()
+            || self
+                .allowed_apps
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(app_name))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RememberedDecision {
+    pub capability: String,
+    /// App or workflow the decision applies to; `None` covers all targets.
+    pub target: Option<String>,
+    pub allow: bool,
+    pub decided_at_ms: i64,
+}
+
+/// Only editable in settings.json by hand; `update_settings` keeps it as is.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DeveloperSettings {
+    /// Allow `run_apple_script` with arbitrary scripts.
+    pub allow_raw_scripts: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AuditSettings {
+    /// Also log prompts and responses of LLM calls.
+    pub include_llm_content: bool,
+    /// Size at which the audit log is rotated.
+    pub max_file_kb: u64,
+    /// Rotated files kept besides the current one.
+    pub max_files: usize,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        Self {
+            include_llm_content: false,
+            max_file_kb: 5 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LoggingSettings {
+    /// Level for everything without a more specific entry in `modules`.
+    pub level: String,
+    /// Levels by target: a module of this app such as "workflow", "helper"
+    /// for the window detector's output, or a full target like "tauri::app".
+    pub modules: BTreeMap<String, String>,
+    /// Daily log files kept in the app log dir.
+    pub max_files: usize,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            modules: BTreeMap::new(),
+            max_files: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SecuritySettings {
+    /// How the app is unlocked: "none", "password", "system" (account
+    /// password through the OS) or "biometric".
+    pub auth_method: String,
+    /// Lock after this many minutes without input; 0 turns it off.
+    pub auto_lock_minutes: u64,
+    pub lock_on_screen_lock: bool,
+}
+
+impl Default for SecuritySettings {
+    fn default() -> Self {
+        Self {
+            auth_method: "none".to_string(),
+            auto_lock_minutes: 0,
+            lock_on_screen_lock: true,
+        }
+    }
+}
+
+/// What is masked in text sent to an LLM provider. Masked values become
+/// placeholders like `[EMAIL_1]` and are put back into the response locally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RedactionSettings {
+    /// "off", "standard" or "strict" (adds phone numbers, IP addresses and a
+    /// lower entropy threshold). Like every field here that can loosen the
+    /// masking, only changed by editing settings.json.
+    pub mode: String,
+    /// Built-in detectors to skip, e.g. "email".
+    pub disabled_detectors: Vec<String>,
+    pub custom_rules: Vec<RedactionRule>,
+    /// Values never masked, e.g. the user's own work address.
+    pub allowed_values: Vec<String>,
+    /// Bits per character above which a long token counts as a secret.
+    pub entropy_threshold: f64,
+    pub app_policies: Vec<RedactionAppPolicy>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            mode: "standard".to_string(),
+            disabled_detectors: Vec::new(),
+            custom_rules: Vec::new(),
+            allowed_values: Vec::new(),
+            entropy_threshold: 3.5,
+            app_policies: Vec::new(),
+        }
+    }
+}
+
+impl RedactionSettings {
+    pub fn mode_for(&self, app_name: Option<&str>) -> &str {
+        app_name
+            .and_then(|app_name| {
+                self.app_policies
+                    .iter()
+                    .find(|policy| policy.app.eq_ignore_ascii_case(app_name))
+            })
+            .map_or(self.mode.as_str(), |policy| policy.mode.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RedactionRule {
+    /// Becomes the placeholder label: "ticket" gives `[TICKET_1]`.
+    pub name: String,
+    /// Regular expression. With a group named `secret`, only that group is
+    /// masked.
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RedactionAppPolicy {
+    pub app: String,
+    /// Replaces the global mode while this app is frontmost.
+    pub mode: String,
+}
+
+/// What `capture_context` may collect, by default and per app.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ContextSettings {
+    pub default_permissions: ContextPermissions,
+    pub app_rules: Vec<ContextAppRule>,
+    /// Selected text longer than this is truncated before leaving Rust.
+    pub max_selection_chars: usize,
+    /// Keep recently copied texts for `leo clipboard history`.
+    pub clipboard_history: bool,
+    pub clipboard_history_size: usize,
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        Self {
+            default_permissions: ContextPermissions::default(),
+            app_rules: Vec::new(),
+            max_selection_chars: 8000,
+            clipboard_history: false,
+            clipboard_history_size: 50,
+        }
+    }
+}
+
+impl ContextSettings {
+    pub fn permissions_for(&self, app_name: &str) -> ContextPermissions {
+        self.app_rules
+            .iter()
+            .find(|rule| rule.app.eq_ignore_ascii_case(app_name))
+            .map(|rule| rule.permissions.clone())
+            .unwrap_or_else(|| self.default_permissions.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ContextPermissions {
+    pub window_title: bool,
+    pub selection: bool,
+    pub screenshot: bool,
+}
+
+impl Default for ContextPermissions {
+    fn default() -> Self {
+        Self {
+            window_title: true,
+            selection: true,
+            screenshot: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ContextAppRule {
+    pub app: String,
+    #[serde(flatten)]
+    pub permissions: ContextPermissions,
+}
+
+static SETTINGS: Lazy<RwLock<AppSettings>> = Lazy::new(|| RwLock::new(AppSettings::default()));
+
+static SETTINGS_PATH: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+/// Load settings from the app config directory, falling back to defaults
+/// when the file is missing or unreadable.
+pub fn init(config_dir: &Path) {
+    let path = config_dir.join(SETTINGS_FILE);
+    let loaded = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| match serde_json::from_str::<AppSettings>(&contents) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                tracing::warn!("Failed to parse {}: {}", path.display(), e);
+                None
+            }
+        })
+        .unwrap_or_default();
+
+    *SETTINGS.write() = loaded;
+    *SETTINGS_PATH.write() = Some(path);
+}
+
+/// Snapshot of the current settings.
+pub fn current() -> AppSettings {
+    SETTINGS.read().clone()
+}
+
+fn persist(settings: &AppSettings) -> AppResult<()> {
+    let path = SETTINGS_PATH.read().clone();
+    let Some(path) = path else {
+        return Err(AppError::InvalidState("Settings have not been initialized".to_string()));
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(settings).map_err(|e| AppError::Failed(e.to_string()))?;
+    Ok(fs::write(&path, contents)?)
+}
+
+#[tauri::command]
+pub fn get_settings() -> AppSettings {
+    current()
+}
+
+/// Change settings from Rust and persist them.
+pub fn update(change: impl FnOnce(&mut AppSettings)) -> AppResult<()> {
+    let mut settings = SETTINGS.write();
+    let mut updated = settings.clone();
+    change(&mut updated);
+    persist(&updated)?;
+    *settings = updated;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn update_settings(mut settings: AppSettings) -> AppResult<()> {
+    // The webview must not be able to grant itself permissions
+    let current = current();
+    settings.permissions = current.permissions;
+    settings.developer = current.developer;
+    // Nor send the OpenAI key to a server of its choosing
+    settings.dictation.transcriber = current.dictation.transcriber;
+    settings.dictation.endpoint = current.dictation.endpoint;
+    // Nor pick where prompts go, or turn off masking and step approval;
+    // `llm::set_ai_provider` asks before sending chats elsewhere
+    settings.ai.provider = current.ai.provider;
+    settings.ai.local.endpoint = current.ai.local.endpoint;
+    // Custom rules only ever mask more; everything else can turn it off
+    settings.redaction = RedactionSettings {
+        custom_rules: settings.redaction.custom_rules,
+        ..current.redaction
+    };
+    settings.agent.approval = current.agent.approval;
+    // Nor turn the lock off; `lock::update_security_settings` asks first
+    settings.security = current.security;
+    let problems = crate::redaction::validate(&settings.redaction);
+    if !problems.is_empty() {
+        return Err(AppError::InvalidInput(problems.join("; ")));
+    }
+
+    persist(&settings)?;
+    *SETTINGS.write() = settings.clone();
+    crate::usage::apply_settings(&settings.privacy);
+    crate::logging::apply_settings(&settings.logging);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn update_settings_keeps_what_the_webview_may_not_change() {
+        let isolated = test_support::isolated(|settings| {
+            settings.ai.provider = "local".to_string();
+            settings.ai.local.endpoint = "http://localhost:11434/v1".to_string();
+        });
+
+        let mut changed = current();
+        changed.ai.provider = "gemini".to_string();
+        changed.ai.local.endpoint = "https://collector.example/v1".to_string();
+        changed.ai.local.model = "qwen".to_string();
+        changed.redaction.mode = "off".to_string();
+        changed.redaction.custom_rules.push(RedactionRule {
+            name: "ticket".to_string(),
+            pattern: r"ACME-\d+".to_string(),
+        });
+        changed.agent.approval = "auto".to_string();
+        changed.dictation.transcriber = "remote".to_string();
+        changed.dictation.endpoint = "https://collector.example/audio".to_string();
+        changed.developer.allow_raw_scripts = true;
+        changed.security.auth_method = "password".to_string();
+        update_settings(changed).unwrap();
+
+        let file = fs::read_to_string(isolated.dir.path().join(SETTINGS_FILE)).unwrap();
+        let saved: AppSettings = serde_json::from_str(&file).unwrap();
+        let defaults = AppSettings::default();
+        assert_eq!(saved.ai.provider, "local");
+        assert_eq!(saved.ai.local.endpoint, "http://localhost:11434/v1");
+        assert_eq!(saved.redaction.mode, defaults.redaction.mode);
+        assert_eq!(saved.redaction.custom_rules[0].name, "ticket");
+        assert_eq!(saved.agent.approval, defaults.agent.approval);
+        assert_eq!(saved.dictation.endpoint, defaults.dictation.endpoint);
+        assert!(!saved.developer.allow_raw_scripts);
+        assert_eq!(saved.security.auth_method, defaults.security.auth_method);
+        // Everything else is the webview's to change
+        assert_eq!(saved.ai.local.model, "qwen");
+    }
+
+    #[test]
+    fn the_webview_cannot_loosen_masking() {
+        let isolated = test_support::isolated(|settings| {
+            settings.redaction.app_policies.push(RedactionAppPolicy {
+                app: "Mail".to_string(),
+                mode: "strict".to_string(),
+            });
+        });
+
+        let mut changed = current();
+        changed.redaction.app_policies = vec![RedactionAppPolicy {
+            app: "Slack".to_string(),
+            mode: "off".to_string(),
+        }];
+        changed.redaction.disabled_detectors = crate::redaction::detector_names()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        changed.redaction.allowed_values = vec!["alice@example.com".to_string()];
+        changed.redaction.entropy_threshold = 100.0;
+        update_settings(changed).unwrap();
+
+        let file = fs::read_to_string(isolated.dir.path().join(SETTINGS_FILE)).unwrap();
+        let saved: AppSettings = serde_json::from_str(&file).unwrap();
+        assert_eq!(saved.redaction.mode_for(Some("Slack")), "standard");
+        assert_eq!(saved.redaction.mode_for(Some("Mail")), "strict");
+        assert!(saved.redaction.disabled_detectors.is_empty());
+        assert!(saved.redaction.allowed_values.is_empty());
+        assert_eq!(saved.redaction.entropy_threshold, 3.5);
+        assert_eq!(current().redaction.app_policies.len(), 1);
+    }
+}
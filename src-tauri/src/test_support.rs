@@ -0,0 +1,174 @@
+// src-tauri/src/test_support.rs
+
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, MutexGuard};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tempfile::TempDir;
+
+use crate::permissions::{self, Consent};
+use crate::settings::{self, AppSettings};
+use crate::{ledger, llm, lock, resilience, secrets};
+
+/// Held by tests that touch process-wide state such as the settings, so
+/// they do not see each other's changes.
+static GLOBAL_STATE: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Settings in a temp config dir, kept to one test at a time.
+pub struct Isolated {
+    _guard: MutexGuard<'static, ()>,
+    pub dir: TempDir,
+}
+
+/// Start from default settings, changed by `change`, with secrets in an
+/// encrypted file in the temp dir, an empty LLM ledger, the app unlocked,
+/// consent prompts denied and nothing scripted for the mock provider.
+pub fn isolated(change: impl FnOnce(&mut AppSettings)) -> Isolated {
+    let guard = GLOBAL_STATE.lock();
+    *permissions::PROMPTER.answer.lock() = Consent::Deny;
+    lock::reset();
+    resilience::reset();
+    llm::MOCK_SCRIPT.lock().clear();
+    let dir = tempfile::tempdir().expect("temp dir");
+    settings::init(dir.path());
+    settings::update(change).expect("settings saved");
+    fs::write(dir.path().join("secrets.store"), "file").expect("secret store chosen");
+    secrets::init(dir.path());
+    ledger::init(dir.path());
+    Isolated { _guard: guard, dir }
+}
+
+/// What the stub server does with one request.
+pub enum Reply {
+    Respond {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: String,
+    },
+    /// Send a 200 response a byte short of the length it announced, then
+    /// close the connection.
+    CutOff { body: String },
+    /// Close the connection without answering.
+    Disconnect,
+}
+
+impl Reply {
+    pub fn json(status: u16, body: Value) -> Self {
+        Reply::Respond {
+            status,
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: body.to_string(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        if let Reply::Respond { headers, .. } = &mut self {
+            headers.push((name.to_string(), value.to_string()));
+        }
+        self
+    }
+}
+
+/// HTTP server on a free local port that answers requests with scripted
+/// replies in order, to inject faults into provider calls. Requests past
+/// the script get a 500.
+pub struct StubServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl StubServer {
+    pub fn start(replies: Vec<Reply>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("stub server bound");
+        let url = format!("http://{}", listener.local_addr().expect("stub address"));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (thread_requests, thread_stop) = (requests.clone(), stop.clone());
+        let mut replies: VecDeque<Reply> = replies.into();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                thread_requests.lock().push(read_request(&stream));
+                let reply = replies
+                    .pop_front()
+                    .unwrap_or_else(|| Reply::json(500, serde_json::json!({ "error": "nothing scripted" })));
+                let (status, headers, body, length) = match reply {
+                    Reply::Respond { status, headers, body } => {
+                        let length = body.len();
+                        (status, headers, body, length)
+                    }
+                    Reply::CutOff { body } => {
+                        let length = body.len() + 1;
+                        (200, Vec::new(), body, length)
+                    }
+                    Reply::Disconnect => continue,
+                };
+                let mut response = format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    status, length
+                );
+                for (name, value) in headers {
+                    response.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                response.push_str("\r\n");
+                response.push_str(&body);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        Self { url, requests, stop }
+    }
+
+    /// Every request so far: request line, headers and body.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().clone()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.url.trim_start_matches("http://"));
+    }
+}
+
+fn read_request(stream: &TcpStream) -> String {
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+        request.push_str(&line);
+    }
+    let mut body = vec![0; content_length];
+    let _ = reader.read_exact(&mut body);
+    request.push_str("\r\n");
+    request.push_str(&String::from_utf8_lossy(&body));
+    request
+}
+
+/// A local address nothing listens on.
+pub fn closed_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("port reserved");
+    format!("http://{}", listener.local_addr().expect("reserved address"))
+}
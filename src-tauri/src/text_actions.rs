@@ -0,0 +1,335 @@
+// src-tauri/src/text_actions.rs
+
+use serde::{Deserialize, Serialize};
+
+use crate::audit;
+use crate::context;
+use crate::error::{AppError, AppResult};
+use crate::llm::{self, ChatMessage, CompletionRequest, Feature, LlmProvider};
+use crate::permissions::{self, Capability};
+use crate::settings::{self, AiSettings};
+
+const SUMMARIZE_PROMPT: &str = "You summarize text the user has selected on their screen. \
+Reply with a concise summary in the same language as the text. Use short bullet points \
+when the text covers several ideas. Do not add commentary.";
+
+const TRANSLATE_PROMPT: &str = "You translate text the user has selected on their screen. \
+Detect the language of the text. If it is already {{target_language}}, translate it into \
+{{fallback_language}}; otherwise translate it into {{target_language}}. Reply with JSON only: \
+{\"sourceLanguage\": \"<detected language>\", \"targetLanguage\": \"<language you translated into>\", \
+\"translation\": \"<translated text>\"}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAction {
+    Summarize,
+    Translate,
+}
+
+impl TextAction {
+    pub fn parse(action: &str) -> Option<Self> {
+        match action {
+            "summarize" => Some(Self::Summarize),
+            "translate" => Some(Self::Translate),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Summarize => "summarize",
+            Self::Translate => "translate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextActionResult {
+    pub action: TextAction,
+    pub source_text: String,
+    pub output: String,
+    pub source_language: Option<String>,
+    pub target_language: Option<String>,
+    pub pasted: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TranslationReply {
+    source_language: Option<String>,
+    target_language: Option<String>,
+    translation: String,
+}
+
+/// Replace `{{name}}` placeholders in a prompt template in one pass, so
+/// placeholders inside the values are left alone. Unknown names are kept.
+pub fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match vars.iter().find(|(name, _)| *name == &after[..end]) {
+            Some((_, value)) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..start + end + 4]),
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+pub fn build_request(action: TextAction, text: &str, ai: &AiSettings) -> CompletionRequest {
+    let (system, temperature) = match action {
+        TextAction::Summarize => (SUMMARIZE_PROMPT.to_string(), 0.3),
+        TextAction::Translate => (
+            render_template(
+                TRANSLATE_PROMPT,
+                &[
+                    ("target_language", &ai.target_language),
+                    ("fallback_language", &ai.fallback_language),
+                ],
+            ),
+            0.2,
+        ),
+    };
+
+    let feature = match action {
+        TextAction::Summarize => Feature::Summarize,
+        TextAction::Translate => Feature::Translate,
+    };
+    let mut request = CompletionRequest::new(vec![ChatMessage::system(system), ChatMessage::user(text)])
+        .for_feature(feature);
+    request.temperature = temperature;
+    request
+}
+
+/// Pull the translation out of the model's JSON reply. Models sometimes wrap
+/// JSON in a code fence or ignore the format, in which case the raw reply is
+/// used as the translation.
+fn parse_translation(reply: &str) -> (String, Option<String>, Option<String>) {
+    let trimmed = reply.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed)
+        .trim();
+
+    match serde_json::from_str::<TranslationReply>(unfenced) {
+        Ok(parsed) => (
+            parsed.translation,
+            parsed.source_language,
+            parsed.target_language,
+        ),
+        Err(_) => (trimmed.to_string(), None, None),
+    }
+}
+
+/// Run `action` over `text` with the given provider.
+pub fn run(
+    action: TextAction,
+    text: &str,
+    provider: &dyn LlmProvider,
+    ai: &AiSettings,
+) -> AppResult<TextActionResult> {
+    if text.trim().is_empty() {
+        return Err(AppError::InvalidInput("No text selected".to_string()));
+    }
+
+    let completion = provider.complete(&build_request(action, text, ai))?;
+    let (output, source_language, target_language) = match action {
+        TextAction::Summarize => (completion.text.trim().to_string(), None, None),
+        TextAction::Translate => parse_translation(&completion.text),
+    };
+
+    Ok(TextActionResult {
+        action,
+        source_text: text.to_string(),
+        output,
+        source_language,
+        target_language,
+        pasted: false,
+    })
+}
+
+/// Current selection after context permissions and redaction hooks.
+pub fn grab_selection() -> AppResult<String> {
+    context::snapshot(false)
+        .selected_text
+        .filter(|text| !text.trim().is_empty())
+        .ok_or_else(|| AppError::InvalidInput("No text selected".to_string()))
+}
+
+/// Paste `text` into the focused app via the clipboard, then restore it.
+#[cfg(target_os = "macos")]
+pub fn paste_text(text: &str) -> AppResult<()> {
+    let saved = context::read_clipboard();
+    context::write_clipboard(text).map_err(AppError::Failed)?;
+    crate::automation::backend().press_keys("cmd+v")?;
+    // Give the target app time to read the clipboard before restoring it
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    if let Some(saved) = saved {
+        context::write_clipboard(&saved).map_err(AppError::Failed)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn paste_text(_text: &str) -> AppResult<()> {
+    Err(AppError::BackendUnavailable("Pasting is not implemented on this platform.".to_string()))
+}
+
+fn run_on_selection(
+    action: TextAction,
+    text: Option<String>,
+    paste: bool,
+) -> AppResult<TextActionResult> {
+    let focused = permissions::focused_app();
+    let text = match text {
+        Some(text) => text,
+        None => {
+            permissions::require(Capability::ReadSelection, focused.as_deref())?;
+            grab_selection()?
+        }
+    };
+    if paste {
+        permissions::require(Capability::TypeText, focused.as_deref())?;
+    }
+    let ai = settings::current().ai;
+    let provider = llm::configured_provider()?;
+    tracing::info!("Running {:?} on {} chars with {}", action, text.len(), provider.name());
+
+    let mut result = run(action, &text, provider.as_ref(), &ai)?;
+    if paste {
+        paste_text(&result.output)?;
+        result.pasted = true;
+    }
+    Ok(result)
+}
+
+/// Entry point for `automate_mac`. `params` is "selection" to return the
+/// result or "selection:paste" to paste it over the selection.
+pub async fn handle_automation(action: TextAction, params: Option<String>) -> AppResult<String> {
+    let paste = match params.as_deref().unwrap_or("selection") {
+        "selection" => false,
+        "selection:paste" => true,
+        other => {
+            let message = format!("Unsupported params for {:?}: {}", action, other);
+            return Err(AppError::InvalidInput(message));
+        }
+    };
+
+    let result = tauri::async_runtime::spawn_blocking(move || run_on_selection(action, None, paste)).await??;
+    Ok(result.output)
+}
+
+/// Summarize or translate `text`, or the current selection when omitted.
+#[tauri::command]
+pub async fn run_text_action(
+    action: String,
+    text: Option<String>,
+    paste: Option<bool>,
+) -> AppResult<TextActionResult> {
+    let action = TextAction::parse(&action)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown text action: {}", action)))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let paste = paste.unwrap_or(false);
+        let details = serde_json::json!({ "paste": paste });
+        let command = format!("run_text_action:{}", action.name());
+        audit::track(&command, None, details, || run_on_selection(action, text, paste))
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{MockTurn, MOCK_SCRIPT};
+    use crate::test_support;
+
+    fn script(replies: &[&str]) {
+        *MOCK_SCRIPT.lock() = replies
+            .iter()
+            .map(|text| MockTurn {
+                text: text.to_string(),
+                ..MockTurn::default()
+            })
+            .collect();
+    }
+
+    fn with_mock_provider() -> test_support::Isolated {
+        test_support::isolated(|settings| settings.ai.provider = "mock".to_string())
+    }
+
+    #[test]
+    fn templates_are_rendered_in_one_pass() {
+        let rendered = render_template(
+            "Into {{target_language}}, else {{fallback_language}}; {{unknown}} {{",
+            &[
+                ("target_language", "{{fallback_language}}"),
+                ("fallback_language", "English"),
+            ],
+        );
+        assert_eq!(rendered, "Into {{fallback_language}}, else English; {{unknown}} {{");
+    }
+
+    #[test]
+    fn summarizes_text_through_the_provider() {
+        let _isolated = with_mock_provider();
+        script(&["  - First point\n- Second point\n"]);
+
+        let result = run_on_selection(TextAction::Summarize, Some("A long text".to_string()), false).unwrap();
+        assert_eq!(result.output, "- First point\n- Second point");
+        assert_eq!(result.source_text, "A long text");
+        assert_eq!(result.source_language, None);
+        assert!(!result.pasted);
+    }
+
+    #[test]
+    fn reads_the_translation_out_of_the_reply() {
+        let _isolated = with_mock_provider();
+        script(&[
+            "```json\n{\"sourceLanguage\": \"German\", \"targetLanguage\": \"English\", \"translation\": \"Good morning\"}\n```",
+        ]);
+
+        let result = run_on_selection(TextAction::Translate, Some("Guten Morgen".to_string()), false).unwrap();
+        assert_eq!(result.output, "Good morning");
+        assert_eq!(result.source_language.as_deref(), Some("German"));
+        assert_eq!(result.target_language.as_deref(), Some("English"));
+
+        // A reply that ignores the format is the translation as is
+        script(&["Bonjour"]);
+        let result = run_on_selection(TextAction::Translate, Some("Good morning".to_string()), false).unwrap();
+        assert_eq!(result.output, "Bonjour");
+        assert_eq!(result.source_language, None);
+    }
+
+    #[test]
+    fn masked_values_come_back_in_the_output() {
+        let _isolated = with_mock_provider();
+        // Nothing scripted, so the provider echoes the masked text
+        let text = "Write to jane.doe@example.com";
+        let result = run_on_selection(TextAction::Summarize, Some(text.to_string()), false).unwrap();
+        assert_eq!(result.output, text);
+    }
+
+    #[test]
+    fn refuses_empty_text_and_denied_capabilities() {
+        let _isolated = with_mock_provider();
+        script(&["unused"]);
+
+        let error = run_on_selection(TextAction::Summarize, None, false).unwrap_err();
+        assert!(matches!(error, AppError::PermissionDenied(_)), "{:?}", error);
+        let error = run_on_selection(TextAction::Translate, Some("Hallo".to_string()), true).unwrap_err();
+        assert!(matches!(error, AppError::PermissionDenied(_)), "{:?}", error);
+        assert_eq!(MOCK_SCRIPT.lock().len(), 1, "no provider was set up");
+
+        let error = run_on_selection(TextAction::Summarize, Some("  \n".to_string()), false).unwrap_err();
+        assert!(matches!(error, AppError::InvalidInput(_)), "{:?}", error);
+    }
+}
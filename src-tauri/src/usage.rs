@@ -0,0 +1,634 @@
+// src-tauri/src/usage.rs
+
+use chrono::{Datelike, Duration as ChronoDuration, Local, Months, NaiveDate, TimeZone};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::{AppError, AppResult};
+use crate::settings::{self, PrivacySettings};
+
+const HISTORY_FILE: &str = "focus_history.jsonl";
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionKind {
+    Focus,
+    Idle,
+}
+
+/// One closed entry of the focus timeline. Idle gaps keep the app that was
+/// frontmost when input stopped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusSession {
+    pub app: String,
+    pub window_title: Option<String>,
+    pub kind: SessionKind,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+impl FocusSession {
+    fn clipped_ms(&self, from_ms: i64, to_ms: i64) -> i64 {
+        (self.end_ms.min(to_ms) - self.start_ms.max(from_ms)).max(0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppUsage {
+    pub app: String,
+    pub total_secs: u64,
+    pub session_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    pub range_start_ms: i64,
+    pub range_end_ms: i64,
+    pub apps: Vec<AppUsage>,
+    pub idle_secs: u64,
+}
+
+struct OpenSession {
+    app: String,
+    window_title: Option<String>,
+    kind: SessionKind,
+    start_ms: i64,
+}
+
+impl OpenSession {
+    fn close(&self, end_ms: i64) -> FocusSession {
+        FocusSession {
+            app: self.app.clone(),
+            window_title: self.window_title.clone(),
+            kind: self.kind,
+            start_ms: self.start_ms,
+            end_ms,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tracker {
+    history_path: Option<PathBuf>,
+    current: Option<OpenSession>,
+    // Last frontmost app, kept so a session can resume after an idle gap.
+    last_app: Option<(String, Option<String>)>,
+}
+
+impl Tracker {
+    fn close_current(&mut self, end_ms: i64) {
+        if let Some(open) = self.current.take() {
+            if end_ms > open.start_ms {
+                self.append(&open.close(end_ms));
+            }
+        }
+    }
+
+    fn open_focus(&mut self, privacy: &PrivacySettings, start_ms: i64) {
+        let Some((app, window_title)) = self.last_app.clone() else {
+            return;
+        };
+        if !privacy.usage_tracking || privacy.is_excluded(&app) {
+            return;
+        }
+        self.current = Some(OpenSession {
+            app,
+            window_title,
+            kind: SessionKind::Focus,
+            start_ms,
+        });
+    }
+
+    fn append(&self, session: &FocusSession) {
+        let Some(path) = self.history_path.as_ref() else {
+            return;
+        };
+        let line = match serde_json::to_string(session) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Failed to serialize focus session: {}", e);
+                return;
+            }
+        };
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", line));
+        if let Err(e) = result {
+            tracing::warn!("Failed to write focus history: {}", e);
+        }
+    }
+}
+
+static TRACKER: Lazy<Mutex<Tracker>> = Lazy::new(|| Mutex::new(Tracker::default()));
+
+fn now_ms() -> i64 {
+    Local::now().timestamp_millis()
+}
+
+/// Point the tracker at its history file inside the app data directory.
+pub fn init(data_dir: &Path) {
+    if let Err(e) = fs::create_dir_all(data_dir) {
+        tracing::warn!("Failed to create {}: {}", data_dir.display(), e);
+    }
+    TRACKER.lock().history_path = Some(data_dir.join(HISTORY_FILE));
+}
+
+/// Record that `app_name` became frontmost. Repeated reports for the same app
+/// (the Rust observer and the Python detector both fire) only fill in the
+/// window title.
+pub fn record_focus(app_name: &str, window_title: Option<String>) {
+    let privacy = settings::current().privacy;
+    let mut tracker = TRACKER.lock();
+    let now = now_ms();
+
+    if let Some(open) = tracker.current.as_mut() {
+        if open.kind == SessionKind::Focus && open.app == app_name {
+            match (&open.window_title, window_title) {
+                (_, None) => return,
+                (None, Some(title)) => {
+                    open.window_title = Some(title);
+                    return;
+                }
+                (Some(current), Some(title)) if *current == title => return,
+                (Some(_), Some(title)) => {
+                    tracker.last_app = Some((app_name.to_string(), Some(title)));
+                    tracker.close_current(now);
+                    tracker.open_focus(&privacy, now);
+                    return;
+                }
+            }
+        }
+    }
+
+    tracker.last_app = Some((app_name.to_string(), window_title));
+    tracker.close_current(now);
+    tracker.open_focus(&privacy, now);
+}
+
+/// Close or reopen the running session after the privacy settings changed.
+pub fn apply_settings(privacy: &PrivacySettings) {
+    let mut tracker = TRACKER.lock();
+    let now = now_ms();
+    let allowed = tracker
+        .current
+        .as_ref()
+        .map(|open| privacy.usage_tracking && !privacy.is_excluded(&open.app));
+
+    match allowed {
+        Some(false) => tracker.close_current(now),
+        None => tracker.open_focus(privacy, now),
+        Some(true) => {}
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn system_idle_seconds() -> Option<f64> {
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(source_state: i32, event_type: u32) -> f64;
+    }
+    // kCGEventSourceStateCombinedSessionState, kCGAnyInputEventType
+    Some(unsafe { CGEventSourceSecondsSinceLastEventType(0, u32::MAX) })
+}
+
+/// The X server's idle time when `xprintidle` is installed, otherwise how
+/// long logind has had the session marked idle. The desktop only sets that
+/// after its own idle delay, so it runs behind by that much.
+#[cfg(target_os = "linux")]
+pub fn system_idle_seconds() -> Option<f64> {
+    if std::env::var_os("DISPLAY").is_some() {
+        let output = std::process::Command::new("xprintidle").output().ok();
+        if let Some(output) = output.filter(|output| output.status.success()) {
+            if let Ok(ms) = String::from_utf8_lossy(&output.stdout).trim().parse::<u64>() {
+                return Some(ms as f64 / 1000.0);
+            }
+        }
+    }
+    let properties = session_properties(&["IdleHint", "IdleSinceHint"])?;
+    let now_us = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_micros() as u64;
+    logind_idle_seconds(&properties, now_us)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn system_idle_seconds() -> Option<f64> {
+    None
+}
+
+/// Properties of this logind session, as `loginctl show-session` prints them.
+#[cfg(target_os = "linux")]
+pub fn session_properties(names: &[&str]) -> Option<HashMap<String, String>> {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    let mut command = std::process::Command::new("loginctl");
+    command.args(["show-session", &session]);
+    for name in names {
+        command.args(["-p", name]);
+    }
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_session_properties(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(any(target_os = "linux", test))]
+fn parse_session_properties(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Seconds since logind marked the session idle; `IdleSinceHint` is in
+/// microseconds since the epoch.
+#[cfg(any(target_os = "linux", test))]
+fn logind_idle_seconds(properties: &HashMap<String, String>, now_us: u64) -> Option<f64> {
+    match properties.get("IdleHint")?.as_str() {
+        "no" => Some(0.0),
+        "yes" => {
+            let since_us = properties.get("IdleSinceHint")?.parse::<u64>().ok()?;
+            Some(now_us.saturating_sub(since_us) as f64 / 1_000_000.0)
+        }
+        _ => None,
+    }
+}
+
+fn update_idle(idle_secs: f64, threshold_secs: u64) {
+    let mut tracker = TRACKER.lock();
+    let now = now_ms();
+    let is_idle = idle_secs >= threshold_secs as f64;
+    let last_input_ms = now - (idle_secs * 1000.0) as i64;
+
+    let Some((kind, app, window_title)) = tracker
+        .current
+        .as_ref()
+        .map(|open| (open.kind, open.app.clone(), open.window_title.clone()))
+    else {
+        return;
+    };
+
+    match kind {
+        SessionKind::Focus if is_idle => {
+            tracker.close_current(last_input_ms);
+            tracker.current = Some(OpenSession {
+                app,
+                window_title,
+                kind: SessionKind::Idle,
+                start_ms: last_input_ms,
+            });
+        }
+        SessionKind::Idle if !is_idle => {
+            let privacy = settings::current().privacy;
+            tracker.close_current(last_input_ms);
+            tracker.open_focus(&privacy, last_input_ms);
+        }
+        _ => {}
+    }
+}
+
+/// Poll system idle time and split focus sessions into idle gaps.
+pub fn start_idle_monitor() {
+    if system_idle_seconds().is_none() {
+        tracing::info!("Idle detection not available on this platform");
+        return;
+    }
+
+    thread::spawn(|| loop {
+        thread::sleep(IDLE_POLL_INTERVAL);
+        if let Some(idle_secs) = system_idle_seconds() {
+            let threshold = settings::current().privacy.idle_threshold_secs;
+            update_idle(idle_secs, threshold);
+        }
+    });
+}
+
+/// All recorded sessions plus the running one, closed at the current time.
+fn load_sessions() -> Vec<FocusSession> {
+    let tracker = TRACKER.lock();
+    let mut sessions = Vec::new();
+
+    if let Some(path) = tracker.history_path.as_ref() {
+        if let Ok(file) = fs::File::open(path) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                match serde_json::from_str::<FocusSession>(&line) {
+                    Ok(session) => sessions.push(session),
+                    Err(e) => tracing::warn!("Skipping malformed focus history line: {}", e),
+                }
+            }
+        }
+    }
+
+    if let Some(open) = tracker.current.as_ref() {
+        sessions.push(open.close(now_ms()));
+    }
+    sessions
+}
+
+fn local_midnight_ms(date: NaiveDate) -> Result<i64, String> {
+    let midnight = date
+        .and_hms_opt(0, 0, 0)
+        .ok_or_else(|| format!("Invalid date: {}", date))?;
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|dt| dt.timestamp_millis())
+        .ok_or_else(|| format!("Invalid local date: {}", date))
+}
+
+/// Resolve "day", "week" (Monday-based) or "month" around `date`
+/// (YYYY-MM-DD, default today) into a local-time millisecond range.
+pub(crate) fn resolve_range(range: &str, date: Option<&str>) -> Result<(i64, i64), String> {
+    let day = match date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date '{}': {}", date, e))?,
+        None => Local::now().date_naive(),
+    };
+
+    let (start, end) = match range {
+        "day" => (day, day + ChronoDuration::days(1)),
+        "week" => {
+            let offset = day.weekday().num_days_from_monday() as i64;
+            let start = day - ChronoDuration::days(offset);
+            (start, start + ChronoDuration::days(7))
+        }
+        "month" => {
+            let start = day.with_day(1).ok_or_else(|| format!("Invalid date: {}", day))?;
+            let end = start
+                .checked_add_months(Months::new(1))
+                .ok_or_else(|| format!("Invalid date: {}", day))?;
+            (start, end)
+        }
+        other => return Err(format!("Unknown range: {}", other)),
+    };
+
+    Ok((local_midnight_ms(start)?, local_midnight_ms(end)?))
+}
+
+fn summarize(sessions: &[FocusSession], from_ms: i64, to_ms: i64) -> UsageSummary {
+    let mut per_app: HashMap<&str, (i64, u32)> = HashMap::new();
+    let mut idle_ms = 0;
+
+    for session in sessions {
+        let clipped = session.clipped_ms(from_ms, to_ms);
+        if clipped == 0 {
+            continue;
+        }
+        match session.kind {
+            SessionKind::Focus => {
+                let entry = per_app.entry(session.app.as_str()).or_default();
+                entry.0 += clipped;
+                entry.1 += 1;
+            }
+            SessionKind::Idle => idle_ms += clipped,
+        }
+    }
+
+    let mut apps: Vec<AppUsage> = per_app
+        .into_iter()
+        .map(|(app, (total_ms, session_count))| AppUsage {
+            app: app.to_string(),
+            total_secs: (total_ms / 1000) as u64,
+            session_count,
+        })
+        .collect();
+    apps.sort_by(|a, b| b.total_secs.cmp(&a.total_secs).then_with(|| a.app.cmp(&b.app)));
+
+    UsageSummary {
+        range_start_ms: from_ms,
+        range_end_ms: to_ms,
+        apps,
+        idle_secs: (idle_ms / 1000) as u64,
+    }
+}
+
+fn format_timestamp(ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn sessions_to_csv(sessions: &[FocusSession]) -> String {
+    let mut csv = String::from("app,window_title,kind,start,end,duration_secs\n");
+    for session in sessions {
+        let kind = match session.kind {
+            SessionKind::Focus => "focus",
+            SessionKind::Idle => "idle",
+        };
+        csv.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            csv_field(&session.app),
+            csv_field(session.window_title.as_deref().unwrap_or("")),
+            kind,
+            format_timestamp(session.start_ms),
+            format_timestamp(session.end_ms),
+            (session.end_ms - session.start_ms) / 1000
+        ));
+    }
+    csv
+}
+
+/// Per-app focus time for a "day", "week" or "month" containing `date`.
+#[tauri::command]
+pub fn get_app_usage(range: String, date: Option<String>) -> AppResult<UsageSummary> {
+    let (from_ms, to_ms) = resolve_range(&range, date.as_deref()).map_err(AppError::InvalidInput)?;
+    Ok(summarize(&load_sessions(), from_ms, to_ms))
+}
+
+/// Export the timeline as "csv" or "json", optionally limited to a range.
+#[tauri::command]
+pub fn export_usage_timeline(
+    format: String,
+    range: Option<String>,
+    date: Option<String>,
+) -> AppResult<String> {
+    let mut sessions = load_sessions();
+    if let Some(range) = range {
+        let (from_ms, to_ms) = resolve_range(&range, date.as_deref()).map_err(AppError::InvalidInput)?;
+        sessions.retain(|session| session.clipped_ms(from_ms, to_ms) > 0);
+    }
+
+    match format.as_str() {
+        "csv" => Ok(sessions_to_csv(&sessions)),
+        "json" => serde_json::to_string_pretty(&sessions).map_err(|e| AppError::Failed(e.to_string())),
+        other => Err(AppError::InvalidInput(format!("Unknown export format: {}", other))),
+    }
+}
+
+#[tauri::command]
+pub fn clear_usage_history() -> AppResult<()> {
+    let mut tracker = TRACKER.lock();
+    if let Some(open) = tracker.current.as_mut() {
+        open.start_ms = now_ms();
+    }
+    match tracker.history_path.as_ref() {
+        Some(path) if path.exists() => Ok(fs::remove_file(path)?),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn midnight(date: &str) -> i64 {
+        local_midnight_ms(NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()).unwrap()
+    }
+
+    fn session(app: &str, kind: SessionKind, start_ms: i64, end_ms: i64) -> FocusSession {
+        FocusSession {
+            app: app.to_string(),
+            window_title: None,
+            kind,
+            start_ms,
+            end_ms,
+        }
+    }
+
+    #[test]
+    fn ranges_cover_the_day_week_or_month_of_the_date() {
+        let range = |range: &str, date: &str| resolve_range(range, Some(date)).unwrap();
+
+        assert_eq!(
+            range("day", "2024-03-06"),
+            (midnight("2024-03-06"), midnight("2024-03-07"))
+        );
+        // Weeks start on Monday, also when the date is one
+        assert_eq!(
+            range("week", "2024-03-06"),
+            (midnight("2024-03-04"), midnight("2024-03-11"))
+        );
+        assert_eq!(
+            range("week", "2024-03-04"),
+            (midnight("2024-03-04"), midnight("2024-03-11"))
+        );
+        assert_eq!(
+            range("week", "2024-03-10"),
+            (midnight("2024-03-04"), midnight("2024-03-11"))
+        );
+        assert_eq!(
+            range("month", "2024-02-29"),
+            (midnight("2024-02-01"), midnight("2024-03-01"))
+        );
+        assert_eq!(
+            range("month", "2023-12-31"),
+            (midnight("2023-12-01"), midnight("2024-01-01"))
+        );
+
+        let (from_ms, to_ms) = resolve_range("day", None).unwrap();
+        assert!((from_ms..to_ms).contains(&now_ms()));
+    }
+
+    #[test]
+    fn bad_ranges_and_dates_are_refused() {
+        assert!(resolve_range("year", Some("2024-03-06"))
+            .unwrap_err()
+            .contains("Unknown range"));
+        assert!(resolve_range("day", Some("2024-02-30"))
+            .unwrap_err()
+            .contains("Invalid date"));
+        assert!(resolve_range("day", Some("06.03.2024")).is_err());
+        assert!(matches!(
+            get_app_usage("fortnight".to_string(), None),
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn focus_time_is_summed_per_app_within_the_range() {
+        let (from_ms, to_ms) = (midnight("2024-03-06"), midnight("2024-03-07"));
+        let minutes = |minutes: i64| minutes * 60_000;
+        let sessions = [
+            // Started the evening before; only today's part counts
+            session("Mail", SessionKind::Focus, from_ms - minutes(30), from_ms + minutes(10)),
+            session("Code", SessionKind::Focus, from_ms + minutes(10), from_ms + minutes(70)),
+            session("Code", SessionKind::Idle, from_ms + minutes(70), from_ms + minutes(85)),
+            session(
+                "Mail",
+                SessionKind::Focus,
+                from_ms + minutes(85),
+                from_ms + minutes(135),
+            ),
+            session(
+                "Notes",
+                SessionKind::Focus,
+                from_ms + minutes(135),
+                from_ms + minutes(140),
+            ),
+            // Runs into tomorrow
+            session("Slack", SessionKind::Focus, to_ms - minutes(5), to_ms + minutes(60)),
+            session("Calendar", SessionKind::Focus, to_ms, to_ms + minutes(5)),
+        ];
+        let summary = summarize(&sessions, from_ms, to_ms);
+
+        let apps: Vec<(&str, u64, u32)> = summary
+            .apps
+            .iter()
+            .map(|usage| (usage.app.as_str(), usage.total_secs, usage.session_count))
+            .collect();
+        assert_eq!(
+            apps,
+            [
+                ("Code", 3600, 1),
+                ("Mail", 3600, 2),
+                ("Notes", 300, 1),
+                ("Slack", 300, 1)
+            ]
+        );
+        assert_eq!(summary.idle_secs, 900);
+        assert_eq!((summary.range_start_ms, summary.range_end_ms), (from_ms, to_ms));
+    }
+
+    #[test]
+    fn csv_fields_with_separators_are_quoted() {
+        let mut focus = session("Mail", SessionKind::Focus, 0, 90_000);
+        focus.window_title = Some("Re: \"Budget\", Q3".to_string());
+        let csv = sessions_to_csv(&[focus, session("Code", SessionKind::Idle, 90_000, 120_000)]);
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "app,window_title,kind,start,end,duration_secs");
+        assert!(
+            lines[1].starts_with("Mail,\"Re: \"\"Budget\"\", Q3\",focus,"),
+            "{}",
+            lines[1]
+        );
+        assert!(lines[1].ends_with(",90"));
+        assert!(lines[2].starts_with("Code,,idle,"));
+        assert!(lines[2].ends_with(",30"));
+    }
+
+    #[test]
+    fn logind_idle_time_counts_from_when_the_session_went_idle() {
+        let idle = parse_session_properties("IdleHint=yes\nIdleSinceHint=1700000000000000\n");
+        assert_eq!(logind_idle_seconds(&idle, 1_700_000_090_000_000), Some(90.0));
+
+        let active = parse_session_properties("IdleHint=no\nIdleSinceHint=0\n");
+        assert_eq!(logind_idle_seconds(&active, 1_700_000_090_000_000), Some(0.0));
+        assert_eq!(logind_idle_seconds(&parse_session_properties(""), 0), None);
+    }
+}
@@ -0,0 +1,319 @@
+// src-tauri/src/vision.rs
+
+use base64::Engine;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::Emitter;
+
+use crate::audit;
+use crate::context;
+use crate::error::{AppError, AppResult};
+use crate::llm::{self, ChatMessage, CompletionRequest, Feature};
+use crate::permissions::{self, Capability};
+use crate::settings::{self, VisionSettings};
+
+/// Pinned captures kept in memory; the oldest goes first.
+const MAX_PINNED: usize = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureTarget {
+    #[default]
+    ActiveWindow,
+    /// A part of the screen the user drags out.
+    Region,
+}
+
+/// An image sent with a vision request. Only pinned captures outlive the
+/// request.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capture {
+    pub id: String,
+    pub captured_at_ms: i64,
+    /// Active app when it was captured.
+    pub app: Option<String>,
+    /// JPEG (or PNG, when it could not be compressed) data URL.
+    pub data_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VisionAnswer {
+    pub request_id: String,
+    pub text: String,
+    pub model: String,
+    /// Set when the capture was pinned or came from the pinned ones.
+    pub capture_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VisionText<'a> {
+    request_id: &'a str,
+    text: &'a str,
+}
+
+static PINNED: Lazy<Mutex<Vec<Capture>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn next_id(prefix: &str) -> String {
+    format!("{}-{}", prefix, NEXT_ID.fetch_add(1, Ordering::SeqCst))
+}
+
+/// Private directory for the image files of one capture, so other users
+/// cannot read them or plant links at their paths. Removed with everything
+/// in it when the capture is done with them.
+struct TempFiles(tempfile::TempDir);
+
+impl TempFiles {
+    fn new() -> AppResult<Self> {
+        Ok(Self(tempfile::Builder::new().prefix("leo-vision-").tempdir()?))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.path().join(name)
+    }
+}
+
+fn capture_command(target: CaptureTarget, app_name: &str, path: &std::path::Path) -> Option<Command> {
+    match target {
+        CaptureTarget::ActiveWindow => context::screenshot_command(context::front_window(app_name).as_ref(), path),
+        CaptureTarget::Region if cfg!(target_os = "macos") => {
+            let mut command = Command::new("screencapture");
+            command.args(["-x", "-i", "-s"]).arg(path);
+            Some(command)
+        }
+        // Without `-window`, import lets the user drag out a rectangle
+        CaptureTarget::Region if which::which("import").is_ok() => {
+            let mut command = Command::new("import");
+            command.arg(path);
+            Some(command)
+        }
+        CaptureTarget::Region => None,
+    }
+}
+
+/// Scale the capture down to `max_dimension` and turn it into a JPEG.
+fn compress_command(png: &std::path::Path, jpeg: &std::path::Path, vision: &VisionSettings) -> Option<Command> {
+    let quality = vision.jpeg_quality.clamp(1, 100).to_string();
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("sips");
+        command
+            .args(["-Z", &vision.max_dimension.to_string()])
+            .args(["-s", "format", "jpeg", "-s", "formatOptions", &quality])
+            .arg(png)
+            .arg("--out")
+            .arg(jpeg);
+        Some(command)
+    } else if which::which("convert").is_ok() {
+        let mut command = Command::new("convert");
+        command
+            .arg(png)
+            // `>` only ever shrinks
+            .args(["-resize", &format!("{0}x{0}>", vision.max_dimension)])
+            .args(["-quality", &quality])
+            .arg(jpeg);
+        Some(command)
+    } else {
+        None
+    }
+}
+
+fn run_quietly(command: &mut Command) -> bool {
+    command
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Capture `target`, unless the privacy settings keep the apps on screen
+/// out of captures.
+pub fn capture(target: CaptureTarget) -> AppResult<Capture> {
+    let app = context::current_active_app().map(|app| app.name);
+    let details = serde_json::json!({ "target": target });
+    audit::track("vision_capture", app.as_deref(), details, || {
+        capture_app(target, app.clone())
+    })
+}
+
+fn capture_app(target: CaptureTarget, app: Option<String>) -> AppResult<Capture> {
+    let app_name = app.clone().unwrap_or_default();
+    if !context::screenshot_allowed(&app_name) {
+        return Err(AppError::PermissionDenied(format!("Screenshots of {} are turned off", app_name)));
+    }
+    // A region can show any window, not just the active app's
+    if target == CaptureTarget::Region {
+        if let Some(blocked) = context::visible_windows()
+            .into_iter()
+            .filter_map(|window| window.app)
+            .find(|app| !context::screenshot_allowed(app))
+        {
+            return Err(AppError::PermissionDenied(format!(
+                "Screenshots of {} are turned off; hide its windows first",
+                blocked
+            )));
+        }
+    }
+    permissions::require(Capability::CaptureScreen, app.as_deref())?;
+
+    let vision = settings::current().vision;
+    let files = TempFiles::new()?;
+    let png = files.path("capture.png");
+    let mut command = capture_command(target, &app_name, &png)
+        .ok_or_else(|| AppError::BackendUnavailable("No screenshot tool available".to_string()))?;
+    if !run_quietly(&mut command) {
+        return Err(AppError::Failed("Screenshot failed".to_string()));
+    }
+    // Interactive captures leave no file when the user presses Escape
+    if !png.exists() {
+        return Err(AppError::Failed("Capture was cancelled".to_string()));
+    }
+
+    let jpeg = files.path("capture.jpg");
+    let compressed = compress_command(&png, &jpeg, &vision).is_some_and(|mut command| run_quietly(&mut command));
+    let (path, media_type) = if compressed {
+        (jpeg, "image/jpeg")
+    } else {
+        tracing::warn!("Could not compress the capture; sending it as PNG");
+        (png, "image/png")
+    };
+    let bytes = fs::read(&path)?;
+    tracing::debug!(bytes = bytes.len(), media_type, "Captured screen for a vision request");
+
+    Ok(Capture {
+        id: next_id("capture"),
+        captured_at_ms: chrono::Local::now().timestamp_millis(),
+        app,
+        data_url: format!(
+            "data:{};base64,{}",
+            media_type,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ),
+    })
+}
+
+fn pin(capture: Capture) {
+    let mut pinned = PINNED.lock();
+    if pinned.len() >= MAX_PINNED {
+        pinned.remove(0);
+    }
+    pinned.push(capture);
+}
+
+fn pinned(id: &str) -> AppResult<Capture> {
+    PINNED
+        .lock()
+        .iter()
+        .find(|capture| capture.id == id)
+        .cloned()
+        .ok_or_else(|| AppError::NotFound(format!("No pinned capture {}", id)))
+}
+
+/// Ask the vision model about a fresh capture of `target`, or about the
+/// pinned capture `capture_id`. The answer is passed to `on_text` as it
+/// arrives.
+pub fn ask(
+    request_id: String,
+    question: &str,
+    target: CaptureTarget,
+    capture_id: Option<&str>,
+    keep: bool,
+    on_text: &mut dyn FnMut(&str),
+) -> AppResult<VisionAnswer> {
+    crate::lock::ensure_unlocked()?;
+    if question.trim().is_empty() {
+        return Err(AppError::InvalidInput("Nothing to ask".to_string()));
+    }
+    let (capture, was_pinned) = match capture_id {
+        Some(id) => (pinned(id)?, true),
+        None => (capture(target)?, false),
+    };
+
+    let app_settings = settings::current();
+    let mut ai = app_settings.ai;
+    ai.model = app_settings.vision.model;
+    let provider = llm::provider_from_settings(&ai)?;
+    let request = CompletionRequest::new(vec![ChatMessage::user(question).with_image(capture.data_url.clone())])
+        .for_feature(Feature::Vision);
+    let completion = provider.stream(&request, on_text)?;
+
+    let capture_id = if was_pinned {
+        Some(capture.id)
+    } else if keep {
+        let id = capture.id.clone();
+        pin(capture);
+        Some(id)
+    } else {
+        None
+    };
+    Ok(VisionAnswer {
+        request_id,
+        text: completion.text,
+        model: completion.model,
+        capture_id,
+    })
+}
+
+/// Request id for callers that did not bring one.
+pub fn new_request_id() -> String {
+    next_id("vision")
+}
+
+fn emit_text(request_id: &str, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(app_handle) = crate::APP_HANDLE.read().as_ref() {
+        if let Err(e) = app_handle.emit("vision_text", VisionText { request_id, text }) {
+            tracing::warn!("Failed to emit vision_text: {}", e);
+        }
+    }
+}
+
+/// Ask about the screen. The answer streams in as `vision_text` events for
+/// `request_id` and is returned whole at the end. `pin` keeps the capture
+/// for later questions; otherwise it is dropped with the request.
+#[tauri::command]
+pub async fn vision_ask(
+    question: String,
+    target: Option<CaptureTarget>,
+    capture_id: Option<String>,
+    pin: Option<bool>,
+    request_id: Option<String>,
+) -> AppResult<VisionAnswer> {
+    let request_id = request_id.unwrap_or_else(new_request_id);
+    tauri::async_runtime::spawn_blocking(move || {
+        let events_id = request_id.clone();
+        ask(
+            request_id,
+            &question,
+            target.unwrap_or_default(),
+            capture_id.as_deref(),
+            pin.unwrap_or(false),
+            &mut |text| emit_text(&events_id, text),
+        )
+    })
+    .await?
+}
+
+#[tauri::command]
+pub fn list_pinned_captures() -> Vec<Capture> {
+    PINNED.lock().clone()
+}
+
+#[tauri::command]
+pub fn unpin_capture(id: String) -> AppResult<()> {
+    let mut pinned = PINNED.lock();
+    let before = pinned.len();
+    pinned.retain(|capture| capture.id != id);
+    if pinned.len() == before {
+        return Err(AppError::NotFound(format!("No pinned capture {}", id)));
+    }
+    Ok(())
+}
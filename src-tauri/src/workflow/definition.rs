@@ -0,0 +1,463 @@
+// src-tauri/src/workflow/definition.rs
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A user-defined automation, loaded from a TOML or JSON file in the
+/// workflows directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workflow {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Global shortcut such as "CmdOrCtrl+Shift+1".
+    #[serde(default)]
+    pub hotkey: Option<String>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// Upper bound for the whole run.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(flatten)]
+    pub action: Action,
+    /// Store the step output in this variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save_as: Option<String>,
+    /// Bounds `wait_for_window` and `run_shell`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+fn default_retry_delay_ms() -> u64 {
+    500
+}
+
+impl Step {
+    pub fn new(action: Action) -> Self {
+        Self {
+            label: None,
+            action,
+            save_as: None,
+            timeout_ms: None,
+            retries: 0,
+            retry_delay_ms: default_retry_delay_ms(),
+            continue_on_error: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    OpenApp {
+        app: String,
+    },
+    WaitForWindow {
+        app: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    TypeText {
+        text: String,
+    },
+    PressKeys {
+        keys: String,
+    },
+    Click {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        button: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        x: Option<i32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        y: Option<i32>,
+        /// Treat x and y as offsets from this app's front window.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        window: Option<String>,
+    },
+    /// Variables in `command` reach the shell as quoted environment
+    /// variables rather than text, so `echo {{name}}` prints the value as
+    /// is and quoting them again is not needed.
+    RunShell {
+        command: String,
+    },
+    CallLlm {
+        prompt: String,
+        #[serde(default)]
+        system: Option<String>,
+    },
+    SetVar {
+        name: String,
+        value: String,
+    },
+    Sleep {
+        ms: u64,
+    },
+    Branch {
+        #[serde(rename = "if")]
+        condition: Condition,
+        #[serde(default)]
+        then: Vec<Step>,
+        #[serde(default, rename = "else")]
+        otherwise: Vec<Step>,
+    },
+}
+
+impl Action {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Action::OpenApp { .. } => "open_app",
+            Action::WaitForWindow { .. } => "wait_for_window",
+            Action::TypeText { .. } => "type_text",
+            Action::PressKeys { .. } => "press_keys",
+            Action::Click { .. } => "click",
+            Action::RunShell { .. } => "run_shell",
+            Action::CallLlm { .. } => "call_llm",
+            Action::SetVar { .. } => "set_var",
+            Action::Sleep { .. } => "sleep",
+            Action::Branch { .. } => "branch",
+        }
+    }
+
+    /// Template strings this action interpolates.
+    pub fn templates(&self) -> Vec<&str> {
+        match self {
+            Action::OpenApp { app } => vec![app],
+            Action::WaitForWindow { app, title } => {
+                let mut templates = vec![app.as_str()];
+                templates.extend(title.as_deref());
+                templates
+            }
+            Action::TypeText { text } => vec![text],
+            Action::PressKeys { keys } => vec![keys],
+            Action::Click { button, window, .. } => {
+                button.as_deref().into_iter().chain(window.as_deref()).collect()
+            }
+            Action::RunShell { command } => vec![command],
+            Action::CallLlm { prompt, system } => {
+                let mut templates = vec![prompt.as_str()];
+                templates.extend(system.as_deref());
+                templates
+            }
+            Action::SetVar { value, .. } => vec![value],
+            Action::Sleep { .. } | Action::Branch { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Checks on a variable; every field that is set must hold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Condition {
+    pub var: String,
+    #[serde(default)]
+    pub equals: Option<String>,
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default)]
+    pub not_empty: Option<bool>,
+}
+
+impl Condition {
+    pub fn evaluate(&self, variables: &HashMap<String, String>) -> bool {
+        let value = variables.get(&self.var).map(|v| v.trim()).unwrap_or("");
+        if self.equals.is_none() && self.contains.is_none() && self.not_empty.is_none() {
+            return !value.is_empty();
+        }
+
+        self.equals.as_deref().is_none_or(|expected| value == expected)
+            && self.contains.as_deref().is_none_or(|needle| value.contains(needle))
+            && self.not_empty.is_none_or(|not_empty| not_empty != value.is_empty())
+    }
+}
+
+/// Variables always available to templates.
+pub const BUILTIN_VARIABLES: &[&str] = &["last_output"];
+
+/// Names referenced as `{{name}}` in a template.
+pub fn template_variables(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                names.push(after[..end].trim());
+                rest = &after[end + 2..];
+            }
+            None => break,
+        }
+    }
+    names
+}
+
+/// Substitute `{{name}}` placeholders, failing on unknown variables.
+pub fn interpolate(template: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        let name = after[..end].trim();
+        let value = variables
+            .get(name)
+            .ok_or_else(|| format!("Unknown variable: {}", name))?;
+        output.push_str(&rest[..start]);
+        output.push_str(value);
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn validate_steps(steps: &[Step], path: &str, defined: &mut HashSet<String>, problems: &mut Vec<String>) {
+    for (index, step) in steps.iter().enumerate() {
+        let step_path = format!("{}{}", path, index + 1);
+
+        for template in step.action.templates() {
+            for name in template_variables(template) {
+                if !defined.contains(name) && !BUILTIN_VARIABLES.contains(&name) {
+                    problems.push(format!("Step {}: unknown variable '{}'", step_path, name));
+                }
+            }
+        }
+
+        match &step.action {
+            Action::Click { button: None, x, y, .. } if x.is_none() || y.is_none() => {
+                problems.push(format!("Step {}: click needs a button or x and y", step_path));
+            }
+            Action::PressKeys { keys } if keys.trim().is_empty() => {
+                problems.push(format!("Step {}: press_keys needs keys", step_path));
+            }
+            Action::SetVar { name, .. } => {
+                defined.insert(name.clone());
+            }
+            Action::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                if condition.var.is_empty() {
+                    problems.push(format!("Step {}: branch condition needs a var", step_path));
+                }
+                // Either side may run, so only variables set before the branch
+                // are guaranteed afterwards
+                let mut then_defined = defined.clone();
+                validate_steps(then, &format!("{}.then.", step_path), &mut then_defined, problems);
+                let mut else_defined = defined.clone();
+                validate_steps(otherwise, &format!("{}.else.", step_path), &mut else_defined, problems);
+                defined.extend(then_defined.intersection(&else_defined).cloned());
+            }
+            _ => {}
+        }
+
+        if let Some(name) = step.save_as.as_ref() {
+            defined.insert(name.clone());
+        }
+    }
+}
+
+/// Static checks run before a workflow is listed or executed.
+pub fn validate(workflow: &Workflow) -> Vec<String> {
+    let mut problems = Vec::new();
+    if workflow.name.trim().is_empty() {
+        problems.push("Workflow needs a name".to_string());
+    }
+    if workflow.steps.is_empty() {
+        problems.push("Workflow has no steps".to_string());
+    }
+    let mut defined: HashSet<String> = workflow.variables.keys().cloned().collect();
+    validate_steps(&workflow.steps, "", &mut defined, &mut problems);
+    problems
+}
+
+/// Parse a workflow from TOML or JSON source.
+pub fn parse(source: &str, format: &str) -> Result<Workflow, String> {
+    match format {
+        "toml" => toml::from_str(source).map_err(|e| e.to_string()),
+        "json" => serde_json::from_str(source).map_err(|e| e.to_string()),
+        other => Err(format!("Unknown workflow format: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow(source: &str) -> Workflow {
+        parse(source, "toml").unwrap()
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn valid_workflows_have_no_problems() {
+        let workflow = workflow(
+            r#"
+            name = "Standup"
+            variables = { app = "Slack" }
+
+            [[steps]]
+            action = "open_app"
+            app = "{{app}}"
+
+            [[steps]]
+            action = "run_shell"
+            command = "date"
+            save_as = "today"
+
+            [[steps]]
+            action = "branch"
+            if = { var = "today", contains = "Mon" }
+            then = [{ action = "set_var", name = "topic", value = "weekend" }]
+            else = [{ action = "set_var", name = "topic", value = "{{ today }}" }]
+
+            [[steps]]
+            action = "type_text"
+            text = "{{topic}} {{last_output}}"
+            "#,
+        );
+        assert_eq!(validate(&workflow), Vec::<String>::new());
+    }
+
+    #[test]
+    fn problems_name_their_step() {
+        let workflow = workflow(
+            r#"
+            name = " "
+
+            [[steps]]
+            action = "click"
+            x = 10
+
+            [[steps]]
+            action = "press_keys"
+            keys = " "
+
+            [[steps]]
+            action = "branch"
+            if = { var = "" }
+            then = [{ action = "set_var", name = "only_then", value = "{{missing}}" }]
+
+            [[steps]]
+            action = "type_text"
+            text = "{{only_then}}"
+            "#,
+        );
+        assert_eq!(
+            validate(&workflow),
+            [
+                "Workflow needs a name",
+                "Step 1: click needs a button or x and y",
+                "Step 2: press_keys needs keys",
+                "Step 3: branch condition needs a var",
+                "Step 3.then.1: unknown variable 'missing'",
+                "Step 4: unknown variable 'only_then'",
+            ]
+        );
+
+        let empty = Workflow {
+            name: "Empty".to_string(),
+            description: String::new(),
+            hotkey: None,
+            variables: HashMap::new(),
+            timeout_ms: None,
+            steps: Vec::new(),
+        };
+        assert_eq!(validate(&empty), ["Workflow has no steps"]);
+    }
+
+    #[test]
+    fn interpolation_fills_in_known_variables() {
+        let variables = vars(&[("name", "Ada"), ("app", "{{name}}")]);
+        assert_eq!(
+            interpolate("Hi {{name}}, {{ app }}!", &variables).unwrap(),
+            "Hi Ada, {{name}}!"
+        );
+        assert_eq!(interpolate("no placeholders", &variables).unwrap(), "no placeholders");
+        assert_eq!(interpolate("open {{ ends", &variables).unwrap(), "open {{ ends");
+        assert_eq!(
+            interpolate("{{nope}}", &variables).unwrap_err(),
+            "Unknown variable: nope"
+        );
+        assert_eq!(template_variables("{{a}} and {{ b }} then {{c"), ["a", "b"]);
+    }
+
+    #[test]
+    fn conditions_check_every_field_that_is_set() {
+        let variables = vars(&[("status", "  Build passed \n"), ("empty", " ")]);
+        let condition = |var: &str| Condition {
+            var: var.to_string(),
+            ..Condition::default()
+        };
+
+        assert!(condition("status").evaluate(&variables));
+        assert!(!condition("empty").evaluate(&variables));
+        assert!(!condition("missing").evaluate(&variables));
+
+        let equals = |expected: &str| Condition {
+            equals: Some(expected.to_string()),
+            ..condition("status")
+        };
+        assert!(equals("Build passed").evaluate(&variables));
+        assert!(!equals("build passed").evaluate(&variables));
+
+        let contains = Condition {
+            contains: Some("passed".to_string()),
+            ..condition("status")
+        };
+        assert!(contains.evaluate(&variables));
+        assert!(!Condition {
+            equals: Some("Build".to_string()),
+            ..contains.clone()
+        }
+        .evaluate(&variables));
+
+        let empty = Condition {
+            not_empty: Some(false),
+            ..condition("empty")
+        };
+        assert!(empty.evaluate(&variables));
+        assert!(Condition {
+            not_empty: Some(false),
+            ..condition("missing")
+        }
+        .evaluate(&variables));
+        assert!(!Condition {
+            not_empty: Some(false),
+            ..condition("status")
+        }
+        .evaluate(&variables));
+    }
+
+    #[test]
+    fn workflows_parse_from_toml_and_json() {
+        let from_json = parse(
+            r#"{"name": "Greet", "steps": [{"action": "type_text", "text": "Hi", "retries": 2}]}"#,
+            "json",
+        )
+        .unwrap();
+        assert_eq!(from_json.steps[0].retries, 2);
+        assert_eq!(from_json.steps[0].retry_delay_ms, 500);
+        assert!(parse("name = \"Greet\"", "toml").is_err());
+        assert_eq!(parse("", "yaml").unwrap_err(), "Unknown workflow format: yaml");
+    }
+}
@@ -0,0 +1,753 @@
+// src-tauri/src/workflow/engine.rs
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::definition::{self, Action, Step, Workflow};
+use crate::automation::AutomationBackend;
+use crate::error::{AppError, AppResult};
+use crate::llm::{self, ChatMessage, CompletionRequest, Feature, LlmProvider};
+
+const DEFAULT_WAIT_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_SHELL_TIMEOUT_MS: u64 = 30_000;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Ok,
+    Failed,
+    DryRun,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepReport {
+    /// Position in the workflow, e.g. "3" or "3.then.1".
+    pub path: String,
+    pub label: Option<String>,
+    pub action: String,
+    pub status: StepStatus,
+    pub attempts: u32,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunReport {
+    pub workflow: String,
+    pub dry_run: bool,
+    pub success: bool,
+    pub error: Option<String>,
+    pub steps: Vec<StepReport>,
+    pub variables: HashMap<String, String>,
+}
+
+/// Executes a workflow against an automation backend. In dry-run mode no
+/// backend, shell or LLM call is made; each step reports what it would do.
+pub struct Engine {
+    backend: Arc<dyn AutomationBackend>,
+    dry_run: bool,
+    variables: HashMap<String, String>,
+    deadline: Option<Instant>,
+    reports: Vec<StepReport>,
+    provider: Option<Box<dyn LlmProvider>>,
+}
+
+impl Engine {
+    pub fn new(backend: Arc<dyn AutomationBackend>, dry_run: bool) -> Self {
+        Self {
+            backend,
+            dry_run,
+            variables: HashMap::new(),
+            deadline: None,
+            reports: Vec::new(),
+            provider: None,
+        }
+    }
+
+    pub fn run(mut self, workflow: &Workflow, overrides: HashMap<String, String>) -> RunReport {
+        let problems = definition::validate(workflow);
+        let result = if problems.is_empty() {
+            self.variables = workflow.variables.clone();
+            self.variables.extend(overrides);
+            self.variables.insert("last_output".to_string(), String::new());
+            self.deadline = workflow
+                .timeout_ms
+                .map(|ms| Instant::now() + Duration::from_millis(ms));
+            self.run_steps(&workflow.steps, "")
+        } else {
+            Err(problems.join("; "))
+        };
+
+        RunReport {
+            workflow: workflow.name.clone(),
+            dry_run: self.dry_run,
+            success: result.is_ok(),
+            error: result.err(),
+            steps: self.reports,
+            variables: self.variables,
+        }
+    }
+
+    fn run_steps(&mut self, steps: &[Step], prefix: &str) -> Result<(), String> {
+        for (index, step) in steps.iter().enumerate() {
+            let path = format!("{}{}", prefix, index + 1);
+            if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(format!("Workflow timed out before step {}", path));
+            }
+
+            if let Action::Branch {
+                condition,
+                then,
+                otherwise,
+            } = &step.action
+            {
+                let (branch, taken) = if condition.evaluate(&self.variables) {
+                    (then, "then")
+                } else {
+                    (otherwise, "else")
+                };
+                self.reports.push(StepReport {
+                    path: path.clone(),
+                    label: step.label.clone(),
+                    action: step.action.kind().to_string(),
+                    status: StepStatus::Ok,
+                    attempts: 1,
+                    output: Some(taken.to_string()),
+                    error: None,
+                    duration_ms: 0,
+                });
+                self.run_steps(branch, &format!("{}.{}.", path, taken))?;
+                continue;
+            }
+
+            let started = Instant::now();
+            let allowed_attempts = if self.dry_run { 1 } else { step.retries + 1 };
+            let mut attempts = 0;
+            let result = loop {
+                attempts += 1;
+                let result = self.execute(step);
+                if result.is_ok() || attempts >= allowed_attempts {
+                    break result;
+                }
+                thread::sleep(Duration::from_millis(step.retry_delay_ms));
+            };
+
+            let mut report = StepReport {
+                path: path.clone(),
+                label: step.label.clone(),
+                action: step.action.kind().to_string(),
+                status: StepStatus::Ok,
+                attempts,
+                output: None,
+                error: None,
+                duration_ms: started.elapsed().as_millis() as u64,
+            };
+
+            match result {
+                Ok(output) => {
+                    if self.dry_run {
+                        report.status = StepStatus::DryRun;
+                    }
+                    self.variables
+                        .insert("last_output".to_string(), output.clone());
+                    if let Some(name) = step.save_as.as_ref() {
+                        self.variables.insert(name.clone(), output.clone());
+                    }
+                    report.output = Some(output);
+                    self.reports.push(report);
+                }
+                Err(e) => {
+                    report.status = StepStatus::Failed;
+                    report.error = Some(e.to_string());
+                    self.reports.push(report);
+                    if !step.continue_on_error {
+                        return Err(format!(
+                            "Step {} ({}) failed: {}",
+                            path,
+                            step.action.kind(),
+                            e
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn interpolate(&self, template: &str) -> AppResult<String> {
+        definition::interpolate(template, &self.variables).map_err(AppError::InvalidInput)
+    }
+
+    fn describe(&self, step: &Step) -> AppResult<String> {
+        let details = step
+            .action
+            .templates()
+            .into_iter()
+            .map(|template| self.interpolate(template))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("would {} {}", step.action.kind(), details.join(" ")).trim().to_string())
+    }
+
+    fn remaining(&self, timeout_ms: u64) -> Duration {
+        let timeout = Duration::from_millis(timeout_ms);
+        match self.deadline {
+            Some(deadline) => timeout.min(deadline.saturating_duration_since(Instant::now())),
+            None => timeout,
+        }
+    }
+
+    fn execute(&mut self, step: &Step) -> AppResult<String> {
+        if let Action::SetVar { name, value } = &step.action {
+            let value = self.interpolate(value)?;
+            self.variables.insert(name.clone(), value.clone());
+            return Ok(value);
+        }
+        if self.dry_run {
+            return self.describe(step);
+        }
+
+        match &step.action {
+            Action::OpenApp { app } => {
+                self.backend.open_application(&self.interpolate(app)?)?;
+                Ok(String::new())
+            }
+            Action::WaitForWindow { app, title } => {
+                let app = self.interpolate(app)?;
+                let title = title.as_deref().map(|t| self.interpolate(t)).transpose()?;
+                let wait = self.remaining(step.timeout_ms.unwrap_or(DEFAULT_WAIT_TIMEOUT_MS));
+                let give_up = Instant::now() + wait;
+                loop {
+                    if self.backend.window_exists(&app, title.as_deref())? {
+                        return Ok(String::new());
+                    }
+                    if Instant::now() >= give_up {
+                        return Err(AppError::Failed(format!("Timed out waiting for a window of {}", app)));
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+            Action::TypeText { text } => {
+                self.backend.type_text(&self.interpolate(text)?)?;
+                Ok(String::new())
+            }
+            Action::PressKeys { keys } => {
+                self.backend.press_keys(&self.interpolate(keys)?)?;
+                Ok(String::new())
+            }
+            Action::Click { button, x, y, window } => {
+                match (button, x, y) {
+                    (Some(button), _, _) => self.backend.click_button(&self.interpolate(button)?)?,
+                    (None, Some(x), Some(y)) => {
+                        let (x, y) = match window {
+                            Some(app) => {
+                                let app = self.interpolate(app)?;
+                                let bounds = self
+                                    .backend
+                                    .window_bounds(&app)?
+                                    .ok_or_else(|| AppError::NotFound(format!("{} has no window to click in", app)))?;
+                                (bounds.x + x, bounds.y + y)
+                            }
+                            None => (*x, *y),
+                        };
+                        self.backend.click_at(x, y)?
+                    }
+                    _ => return Err(AppError::InvalidInput("click needs a button or x and y".to_string())),
+                }
+                Ok(String::new())
+            }
+            Action::RunShell { command } => {
+                let (command, env) = shell_command(command, &self.variables).map_err(AppError::InvalidInput)?;
+                let timeout = self.remaining(step.timeout_ms.unwrap_or(DEFAULT_SHELL_TIMEOUT_MS));
+                run_shell(&command, &env, timeout).map_err(AppError::Failed)
+            }
+            Action::CallLlm { prompt, system } => {
+                let mut messages = Vec::new();
+                if let Some(system) = system {
+                    messages.push(ChatMessage::system(self.interpolate(system)?));
+                }
+                messages.push(ChatMessage::user(self.interpolate(prompt)?));
+
+                if self.provider.is_none() {
+                    self.provider = Some(llm::configured_provider()?);
+                }
+                let provider = self.provider.as_ref().expect("provider set above");
+                let completion = provider.complete(&CompletionRequest::new(messages).for_feature(Feature::Workflow))?;
+                Ok(completion.text.trim().to_string())
+            }
+            Action::Sleep { ms } => {
+                thread::sleep(self.remaining(*ms));
+                Ok(String::new())
+            }
+            Action::SetVar { .. } | Action::Branch { .. } => {
+                unreachable!("handled before dispatch")
+            }
+        }
+    }
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_string(&mut output);
+        }
+        output
+    })
+}
+
+/// Turn a `run_shell` template into a command that reads its variables from
+/// the environment. Values come from callers, LLM answers and earlier
+/// commands, so they are never spliced into the command text where the
+/// shell would parse them. Each `{{name}}` becomes a quoted reference to an
+/// environment variable holding the value.
+fn shell_command(
+    template: &str,
+    variables: &HashMap<String, String>,
+) -> Result<(String, Vec<(String, String)>), String> {
+    let mut command = String::with_capacity(template.len());
+    let mut env: Vec<(String, String)> = Vec::new();
+    let mut names: Vec<&str> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        let name = after[..end].trim();
+        let value = variables
+            .get(name)
+            .ok_or_else(|| format!("Unknown variable: {}", name))?;
+        let index = match names.iter().position(|known| *known == name) {
+            Some(index) => index,
+            None => {
+                names.push(name);
+                env.push((format!("LEO_VAR_{}", names.len()), value.clone()));
+                names.len() - 1
+            }
+        };
+        command.push_str(&rest[..start]);
+        // cmd expands !var! after parsing the line when delayed expansion is on
+        if cfg!(target_os = "windows") {
+            command.push_str(&format!("!{}!", env[index].0));
+        } else {
+            command.push_str(&format!("\"${}\"", env[index].0));
+        }
+        rest = &after[end + 2..];
+    }
+    command.push_str(rest);
+    Ok((command, env))
+}
+
+/// Run `command` through the platform shell with `env` set, killing it
+/// after `timeout`.
+fn run_shell(command: &str, env: &[(String, String)], timeout: Duration) -> Result<String, String> {
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut shell = Command::new("cmd");
+        shell.args(["/V:ON", "/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    let mut child = shell
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run shell command: {}", e))?;
+
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    let give_up = Instant::now() + timeout;
+
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= give_up => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Shell command timed out after {}ms", timeout.as_millis()));
+            }
+            None => thread::sleep(Duration::from_millis(50)),
+        }
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if status.success() {
+        Ok(stdout.trim().to_string())
+    } else {
+        Err(format!("Exited with {}: {}", status, stderr.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automation::{AutomationCall, MockBackend, MOCK_WINDOW_BOUNDS};
+    use crate::llm::{MockTurn, MOCK_SCRIPT};
+    use crate::test_support;
+
+    fn workflow(source: &str) -> Workflow {
+        definition::parse(source, "toml").unwrap()
+    }
+
+    fn run(workflow: &Workflow, dry_run: bool) -> (RunReport, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::default());
+        let report = Engine::new(backend.clone(), dry_run).run(workflow, HashMap::new());
+        (report, backend)
+    }
+
+    fn statuses(report: &RunReport) -> Vec<(&str, StepStatus, u32)> {
+        report
+            .steps
+            .iter()
+            .map(|step| (step.path.as_str(), step.status, step.attempts))
+            .collect()
+    }
+
+    #[test]
+    fn steps_drive_the_backend_with_their_variables() {
+        let workflow = workflow(
+            r#"
+            name = "Reply"
+            variables = { app = "Mail", greeting = "Thanks!" }
+
+            [[steps]]
+            action = "open_app"
+            app = "{{app}}"
+
+            [[steps]]
+            action = "wait_for_window"
+            app = "{{app}}"
+
+            [[steps]]
+            action = "click"
+            x = 10
+            y = 20
+            window = "{{app}}"
+
+            [[steps]]
+            action = "type_text"
+            text = "{{greeting}}"
+
+            [[steps]]
+            action = "press_keys"
+            keys = "cmd+enter"
+            "#,
+        );
+        let backend = Arc::new(MockBackend::default());
+        let overrides = HashMap::from([("greeting".to_string(), "Will do.".to_string())]);
+        let report = Engine::new(backend.clone(), false).run(&workflow, overrides);
+
+        assert!(report.success, "{:?}", report.error);
+        assert!(report.steps.iter().all(|step| step.status == StepStatus::Ok));
+        assert_eq!(
+            backend.calls(),
+            [
+                AutomationCall::OpenApplication {
+                    app_name: "Mail".to_string()
+                },
+                AutomationCall::ClickAt {
+                    x: MOCK_WINDOW_BOUNDS.x + 10,
+                    y: MOCK_WINDOW_BOUNDS.y + 20
+                },
+                AutomationCall::TypeText {
+                    text: "Will do.".to_string()
+                },
+                AutomationCall::PressKeys {
+                    keys: "cmd+enter".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_steps_are_retried() {
+        let workflow = workflow(
+            r#"
+            name = "Retry"
+
+            [[steps]]
+            action = "press_keys"
+            keys = "cmd+n"
+            retries = 2
+            retry_delay_ms = 1
+
+            [[steps]]
+            action = "type_text"
+            text = "hello"
+            "#,
+        );
+        let backend = Arc::new(MockBackend::default());
+        backend.fail_next(2);
+        let report = Engine::new(backend.clone(), false).run(&workflow, HashMap::new());
+        assert!(report.success, "{:?}", report.error);
+        assert_eq!(statuses(&report), [("1", StepStatus::Ok, 3), ("2", StepStatus::Ok, 1)]);
+
+        // Out of retries, so the next step never runs
+        backend.fail_next(3);
+        let report = Engine::new(backend.clone(), false).run(&workflow, HashMap::new());
+        assert!(!report.success);
+        assert_eq!(statuses(&report), [("1", StepStatus::Failed, 3)]);
+        let error = report.error.unwrap();
+        assert!(
+            error.starts_with("Step 1 (press_keys) failed: Mock automation failed"),
+            "{}",
+            error
+        );
+        assert!(report.steps[0].error.is_some());
+    }
+
+    #[test]
+    fn failures_can_be_skipped() {
+        let mut workflow = workflow(
+            r#"
+            name = "Best effort"
+
+            [[steps]]
+            action = "click"
+            button = "Dismiss"
+
+            [[steps]]
+            action = "type_text"
+            text = "{{last_output}}done"
+            "#,
+        );
+        workflow.steps[0].continue_on_error = true;
+        let backend = Arc::new(MockBackend::default());
+        backend.fail_next(1);
+        let report = Engine::new(backend.clone(), false).run(&workflow, HashMap::new());
+
+        assert!(report.success, "{:?}", report.error);
+        assert_eq!(
+            statuses(&report),
+            [("1", StepStatus::Failed, 1), ("2", StepStatus::Ok, 1)]
+        );
+        assert_eq!(
+            backend.calls(),
+            [AutomationCall::TypeText {
+                text: "done".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn branches_run_one_side() {
+        let workflow = workflow(
+            r#"
+            name = "Branch"
+
+            [[steps]]
+            action = "set_var"
+            name = "mood"
+            value = "sunny"
+
+            [[steps]]
+            action = "branch"
+            if = { var = "mood", equals = "sunny" }
+            then = [{ action = "type_text", text = "outside" }]
+            else = [{ action = "type_text", text = "inside" }]
+
+            [[steps]]
+            action = "branch"
+            if = { var = "mood", contains = "rain" }
+            then = [{ action = "press_keys", keys = "cmd+u" }]
+            "#,
+        );
+        let (report, backend) = run(&workflow, false);
+
+        assert!(report.success, "{:?}", report.error);
+        let paths: Vec<_> = report.steps.iter().map(|step| step.path.as_str()).collect();
+        assert_eq!(paths, ["1", "2", "2.then.1", "3"]);
+        assert_eq!(report.steps[1].output.as_deref(), Some("then"));
+        assert_eq!(report.steps[3].output.as_deref(), Some("else"));
+        assert_eq!(
+            backend.calls(),
+            [AutomationCall::TypeText {
+                text: "outside".to_string()
+            }]
+        );
+        assert_eq!(report.variables["mood"], "sunny");
+    }
+
+    #[test]
+    fn dry_runs_describe_steps_without_running_them() {
+        let workflow = workflow(
+            r#"
+            name = "Dry"
+            variables = { app = "Notes" }
+
+            [[steps]]
+            action = "open_app"
+            app = "{{app}}"
+            retries = 3
+
+            [[steps]]
+            action = "set_var"
+            name = "note"
+            value = "for {{app}}"
+
+            [[steps]]
+            action = "call_llm"
+            prompt = "Write a {{note}}"
+
+            [[steps]]
+            action = "run_shell"
+            command = "rm -rf {{note}}"
+            "#,
+        );
+        let (report, backend) = run(&workflow, true);
+
+        assert!(report.success, "{:?}", report.error);
+        assert!(report.dry_run);
+        assert!(backend.calls().is_empty());
+        let outputs: Vec<_> = report
+            .steps
+            .iter()
+            .map(|step| step.output.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            outputs,
+            [
+                "would open_app Notes",
+                "for Notes",
+                "would call_llm Write a for Notes",
+                "would run_shell rm -rf for Notes"
+            ]
+        );
+        assert_eq!(
+            statuses(&report),
+            [
+                ("1", StepStatus::DryRun, 1),
+                ("2", StepStatus::DryRun, 1),
+                ("3", StepStatus::DryRun, 1),
+                ("4", StepStatus::DryRun, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn timeouts_bound_waits_and_the_whole_run() {
+        let mut waiting = workflow(
+            r#"
+            name = "Wait"
+
+            [[steps]]
+            action = "wait_for_window"
+            app = "Never Opened"
+            "#,
+        );
+        waiting.steps[0].timeout_ms = Some(10);
+        let (report, _) = run(&waiting, false);
+        assert_eq!(
+            report.steps[0].error.as_deref(),
+            Some("Timed out waiting for a window of Never Opened")
+        );
+
+        let mut slow = workflow(
+            r#"
+            name = "Slow"
+
+            [[steps]]
+            action = "sleep"
+            ms = 10000
+
+            [[steps]]
+            action = "type_text"
+            text = "late"
+            "#,
+        );
+        slow.timeout_ms = Some(50);
+        let started = Instant::now();
+        let (report, backend) = run(&slow, false);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(report.error.as_deref(), Some("Workflow timed out before step 2"));
+        assert!(backend.calls().is_empty());
+    }
+
+    #[test]
+    fn llm_answers_can_be_saved_and_typed() {
+        let _isolated = test_support::isolated(|settings| settings.ai.provider = "mock".to_string());
+        *MOCK_SCRIPT.lock() = vec![MockTurn {
+            text: "  Bonjour \n".to_string(),
+            ..MockTurn::default()
+        }];
+        let workflow = workflow(
+            r#"
+            name = "Translate"
+
+            [[steps]]
+            action = "call_llm"
+            prompt = "Say hello in French"
+            save_as = "hello"
+
+            [[steps]]
+            action = "type_text"
+            text = "{{hello}}, {{last_output}}"
+            "#,
+        );
+        let (report, backend) = run(&workflow, false);
+
+        assert!(report.success, "{:?}", report.error);
+        assert_eq!(
+            backend.calls(),
+            [AutomationCall::TypeText {
+                text: "Bonjour, Bonjour".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_workflows_do_not_run() {
+        let workflow = workflow(
+            r#"
+            name = "Broken"
+
+            [[steps]]
+            action = "type_text"
+            text = "{{undefined}}"
+            "#,
+        );
+        let (report, backend) = run(&workflow, false);
+
+        assert!(!report.success);
+        assert_eq!(report.error.as_deref(), Some("Step 1: unknown variable 'undefined'"));
+        assert!(report.steps.is_empty());
+        assert!(backend.calls().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_variables_are_not_parsed_by_the_shell() {
+        let hostile = "x; echo injected $(echo nested) `echo tick`";
+        let variables = HashMap::from([
+            ("name".to_string(), hostile.to_string()),
+            ("quote".to_string(), "it's \"quoted\"".to_string()),
+        ]);
+        let (command, env) = shell_command("printf '%s|%s|%s' {{name}} {{ quote }} {{name}}", &variables).unwrap();
+        assert!(!command.contains("injected"));
+        assert_eq!(env.len(), 2);
+
+        let output = run_shell(&command, &env, Duration::from_secs(5)).unwrap();
+        assert_eq!(output, format!("{}|it's \"quoted\"|{}", hostile, hostile));
+    }
+
+    #[test]
+    fn shell_commands_fail_on_unknown_variables() {
+        assert_eq!(
+            shell_command("echo {{missing}}", &HashMap::new()).unwrap_err(),
+            "Unknown variable: missing"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+// src-tauri/src/workflow/mod.rs
+
+mod definition;
+mod engine;
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tauri::Emitter;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::audit;
+use crate::automation;
+use crate::error::{AppError, AppResult};
+use crate::permissions::{self, Capability};
+pub use definition::{Action, Step, Workflow};
+pub(crate) use engine::Engine;
+pub use engine::RunReport;
+
+static WORKFLOWS_DIR: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowSummary {
+    pub file: String,
+    pub name: String,
+    pub description: String,
+    pub hotkey: Option<String>,
+    pub step_count: usize,
+    /// Validation or parse errors; workflows with problems will not run.
+    pub problems: Vec<String>,
+}
+
+/// Use `<config_dir>/workflows` for workflow files.
+pub fn init(config_dir: &Path) {
+    let dir = config_dir.join("workflows");
+    if let Err(e) = fs::create_dir_all(&dir) {
+        tracing::warn!("Failed to create {}: {}", dir.display(), e);
+    }
+    *WORKFLOWS_DIR.write() = Some(dir);
+}
+
+pub fn workflows_dir() -> AppResult<PathBuf> {
+    WORKFLOWS_DIR
+        .read()
+        .clone()
+        .ok_or_else(|| AppError::InvalidState("Workflows have not been initialized".to_string()))
+}
+
+fn format_of(path: &Path) -> Option<&'static str> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => Some("toml"),
+        Some("json") => Some("json"),
+        _ => None,
+    }
+}
+
+/// A workflow file and its parse result.
+type LoadedWorkflow = (PathBuf, Result<Workflow, String>);
+
+/// Every workflow file, sorted by file name.
+fn load_all() -> AppResult<Vec<LoadedWorkflow>> {
+    let dir = workflows_dir()?;
+    let mut entries: Vec<PathBuf> = fs::read_dir(&dir)
+        .map_err(|e| AppError::Io(format!("Failed to read {}: {}", dir.display(), e)))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| format_of(path).is_some())
+        .collect();
+    entries.sort();
+
+    Ok(entries
+        .into_iter()
+        .map(|path| {
+            let workflow = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| definition::parse(&source, format_of(&path).unwrap_or("toml")));
+            (path, workflow)
+        })
+        .collect())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Find a workflow by its `name` or file stem.
+pub fn find(name: &str) -> AppResult<Workflow> {
+    load_all()?
+        .into_iter()
+        .find(|(path, workflow)| {
+            file_stem(path) == name || workflow.as_ref().is_ok_and(|w| w.name == name)
+        })
+        .ok_or_else(|| AppError::NotFound(format!("No workflow named {}", name)))
+        .and_then(|(_, workflow)| workflow.map_err(AppError::InvalidInput))
+}
+
+/// File name for a workflow: its name lowercased with runs of other
+/// characters collapsed to dashes.
+fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Write a new workflow as JSON into the workflows directory.
+pub fn save(workflow: &Workflow) -> AppResult<PathBuf> {
+    let problems = definition::validate(workflow);
+    if !problems.is_empty() {
+        return Err(AppError::InvalidInput(problems.join("; ")));
+    }
+    if find(&workflow.name).is_ok() {
+        return Err(AppError::InvalidInput(format!("A workflow named {} already exists", workflow.name)));
+    }
+
+    let slug = slug(&workflow.name);
+    if slug.is_empty() {
+        return Err(AppError::InvalidInput(format!("Cannot derive a file name from {}", workflow.name)));
+    }
+    let path = workflows_dir()?.join(format!("{}.json", slug));
+    if path.exists() {
+        return Err(AppError::InvalidInput(format!("{} already exists", path.display())));
+    }
+
+    let json = serde_json::to_string_pretty(workflow).map_err(|e| AppError::Failed(e.to_string()))?;
+    fs::write(&path, json).map_err(|e| AppError::Io(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(path)
+}
+
+/// Run a workflow by name on the current automation backend. Every entry
+/// point comes through here, so this is where real runs are checked;
+/// dry runs act on nothing.
+pub fn run_named(name: &str, dry_run: bool, variables: HashMap<String, String>) -> AppResult<RunReport> {
+    let workflow = find(name)?;
+    if !dry_run {
+        permissions::require(Capability::RunWorkflow, Some(name))?;
+    }
+    tracing::info!("Running workflow {} (dry run: {})", workflow.name, dry_run);
+    Ok(Engine::new(automation::backend(), dry_run).run(&workflow, variables))
+}
+
+/// A run that stopped at a failing step still returns its report, so audit
+/// it as failed.
+fn audit_run(command: &str, name: &str, dry_run: bool, started: Instant, result: &AppResult<RunReport>) {
+    let outcome = match result {
+        Ok(report) => report.error.clone().map_or(Ok(()), |e| Err(AppError::Failed(e))),
+        Err(e) => Err(e.clone()),
+    };
+    let details = serde_json::json!({ "dryRun": dry_run });
+    audit::record_command(command, Some(name), details, started, &outcome);
+}
+
+/// Entry point for `automate_mac`: `params` is the workflow name.
+pub async fn handle_automation(params: Option<String>) -> AppResult<String> {
+    let name = params.ok_or_else(|| AppError::InvalidInput("Missing workflow name".to_string()))?;
+    let report = tauri::async_runtime::spawn_blocking(move || run_named(&name, false, HashMap::new())).await??;
+    match report.error {
+        Some(error) => Err(AppError::Failed(error)),
+        None => Ok(report.variables.get("last_output").cloned().unwrap_or_default()),
+    }
+}
+
+/// Register a global shortcut for every workflow that declares a hotkey.
+/// None are registered in safe mode.
+pub fn register_hotkeys(app_handle: &tauri::AppHandle) {
+    let shortcuts = app_handle.global_shortcut();
+    if let Err(e) = shortcuts.unregister_all() {
+        tracing::warn!("Failed to clear workflow hotkeys: {}", e);
+    }
+    if crate::crash::safe_mode() {
+        return;
+    }
+
+    let workflows = match load_all() {
+        Ok(workflows) => workflows,
+        Err(e) => {
+            tracing::warn!("Failed to load workflows: {}", e);
+            return;
+        }
+    };
+
+    for (_, workflow) in workflows {
+        let Ok(workflow) = workflow else { continue };
+        let Some(hotkey) = workflow.hotkey.clone() else { continue };
+        let name = workflow.name.clone();
+
+        let result = shortcuts.on_shortcut(hotkey.as_str(), move |app, _shortcut, event| {
+            if event.state != ShortcutState::Pressed {
+                return;
+            }
+            let app = app.clone();
+            let name = name.clone();
+            std::thread::spawn(move || {
+                let started = Instant::now();
+                let result = run_named(&name, false, HashMap::new());
+                audit_run("workflow_hotkey", &name, false, started, &result);
+                match result {
+                    Ok(report) => {
+                        if let Err(e) = app.emit("workflow_finished", report) {
+                            tracing::warn!("Failed to emit workflow result: {}", e);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Workflow {} failed to start: {}", name, e),
+                }
+            });
+        });
+        match result {
+            Ok(()) => tracing::info!("Registered hotkey {} for workflow {}", hotkey, workflow.name),
+            Err(e) => tracing::warn!("Failed to register hotkey {}: {}", hotkey, e),
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_workflows() -> AppResult<Vec<WorkflowSummary>> {
+    Ok(load_all()?
+        .into_iter()
+        .map(|(path, workflow)| {
+            let file = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default()
+                .to_string();
+            match workflow {
+                Ok(workflow) => WorkflowSummary {
+                    file,
+                    problems: definition::validate(&workflow),
+                    name: workflow.name,
+                    description: workflow.description,
+                    hotkey: workflow.hotkey,
+                    step_count: workflow.steps.len(),
+                },
+                Err(e) => WorkflowSummary {
+                    name: file_stem(&path),
+                    file,
+                    description: String::new(),
+                    hotkey: None,
+                    step_count: 0,
+                    problems: vec![e],
+                },
+            }
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn run_workflow(
+    name: String,
+    dry_run: Option<bool>,
+    variables: Option<HashMap<String, String>>,
+) -> AppResult<RunReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let dry_run = dry_run.unwrap_or(false);
+        let started = Instant::now();
+        let result = run_named(&name, dry_run, variables.unwrap_or_default());
+        audit_run("run_workflow", &name, dry_run, started, &result);
+        result
+    })
+    .await?
+}
+
+/// Check workflow source without saving it.
+#[tauri::command]
+pub fn validate_workflow(source: String, format: String) -> Vec<String> {
+    match definition::parse(&source, &format) {
+        Ok(workflow) => definition::validate(&workflow),
+        Err(e) => vec![e],
+    }
+}
+
+/// Re-read workflow files and re-register their hotkeys.
+#[tauri::command]
+pub fn reload_workflows(app_handle: tauri::AppHandle) -> AppResult<Vec<WorkflowSummary>> {
+    register_hotkeys(&app_handle);
+    list_workflows()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::{Consent, PROMPTER};
+    use crate::test_support;
+
+    const GREETING: &str = r#"
+        name = "Greeting"
+        hotkey = "CmdOrCtrl+Shift+G"
+
+        [[steps]]
+        action = "type_text"
+        text = "Hello"
+    "#;
+
+    fn isolated(auth_method: &str) -> test_support::Isolated {
+        let isolated = test_support::isolated(|settings| settings.security.auth_method = auth_method.to_string());
+        init(isolated.dir.path());
+        fs::write(workflows_dir().unwrap().join("greeting.toml"), GREETING).unwrap();
+        isolated
+    }
+
+    #[test]
+    fn runs_need_consent_and_an_unlocked_app() {
+        let _isolated = isolated("system");
+
+        assert!(matches!(
+            run_named("Greeting", false, HashMap::new()),
+            Err(AppError::PermissionDenied(_))
+        ));
+        *PROMPTER.answer.lock() = Consent::AllowOnce;
+        assert!(run_named("Greeting", false, HashMap::new()).unwrap().success);
+
+        crate::lock::lock_app().unwrap();
+        assert!(matches!(
+            run_named("Greeting", false, HashMap::new()),
+            Err(AppError::Locked)
+        ));
+        // A dry run acts on nothing, so it needs neither
+        assert!(run_named("greeting", true, HashMap::new()).unwrap().success);
+        assert!(matches!(
+            run_named("Farewell", false, HashMap::new()),
+            Err(AppError::NotFound(_))
+        ));
+    }
+}
@@ -29,7 +29,7 @@ from Quartz import (
 # Set up logging
 logging.basicConfig(
     level=logging.INFO,
-    format='%(message)s',
+    format='%(levelname)s %(message)s',
     stream=sys.stderr
 )
 logger = logging.getLogger(__name__)
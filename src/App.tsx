@@ -13,6 +13,8 @@ import './fonts.css'
 import cursorLogo from './assets/cursor-logo.png'
 import lightning from './assets/lightning.png'
 import { AutomationView } from './components/AutomationView'
+import { VisionView } from './components/VisionView'
+import { LockScreen } from './components/LockScreen'
 
 // Base sizes in logical pixels (will be scaled by Tauri)
 const BASE_SIZES = {
@@ -145,12 +147,15 @@ function App() {
   })
   const [isExpanded, setIsExpanded] = useState(false)
   const [isChatOpen, setIsChatOpen] = useState(false)
+  const [chatPrompt, setChatPrompt] = useState<string | null>(null)
   const [isTheaterMode, setIsTheaterMode] = useState(false)
   const [searchQuery, setSearchQuery] = useState('')
   const [isSettingsOpen, setIsSettingsOpen] = useState(false)
   const [isAutomationOpen, setIsAutomationOpen] = useState(false)
+  const [isVisionOpen, setIsVisionOpen] = useState(false)
   const [activeWindows, setActiveWindows] = useState<WindowInfo[]>([])
   const [activeApp, setActiveApp] = useState<AppInfo | null>(null)
+  const [lockMethod, setLockMethod] = useState<string | null>(null)
 
   // --- Refs ---
   const expandedMenuRef = useRef<HTMLDivElement>(null)
@@ -308,7 +313,7 @@ function App() {
     { 
       label: <strong>Vision</strong>, 
       icon: '', 
-      onClick: () => {}, 
+      onClick: () => setIsVisionOpen(true), 
       iconType: 'vision',
       description: 'Visual recognition & analysis',
       keywords: ['vision', 'image', 'recognition', 'visual', 'camera']
@@ -443,6 +448,51 @@ function App() {
     };
   }, []);
 
+  // Chats started from outside the app, e.g. `start_chat` over IPC
+  useEffect(() => {
+    const unlisten = listen<{ prompt: string | null }>('chat_requested', async (event) => {
+      setIsSettingsOpen(false);
+      setIsAutomationOpen(false);
+      setIsVisionOpen(false);
+      await handleExpand();
+      setIsChatOpen(true);
+      setChatPrompt(event.payload.prompt);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // While locked, chats and panels are closed so their history is not on screen
+  useEffect(() => {
+    const applyLock = async (locked: boolean) => {
+      if (!locked) {
+        setLockMethod(null);
+        return;
+      }
+      const status = await invoke<{ authMethod: string }>('lock_status');
+      setIsChatOpen(false);
+      setChatPrompt(null);
+      setIsSettingsOpen(false);
+      setIsAutomationOpen(false);
+      setIsVisionOpen(false);
+      setIsTheaterMode(false);
+      setLockMethod(status.authMethod);
+    };
+
+    invoke<{ locked: boolean }>('lock_status')
+      .then((status) => applyLock(status.locked))
+      .catch((error) => console.error('Failed to get lock status:', error));
+    const unlisten = listen<{ locked: boolean }>('lock_changed', (event) => {
+      applyLock(event.payload.locked);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   // New CurrentApp implementation as a memo-ized component for better performance
   const CurrentApp = React.memo(() => {
     console.log('Rendering CurrentApp with:', activeApp?.name);
@@ -526,12 +576,17 @@ function App() {
                 }}
                 preset={
                   isAutomationOpen ? 'automation' : 
+                  isVisionOpen ? 'chat' : 
                   isSettingsOpen ? 'settings' : 
                   isChatOpen ? 'chat' : 
                   'default'
                 }
               >
-                {!isAutomationOpen && !isSettingsOpen && !isChatOpen && (
+                {lockMethod && (
+                  <LockScreen authMethod={lockMethod} onUnlocked={() => setLockMethod(null)} />
+                )}
+
+                {!lockMethod && !isAutomationOpen && !isVisionOpen && !isSettingsOpen && !isChatOpen && (
                   <>
                     {/* Top bar with search and back button */}
                     <div className="flex items-center justify-between mb-2 shrink-0 gap-2">
@@ -692,20 +747,30 @@ function App() {
                   )}
                 </AnimatePresence>
 
+                {/* Vision Panel */}
+                <AnimatePresence>
+                  {isVisionOpen && (
+                    <VisionView onClose={() => setIsVisionOpen(false)} />
+                  )}
+                </AnimatePresence>
+
                 {/* AI Chat Panel */}
                 <AnimatePresence>
                   {isChatOpen && (
                     <AIChat 
                       onBack={() => {
                         setIsChatOpen(false);
+                        setChatPrompt(null);
                         setIsExpanded(true);
                       }}
                       onClose={() => {
                         setIsChatOpen(false);
+                        setChatPrompt(null);
                         setIsTheaterMode(false);
                         handleCollapse();
                       }}
                       isTheaterMode={isTheaterMode}
+                      initialPrompt={chatPrompt}
                       onTheaterModeChange={async (enabled) => {
                         setIsTheaterMode(enabled);
                         await transitionWindowState({
@@ -4,6 +4,7 @@ import { Select } from './ui/Select'
 import { Toggle } from './ui/Toggle'
 import { SecureInput } from './ui/SecureInput'
 import { store } from './store'
+import { invoke } from '@tauri-apps/api/core'
 
 // Utility function for class names
 const cn = (...classes: (string | boolean | undefined)[]) => 
@@ -42,6 +43,139 @@ export const BackButton: React.FC = () => (
   </button>
 )
 
+interface SecuritySettings {
+  authMethod: 'none' | 'password' | 'system' | 'biometric'
+  autoLockMinutes: number
+  lockOnScreenLock: boolean
+}
+
+interface LockStatus {
+  locked: boolean
+  enabled: boolean
+  authMethod: string
+  passwordSet: boolean
+  authenticator: string
+  idleLockAvailable: boolean
+}
+
+// The app lock lives in the Rust settings, not the webview store
+const AppLockSettings = () => {
+  const [security, setSecurity] = useState<SecuritySettings | null>(null)
+  const [status, setStatus] = useState<LockStatus | null>(null)
+  const [current, setCurrent] = useState('')
+  const [password, setPassword] = useState('')
+
+  const refresh = async () => {
+    try {
+      const appSettings = await invoke<{ security: SecuritySettings }>('get_settings')
+      setSecurity(appSettings.security)
+      setStatus(await invoke<LockStatus>('lock_status'))
+    } catch (error) {
+      console.error('Failed to load security settings:', error)
+    }
+  }
+
+  useEffect(() => {
+    refresh()
+  }, [])
+
+  // Changing the lock while it is on needs the current password or the OS
+  const update = async (changes: Partial<SecuritySettings>) => {
+    try {
+      const appSettings = await invoke<{ security: SecuritySettings }>('get_settings')
+      await invoke('update_security_settings', {
+        current: current || null,
+        security: { ...appSettings.security, ...changes }
+      })
+      setCurrent('')
+      refresh()
+    } catch (error) {
+      console.error('Failed to save security settings:', error)
+    }
+  }
+
+  const savePassword = async () => {
+    if (!password) return
+    try {
+      await invoke('set_lock_password', {
+        current: status?.passwordSet ? current : null,
+        password
+      })
+      setCurrent('')
+      setPassword('')
+      refresh()
+    } catch (error) {
+      console.error('Failed to set app password:', error)
+    }
+  }
+
+  if (!security) return null
+
+  return (
+    <>
+      <SettingItem
+        icon="🛡️"
+        title="Security"
+        description="Authentication method"
+      >
+        <Select
+          value={security.authMethod}
+          onChange={(authMethod) => update({ authMethod: authMethod as SecuritySettings['authMethod'] })}
+          options={[
+            { value: 'none', label: 'Off' },
+            { value: 'system', label: 'System Auth' },
+            { value: 'password', label: 'Password' },
+            { value: 'biometric', label: 'Biometric' }
+          ]}
+        />
+      </SettingItem>
+      {security.authMethod === 'password' && (
+        <SettingItem
+          icon="🔑"
+          title="App Password"
+          description={status?.passwordSet ? 'Change password' : 'Set a password'}
+        >
+          <div className="space-y-1">
+            {status?.passwordSet && (
+              <SecureInput placeholder="Current" value={current} onChange={setCurrent} />
+            )}
+            <SecureInput placeholder="New" value={password} onChange={setPassword} onSubmit={savePassword} />
+          </div>
+        </SettingItem>
+      )}
+      {security.authMethod !== 'none' && (
+        <SettingItem
+          icon="⏱️"
+          title="Auto-Lock"
+          description={
+            status?.idleLockAvailable === false
+              ? 'Lock when the screen locks (idle time is not available here)'
+              : 'Lock when idle or the screen locks'
+          }
+        >
+          <div className="space-y-2">
+            <Select
+              value={String(security.autoLockMinutes)}
+              onChange={(minutes) => update({ autoLockMinutes: Number(minutes) })}
+              options={[
+                { value: '0', label: 'Never' },
+                { value: '5', label: '5 min' },
+                { value: '15', label: '15 min' },
+                { value: '60', label: '1 hour' }
+              ]}
+            />
+            <Toggle
+              label="Screen Lock"
+              defaultChecked={security.lockOnScreenLock}
+              onChange={(lockOnScreenLock) => update({ lockOnScreenLock })}
+            />
+          </div>
+        </SettingItem>
+      )}
+    </>
+  )
+}
+
 // Privacy Settings Component
 const PrivacySettings = () => (
   <div className="space-y-3 overflow-y-auto
@@ -55,20 +189,7 @@ const PrivacySettings = () => (
     >
       <Toggle defaultChecked={false} />
     </SettingItem>
-    <SettingItem
-      icon="🛡️"
-      title="Security"
-      description="Authentication method"
-    >
-      <Select
-        value="system"
-        options={[
-          { value: 'system', label: 'System Auth' },
-          { value: 'password', label: 'Password' },
-          { value: 'biometric', label: 'Biometric' }
-        ]}
-      />
-    </SettingItem>
+    <AppLockSettings />
   </div>
 )
 
@@ -118,14 +239,12 @@ interface Settings {
   }
   ai: {
     model: string
-    apiKey: string
     codeCompletion: boolean
     imageGeneration: boolean
     voiceCommands: boolean
   }
   privacy: {
     dataCollection: boolean
-    authMethod: 'system' | 'password' | 'biometric'
   }
 }
 
@@ -146,14 +265,12 @@ export const SettingsPanel: React.FC<SettingsPanelProps> = ({ onClose }) => {
     },
     ai: {
       model: 'gpt4',
-      apiKey: '',
       codeCompletion: true,
       imageGeneration: false,
       voiceCommands: false
     },
     privacy: {
-      dataCollection: false,
-      authMethod: 'system'
+      dataCollection: false
     }
   });
 
@@ -407,11 +524,325 @@ const ThemeSettings = () => (
   </div>
 )
 
+interface SecretStatus {
+  name: string
+  stored: boolean
+  fromEnv: boolean
+  backend: string
+}
+
+// Keys go straight to the OS keyring; the webview can only ask whether one is set
+const ApiKeyField: React.FC<{ provider: string }> = ({ provider }) => {
+  const [draft, setDraft] = useState('')
+  const [status, setStatus] = useState<SecretStatus | null>(null)
+
+  const refresh = () =>
+    invoke<SecretStatus>('has_secret', { name: provider })
+      .then(setStatus)
+      .catch((error) => console.error('Failed to check API key:', error))
+
+  useEffect(() => {
+    refresh()
+  }, [provider])
+
+  const save = async () => {
+    if (!draft.trim()) return
+    try {
+      await invoke('set_secret', { name: provider, value: draft })
+      setDraft('')
+      refresh()
+    } catch (error) {
+      console.error('Failed to save API key:', error)
+    }
+  }
+
+  const placeholder = status?.stored
+    ? 'Saved — type to replace'
+    : status?.fromEnv
+      ? 'Using environment variable'
+      : 'sk-...'
+
+  return (
+    <SecureInput
+      placeholder={placeholder}
+      value={draft}
+      onChange={setDraft}
+      onSubmit={save}
+    />
+  )
+}
+
+interface LocalModelSettings {
+  endpoint: string
+  model: string
+  contextWindow: number | null
+  offlineFallback: boolean
+}
+
+interface LocalModel {
+  id: string
+  contextWindow: number | null
+}
+
+// Provider and local model live in the Rust settings, like the app lock
+const ProviderSettings = () => {
+  const [ai, setAi] = useState<{ provider: string; local: LocalModelSettings } | null>(null)
+  const [endpoint, setEndpoint] = useState('')
+  const [models, setModels] = useState<LocalModel[]>([])
+  const [modelsError, setModelsError] = useState<string | null>(null)
+
+  const refresh = async () => {
+    try {
+      const appSettings = await invoke<{ ai: { provider: string; local: LocalModelSettings } }>('get_settings')
+      setAi(appSettings.ai)
+      setEndpoint(appSettings.ai.local.endpoint)
+    } catch (error) {
+      console.error('Failed to load AI settings:', error)
+    }
+  }
+
+  const discover = () =>
+    invoke<LocalModel[]>('list_local_models')
+      .then((found) => {
+        setModels(found)
+        setModelsError(null)
+      })
+      .catch((error) => {
+        setModels([])
+        setModelsError(error.message ?? String(error))
+      })
+
+  useEffect(() => {
+    refresh().then(discover)
+  }, [])
+
+  const update = async (changes: { provider?: string; local?: Partial<LocalModelSettings> }) => {
+    try {
+      const appSettings = await invoke<{ ai: { provider: string; local: LocalModelSettings } }>('get_settings')
+      // Where chats go is changed on its own, after a native confirmation
+      if (changes.provider || changes.local?.endpoint) {
+        await invoke('set_ai_provider', {
+          provider: changes.provider ?? appSettings.ai.provider,
+          endpoint: changes.local?.endpoint ?? null
+        })
+      }
+      await invoke('update_settings', {
+        settings: {
+          ...appSettings,
+          ai: {
+            ...appSettings.ai,
+            local: { ...appSettings.ai.local, ...changes.local }
+          }
+        }
+      })
+      await refresh()
+      if (changes.local?.endpoint) discover()
+    } catch (error) {
+      console.error('Failed to save AI settings:', error)
+    }
+  }
+
+  if (!ai) return null
+
+  return (
+    <>
+      <SettingItem
+        icon="☁️"
+        title="Provider"
+        description="Where requests from the app go"
+      >
+        <Select
+          value={ai.provider}
+          onChange={(provider) => update({ provider })}
+          options={[
+            { value: 'gemini', label: 'Gemini' },
+            { value: 'local', label: 'Local model' }
+          ]}
+        />
+      </SettingItem>
+      <SettingItem
+        icon="💻"
+        title="Local Model"
+        description={modelsError ?? 'OpenAI-compatible server, e.g. Ollama'}
+      >
+        <div className="space-y-1">
+          <input
+            type="text"
+            value={endpoint}
+            onChange={(e) => setEndpoint(e.target.value)}
+            onBlur={() => endpoint !== ai.local.endpoint && update({ local: { endpoint } })}
+            className="w-full bg-white/5 border border-white/10 rounded-lg px-2 py-1 text-[10px] text-white/90 outline-none"
+          />
+          <Select
+            value={ai.local.model}
+            onChange={(model) => update({ local: { model } })}
+            options={[
+              { value: '', label: 'First available' },
+              ...models.map((model) => ({
+                value: model.id,
+                label: model.contextWindow ? `${model.id} (${Math.round(model.contextWindow / 1024)}k)` : model.id
+              }))
+            ]}
+          />
+          {ai.provider !== 'local' && (
+            <Toggle
+              label="Use offline"
+              defaultChecked={ai.local.offlineFallback}
+              onChange={(offlineFallback) => update({ local: { offlineFallback } })}
+            />
+          )}
+        </div>
+      </SettingItem>
+    </>
+  )
+}
+
+interface BudgetStatus {
+  dailySpentUsd: number
+  dailyLimitUsd: number | null
+  monthlySpentUsd: number
+  monthlyLimitUsd: number | null
+}
+
+// Limits live in the Rust settings; spending comes from the LLM ledger
+const SpendingSettings = () => {
+  const [status, setStatus] = useState<BudgetStatus | null>(null)
+
+  const refresh = () =>
+    invoke<BudgetStatus>('get_budget_status')
+      .then(setStatus)
+      .catch((error) => console.error('Failed to load budget status:', error))
+
+  useEffect(() => {
+    refresh()
+  }, [])
+
+  const setLimit = async (key: 'dailyLimitUsd' | 'monthlyLimitUsd', value: string) => {
+    const limit = value.trim() === '' ? null : Number(value)
+    if (limit !== null && (Number.isNaN(limit) || limit < 0)) return
+    try {
+      const appSettings = await invoke<{ budget: Record<string, unknown> }>('get_settings')
+      await invoke('update_settings', {
+        settings: { ...appSettings, budget: { ...appSettings.budget, [key]: limit } }
+      })
+      refresh()
+    } catch (error) {
+      console.error('Failed to save budget:', error)
+    }
+  }
+
+  if (!status) return null
+
+  const limitInput = (key: 'dailyLimitUsd' | 'monthlyLimitUsd', spent: number, label: string) => (
+    <label className="flex items-center justify-between gap-2 text-[10px] text-white/60">
+      <span>{label}: ${spent.toFixed(2)} of</span>
+      <input
+        type="number"
+        min="0"
+        step="0.5"
+        placeholder="no limit"
+        defaultValue={status[key] ?? ''}
+        onBlur={(e) => setLimit(key, e.target.value)}
+        className="w-16 bg-white/5 border border-white/10 rounded-lg px-2 py-1 text-white/90 outline-none"
+      />
+    </label>
+  )
+
+  return (
+    <SettingItem
+      icon="💰"
+      title="Spending"
+      description="Estimated cost of cloud requests; requests stop at a limit"
+    >
+      <div className="space-y-1">
+        {limitInput('dailyLimitUsd', status.dailySpentUsd, 'Today')}
+        {limitInput('monthlyLimitUsd', status.monthlySpentUsd, 'This month')}
+      </div>
+    </SettingItem>
+  )
+}
+
+interface PromptTemplateSummary {
+  file: string | null
+  name: string
+  description: string
+  apps: string[]
+  isDefault: boolean
+  problems: string[]
+}
+
+interface PromptTemplates {
+  templates: PromptTemplateSummary[]
+  bindingProblems: string[]
+}
+
+// Templates are files in the prompts folder of the config dir; bindings to
+// apps are edited in settings.json
+const PromptSettings = () => {
+  const [templates, setTemplates] = useState<PromptTemplateSummary[]>([])
+  const [bindingProblems, setBindingProblems] = useState<string[]>([])
+
+  const refresh = () =>
+    invoke<PromptTemplates>('list_prompt_templates')
+      .then((list) => {
+        setTemplates(list.templates)
+        setBindingProblems(list.bindingProblems)
+      })
+      .catch((error) => console.error('Failed to load prompt templates:', error))
+
+  useEffect(() => {
+    refresh()
+  }, [])
+
+  const setDefault = async (name: string) => {
+    try {
+      const appSettings = await invoke<{ prompts: Record<string, unknown> }>('get_settings')
+      await invoke('update_settings', {
+        settings: { ...appSettings, prompts: { ...appSettings.prompts, defaultTemplate: name } }
+      })
+      refresh()
+    } catch (error) {
+      console.error('Failed to save default template:', error)
+    }
+  }
+
+  if (templates.length === 0) return null
+
+  const selected = templates.find((template) => template.isDefault)
+  const problems = [
+    ...bindingProblems,
+    ...templates.flatMap((template) => template.problems.map((problem) => `${template.name}: ${problem}`))
+  ]
+
+  return (
+    <SettingItem
+      icon="📝"
+      title="Prompt"
+      description={problems[0] ?? selected?.description ?? 'Template used outside bound apps'}
+    >
+      <Select
+        value={selected?.name ?? ''}
+        onChange={setDefault}
+        options={templates
+          .filter((template) => template.problems.length === 0 || template.isDefault)
+          .map((template) => ({
+            value: template.name,
+            label: template.apps.length > 0 ? `${template.name} (${template.apps.join(', ')})` : template.name
+          }))}
+      />
+    </SettingItem>
+  )
+}
+
 const AISettings = () => (
   <div className="space-y-2 overflow-y-auto
                   scrollbar-thin scrollbar-track-white/5 
                   scrollbar-thumb-white/10 hover:scrollbar-thumb-white/20
                   scrollbar-thumb-rounded">
+    <ProviderSettings />
+    <SpendingSettings />
+    <PromptSettings />
+
     <SettingItem
       icon="🧠"
       title="AI Model"
@@ -432,7 +863,7 @@ const AISettings = () => (
       title="API Key"
       description="OpenAI API key"
     >
-      <SecureInput placeholder="sk-..." />
+      <ApiKeyField provider="openai" />
     </SettingItem>
 
     <SettingItem
@@ -1,5 +1,7 @@
 import { motion } from 'framer-motion'
 import { useState, useRef, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import { GeminiService, ChatMessage } from '../services/gemini'
 
 interface AIChatProps {
@@ -7,9 +9,61 @@ interface AIChatProps {
   onClose: () => void;
   isTheaterMode?: boolean;
   onTheaterModeChange?: (enabled: boolean) => void;
+  /** Sent as soon as the chat opens, e.g. when started over IPC. */
+  initialPrompt?: string | null;
 }
 
-export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeChange }: AIChatProps) {
+interface CommandError {
+  code?: string;
+  message?: string;
+  details?: { class?: string } | null;
+}
+
+// What to tell the user about a failed chat message
+const chatErrorMessage = ({ code, message, details }: CommandError) => {
+  if (code === 'BudgetExceeded' && message) return message;
+  if (code !== 'Provider') return 'Sorry, I encountered an error. Please try again.';
+  switch (details?.class) {
+    case 'rate_limited':
+      return 'The AI provider is busy right now. Please try again in a minute.';
+    case 'overloaded':
+    case 'network':
+    case 'circuit_open':
+      return 'The AI provider cannot be reached right now. Please try again later.';
+    case 'auth':
+      return 'The AI provider did not accept the API key. Check it in Settings.';
+    case 'content_filter':
+      return 'The AI provider declined to answer this message.';
+    default:
+      return message ?? 'Sorry, I encountered an error. Please try again.';
+  }
+};
+
+interface BudgetWarning {
+  period: 'day' | 'month';
+  spentUsd: number;
+  limitUsd: number;
+  exceeded: boolean;
+}
+
+interface AgentStep {
+  runId: string;
+  index: number;
+  tool: string;
+  arguments: Record<string, unknown>;
+  status: 'awaiting_approval' | 'running' | 'ok' | 'failed' | 'rejected' | 'preview';
+  output?: string | null;
+  error?: string | null;
+}
+
+interface AgentRun {
+  runId: string;
+  stopped: 'answered' | 'step_limit' | 'cancelled' | 'failed';
+  answer?: string | null;
+  error?: string | null;
+}
+
+export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeChange, initialPrompt }: AIChatProps) {
   const [messages, setMessages] = useState<ChatMessage[]>([
     {
       content: 'Hello! How can I help you today?',
@@ -18,6 +72,8 @@ export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeCh
   ]);
   const [inputMessage, setInputMessage] = useState('');
   const [isTyping, setIsTyping] = useState(false);
+  const [agentMode, setAgentMode] = useState(false);
+  const [agentSteps, setAgentSteps] = useState<AgentStep[]>([]);
   const messagesEndRef = useRef<HTMLDivElement>(null);
   const geminiService = useRef(new GeminiService());
 
@@ -33,14 +89,56 @@ export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeCh
 
   useEffect(() => {
     scrollToBottom();
-  }, [messages]);
+  }, [messages, agentSteps]);
+
+  useEffect(() => {
+    const unlisten = listen<AgentStep>('agent_step', (event) => {
+      setAgentSteps(prev => {
+        const step = event.payload;
+        const existing = prev.findIndex(s => s.runId === step.runId && s.index === step.index);
+        if (existing === -1) return [...prev, step];
+        return prev.map((s, i) => (i === existing ? step : s));
+      });
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  // Spending warnings from the backend show up in the conversation
+  useEffect(() => {
+    const unlisten = listen<BudgetWarning>('budget_warning', (event) => {
+      const { period, spentUsd, limitUsd, exceeded } = event.payload;
+      const name = period === 'day' ? 'daily' : 'monthly';
+      const content = exceeded
+        ? `The ${name} AI budget of $${limitUsd.toFixed(2)} is used up; cloud requests are paused.`
+        : `$${spentUsd.toFixed(2)} of the ${name} AI budget of $${limitUsd.toFixed(2)} is spent.`;
+      setMessages(prev => [...prev, { role: 'assistant', content }]);
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  const runAgent = async (text: string) => {
+    setAgentSteps([]);
+    const run = await invoke<AgentRun>('run_agent', {
+      messages: [{ role: 'user', content: text }],
+    });
+    if (run.answer) return run.answer;
+    return `Stopped (${run.stopped})${run.error ? `: ${run.error}` : ''}`;
+  };
+
+  const answerStep = (step: AgentStep, approve: boolean) =>
+    invoke('approve_agent_step', { runId: step.runId, index: step.index, approve })
+      .catch(error => console.error('Failed to answer agent step:', error));
 
-  const handleSendMessage = async () => {
-    if (!inputMessage.trim()) return;
+  const sendMessage = async (text: string) => {
+    if (!text.trim()) return;
 
     // Add user message
     const userMessage: ChatMessage = {
-      content: inputMessage,
+      content: text,
       role: 'user'
     };
 
@@ -49,8 +147,9 @@ export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeCh
     setIsTyping(true);
 
     try {
-      // Get response from Gemini
-      const response = await geminiService.current.sendMessage(inputMessage);
+      const response = agentMode
+        ? await runAgent(text)
+        : await geminiService.current.sendMessage(text);
       
       const aiMessage: ChatMessage = {
         content: response,
@@ -60,9 +159,9 @@ export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeCh
       setMessages(prev => [...prev, aiMessage]);
     } catch (error) {
       console.error('Failed to get AI response:', error);
-      // Add error message
+      // Add error message; the backend has already retried what it could
       const errorMessage: ChatMessage = {
-        content: 'Sorry, I encountered an error. Please try again.',
+        content: chatErrorMessage(error as CommandError),
         role: 'assistant'
       };
       setMessages(prev => [...prev, errorMessage]);
@@ -71,6 +170,14 @@ export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeCh
     }
   };
 
+  const handleSendMessage = () => sendMessage(inputMessage);
+
+  useEffect(() => {
+    if (initialPrompt) {
+      sendMessage(initialPrompt);
+    }
+  }, [initialPrompt]);
+
   const ModelIndicator = () => (
     <div className="model-indicator">
       gemini
@@ -119,6 +226,20 @@ export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeCh
           </div>
         </div>
         <div className="flex items-center gap-2">
+          <motion.button
+            onClick={() => setAgentMode(!agentMode)}
+            disabled={isTyping}
+            className={`text-[10px] px-2 py-1 rounded-full transition-colors border ${
+              agentMode
+                ? 'text-blue-50 bg-blue-600/40 border-blue-400/50'
+                : 'text-white/50 hover:text-white/90 border-white/10 hover:bg-blue-900/20'
+            }`}
+            whileHover={{ scale: 1.05 }}
+            whileTap={{ scale: 0.95 }}
+            aria-label={agentMode ? "Leave agent mode" : "Enter agent mode"}
+          >
+            Agent
+          </motion.button>
           <motion.button
             onClick={() => onTheaterModeChange?.(!isTheaterMode)}
             className="text-white/50 hover:text-white/90 transition-colors p-1.5 rounded-full
@@ -207,6 +328,44 @@ export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeCh
             </div>
           </div>
         ))}
+        {agentSteps.length > 0 && (
+          <div className="ml-9 space-y-1.5">
+            {agentSteps.map(step => (
+              <div
+                key={`${step.runId}-${step.index}`}
+                className="bg-black/30 border border-blue-500/20 rounded-lg px-2.5 py-1.5 text-[11px] text-white/80"
+              >
+                <div className="flex items-center justify-between gap-2">
+                  <span className="font-mono truncate">
+                    {step.tool}({JSON.stringify(step.arguments)})
+                  </span>
+                  <span className="text-white/40 shrink-0">{step.status.replace('_', ' ')}</span>
+                </div>
+                {(step.error || step.output) && (
+                  <p className={`mt-0.5 ${step.error ? 'text-red-400' : 'text-white/50'}`}>
+                    {step.error ?? step.output}
+                  </p>
+                )}
+                {step.status === 'awaiting_approval' && (
+                  <div className="flex gap-2 mt-1">
+                    <button
+                      onClick={() => answerStep(step, true)}
+                      className="px-2 py-0.5 rounded bg-blue-600/50 hover:bg-blue-600/70 text-white/90"
+                    >
+                      Run
+                    </button>
+                    <button
+                      onClick={() => answerStep(step, false)}
+                      className="px-2 py-0.5 rounded bg-white/10 hover:bg-white/20 text-white/70"
+                    >
+                      Skip
+                    </button>
+                  </div>
+                )}
+              </div>
+            ))}
+          </div>
+        )}
         {isTyping && (
           <div className="flex items-start gap-2">
             <div className="w-7 h-7 rounded-full bg-gradient-to-br from-blue-600 via-blue-700 to-blue-800
@@ -245,7 +404,7 @@ export function AIChat({ onBack, onClose, isTheaterMode = false, onTheaterModeCh
               type="text"
               value={inputMessage}
               onChange={(e) => setInputMessage(e.target.value)}
-              placeholder="Type a message..."
+              placeholder={agentMode ? "Ask Leo to do something..." : "Type a message..."}
               className="flex-1 bg-transparent border-none outline-none text-white/90 
                        placeholder:text-white/40 text-sm px-2"
             />
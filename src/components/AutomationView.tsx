@@ -1,4 +1,4 @@
-import { useState } from 'react'
+import { useEffect, useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { motion } from 'framer-motion'
 import { CardSpotlight } from '../ui/card-spotlight'
@@ -19,10 +19,26 @@ interface AutomationAction {
   keywords?: string[]
 }
 
+interface WorkflowSummary {
+  file: string
+  name: string
+  description: string
+  hotkey: string | null
+  stepCount: number
+  problems: string[]
+}
+
 export function AutomationView({ onClose }: AutomationViewProps) {
   const [selectedAction, setSelectedAction] = useState<string | null>(null)
   const [isLoading, setIsLoading] = useState(false)
   const [searchQuery, setSearchQuery] = useState('')
+  const [workflows, setWorkflows] = useState<WorkflowSummary[]>([])
+
+  useEffect(() => {
+    invoke<WorkflowSummary[]>('list_workflows')
+      .then(setWorkflows)
+      .catch(error => console.error('Failed to load workflows:', error))
+  }, [])
 
   // Helper function to handle automation actions
   const handleAction = async (action: () => Promise<void>) => {
@@ -122,7 +138,38 @@ export function AutomationView({ onClose }: AutomationViewProps) {
       },
       shortcut: '⌘ + S',
       keywords: ['summarize', 'ai', 'text']
-    }
+    },
+    {
+      id: 'record',
+      title: 'Record Macro',
+      description: 'Record input as a workflow',
+      icon: '⏺️',
+      action: async () => {
+        const state = await invoke<string>('automate_mac', {
+          action: 'record',
+          params: 'toggle'
+        })
+        if (state !== 'stopped') return
+        const name = window.prompt('Name this workflow')
+        if (name) {
+          await invoke('save_recording', { name })
+          setWorkflows(await invoke<WorkflowSummary[]>('list_workflows'))
+        }
+      },
+      keywords: ['record', 'macro', 'workflow']
+    },
+    ...workflows.map(workflow => ({
+      id: `workflow:${workflow.file}`,
+      title: workflow.name,
+      description: workflow.problems[0] ?? (workflow.description || `${workflow.stepCount} steps`),
+      icon: '🔁',
+      action: async () => {
+        await invoke('run_workflow', { name: workflow.name })
+      },
+      isEnabled: workflow.problems.length === 0,
+      shortcut: workflow.hotkey ?? undefined,
+      keywords: ['workflow', workflow.name]
+    }))
   ]
 
   // Filter automation actions based on search
@@ -0,0 +1,63 @@
+import { useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { motion } from 'framer-motion'
+import { SecureInput } from '../ui/SecureInput'
+
+interface LockScreenProps {
+  authMethod: string
+  onUnlocked: () => void
+}
+
+interface CommandError {
+  code: string
+  message: string
+}
+
+export function LockScreen({ authMethod, onUnlocked }: LockScreenProps) {
+  const [password, setPassword] = useState('')
+  const [error, setError] = useState<string | null>(null)
+  const [isUnlocking, setIsUnlocking] = useState(false)
+
+  const unlock = async () => {
+    if (isUnlocking || (authMethod === 'password' && !password)) return
+    setIsUnlocking(true)
+    try {
+      await invoke('unlock_app', { password: authMethod === 'password' ? password : null })
+      setPassword('')
+      setError(null)
+      onUnlocked()
+    } catch (e) {
+      setError((e as CommandError).message ?? String(e))
+    } finally {
+      setIsUnlocking(false)
+    }
+  }
+
+  return (
+    <motion.div
+      initial={{ opacity: 0 }}
+      animate={{ opacity: 1 }}
+      className="flex flex-col items-center justify-center h-full gap-3 px-4"
+    >
+      <span className="text-2xl">🔒</span>
+      <p className="text-xs text-white/70">Leo AI is locked</p>
+      {authMethod === 'password' ? (
+        <SecureInput
+          placeholder="Password"
+          value={password}
+          onChange={setPassword}
+          onSubmit={unlock}
+        />
+      ) : (
+        <button
+          className="text-xs text-white/90 bg-white/10 hover:bg-white/20 px-3 py-1.5 rounded-lg transition-colors"
+          onClick={unlock}
+          disabled={isUnlocking}
+        >
+          {authMethod === 'biometric' ? 'Unlock with Touch ID' : 'Unlock'}
+        </button>
+      )}
+      {error && <p className="text-[10px] text-red-400 text-center">{error}</p>}
+    </motion.div>
+  )
+}
@@ -0,0 +1,214 @@
+import { useEffect, useRef, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import { motion } from 'framer-motion'
+
+interface VisionViewProps {
+  onClose: () => void
+}
+
+type CaptureTarget = 'active_window' | 'region'
+
+interface Capture {
+  id: string
+  capturedAtMs: number
+  app: string | null
+  dataUrl: string
+}
+
+interface VisionAnswer {
+  requestId: string
+  text: string
+  model: string
+  captureId: string | null
+}
+
+interface CommandError {
+  code: string
+  message: string
+}
+
+export function VisionView({ onClose }: VisionViewProps) {
+  const [question, setQuestion] = useState('')
+  const [target, setTarget] = useState<CaptureTarget>('active_window')
+  const [pin, setPin] = useState(false)
+  const [pinned, setPinned] = useState<Capture[]>([])
+  const [selectedCapture, setSelectedCapture] = useState<string | null>(null)
+  const [answer, setAnswer] = useState('')
+  const [error, setError] = useState<string | null>(null)
+  const [isAsking, setIsAsking] = useState(false)
+  const requestIdRef = useRef<string | null>(null)
+
+  const loadPinned = () =>
+    invoke<Capture[]>('list_pinned_captures')
+      .then(setPinned)
+      .catch(e => console.error('Failed to load pinned captures:', e))
+
+  useEffect(() => {
+    loadPinned()
+    const unlisten = listen<{ requestId: string; text: string }>('vision_text', (event) => {
+      if (event.payload.requestId === requestIdRef.current) {
+        setAnswer(prev => prev + event.payload.text)
+      }
+    })
+    return () => {
+      unlisten.then(fn => fn())
+    }
+  }, [])
+
+  const ask = async () => {
+    if (isAsking || !question.trim()) return
+    const requestId = `vision-${Date.now()}`
+    requestIdRef.current = requestId
+    setIsAsking(true)
+    setAnswer('')
+    setError(null)
+    try {
+      const result = await invoke<VisionAnswer>('vision_ask', {
+        question,
+        target,
+        captureId: selectedCapture,
+        pin,
+        requestId,
+      })
+      setAnswer(result.text)
+      if (result.captureId) loadPinned()
+    } catch (e) {
+      setError((e as CommandError).message ?? String(e))
+    } finally {
+      setIsAsking(false)
+    }
+  }
+
+  const unpin = async (id: string) => {
+    try {
+      await invoke('unpin_capture', { id })
+      if (selectedCapture === id) setSelectedCapture(null)
+      loadPinned()
+    } catch (e) {
+      console.error('Failed to unpin capture:', e)
+    }
+  }
+
+  return (
+    <motion.div
+      initial={{ opacity: 0, x: 20 }}
+      animate={{ opacity: 1, x: 0 }}
+      exit={{ opacity: 0, x: -20 }}
+      className="absolute inset-0 flex flex-col overflow-hidden"
+    >
+      {/* Header */}
+      <div className="flex items-center justify-between px-2.5 py-2 shrink-0">
+        <div className="flex items-center gap-2">
+          <span className="text-base">👁</span>
+          <div>
+            <h2 className="text-blue-50/90 text-sm font-medium">Vision</h2>
+            <p className="text-blue-100/40 text-[10px]">Ask about what is on screen</p>
+          </div>
+        </div>
+        <button
+          onClick={onClose}
+          className="text-blue-100/60 hover:text-blue-100/90 transition-colors p-1.5 rounded-full
+                     hover:bg-blue-900/20 active:bg-blue-900/30"
+        >
+          <svg width="14" height="14" viewBox="0 0 24 24" fill="none" stroke="currentColor"
+               strokeWidth="2" strokeLinecap="round" strokeLinejoin="round">
+            <path d="M18 6L6 18M6 6l12 12" />
+          </svg>
+        </button>
+      </div>
+
+      <div className="flex-1 overflow-y-auto min-h-0 px-2.5 pb-2 space-y-2">
+        {/* What to capture */}
+        <div className="flex items-center gap-1.5 text-[10px]">
+          {(['active_window', 'region'] as CaptureTarget[]).map(option => (
+            <button
+              key={option}
+              onClick={() => {
+                setTarget(option)
+                setSelectedCapture(null)
+              }}
+              className={`px-2 py-1 rounded-full border transition-colors ${
+                target === option && !selectedCapture
+                  ? 'text-blue-50 bg-blue-600/40 border-blue-400/50'
+                  : 'text-white/50 hover:text-white/90 border-white/10'
+              }`}
+            >
+              {option === 'region' ? 'Region' : 'Active window'}
+            </button>
+          ))}
+          <label className="ml-auto flex items-center gap-1 text-white/50">
+            <input
+              type="checkbox"
+              checked={pin}
+              disabled={!!selectedCapture}
+              onChange={(e) => setPin(e.target.checked)}
+            />
+            Pin
+          </label>
+        </div>
+
+        {/* Pinned captures */}
+        {pinned.length > 0 && (
+          <div className="flex gap-1.5 overflow-x-auto pb-1">
+            {pinned.map(capture => (
+              <div key={capture.id} className="relative shrink-0">
+                <img
+                  src={capture.dataUrl}
+                  alt={capture.app ?? 'Pinned capture'}
+                  onClick={() => setSelectedCapture(selectedCapture === capture.id ? null : capture.id)}
+                  className={`w-14 h-10 object-cover rounded cursor-pointer border ${
+                    selectedCapture === capture.id ? 'border-blue-400' : 'border-white/10'
+                  }`}
+                />
+                <button
+                  onClick={() => unpin(capture.id)}
+                  className="absolute -top-1 -right-1 w-3.5 h-3.5 rounded-full bg-black/80 text-[8px] text-white/70"
+                  aria-label="Unpin"
+                >
+                  ×
+                </button>
+              </div>
+            ))}
+          </div>
+        )}
+
+        {/* Question */}
+        <form
+          onSubmit={(e) => {
+            e.preventDefault()
+            ask()
+          }}
+          className="flex items-center gap-2 bg-black/50 rounded-lg p-2 border border-blue-500/30"
+        >
+          <input
+            type="text"
+            value={question}
+            onChange={(e) => setQuestion(e.target.value)}
+            placeholder={selectedCapture ? 'Ask about the pinned capture...' : 'What is on my screen?'}
+            className="flex-1 bg-transparent border-none outline-none text-white/90
+                     placeholder:text-white/40 text-xs px-1"
+          />
+          <button
+            type="submit"
+            disabled={isAsking || !question.trim()}
+            className="w-6 h-6 rounded-full bg-gradient-to-br from-blue-600 to-blue-800
+                     flex items-center justify-center text-white/90 text-xs
+                     disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            ↑
+          </button>
+        </form>
+
+        {/* Answer */}
+        {(answer || isAsking) && (
+          <div className="bg-gradient-to-br from-blue-500/20 via-blue-600/20 to-blue-700/20 border border-blue-500/30
+                        rounded-lg p-2.5 text-xs text-white/90 whitespace-pre-wrap break-words">
+            {answer || '...'}
+          </div>
+        )}
+        {error && <p className="text-[10px] text-red-400">{error}</p>}
+      </div>
+    </motion.div>
+  )
+}
@@ -1,48 +1,44 @@
-import { GoogleGenerativeAI } from '@google/generative-ai';
-
-// Initialize the Gemini API
-const genAI = new GoogleGenerativeAI(import.meta.env.VITE_GOOGLE_API_KEY || '');
-
-// Get the model
-const model = genAI.getGenerativeModel({ model: 'gemini-pro' });
+import { invoke } from '@tauri-apps/api/core';
 
 export interface ChatMessage {
   role: 'user' | 'assistant';
   content: string;
 }
 
+export interface TokenUsage {
+  promptTokens: number;
+  completionTokens: number;
+}
+
+interface Completion {
+  text: string;
+  model: string;
+  usage: TokenUsage | null;
+}
+
+// Answers come from the configured provider in the backend, which keeps the
+// API key, counts each call against the spending limits and adds the system
+// prompt of the template for the active app.
 export class GeminiService {
-  private chat;
   private history: ChatMessage[] = [];
-
-  constructor() {
-    this.chat = model.startChat({
-      history: [],
-      generationConfig: {
-        maxOutputTokens: 1000,
-        temperature: 0.7,
-        topP: 0.8,
-        topK: 40,
-      },
-    });
-  }
+  private lastUsage: TokenUsage | null = null;
 
   async sendMessage(message: string): Promise<string> {
     try {
       // Add user message to history
       this.history.push({ role: 'user', content: message });
 
-      // Get response from Gemini
-      const result = await this.chat.sendMessage(message);
-      const response = await result.response;
-      const text = response.text();
+      const completion = await invoke<Completion>('chat', { messages: this.history });
+      this.lastUsage = completion.usage;
 
       // Add assistant response to history
-      this.history.push({ role: 'assistant', content: text });
+      this.history.push({ role: 'assistant', content: completion.text });
 
-      return text;
+      return completion.text;
     } catch (error) {
-      console.error('Error sending message to Gemini:', error);
+      // Leave the history as it was before the failed message
+      this.history.pop();
+      console.error('Error sending chat message:', error);
       throw error;
     }
   }
@@ -51,16 +47,12 @@ export class GeminiService {
     return this.history;
   }
 
+  getLastUsage(): TokenUsage | null {
+    return this.lastUsage;
+  }
+
   clearHistory(): void {
     this.history = [];
-    this.chat = model.startChat({
-      history: [],
-      generationConfig: {
-        maxOutputTokens: 1000,
-        temperature: 0.7,
-        topP: 0.8,
-        topK: 40,
-      },
-    });
+    this.lastUsage = null;
   }
-} 
\ No newline at end of file
+}
@@ -2,6 +2,8 @@ interface SecureInputProps {
   placeholder?: string
   value?: string
   onChange?: (value: string) => void
+  /** Called on Enter and when the field loses focus. */
+  onSubmit?: () => void
   className?: string
 }
 
@@ -9,6 +11,7 @@ export const SecureInput: React.FC<SecureInputProps> = ({
   placeholder, 
   value, 
   onChange,
+  onSubmit,
   className 
 }) => (
   <div className="relative">
@@ -16,6 +19,8 @@ export const SecureInput: React.FC<SecureInputProps> = ({
       type="password"
       value={value}
       onChange={(e) => onChange?.(e.target.value)}
+      onKeyDown={(e) => e.key === 'Enter' && onSubmit?.()}
+      onBlur={() => onSubmit?.()}
       placeholder={placeholder}
       className={`
         w-full bg-white/5 border border-white/10 rounded-lg 